@@ -29,6 +29,7 @@ mkdir -p $GOUP_DIR
 printf "export GOPATH=\"$GOPATH\" # The global dir for packages and installed binaries\n\
 export GOROOT=\"$GOROOT\" # The installed Go development kit\n\
 export PATH=\"${GOROOT}/bin:${GOPATH}/bin:\${PATH}\"\n\
+export GOTOOLCHAIN=local # Use goup's Go, rather than letting go download its own\n\
 " > $GOUP_DIR/env
 
 echo "Compiling goup..."
@@ -0,0 +1,94 @@
+//! Building Go from its source release, for platforms that go.dev publishes no archive for
+
+use crate::error::GoupError;
+use crate::progress::Progress;
+use crate::version::GoVersion;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// The oldest release of Go that can bootstrap a build of `version`, as listed in
+/// <https://go.dev/doc/install/source>. From Go 1.22 on, each release needs the last patch
+/// release of the series two before it, rounded down to an even minor version.
+pub fn bootstrap_version(version: GoVersion) -> GoVersion {
+    let (minor, patch) = match version.minor {
+        ..=19 => (4, 0),
+        20 | 21 => (17, 13),
+        minor => ((minor - 2) & !1, 6),
+    };
+    GoVersion {
+        major: 1,
+        minor,
+        patch,
+        pre: None,
+        explicit_patch: true,
+    }
+}
+
+/// The script in `src` that builds the toolchain
+const MAKE: &str = if cfg!(windows) {
+    "make.bat"
+} else {
+    "make.bash"
+};
+
+/// Build the source tree at `goroot` with the Go installation at `bootstrap`, writing
+/// everything the build prints to `log` and passing each line to `progress`
+pub(crate) fn make(
+    version: GoVersion,
+    goroot: &Path,
+    bootstrap: &Path,
+    log: &Path,
+    progress: &dyn Progress,
+) -> Result<(), GoupError> {
+    if let Some(dir) = log.parent() {
+        fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+    }
+    let log_file = File::create(log).map_err(GoupError::io("create", log))?;
+
+    let src = goroot.join("src");
+    let start = Instant::now();
+    let mut child = Command::new(src.join(MAKE))
+        .current_dir(&src)
+        .env("GOROOT_BOOTSTRAP", bootstrap)
+        // The tree being built decides its own GOROOT, and the bootstrap toolchain must not
+        // go looking for a newer one
+        .env_remove("GOROOT")
+        .env("GOTOOLCHAIN", "local")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(GoupError::io("run", src.join(MAKE)))?;
+
+    let log_file = Mutex::new(log_file);
+    let forward = |stream: &mut dyn Read| {
+        for line in BufReader::new(stream).lines() {
+            let Ok(line) = line else { break };
+            // A log that can't be written is no reason to stop the build
+            let _ = writeln!(log_file.lock().unwrap(), "{}", line);
+            progress.output(&line);
+        }
+    };
+    let mut stdout = child.stdout.take().expect("stdout is piped");
+    let mut stderr = child.stderr.take().expect("stderr is piped");
+    thread::scope(|s| {
+        s.spawn(|| forward(&mut stdout));
+        forward(&mut stderr);
+    });
+    let status = child.wait().map_err(GoupError::io("run", src.join(MAKE)))?;
+    progress.phase("build", start.elapsed());
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(GoupError::BuildFailed {
+            version,
+            log: log.to_path_buf(),
+        })
+    }
+}
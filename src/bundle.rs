@@ -0,0 +1,206 @@
+//! Relocatable bundles of installed versions, for moving them to machines that can't reach
+//! any mirror
+//!
+//! A bundle is a tar archive compressed with zstd, holding a manifest followed by the
+//! installation itself. Bundles compressed with gzip, as goup wrote them at first, can still
+//! be read.
+
+use crate::error::GoupError;
+use crate::provenance::Provenance;
+use crate::remote;
+use crate::version::GoVersion;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek};
+use std::path::{Component, Path};
+use tar::{Archive, Builder, EntryType, Header};
+
+/// The version of the bundle format written by this version of goup. Bundles in any other
+/// format are refused, rather than risk unpacking them wrongly.
+pub const FORMAT: u32 = 1;
+
+/// The name of the manifest, which is always the first entry in a bundle
+pub const MANIFEST_NAME: &str = "goup-bundle.json";
+
+/// A description of a bundle's contents
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub format: u32,
+    pub version: GoVersion,
+    pub os: String,
+    pub arch: String,
+    /// The SHA256 digest of every regular file in the bundle, by its path within the bundle
+    pub files: BTreeMap<String, String>,
+    /// Where the bundled installation originally came from, if that was recorded
+    pub provenance: Option<Provenance>,
+}
+
+/// The platform that a bundle made on this machine is for, as Go names it
+fn platform() -> Result<(&'static str, &'static str), GoupError> {
+    let arch = remote::arch().ok_or(GoupError::Unsupported {
+        os: env::consts::OS,
+        arch: env::consts::ARCH,
+    })?;
+    Ok((env::consts::OS, arch))
+}
+
+/// Write the Go installation at `goroot` into a bundle at `out`
+pub fn write(
+    goroot: &Path,
+    version: GoVersion,
+    provenance: Option<Provenance>,
+    out: &Path,
+) -> Result<(), GoupError> {
+    let (os, arch) = platform()?;
+    let mut files = BTreeMap::new();
+    hash_tree(goroot, Path::new("go"), &mut files)?;
+    let manifest = Manifest {
+        format: FORMAT,
+        version,
+        os: os.to_string(),
+        arch: arch.to_string(),
+        files,
+        provenance,
+    };
+
+    let file = File::create(out).map_err(GoupError::io("create", out))?;
+    let encoder = zstd::Encoder::new(file, zstd::DEFAULT_COMPRESSION_LEVEL)
+        .map_err(GoupError::io("write", out))?;
+    let mut builder = Builder::new(encoder);
+    builder.follow_symlinks(false);
+    let json = serde_json::to_vec_pretty(&manifest).expect("a manifest can always be serialized");
+    let mut header = Header::new_gnu();
+    header.set_size(json.len() as u64);
+    header.set_mode(0o644);
+    header.set_cksum();
+    let res = builder
+        .append_data(&mut header, MANIFEST_NAME, json.as_slice())
+        .and_then(|()| builder.append_dir_all("go", goroot))
+        .and_then(|()| builder.into_inner()?.finish())
+        .map(drop);
+    res.map_err(GoupError::io("write", out))
+}
+
+/// Record the digest of every regular file within `dir`, named as if `dir` were at `name`
+fn hash_tree(
+    dir: &Path,
+    name: &Path,
+    files: &mut BTreeMap<String, String>,
+) -> Result<(), GoupError> {
+    for entry in fs::read_dir(dir).map_err(GoupError::io("read", dir))? {
+        let entry = entry.map_err(GoupError::io("read", dir))?;
+        let path = entry.path();
+        let kind = entry.file_type().map_err(GoupError::io("read", &path))?;
+        let name = name.join(entry.file_name());
+        if kind.is_dir() {
+            hash_tree(&path, &name, files)?;
+        } else if kind.is_file() {
+            let mut file = File::open(&path).map_err(GoupError::io("read", &path))?;
+            let digest = sha256(&mut file).map_err(GoupError::io("read", &path))?;
+            files.insert(bundle_path(&name), digest);
+        }
+    }
+    Ok(())
+}
+
+/// Unpack the bundle at `path` into `target`, checking that it is for this platform and that
+/// every file matches the manifest. On failure, `target` may be left partly unpacked.
+pub fn read(path: &Path, target: &Path) -> Result<Manifest, GoupError> {
+    let invalid = |reason: &str| GoupError::InvalidBundle {
+        path: path.into(),
+        reason: reason.to_string(),
+    };
+    let mut archive = Archive::new(decompress(path)?);
+    let mut entries = archive.entries().map_err(GoupError::io("read", path))?;
+
+    let mut first = match entries.next() {
+        Some(entry) => entry.map_err(GoupError::io("read", path))?,
+        None => return Err(invalid("it is empty")),
+    };
+    if first.path().ok().as_deref() != Some(Path::new(MANIFEST_NAME)) {
+        return Err(invalid("it has no manifest"));
+    }
+    let mut json = Vec::new();
+    first
+        .read_to_end(&mut json)
+        .map_err(GoupError::io("read", path))?;
+    let manifest: Manifest =
+        serde_json::from_slice(&json).map_err(|_| invalid("its manifest can't be parsed"))?;
+    if manifest.format != FORMAT {
+        return Err(invalid(&format!(
+            "it uses format {}, but this version of goup only reads format {}",
+            manifest.format, FORMAT
+        )));
+    }
+    let (os, arch) = platform()?;
+    if (manifest.os.as_str(), manifest.arch.as_str()) != (os, arch) {
+        return Err(GoupError::BundlePlatformMismatch {
+            bundle: format!("{}/{}", manifest.os, manifest.arch),
+            host: format!("{}/{}", os, arch),
+        });
+    }
+
+    fs::create_dir_all(target).map_err(GoupError::io("create", target))?;
+    let mut remaining = manifest.files.clone();
+    for entry in entries {
+        let mut entry = entry.map_err(GoupError::io("read", path))?;
+        let name = entry
+            .path()
+            .map_err(GoupError::io("read", path))?
+            .into_owned();
+        if !name.starts_with("go") || name.components().any(|c| c == Component::ParentDir) {
+            return Err(invalid(&format!("it contains {}", name.display())));
+        }
+        entry
+            .unpack_in(target)
+            .map_err(GoupError::io("unpack bundle into", target))?;
+        if entry.header().entry_type() == EntryType::Regular {
+            let unpacked = target.join(&name);
+            let key = bundle_path(&name);
+            let expected = remaining
+                .remove(&key)
+                .ok_or_else(|| invalid(&format!("{} isn't in its manifest", key)))?;
+            let mut file = File::open(&unpacked).map_err(GoupError::io("read", &unpacked))?;
+            let actual = sha256(&mut file).map_err(GoupError::io("read", &unpacked))?;
+            if actual != expected {
+                return Err(invalid(&format!("{} is corrupt", key)));
+            }
+        }
+    }
+    if let Some(missing) = remaining.keys().next() {
+        return Err(invalid(&format!("{} is missing", missing)));
+    }
+    Ok(manifest)
+}
+
+/// The tar archive within the bundle at `path`, decompressed with zstd, or with gzip for the
+/// bundles that earlier versions of goup wrote
+fn decompress(path: &Path) -> Result<Box<dyn Read>, GoupError> {
+    let mut file = File::open(path).map_err(GoupError::io("read", path))?;
+    let mut magic = [0; 2];
+    let gzip = file.read_exact(&mut magic).is_ok() && magic == [0x1f, 0x8b];
+    file.rewind().map_err(GoupError::io("read", path))?;
+    if gzip {
+        Ok(Box::new(GzDecoder::new(file)))
+    } else {
+        let decoder = zstd::Decoder::new(file).map_err(GoupError::io("read", path))?;
+        Ok(Box::new(decoder))
+    }
+}
+
+/// The hex-encoded SHA256 digest of everything read from `reader`
+pub(crate) fn sha256(reader: &mut impl Read) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(reader, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A path within a bundle, always with forward slashes
+fn bundle_path(path: &Path) -> String {
+    let parts: Vec<_> = path.iter().map(|c| c.to_string_lossy()).collect();
+    parts.join("/")
+}
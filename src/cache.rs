@@ -0,0 +1,280 @@
+//! An on-disk cache of HTTP responses, revalidated with conditional requests, and of
+//! downloaded archives
+
+use crate::error::GoupError;
+use crate::net;
+use crate::version::GoVersion;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use ureq::Agent;
+
+/// How a [`Goup`](crate::Goup) handle may use its cache of responses from go.dev
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CacheMode {
+    /// Reuse recent responses, revalidate older ones, and fall back to them when offline
+    #[default]
+    Normal,
+    /// Fetch everything afresh, but save the responses for next time
+    Refresh,
+    /// Neither read nor write the cache
+    Off,
+}
+
+/// A response body saved along with the validators needed to check it is still current
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    url: String,
+    etag: Option<String>,
+    last_modified: Option<String>,
+    /// Seconds since the Unix epoch at which the server last confirmed the body
+    #[serde(default)]
+    fetched: u64,
+    /// The status and type of the response the body came in, for describing one that can't be
+    /// used
+    #[serde(default)]
+    status: Option<u16>,
+    #[serde(default)]
+    content_type: Option<String>,
+    body: String,
+}
+
+impl Entry {
+    fn fetched(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.fetched)
+    }
+
+    fn into_cached(self, reused: bool, offline: bool) -> Cached {
+        Cached {
+            fetched: self.fetched(),
+            body: self.body,
+            status: self.status,
+            content_type: self.content_type,
+            reused,
+            offline,
+        }
+    }
+}
+
+/// A body retrieved through an [`HttpCache`]
+pub(crate) struct Cached {
+    pub body: String,
+    /// The status of the response the body came in, where it was recorded
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    /// When the server last confirmed this body was current
+    pub fetched: SystemTime,
+    /// Whether the body was read from the cache, rather than sent by the server just now
+    pub reused: bool,
+    /// Whether the server couldn't be reached, so the body may be out of date
+    pub offline: bool,
+}
+
+/// A single cached response, stored as a JSON file
+pub(crate) struct HttpCache {
+    path: PathBuf,
+    mode: CacheMode,
+}
+
+impl HttpCache {
+    pub fn new(path: PathBuf, mode: CacheMode) -> Self {
+        Self { path, mode }
+    }
+
+    /// Fetch the body at `url`. A cached body younger than `max_age` is used without asking
+    /// the server; otherwise it is reused if the server reports it unchanged, or can't be
+    /// reached at all. `agent` is an error when goup is offline, in which case any cached
+    /// body will do.
+    pub fn get(
+        &self,
+        agent: Result<&Agent, GoupError>,
+        url: &str,
+        max_age: Option<Duration>,
+    ) -> Result<Cached, GoupError> {
+        let mut cached = match self.mode {
+            CacheMode::Normal => self.load().filter(|e| e.url == url),
+            CacheMode::Refresh | CacheMode::Off => None,
+        };
+
+        if let (Some(entry), Some(max_age)) = (&cached, max_age) {
+            let age = entry.fetched().elapsed().unwrap_or_default();
+            if age <= max_age {
+                return Ok(Cached {
+                    fetched: entry.fetched(),
+                    body: entry.body.clone(),
+                    status: entry.status,
+                    content_type: entry.content_type.clone(),
+                    reused: true,
+                    offline: false,
+                });
+            }
+        }
+
+        let agent = match (agent, cached) {
+            (Ok(agent), c) => {
+                cached = c;
+                agent
+            }
+            (Err(_), Some(entry)) => return Ok(entry.into_cached(true, true)),
+            (Err(e), None) => return Err(e),
+        };
+
+        let mut request = agent.get(url);
+        if let Some(entry) = &cached {
+            if let Some(etag) = &entry.etag {
+                request = request.set("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &entry.last_modified {
+                request = request.set("If-Modified-Since", last_modified);
+            }
+        }
+
+        let (response, cached) = match (request.call(), cached) {
+            (Ok(response), cached) => (response, cached),
+            (Err(e), Some(entry)) if net::is_unreachable(&e) => {
+                return Ok(entry.into_cached(true, true))
+            }
+            (Err(e), _) => return Err(GoupError::network(url, e)),
+        };
+
+        let now = SystemTime::now();
+        let reused = response.status() == 304 && cached.is_some();
+        let mut entry = match (response.status(), cached) {
+            (304, Some(entry)) => entry,
+            _ => Entry {
+                url: url.to_string(),
+                etag: response.header("ETag").map(str::to_string),
+                last_modified: response.header("Last-Modified").map(str::to_string),
+                fetched: 0,
+                status: Some(response.status()),
+                content_type: response.header("Content-Type").map(str::to_string),
+                body: response
+                    .into_string()
+                    .map_err(|e| GoupError::network(url, e))?,
+            },
+        };
+        entry.fetched = now.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+        self.store(&entry);
+        Ok(entry.into_cached(reused, false))
+    }
+
+    /// Drop the cached body for `url`, once it turns out not to be usable, so that it isn't
+    /// reused until it expires
+    pub fn forget(&self, url: &str) {
+        if self.load().is_some_and(|e| e.url == url) {
+            let _ = fs::remove_file(&self.path);
+        }
+    }
+
+    /// The cached entry, if there is one that can be read. A corrupt cache is as good as none.
+    fn load(&self) -> Option<Entry> {
+        let text = fs::read_to_string(&self.path).ok()?;
+        serde_json::from_str(&text).ok()
+    }
+
+    /// Save the entry, ignoring failures, since the cache is only an optimization
+    fn store(&self, entry: &Entry) {
+        if self.mode == CacheMode::Off {
+            return;
+        }
+        if let Some(dir) = self.path.parent() {
+            let _ = fs::create_dir_all(dir);
+        }
+        if let Ok(text) = serde_json::to_string(entry) {
+            let _ = write_atomic(&self.path, &text);
+        }
+    }
+}
+
+/// Write the file alongside its final location and rename it into place, so that an
+/// interrupted write never leaves a truncated cache behind
+fn write_atomic(path: &Path, contents: &str) -> std::io::Result<()> {
+    let staging = path.with_extension("tmp");
+    fs::write(&staging, contents)?;
+    fs::rename(&staging, path)
+}
+
+/// The subdirectory of the cache holding downloaded archives, so that reinstalling a version
+/// doesn't need the network
+pub(crate) const ARCHIVES: &str = "archives";
+
+/// A file in goup's cache, as listed by [`Goup::cache_entries`](crate::Goup::cache_entries)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    /// The version of Go that the file is an archive of. Cached responses from go.dev have
+    /// no version.
+    pub version: Option<GoVersion>,
+    /// The size of the file in bytes
+    pub size: u64,
+    /// When the file was last written or reused
+    pub used: SystemTime,
+}
+
+impl CacheEntry {
+    /// How long ago the file was last written or reused
+    pub fn age(&self) -> Duration {
+        self.used.elapsed().unwrap_or_default()
+    }
+}
+
+/// The outcome of [`Goup::clean_cache`](crate::Goup::clean_cache)
+#[derive(Debug, Default)]
+pub struct CacheCleanReport {
+    /// The files that were deleted
+    pub removed: Vec<CacheEntry>,
+    /// Archives left alone because another goup process is installing from them
+    pub in_use: Vec<CacheEntry>,
+}
+
+/// Every file within the cache directory, least recently used first. A missing cache is empty.
+pub(crate) fn entries(dir: &Path) -> Result<Vec<CacheEntry>, GoupError> {
+    let mut entries = Vec::new();
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        let listing = match fs::read_dir(&dir) {
+            Ok(listing) => listing,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(GoupError::io("read", dir)(e)),
+        };
+        for entry in listing {
+            let entry = entry.map_err(GoupError::io("read", &dir))?;
+            let path = entry.path();
+            let metadata = entry.metadata().map_err(GoupError::io("read", &path))?;
+            if metadata.is_dir() {
+                pending.push(path);
+                continue;
+            }
+            let version = if dir.file_name().is_some_and(|name| name == ARCHIVES) {
+                entry.file_name().to_str().and_then(archive_version)
+            } else {
+                None
+            };
+            entries.push(CacheEntry {
+                version,
+                size: metadata.len(),
+                used: metadata.modified().unwrap_or(UNIX_EPOCH),
+                path,
+            });
+        }
+    }
+    entries.sort_by(|a, b| a.used.cmp(&b.used).then_with(|| a.path.cmp(&b.path)));
+    Ok(entries)
+}
+
+/// Mark a cached archive as just used, so that it is the last to be evicted
+pub(crate) fn touch(path: &Path) {
+    if let Ok(file) = fs::File::options().append(true).open(path) {
+        let _ = file.set_modified(SystemTime::now());
+    }
+}
+
+/// The version an archive is of, from its name as go.dev gives it, like
+/// `go1.22.1.linux-amd64.tar.gz`: the longest run of its dot-separated parts that names one
+fn archive_version(name: &str) -> Option<GoVersion> {
+    name.match_indices('.')
+        .rev()
+        .find_map(|(end, _)| name[..end].parse().ok())
+}
@@ -0,0 +1,200 @@
+//! A database of the official checksums of Go's archives, exported on a machine that can reach
+//! go.dev and imported on ones that can't, so that what they install is checked against values
+//! that came from go.dev rather than from whoever supplied the archive
+
+use crate::error::GoupError;
+use crate::remote::{FileInfo, Release};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The version of the database format written by this version of goup. Databases in a newer
+/// format are refused, rather than risk misreading which checksums they vouch for.
+pub const FORMAT: u32 = 1;
+
+/// The official checksums of archives, by the archive's file name
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Database {
+    pub format: u32,
+    pub archives: BTreeMap<String, Entry>,
+}
+
+/// What was recorded about one archive
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    pub version: String,
+    pub os: String,
+    pub arch: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub size: u64,
+    /// The index the checksum was read from
+    pub source: String,
+    /// Seconds since the Unix epoch at which it was read
+    pub exported_at: u64,
+    /// The file it was imported from, once it has been imported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub imported_from: Option<String>,
+}
+
+impl Entry {
+    /// Where the checksum came from, for messages about it
+    pub fn describe(&self) -> String {
+        match &self.imported_from {
+            Some(file) => format!("{}, by way of {}", self.source, file),
+            None => self.source.clone(),
+        }
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self {
+            format: FORMAT,
+            archives: BTreeMap::new(),
+        }
+    }
+}
+
+impl Database {
+    /// A database of every archive in `releases` that `select` keeps, read from the index at
+    /// `source` just now
+    pub fn export(
+        source: &str,
+        releases: &[Release],
+        select: impl Fn(&Release, &FileInfo) -> bool,
+    ) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        let exported_at = now.unwrap_or_default().as_secs();
+        let archives = releases
+            .iter()
+            .flat_map(|release| release.files.iter().map(move |file| (release, file)))
+            .filter(|&(release, file)| file.is_archive() && select(release, file))
+            .map(|(release, file)| {
+                let entry = Entry {
+                    version: release.version.to_string(),
+                    os: file.os.clone(),
+                    arch: file.arch.clone(),
+                    sha256: file.sha256.to_lowercase(),
+                    size: file.size,
+                    source: source.to_string(),
+                    exported_at,
+                    imported_from: None,
+                };
+                (file.filename.clone(), entry)
+            })
+            .collect();
+        Self {
+            format: FORMAT,
+            archives,
+        }
+    }
+
+    /// Read the database at `path`. A database that doesn't exist yet is empty.
+    pub fn load(path: &Path) -> Result<Self, GoupError> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(path, &text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(GoupError::io("read", path)(e)),
+        }
+    }
+
+    /// Read a database exported by `goup checksums export`
+    pub fn parse(path: &Path, text: &str) -> Result<Self, GoupError> {
+        let invalid = |reason: String| GoupError::InvalidChecksums {
+            path: path.to_path_buf(),
+            reason,
+        };
+        let format = serde_json::from_str::<serde_json::Value>(text)
+            .map_err(|e| invalid(e.to_string()))?["format"]
+            .as_u64();
+        match format {
+            Some(format) if format == u64::from(FORMAT) => {}
+            Some(format) => {
+                return Err(invalid(format!(
+                    "it uses format {}, but this version of goup only reads format {}",
+                    format, FORMAT
+                )))
+            }
+            None => return Err(invalid("it doesn't say which format it uses".to_string())),
+        }
+        serde_json::from_str(text).map_err(|e| invalid(e.to_string()))
+    }
+
+    pub fn store(&self, path: &Path) -> Result<(), GoupError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        let text = serde_json::to_string_pretty(self).expect("a database is always serializable");
+        fs::write(path, text).map_err(GoupError::io("write", path))
+    }
+
+    /// Add the entries of `other`, imported from `from`, returning how many were new. An
+    /// archive recorded in both with different checksums is refused, leaving `self` unchanged.
+    pub fn merge(&mut self, other: Database, from: &Path) -> Result<usize, GoupError> {
+        for (file, entry) in &other.archives {
+            match self.archives.get(file) {
+                Some(ours) if !ours.sha256.eq_ignore_ascii_case(&entry.sha256) => {
+                    return Err(GoupError::ChecksumConflict {
+                        file: file.clone(),
+                        recorded: format!("{} from {}", ours.sha256, ours.describe()),
+                        imported: format!("{} from {}", entry.sha256, entry.source),
+                        path: from.to_path_buf(),
+                    });
+                }
+                _ => {}
+            }
+        }
+        let mut added = 0;
+        for (file, mut entry) in other.archives {
+            if self.archives.contains_key(&file) {
+                continue;
+            }
+            entry
+                .imported_from
+                .get_or_insert_with(|| from.display().to_string());
+            self.archives.insert(file, entry);
+            added += 1;
+        }
+        Ok(added)
+    }
+
+    /// `file` as the index describes it, but with the checksum recorded here in place of the
+    /// index's, if the archive is recorded here at all
+    pub fn official(&self, file: &FileInfo) -> Option<FileInfo> {
+        let entry = self.archives.get(&file.filename)?;
+        Some(FileInfo {
+            sha256: entry.sha256.clone(),
+            ..file.clone()
+        })
+    }
+
+    /// `e`, unless it says that an archive recorded here didn't have the checksum expected of
+    /// it, in which case the error naming where the official checksum came from
+    pub fn explain(&self, e: GoupError) -> GoupError {
+        if let GoupError::ChecksumMismatch { file, actual, .. } = &e {
+            if let Err(unofficial) = self.verify(file, actual) {
+                return unofficial;
+            }
+        }
+        e
+    }
+
+    /// Check `actual`, the digest of the archive `file`, against the official one, if it is
+    /// recorded here
+    pub fn verify(&self, file: &str, actual: &str) -> Result<(), GoupError> {
+        match self.archives.get(file) {
+            Some(entry) if !entry.sha256.eq_ignore_ascii_case(actual) => {
+                Err(GoupError::UnofficialChecksum {
+                    file: file.to_string(),
+                    expected: entry.sha256.clone(),
+                    recorded: entry.describe(),
+                    actual: actual.to_lowercase(),
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Downloading an archive over several connections at once, each fetching a range of it, for
+//! links where a single connection can't use all of the bandwidth
+
+use crate::config::Config;
+use crate::error::GoupError;
+use crate::progress::Progress;
+use std::collections::BTreeSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use ureq::Agent;
+
+/// The smallest range requested at once, so that small archives aren't split needlessly
+const MIN_CHUNK_SIZE: u64 = 64 * 1024;
+
+/// The largest range requested at once, so that a failed range doesn't lose much
+const MAX_CHUNK_SIZE: u64 = 8 * 1024 * 1024;
+
+/// The size of the ranges to request for an archive of `size` bytes over `connections`
+/// connections: small enough that each connection fetches a few, so that a slow one doesn't
+/// hold up the end
+fn chunk_size(size: u64, connections: usize) -> u64 {
+    (size / (connections as u64 * 4)).clamp(MIN_CHUNK_SIZE, MAX_CHUNK_SIZE)
+}
+
+/// The file recording which chunks of `dest` have arrived, so that an interrupted download
+/// can carry on where it left off. Its first line gives the size of the chunks, since the
+/// same index means a different range of the file with a different number of connections.
+fn done_file(dest: &Path) -> PathBuf {
+    let mut name = dest.as_os_str().to_owned();
+    name.push(".done");
+    PathBuf::from(name)
+}
+
+/// Download the `size` bytes at `url` into `dest` with up to `connections` ranged requests
+/// at once. Chunks that arrived during an earlier attempt are kept.
+///
+/// Returns `false`, having written nothing, if the server doesn't serve ranges of the file,
+/// so that it can be downloaded in one piece instead. The caller must verify the result, since
+/// the chunks are only checked for their lengths.
+pub(crate) fn download(
+    agent: &Agent,
+    config: &Config,
+    url: &str,
+    size: u64,
+    dest: &Path,
+    connections: usize,
+    progress: &dyn Progress,
+) -> Result<bool, GoupError> {
+    if !serves_ranges(agent, url, size)? {
+        return Ok(false);
+    }
+
+    let chunk = chunk_size(size, connections);
+    let header = format!("chunk {}", chunk);
+    let done_path = done_file(dest);
+    let mut done = BTreeSet::new();
+    if fs::metadata(dest).is_ok_and(|m| m.len() == size) {
+        if let Ok(text) = fs::read_to_string(&done_path) {
+            let mut lines = text.lines();
+            // Chunks recorded at another size are fetched again, as their ranges differ
+            if lines.next() == Some(header.as_str()) {
+                done.extend(lines.filter_map(|l| l.parse::<u64>().ok()));
+            }
+        }
+    } else {
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        let file = File::create(dest).map_err(GoupError::io("create", dest))?;
+        file.set_len(size).map_err(GoupError::io("write", dest))?;
+    }
+    if done.is_empty() {
+        fs::write(&done_path, format!("{}\n", header))
+            .map_err(GoupError::io("write", &done_path))?;
+    }
+    let record = OpenOptions::new()
+        .append(true)
+        .open(&done_path)
+        .map_err(GoupError::io("write", &done_path))?;
+
+    let ranges: Vec<_> = (0..size.div_ceil(chunk))
+        .filter(|i| !done.contains(i))
+        .map(|i| (i, i * chunk, ((i + 1) * chunk).min(size)))
+        .collect();
+    progress.start(size);
+    progress.advance(
+        size - ranges
+            .iter()
+            .map(|(_, start, end)| end - start)
+            .sum::<u64>(),
+    );
+
+    let queue = Mutex::new(ranges.into_iter().rev().collect::<Vec<_>>());
+    let record = Mutex::new(record);
+    let failure = Mutex::new(None);
+    let failed = AtomicBool::new(false);
+    thread::scope(|s| {
+        for _ in 0..connections {
+            s.spawn(|| {
+                // After a failure, no more chunks are started, but those underway are finished
+                while !failed.load(Ordering::Relaxed) {
+                    let Some((index, start, end)) = queue.lock().unwrap().pop() else {
+                        break;
+                    };
+                    match fetch(agent, config, url, dest, start, end, progress) {
+                        Ok(()) => {
+                            // Losing the record only means fetching the chunk again
+                            let _ = writeln!(record.lock().unwrap(), "{}", index);
+                        }
+                        Err(e) => {
+                            failed.store(true, Ordering::Relaxed);
+                            failure.lock().unwrap().get_or_insert(e);
+                        }
+                    }
+                }
+            });
+        }
+    });
+    let failure = failure.into_inner().unwrap();
+    progress.finish(failure.is_none());
+    match failure {
+        Some(e) => Err(e),
+        None => {
+            let _ = fs::remove_file(&done_path);
+            Ok(true)
+        }
+    }
+}
+
+/// Whether the server answers a request for the first byte at `url` with just that byte, out
+/// of a file of the expected size
+fn serves_ranges(agent: &Agent, url: &str, size: u64) -> Result<bool, GoupError> {
+    let response = agent
+        .get(url)
+        .set("Range", "bytes=0-0")
+        .call()
+        .map_err(|e| GoupError::network(url, e))?;
+    let total = response
+        .header("Content-Range")
+        .and_then(|range| range.rsplit_once('/'))
+        .and_then(|(_, total)| total.parse::<u64>().ok());
+    Ok(response.status() == 206 && total == Some(size))
+}
+
+/// Fetch the bytes from `start` up to `end` at `url` into the same place in `dest`, and sync
+/// them to disk
+fn fetch(
+    agent: &Agent,
+    config: &Config,
+    url: &str,
+    dest: &Path,
+    start: u64,
+    end: u64,
+    progress: &dyn Progress,
+) -> Result<(), GoupError> {
+    let response = agent
+        .get(url)
+        .set("Range", &format!("bytes={}-{}", start, end - 1))
+        .call()
+        .map_err(|e| GoupError::network(url, e))?;
+    if response.status() != 206 {
+        return Err(GoupError::network(
+            url,
+            io::Error::other(format!(
+                "expected part of the file, but the server sent status {}",
+                response.status()
+            )),
+        ));
+    }
+
+    let mut file = OpenOptions::new()
+        .write(true)
+        .open(dest)
+        .map_err(GoupError::io("open", dest))?;
+    file.seek(SeekFrom::Start(start))
+        .map_err(GoupError::io("write", dest))?;
+    let mut reader = response.into_reader().take(end - start);
+    let mut buf = vec![0; 64 * 1024];
+    let mut received = 0;
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                return Err(GoupError::DownloadStalled {
+                    url: url.to_string(),
+                    seconds: config.stall_timeout().as_secs(),
+                })
+            }
+            Err(e) => return Err(GoupError::network(url, e)),
+        };
+        file.write_all(&buf[..n])
+            .map_err(GoupError::io("write", dest))?;
+        received += n as u64;
+        progress.advance(n as u64);
+    }
+    if received != end - start {
+        return Err(GoupError::SizeMismatch {
+            url: url.to_string(),
+            expected: end - start,
+            received,
+        });
+    }
+    // The chunk is only recorded as done once it is sure to be on disk
+    file.sync_data().map_err(GoupError::io("write", dest))
+}
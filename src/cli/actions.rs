@@ -0,0 +1,71 @@
+//! Setting up Go for the later steps of a GitHub Actions job, through the files that Actions
+//! reads extra PATH entries and environment variables from, for `goup env --github-actions`
+
+use anyhow::{Context, Result};
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+
+/// What later steps of the job need to run Go
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setup {
+    /// Directories to add to PATH
+    pub path: Vec<PathBuf>,
+    /// Environment variables to set
+    pub env: Vec<(String, String)>,
+}
+
+/// One of the files Actions reads after each step, named by an environment variable
+struct ActionsFile {
+    var: &'static str,
+    lines: Vec<String>,
+}
+
+/// Append the setup to the files named by `$GITHUB_PATH` and `$GITHUB_ENV`. Where either
+/// variable isn't set, nothing is created; the equivalent shell commands are returned instead,
+/// to be printed.
+pub fn write(setup: &Setup) -> Result<Vec<String>> {
+    let files = [
+        ActionsFile {
+            var: "GITHUB_PATH",
+            lines: setup.path.iter().map(|p| p.display().to_string()).collect(),
+        },
+        ActionsFile {
+            var: "GITHUB_ENV",
+            lines: setup
+                .env
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect(),
+        },
+    ];
+
+    let mut commands = Vec::new();
+    for file in files {
+        let Some(target) = env::var_os(file.var).filter(|v| !v.is_empty()) else {
+            commands.extend(
+                file.lines
+                    .iter()
+                    .map(|line| format!("echo \"{}\" >> \"${}\"", line, file.var)),
+            );
+            continue;
+        };
+        let target = PathBuf::from(target);
+        let mut contents = file.lines.join("\n");
+        contents.push('\n');
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&target)
+            .and_then(|mut f| f.write_all(contents.as_bytes()))
+            .with_context(|| {
+                format!(
+                    "Unable to append to {}, the file named by ${}",
+                    target.display(),
+                    file.var
+                )
+            })?;
+    }
+    Ok(commands)
+}
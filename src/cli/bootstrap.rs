@@ -0,0 +1,185 @@
+//! Scripts that install a release of Go on machines that don't have goup, for
+//! `goup bootstrap-script`
+
+use goup::{FileInfo, GoVersion};
+
+/// The language a bootstrap script is written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Shell {
+    /// A POSIX shell script, needing curl or wget, and sha256sum or shasum
+    Sh,
+    /// A PowerShell script
+    Powershell,
+}
+
+impl Shell {
+    /// Where the script puts Go unless told otherwise
+    pub fn default_prefix(self) -> &'static str {
+        match self {
+            Shell::Sh => "/usr/local",
+            Shell::Powershell => "C:\\Program Files",
+        }
+    }
+}
+
+/// A script that downloads `file`, the archive of `version`, from `url`, checks it against the
+/// checksum in `file`, and unpacks it into `go` within the prefix given as its argument, or
+/// `prefix` by default
+pub fn script(
+    shell: Shell,
+    version: GoVersion,
+    file: &FileInfo,
+    url: &str,
+    prefix: &str,
+) -> String {
+    match shell {
+        Shell::Sh => sh(version, file, url, prefix),
+        Shell::Powershell => powershell(version, file, url, prefix),
+    }
+}
+
+fn sh(version: GoVersion, file: &FileInfo, url: &str, prefix: &str) -> String {
+    let unpack = if file.filename.ends_with(".zip") {
+        "unzip -q \"$tmp/$archive\" -d \"$prefix\""
+    } else {
+        "tar -C \"$prefix\" -xzf \"$tmp/$archive\""
+    };
+    format!(
+        r#"#!/bin/sh
+# Installs {version} for {os}/{arch}, as generated by `goup bootstrap-script`.
+# Usage: sh {script} [PREFIX]    (Go is put in PREFIX/go, by default {prefix_comment}/go)
+set -eu
+
+url={url}
+archive={filename}
+sha256={sha256}
+default_prefix={prefix}
+prefix="${{1:-$default_prefix}}"
+
+if [ -e "$prefix/go" ]; then
+  echo "$prefix/go already exists; remove it first to install {version} there" >&2
+  exit 1
+fi
+
+tmp="$(mktemp -d)"
+trap 'rm -rf "$tmp"' EXIT
+
+echo "Downloading $url" >&2
+if command -v curl >/dev/null 2>&1; then
+  curl -fsSL -o "$tmp/$archive" "$url"
+elif command -v wget >/dev/null 2>&1; then
+  wget -q -O "$tmp/$archive" "$url"
+else
+  echo "Neither curl nor wget is installed" >&2
+  exit 1
+fi
+
+if command -v sha256sum >/dev/null 2>&1; then
+  actual="$(sha256sum "$tmp/$archive" | cut -d ' ' -f 1)"
+elif command -v shasum >/dev/null 2>&1; then
+  actual="$(shasum -a 256 "$tmp/$archive" | cut -d ' ' -f 1)"
+else
+  echo "Neither sha256sum nor shasum is installed, so the download can't be checked" >&2
+  exit 1
+fi
+if [ "$actual" != "$sha256" ]; then
+  echo "The download's checksum is $actual, not $sha256 as published; not installing it" >&2
+  exit 1
+fi
+
+mkdir -p "$prefix"
+{unpack}
+echo "Installed {version} in $prefix/go. Add it to PATH with:" >&2
+echo "export PATH=\"$prefix/go/bin:\$PATH\""
+"#,
+        version = version,
+        os = file.os,
+        arch = file.arch,
+        script = script_name(Shell::Sh, version, file),
+        prefix_comment = prefix,
+        url = sh_quote(url),
+        filename = sh_quote(&file.filename),
+        sha256 = sh_quote(&file.sha256),
+        prefix = sh_quote(prefix),
+        unpack = unpack,
+    )
+}
+
+fn powershell(version: GoVersion, file: &FileInfo, url: &str, prefix: &str) -> String {
+    let unpack = if file.filename.ends_with(".zip") {
+        "Expand-Archive -Path $archive -DestinationPath $Prefix"
+    } else {
+        "tar -C $Prefix -xzf $archive"
+    };
+    format!(
+        r#"# Installs {version} for {os}/{arch}, as generated by `goup bootstrap-script`.
+# Usage: .\{script} [-Prefix PREFIX]    (Go is put in PREFIX\go, by default {prefix_comment}\go)
+param([string]$Prefix = {prefix})
+$ErrorActionPreference = 'Stop'
+$ProgressPreference = 'SilentlyContinue'
+
+$url = {url}
+$sha256 = {sha256}
+$goroot = Join-Path $Prefix 'go'
+
+if (Test-Path $goroot) {{
+  Write-Error "$goroot already exists; remove it first to install {version} there"
+  exit 1
+}}
+
+$tmp = Join-Path ([System.IO.Path]::GetTempPath()) ([System.IO.Path]::GetRandomFileName())
+New-Item -ItemType Directory -Path $tmp | Out-Null
+try {{
+  $archive = Join-Path $tmp {filename}
+  Write-Host "Downloading $url"
+  Invoke-WebRequest -Uri $url -OutFile $archive -UseBasicParsing
+
+  $actual = (Get-FileHash -Algorithm SHA256 -Path $archive).Hash.ToLower()
+  if ($actual -ne $sha256) {{
+    Write-Error "The download's checksum is $actual, not $sha256 as published; not installing it"
+    exit 1
+  }}
+
+  New-Item -ItemType Directory -Force -Path $Prefix | Out-Null
+  {unpack}
+}} finally {{
+  Remove-Item -Recurse -Force $tmp
+}}
+
+Write-Host "Installed {version} in $goroot. Add it to PATH with:"
+Write-Output "`$env:Path = `"$goroot\bin;`$env:Path`""
+"#,
+        version = version,
+        os = file.os,
+        arch = file.arch,
+        script = script_name(Shell::Powershell, version, file),
+        prefix_comment = prefix,
+        url = ps_quote(url),
+        filename = ps_quote(&file.filename),
+        sha256 = ps_quote(&file.sha256),
+        prefix = ps_quote(prefix),
+        unpack = unpack,
+    )
+}
+
+/// What the script is suggested to be saved as, in its usage line
+fn script_name(shell: Shell, version: GoVersion, file: &FileInfo) -> String {
+    let extension = match shell {
+        Shell::Sh => "sh",
+        Shell::Powershell => "ps1",
+    };
+    format!(
+        "install-{}-{}-{}.{}",
+        version, file.os, file.arch, extension
+    )
+}
+
+/// `text` as a single word in a POSIX shell, taken literally
+fn sh_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// `text` as a literal string in PowerShell
+fn ps_quote(text: &str) -> String {
+    format!("'{}'", text.replace('\'', "''"))
+}
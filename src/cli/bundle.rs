@@ -0,0 +1,134 @@
+//! Moving versions and checksums between machines, for `goup export`, `goup import`,
+//! `goup import-from`, `goup checksums`, and `goup debug-bundle`
+
+use super::debug;
+use crate::ImportSource;
+use anyhow::{bail, Context, Result};
+use console::style;
+use goup::managers::{self, Manager};
+use goup::version::VersionRequest;
+use goup::{GoVersion, Goup};
+use std::fs;
+use std::path::PathBuf;
+
+pub fn export(goup: &Goup, version: GoVersion, out: Option<PathBuf>) -> Result<()> {
+    let out = out.unwrap_or_else(|| format!("{}-bundle.tar.zst", version).into());
+    goup.export(version, &out)?;
+    status!("Exported {} to {}", version, out.display());
+    Ok(())
+}
+
+pub fn checksums_export(
+    goup: &Goup,
+    series: &[VersionRequest],
+    platforms: &[(String, String)],
+    out: Option<PathBuf>,
+) -> Result<()> {
+    let database = goup.export_checksums(series, platforms)?;
+    if database.archives.is_empty() {
+        bail!("No archives in the index match; check --series and --platform");
+    }
+    let json = serde_json::to_string_pretty(&database)?;
+    match out {
+        Some(out) => {
+            fs::write(&out, json).with_context(|| format!("Unable to write {}", out.display()))?;
+            status!(
+                "Wrote the checksums of {} archive(s) to {}",
+                database.archives.len(),
+                out.display()
+            );
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+pub fn checksums_import(goup: &Goup, files: &[PathBuf]) -> Result<()> {
+    let added = goup.import_checksums(files)?;
+    let total = goup.checksums()?.archives.len();
+    status!(
+        "Imported {} new checksum(s); {} archive(s) are now checked against their official \
+         checksums",
+        added,
+        total
+    );
+    Ok(())
+}
+
+pub fn debug_bundle(goup: &Goup, flags: &[&str], out: Option<PathBuf>) -> Result<()> {
+    let out = out.unwrap_or_else(|| "goup-debug.tar.gz".into());
+    let items = debug::collect(goup, flags);
+    debug::write(&items, &out)?;
+    status!("Collected:");
+    for item in &items {
+        status!("  {:<16} {}", item.name, item.description);
+    }
+    status!("Wrote {}", out.display());
+    Ok(())
+}
+
+pub fn import(goup: &Goup, bundle: PathBuf) -> Result<()> {
+    let version = goup.import(&bundle)?;
+    status!(
+        "{} installed successfully from {}",
+        version,
+        bundle.display()
+    );
+    Ok(())
+}
+
+pub fn import_from(goup: &Goup, source: ImportSource) -> Result<()> {
+    let (manager, root, options) = match source {
+        ImportSource::G { root, options } => (Some(Manager::G), root, options),
+        ImportSource::Gvm { root, options } => (Some(Manager::Gvm), root, options),
+        ImportSource::Asdf { root, options } => (Some(Manager::Asdf), root, options),
+        ImportSource::Dir { path, options } => (None, Some(path), options),
+    };
+    let root = root
+        .or_else(|| manager.and_then(|m| m.default_root()))
+        .context("Unable to find the other manager's installations; pass --root")?;
+    if !root.exists() {
+        bail!(
+            "{} does not exist; pass --root if its versions are elsewhere",
+            root.display()
+        );
+    }
+
+    let installed = goup.installed()?;
+    let mut imported = 0;
+    let mut failed = 0;
+    for found in managers::find(&root)? {
+        let (goroot, version) = match found.tree {
+            Ok(tree) => tree,
+            Err(e) => {
+                diagnostic!("Skipping {}", e);
+                continue;
+            }
+        };
+        if installed.contains(&version) {
+            diagnostic!("Skipping {}, which is already installed", version);
+            continue;
+        }
+        if options.dry_run {
+            status!("Would import {} from {}", version, found.path.display());
+            continue;
+        }
+        match goup.adopt(&goroot, version, options.move_trees) {
+            Ok(()) => {
+                status!("Imported {} from {}", version, found.path.display());
+                imported += 1;
+            }
+            Err(e) => {
+                diagnostic!("{} {}: {}", style("Failed").red(), version, e);
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        bail!("{} version(s) could not be imported", failed);
+    }
+    if imported == 0 && !options.dry_run {
+        status!("Nothing to import from {}", root.display());
+    }
+    Ok(())
+}
@@ -0,0 +1,127 @@
+//! Managing the cache of responses and archives, for `goup cache` and `goup mirror`
+
+use super::parse::format_age;
+use anyhow::Result;
+use goup::size::format_size;
+use goup::{CacheEntry, Goup};
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+pub fn list(goup: &Goup, json: bool) -> Result<()> {
+    let entries = goup.cache_entries()?;
+    if json {
+        let entries: Vec<_> = entries.iter().map(entry_json).collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        diagnostic!("The cache is empty");
+    }
+    let cache_dir = goup.cache_dir();
+    for entry in &entries {
+        let version = entry.version.map(|v| v.to_string());
+        println!(
+            "{:>10}  {:>4}  {:<10}  {}",
+            format_size(entry.size),
+            format_age(entry.age()),
+            version.as_deref().unwrap_or("-"),
+            entry
+                .path
+                .strip_prefix(&cache_dir)
+                .unwrap_or(&entry.path)
+                .display()
+        );
+    }
+    Ok(())
+}
+
+pub fn clean(
+    goup: &Goup,
+    older_than: Option<Duration>,
+    uninstalled: bool,
+    json: bool,
+) -> Result<()> {
+    let installed = if uninstalled {
+        goup.installed()?
+    } else {
+        BTreeSet::new()
+    };
+    let report = goup.clean_cache(|entry| {
+        older_than.is_none_or(|age| entry.age() >= age)
+            && (!uninstalled || entry.version.is_some_and(|v| !installed.contains(&v)))
+    })?;
+
+    let freed: u64 = report.removed.iter().map(|e| e.size).sum();
+    if json {
+        let summary = serde_json::json!({
+            "removed": report.removed.iter().map(entry_json).collect::<Vec<_>>(),
+            "in_use": report.in_use.iter().map(entry_json).collect::<Vec<_>>(),
+            "freed": freed,
+        });
+        println!("{}", serde_json::to_string_pretty(&summary)?);
+        return Ok(());
+    }
+    for entry in &report.in_use {
+        diagnostic!(
+            "Skipped {}, which another goup process is installing from",
+            entry.path.display()
+        );
+    }
+    status!(
+        "Removed {} file(s), freeing {}",
+        report.removed.len(),
+        format_size(freed)
+    );
+    Ok(())
+}
+
+pub fn size(goup: &Goup, json: bool) -> Result<()> {
+    let entries = goup.cache_entries()?;
+    let size: u64 = entries.iter().map(|e| e.size).sum();
+    if json {
+        println!(
+            "{}",
+            serde_json::json!({ "files": entries.len(), "size": size })
+        )
+    } else {
+        println!("{}", format_size(size))
+    }
+    Ok(())
+}
+
+/// Describe a cached file for the `--json` output of the cache commands
+fn entry_json(entry: &CacheEntry) -> serde_json::Value {
+    serde_json::json!({
+        "path": entry.path,
+        "version": entry.version.map(|v| v.to_string()),
+        "size": entry.size,
+        "age_secs": entry.age().as_secs(),
+    })
+}
+
+pub fn mirror_bench(goup: &Goup, save: bool) -> Result<()> {
+    let probes = goup.probe_mirrors()?;
+    let width = probes.iter().map(|p| p.mirror.len()).max().unwrap_or(0);
+    println!(
+        "{:width$}  {:>8}  {:>12}",
+        "MIRROR", "LATENCY", "THROUGHPUT"
+    );
+    for probe in &probes {
+        match &probe.result {
+            Ok(m) => println!(
+                "{:width$}  {:>6}ms  {:>8.1} KB/s",
+                probe.mirror,
+                m.latency.as_millis(),
+                m.throughput / 1024.0
+            ),
+            Err(e) => println!("{:width$}  failed: {}", probe.mirror, e),
+        }
+    }
+
+    if save {
+        let ranking = goup::mirror::Ranking::new(goup::mirror::rank(&probes));
+        ranking.store(&goup.mirror_ranking_file())?;
+        status!("Downloads will try {} first", ranking.mirrors[0]);
+    }
+    Ok(())
+}
@@ -0,0 +1,101 @@
+//! What happens when goup panics: an apology, and a report worth attaching to an issue
+
+use super::{debug, report};
+use std::backtrace::Backtrace;
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::{self, Write};
+use std::panic::{self, PanicHookInfo};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Replace the usual panic message for the rest of the program. Unless `write_report` is
+/// false, the details are written to a file in the temporary directory that the message names.
+pub fn install(write_report: bool) {
+    panic::set_hook(Box::new(move |info| {
+        restore_terminal();
+        let report = write_report.then(|| save(&contents(info)));
+        let mut stderr = io::stderr().lock();
+        let _ = writeln!(
+            stderr,
+            "\ngoup crashed: {}\nThis is a bug in goup, and we're sorry. Please report it at {}/issues",
+            message(info),
+            env!("CARGO_PKG_REPOSITORY")
+        );
+        let _ = match report {
+            Some(Ok(path)) => writeln!(
+                stderr,
+                "A report to attach to the issue has been written to {}",
+                path.display()
+            ),
+            Some(Err(e)) => writeln!(stderr, "Unable to write a crash report: {}", e),
+            None => Ok(()),
+        };
+    }));
+}
+
+/// Take any progress bars off the screen so they don't garble the message. The panic may have
+/// happened within the bars' own locks, so they are only given a moment to clear.
+fn restore_terminal() {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        report::abandon();
+        let _ = tx.send(());
+    });
+    let finished = rx.recv_timeout(Duration::from_millis(200)).is_ok();
+    let term = console::Term::stderr();
+    if term.is_term() {
+        if !finished {
+            // Clear whatever is on the line the bars stopped at
+            let _ = term.clear_line();
+        }
+        let _ = term.show_cursor();
+    }
+}
+
+/// The message the code panicked with
+fn message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    let text = match (
+        payload.downcast_ref::<&str>(),
+        payload.downcast_ref::<String>(),
+    ) {
+        (Some(text), _) => text,
+        (None, Some(text)) => text.as_str(),
+        (None, None) => "unknown panic",
+    };
+    match info.location() {
+        Some(location) => format!("{} at {}", text, location),
+        None => text.to_string(),
+    }
+}
+
+/// Everything the report holds, with credentials taken out of the command line
+fn contents(info: &PanicHookInfo) -> String {
+    let command: Vec<_> = env::args_os()
+        .map(|arg| format!("{:?}", arg.to_string_lossy()))
+        .collect();
+    let mut out = String::new();
+    writeln!(out, "goup {}", env!("CARGO_PKG_VERSION")).unwrap();
+    writeln!(out, "Built for {}-{}", env::consts::OS, env::consts::ARCH).unwrap();
+    writeln!(out, "Command: {}", debug::redact(&command.join(" "))).unwrap();
+    let thread = thread::current();
+    writeln!(out, "Thread: {}", thread.name().unwrap_or("unnamed")).unwrap();
+    writeln!(out, "Panic: {}", message(info)).unwrap();
+    writeln!(out, "\nBacktrace:\n{}", Backtrace::force_capture()).unwrap();
+    out
+}
+
+/// Write the report to a file of its own, returning its path
+fn save(contents: &str) -> io::Result<PathBuf> {
+    let time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    let path = env::temp_dir().join(format!("goup-crash-{}-{}.txt", time, std::process::id()));
+    fs::write(&path, contents)?;
+    Ok(path)
+}
@@ -0,0 +1,253 @@
+//! A redacted snapshot of goup's setup, for attaching to support requests, by
+//! `goup debug-bundle`
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use goup::config::Layer;
+use goup::{Goup, GoupError};
+use regex::Regex;
+use std::env;
+use std::fmt::Write;
+use std::fs::{self, File};
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use tar::{Builder, Header};
+
+static CREDENTIALS_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// One file in the bundle
+pub struct Item {
+    pub name: &'static str,
+    /// What the file holds, as printed once the bundle is written
+    pub description: &'static str,
+    pub contents: String,
+}
+
+/// Gather everything the bundle holds. `flags` names the command line options that changed
+/// the config, so the sources of the effective settings can be given. Nothing is read from
+/// within the installed versions themselves.
+pub fn collect(goup: &Goup, flags: &[&str]) -> Vec<Item> {
+    let mut items = vec![
+        Item {
+            name: "goup.txt",
+            description: "goup's version and the platform it was built for",
+            contents: format!(
+                "goup {}\nBuilt for {}-{}\n",
+                env!("CARGO_PKG_VERSION"),
+                env::consts::OS,
+                env::consts::ARCH
+            ),
+        },
+        Item {
+            name: "report.txt",
+            description: "the enabled version, and the problems `goup check` warns about",
+            contents: report(goup),
+        },
+    ];
+    // Only goup's own record is read, and it holds nothing secret
+    if let Ok(state) = fs::read_to_string(goup.version_file()) {
+        items.push(Item {
+            name: "versions.json",
+            description: "goup's record of what is installed, as is",
+            contents: state,
+        });
+    }
+    items.extend([
+        Item {
+            name: "config.txt",
+            description: "the settings in effect and where they came from, without secrets",
+            contents: config(goup, flags),
+        },
+        Item {
+            name: "listing.txt",
+            description: "the names and sizes of the files in the goup directory",
+            contents: listing(goup.root()),
+        },
+        Item {
+            name: "environment.txt",
+            description: "the environment variables goup reads, without credentials",
+            contents: environment(),
+        },
+    ]);
+    items
+}
+
+/// Write `items` into a gzipped tarball at `out`
+pub fn write(items: &[Item], out: &Path) -> Result<(), GoupError> {
+    let file = File::create(out).map_err(GoupError::io("create", out))?;
+    let mut builder = Builder::new(GzEncoder::new(file, Compression::default()));
+    let res = items
+        .iter()
+        .try_for_each(|item| {
+            let mut header = Header::new_gnu();
+            header.set_size(item.contents.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, item.name, item.contents.as_bytes())
+        })
+        .and_then(|()| builder.into_inner()?.finish())
+        .map(drop);
+    res.map_err(GoupError::io("write", out))
+}
+
+/// Remove the user and password from any URLs in `text`, such as those of proxies and mirrors,
+/// including proxy variables given without a scheme
+///
+/// The password may hold characters that aren't allowed there, so everything up to the last
+/// `@` before the host is taken as credentials.
+pub fn redact(text: &str) -> String {
+    let regex = CREDENTIALS_REGEX
+        .get_or_init(|| Regex::new(r"(?im)(://|^[a-z_]*proxy=)[^/\s\x22]*@").unwrap());
+    regex.replace_all(text, "${1}<redacted>@").into_owned()
+}
+
+/// What `goup check` and `goup info` would say, without using the network
+fn report(goup: &Goup) -> String {
+    let mut out = format!("Layout: {}\n", goup.layout().number());
+    let state = match goup.state() {
+        Ok(state) => state,
+        Err(e) => {
+            writeln!(out, "Unable to read the installed versions: {}", e).unwrap();
+            return out;
+        }
+    };
+    match (state.enabled, &state.enabled_linked) {
+        (_, Some(name)) => writeln!(out, "Enabled: {} (linked)", name).unwrap(),
+        (Some(version), None) => {
+            writeln!(out, "Enabled: {}", version).unwrap();
+            for line in super::info::info_lines(goup, &state, version) {
+                writeln!(out, "  {}", line).unwrap();
+            }
+        }
+        (None, None) => writeln!(out, "Enabled: none").unwrap(),
+    }
+    let installed: Vec<_> = state.installed.iter().map(|v| v.to_string()).collect();
+    writeln!(out, "Installed: {}", installed.join(", ")).unwrap();
+    if let Some(link) = goup.link_status() {
+        let kind = if link.relative {
+            "relative"
+        } else {
+            "absolute"
+        };
+        let resolves = if link.resolves {
+            "resolves"
+        } else {
+            "doesn't resolve"
+        };
+        writeln!(out, "Link: {} symlink, which {}", kind, resolves).unwrap();
+    }
+    if let Some(goroot) = goup.conflicting_goroot() {
+        writeln!(out, "GOROOT points elsewhere: {}", goroot.display()).unwrap();
+    }
+    for shadow in goup.shadowing() {
+        writeln!(out, "Shadowed by: {}", shadow.path.display()).unwrap();
+    }
+    redact(&out)
+}
+
+/// The effective settings, followed by where they came from, in order of precedence
+fn config(goup: &Goup, flags: &[&str]) -> String {
+    // Header values are redacted by the config's own debug output
+    let mut out = format!(
+        "{:#?}\n\nSources, each overriding the last:\n  defaults\n",
+        goup.config()
+    );
+    let file = goup.config_file();
+    let keys = fs::read_to_string(&file)
+        .ok()
+        .and_then(|text| text.parse::<toml::Table>().ok());
+    match keys {
+        Some(table) => {
+            let keys: Vec<_> = table.keys().map(String::as_str).collect();
+            writeln!(out, "  {}: {}", file.display(), keys.join(", ")).unwrap();
+        }
+        None if file.exists() => writeln!(out, "  {}: unreadable", file.display()).unwrap(),
+        None => writeln!(out, "  {}: doesn't exist", file.display()).unwrap(),
+    }
+    let mut project = None;
+    let mut project_keys = Vec::new();
+    for (key, setting) in &goup.config().sources {
+        if let Layer::Project(path) = &setting.layer {
+            project = Some(path);
+            project_keys.push(key.as_str());
+        }
+    }
+    if let Some(path) = project {
+        writeln!(out, "  {}: {}", path.display(), project_keys.join(", ")).unwrap();
+    }
+    let vars: Vec<_> = env::vars_os()
+        .filter_map(|(name, _)| name.into_string().ok())
+        .filter(|name| name.starts_with("GOUP_"))
+        .collect();
+    if !vars.is_empty() {
+        writeln!(out, "  environment: {}", vars.join(", ")).unwrap();
+    }
+    if !flags.is_empty() {
+        writeln!(out, "  command line: {}", flags.join(", ")).unwrap();
+    }
+    redact(&out)
+}
+
+/// Every file in the goup directory with its size, except within installations of Go
+fn listing(root: &Path) -> String {
+    let mut out = String::new();
+    if let Err(e) = list(root, Path::new(""), &mut out) {
+        writeln!(out, "Unable to list {}: {}", root.display(), e).unwrap();
+    }
+    out
+}
+
+fn list(dir: &Path, name: &Path, out: &mut String) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|e| e.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = name.join(entry.file_name());
+        let meta = fs::symlink_metadata(&path)?;
+        if meta.is_symlink() {
+            let target = fs::read_link(&path)?;
+            writeln!(out, "{} -> {}", name.display(), target.display()).unwrap();
+        } else if meta.is_dir() && path.join("VERSION").is_file() {
+            // A GOROOT, which is the same for everyone with the version
+            writeln!(out, "{}/ (installation of Go, not listed)", name.display()).unwrap();
+        } else if meta.is_dir() {
+            writeln!(out, "{}/", name.display()).unwrap();
+            list(&path, &name, out)?;
+        } else {
+            writeln!(out, "{}  {} bytes", name.display(), meta.len()).unwrap();
+        }
+    }
+    Ok(())
+}
+
+/// The environment variables that affect goup and the go command it sets up. PATH is reduced
+/// to the directories that have anything to do with Go.
+fn environment() -> String {
+    let mut out = String::new();
+    let path = env::var_os("PATH").unwrap_or_default();
+    let dirs: Vec<_> = env::split_paths(&path)
+        .filter(|dir| dir.to_string_lossy().to_lowercase().contains("go"))
+        .map(|dir| dir.display().to_string())
+        .collect();
+    writeln!(out, "PATH (entries containing \"go\"):").unwrap();
+    for dir in dirs {
+        writeln!(out, "  {}", dir).unwrap();
+    }
+    let mut vars: Vec<_> = env::vars()
+        .filter(|(name, _)| {
+            let upper = name.to_uppercase();
+            matches!(name.as_str(), "GOPATH" | "GOROOT" | "GOTOOLCHAIN")
+                || name.starts_with("GOUP_")
+                || matches!(
+                    upper.as_str(),
+                    "HTTP_PROXY" | "HTTPS_PROXY" | "ALL_PROXY" | "NO_PROXY"
+                )
+        })
+        .collect();
+    vars.sort();
+    for (name, value) in vars {
+        writeln!(out, "{}={}", name, value).unwrap();
+    }
+    redact(&out)
+}
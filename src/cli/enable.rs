@@ -0,0 +1,230 @@
+//! Choosing the version of Go that `go` runs, for `goup enable` and `goup link`
+
+use super::prompt;
+use super::report;
+use super::tools::rebuild;
+use anyhow::{Context, Result};
+use console::style;
+use goup::exec::{self, Shadow};
+use goup::project::{self, Need, Resolution};
+use goup::tools::Snapshot;
+use goup::version::VersionRequest;
+use goup::{GoVersion, Goup, GoupError};
+use std::env;
+#[cfg(unix)]
+use std::fs;
+use std::path::Path;
+
+pub fn enable(goup: &Goup, version: GoVersion, rebuild_tools: bool, force: bool) -> Result<()> {
+    let previous = goup.state()?.enabled;
+    if force {
+        goup.transaction(|tx| {
+            if tx.clear_link()? {
+                status!("Deleted {}", goup.link_path().display());
+            }
+            tx.enable(version)
+        })?
+    } else {
+        goup.enable(version)?
+    }
+    warn_if_shadowed(goup, version);
+    warn_about_goroot(goup, true);
+
+    let new_minor = previous.is_none_or(|p| (p.major, p.minor) != (version.major, version.minor));
+    if !rebuild_tools || !new_minor {
+        return Ok(());
+    }
+    let tools = match Snapshot::load(&goup.tools_file())? {
+        Some(snapshot) if !snapshot.tools.is_empty() => snapshot.tools,
+        _ => return Ok(()),
+    };
+    let question = format!("Rebuild {} tool(s) with {}?", tools.len(), version);
+    if prompt::confirm(&question)? {
+        rebuild(goup, version, &tools)
+    } else {
+        Ok(())
+    }
+}
+
+/// Warn if running `go` won't find the version that was just enabled, because another `go`
+/// comes before goup's on PATH
+fn warn_if_shadowed(goup: &Goup, version: impl std::fmt::Display) {
+    let shadows = goup.shadowing();
+    if shadows.is_empty() || !goup.config().warn_shadowed() {
+        return;
+    }
+    diagnostic!(
+        "{} {} is enabled, but `go` will run another version that comes first on your PATH:",
+        style("Warning:").yellow(),
+        version
+    );
+    for shadow in &shadows {
+        diagnostic!("  {}", describe_shadow(shadow));
+    }
+    let path = env::var_os("PATH").unwrap_or_default();
+    let reordered = exec::reordered_path(&goup.link_path().join("bin"), &path);
+    diagnostic!(
+        "To use goup's, put it first: export PATH=\"{}\"",
+        reordered.to_string_lossy()
+    );
+    diagnostic!("Set warn_shadowed = false in the config file to stop this warning.");
+}
+
+/// Warn if GOROOT points at another installation of Go, which the go command would use in
+/// place of the enabled version. With `once_per_shell`, each shell is only warned once.
+pub fn warn_about_goroot(goup: &Goup, once_per_shell: bool) {
+    if !goup.config().warn_goroot() {
+        return;
+    }
+    let Some(goroot) = goup.conflicting_goroot() else {
+        return;
+    };
+    if once_per_shell && !first_warning_in_shell(goup) {
+        return;
+    }
+    diagnostic!(
+        "{} GOROOT is set to {}, so `go` will use the standard library and tools found there          instead of the enabled version's, and may complain that packages were compiled with a          different version.",
+        style("Warning:").yellow(),
+        goroot.display()
+    );
+    diagnostic!(
+        "To fix this, remove GOROOT from your shell profile and run: unset GOROOT          (or: export GOROOT=\"{}\")",
+        goup.link_path().display()
+    );
+    diagnostic!("Set warn_goroot = false in the config file to stop this warning.");
+}
+
+/// Whether the shell that ran goup hasn't been warned about GOROOT yet, recording that it now
+/// has. Outside of Unix, where the shell can't be told apart, every warning is the first.
+fn first_warning_in_shell(goup: &Goup) -> bool {
+    #[cfg(unix)]
+    {
+        let shell = unsafe { libc::getppid() }.to_string();
+        let marker = goup.cache_dir().join("goroot-warned");
+        if fs::read_to_string(&marker).is_ok_and(|warned| warned == shell) {
+            return false;
+        }
+        let _ = fs::create_dir_all(goup.cache_dir());
+        let _ = fs::write(&marker, shell);
+    }
+    #[cfg(not(unix))]
+    let _ = goup;
+    true
+}
+
+/// The path of a `go` that shadows goup's, and its version if known
+pub fn describe_shadow(shadow: &Shadow) -> String {
+    match shadow.version {
+        Some(version) => format!("{} ({})", shadow.path.display(), version),
+        None => shadow.path.display().to_string(),
+    }
+}
+
+pub fn enable_linked(goup: &Goup, name: &str, force: bool) -> Result<()> {
+    goup.transaction(|tx| {
+        if force && tx.clear_link()? {
+            status!("Deleted {}", goup.link_path().display());
+        }
+        tx.enable_linked(name)
+    })?;
+    warn_if_shadowed(goup, name);
+    warn_about_goroot(goup, true);
+    Ok(())
+}
+
+/// Print the default version, and the version active in the current directory
+pub fn show_default(goup: &Goup) -> Result<()> {
+    let state = goup.state()?;
+    let default = match (&state.enabled_linked, state.enabled) {
+        (Some(name), _) => Some(name.clone()),
+        (None, version) => version.map(|v| v.to_string()),
+    };
+    if report::quiet() {
+        println!("{}", default.ok_or(GoupError::NoVersionEnabled)?);
+        return Ok(());
+    }
+    let dir = env::current_dir().context("Unable to find the current directory")?;
+    let resolution = goup.resolve(&dir)?;
+    println!("Default: {}", default.as_deref().unwrap_or("none"));
+    match (&state.enabled_linked, resolution.decision()) {
+        (Some(name), Some((project::Source::Enabled, _)) | None) => {
+            println!("Active here: {} (the default)", name)
+        }
+        _ => println!("Active here: {}", active_here(&resolution, &dir)),
+    }
+    Ok(())
+}
+
+/// The version active in `dir`, and what decided it, like `go1.21.13 (from ./go.mod toolchain
+/// line)`
+pub fn active_here(resolution: &Resolution, dir: &Path) -> String {
+    let Some((source, need)) = resolution.decision() else {
+        return "none, since nothing asks for one".to_string();
+    };
+    let from = match source {
+        project::Source::Enabled => "the default".to_string(),
+        project::Source::File(file) => {
+            let shown = match file.strip_prefix(dir) {
+                Ok(relative) => Path::new(".").join(relative),
+                Err(_) => file.clone(),
+            };
+            let line = match (file.file_name(), need) {
+                (Some(name), Need::AtLeast(_)) if name == "go.mod" => " go line",
+                (Some(name), Need::Request(_)) if name == "go.mod" => " toolchain line",
+                _ => "",
+            };
+            format!("from {}{}", shown.display(), line)
+        }
+        source => format!("from {}", source),
+    };
+    match resolution.version {
+        Some(version) => format!("{} ({})", version, from),
+        None => format!("none, since {} isn't installed ({})", need, from),
+    }
+}
+
+/// The default version that `resolution` fell back to, or would have
+pub fn default_of(resolution: &Resolution) -> Option<GoVersion> {
+    resolution
+        .consulted
+        .iter()
+        .find_map(|(source, found)| match (source, found) {
+            (
+                project::Source::Enabled,
+                project::Found::Need(Need::Request(VersionRequest::Exact(version))),
+            ) => Some(*version),
+            _ => None,
+        })
+}
+
+pub fn link(goup: &Goup, name: &str, path: &Path) -> Result<()> {
+    let toolchain = goup.link(name, path)?;
+    status!(
+        "Linked {} to {} ({})",
+        name,
+        toolchain.path.display(),
+        toolchain.go_version
+    );
+    Ok(())
+}
+
+pub fn unlink(goup: &Goup, name: &str) -> Result<()> {
+    let was_enabled = goup.state()?.enabled_linked.as_deref() == Some(name);
+    let toolchain = goup.unlink(name)?;
+    if toolchain.is_custom() {
+        status!("Removed {}", name)
+    } else {
+        status!(
+            "Unlinked {}; {} was left as it is",
+            name,
+            toolchain.path.display()
+        )
+    }
+    if was_enabled {
+        status!(
+            "{} was the default. Use 'goup default' to choose another.",
+            name
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,257 @@
+//! Explaining why a command failed, and choosing the exit code that says so
+
+use super::events;
+use super::exit;
+use super::prompt::NonInteractive;
+use goup::response;
+use goup::version::VersionRequest;
+use goup::{GoVersion, Goup, GoupError};
+use std::process::ExitCode;
+
+/// A command run by `goup exec` failed with some of the versions, giving the worst exit code
+#[derive(Debug, thiserror::Error)]
+#[error("The command failed with {failed} of {total} version(s)")]
+pub struct ExecFailed {
+    pub failed: usize,
+    pub total: usize,
+    pub code: u8,
+}
+
+/// A command run by `goup try` failed, giving its exit code
+#[derive(Debug, thiserror::Error)]
+#[error("{program} failed with exit code {code}")]
+pub struct TryFailed {
+    pub program: String,
+    pub code: u8,
+}
+
+/// The enabled version is behind the latest, which `goup check` reports through its exit code
+#[derive(Debug, thiserror::Error)]
+#[error("{enabled} is out of date")]
+pub struct Outdated {
+    pub enabled: GoVersion,
+    pub security: bool,
+}
+
+/// Tell the user about an error, and how they might fix it
+pub fn report(e: &anyhow::Error, goup: Option<&Goup>, verbose: bool) -> ExitCode {
+    match goup_error(e) {
+        // The details of why the connection failed are rarely interesting when offline
+        Some(err @ GoupError::Network { url, .. }) if err.is_unreachable() => {
+            diagnostic!(
+                "Error: Could not reach {}: network appears to be offline",
+                host(url)
+            );
+            if verbose {
+                diagnostic!("Cause: {:#}", e);
+            }
+        }
+        _ => diagnostic!("Error: {:#}", e),
+    }
+    if let Some(hint) = goup.and_then(|goup| hint(e, goup)) {
+        diagnostic!("{}", hint);
+    }
+    let code = exit_code(e);
+    if events::enabled() {
+        events::result(code, Some(format!("{:#}", e)));
+    }
+    ExitCode::from(code)
+}
+
+/// The host name within a URL, for messages about it
+fn host(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?']).next().unwrap_or(rest)
+}
+
+/// The [`GoupError`] at the root of the given error, if there is one
+fn goup_error(e: &anyhow::Error) -> Option<&GoupError> {
+    e.chain()
+        .find_map(|cause| cause.downcast_ref::<GoupError>())
+}
+
+/// A suggestion of how to resolve the given error, if there is an obvious one
+fn hint(e: &anyhow::Error, goup: &Goup) -> Option<String> {
+    match goup_error(e)? {
+        GoupError::VersionNotInstalled(v) => {
+            let installed = goup.installed().unwrap_or_default();
+            did_you_mean(*v, installed)
+                .or_else(|| Some(format!("Use 'goup install {}' to install it", v)))
+        }
+        GoupError::VersionNotAvailable(v) => {
+            let available = goup.available().ok()?;
+            did_you_mean(*v, available.into_keys())
+        }
+        GoupError::SeriesNotInstalled(series) => {
+            did_you_mean(first_of(*series)?, goup.installed().unwrap_or_default())
+                .or_else(|| Some(format!("Use 'goup install {}' to install it", series)))
+        }
+        GoupError::SeriesNotAvailable(series) => {
+            let available = goup.available().ok()?;
+            did_you_mean(first_of(*series)?, available.into_keys())
+        }
+        GoupError::VersionNotPinned(v) => did_you_mean(*v, goup.state().ok()?.pinned),
+        GoupError::NoReleaseAsOf { request, .. } => {
+            let dates = goup.release_dates().ok()?;
+            let (first, released) = dates.iter().find(|(&v, _)| request.matches(v))?;
+            Some(format!(
+                "The first, {}, was released on {}",
+                first, released
+            ))
+        }
+        GoupError::NoVersionEnabled => Some("Use 'goup default' to choose one".to_string()),
+        GoupError::RequirementNotInstalled { .. } => Some(
+            "Use 'goup resolve --ensure' to install it, with auto_install = true in the config \
+             file"
+                .to_string(),
+        ),
+        GoupError::ToolchainNotLinked(name) => {
+            let linked: Vec<_> = goup.state().ok()?.linked.into_keys().collect();
+            if linked.is_empty() {
+                Some(format!(
+                    "Use 'goup link {} <path>' to register a toolchain under that name",
+                    name
+                ))
+            } else {
+                Some(format!("The linked toolchains are: {}", linked.join(", ")))
+            }
+        }
+        GoupError::ForeignLink {
+            path,
+            version: Some(v),
+        } => Some(format!(
+            "It looks like an installation of {}. Use 'goup import-from dir {} --move' to keep \
+             it as an installed version, or 'goup enable --force' to delete it",
+            v,
+            path.display()
+        )),
+        GoupError::ForeignLink { path, .. } => Some(format!(
+            "Move {} somewhere else if you need it, or use 'goup enable --force' to delete it",
+            path.display()
+        )),
+        GoupError::ChecksumMismatch { .. } => Some(
+            "The download changed on its way here. If a proxy alters downloads, 'goup install' \
+             and 'goup update' take '--no-verify' to skip this check"
+                .to_string(),
+        ),
+        GoupError::BeyondMaxVersion { .. } | GoupError::VersionBlocked { .. } => {
+            Some("Pass '--override-policy' to install it anyway".to_string())
+        }
+        GoupError::InvalidIndex { received, .. } | GoupError::NotAnArchive { received, .. } => {
+            match received.kind {
+                response::Kind::LoginPage => Some(
+                    "Are you behind a login portal, as on hotel or airport Wi-Fi? Log in \
+                     through a web browser, then try again"
+                        .to_string(),
+                ),
+                response::Kind::ErrorPage => Some(
+                    "The mirror returned an error page. Check that it is up, and that \
+                     GOUP_MIRROR or the 'mirror' setting points at a mirror of go.dev/dl"
+                        .to_string(),
+                ),
+                response::Kind::Page => Some(
+                    "Check that GOUP_MIRROR or the 'mirror' setting points at a mirror of \
+                     go.dev/dl"
+                        .to_string(),
+                ),
+                response::Kind::Empty | response::Kind::Other => None,
+            }
+        }
+        GoupError::Unsupported { .. } => Some(
+            "Use 'goup install <version> --build-from-source' to build Go from source instead"
+                .to_string(),
+        ),
+        GoupError::BootstrapTooOld { needs, .. } => Some(format!(
+            "Pass '--bootstrap' an installed version from {} on, or leave it out to have one \
+             chosen",
+            needs
+        )),
+        e if e.is_unreachable() => Some(
+            "Use 'goup list --installed' to see the versions already installed, or '--offline' \
+             to work from cached data"
+                .to_string(),
+        ),
+        GoupError::Network { source, .. } if source.to_string().contains("certificate") => Some(
+            "If a proxy intercepts your connections, trust its certificate with '--cacert' or \
+             the 'ca_bundle' setting, or set 'native_certs = true' to use the platform's \
+             certificate store"
+                .to_string(),
+        ),
+        _ => None,
+    }
+}
+
+/// The first release of `series`, for suggesting releases close to it
+fn first_of(series: VersionRequest) -> Option<GoVersion> {
+    match series {
+        VersionRequest::Series { major, minor } => Some(GoVersion {
+            major,
+            minor,
+            ..Default::default()
+        }),
+        _ => None,
+    }
+}
+
+/// Suggest versions from the candidates that the user may have meant instead of `version`
+fn did_you_mean(
+    version: GoVersion,
+    candidates: impl IntoIterator<Item = GoVersion>,
+) -> Option<String> {
+    let names: Vec<_> = goup::suggest::suggestions(version, candidates)
+        .iter()
+        .map(GoVersion::to_string)
+        .collect();
+    match names.as_slice() {
+        [] => None,
+        [only] => Some(format!("Did you mean {}?", only)),
+        [rest @ .., last] => Some(format!("Did you mean {} or {}?", rest.join(", "), last)),
+    }
+}
+
+/// Pick the exit code that best describes the given error
+fn exit_code(e: &anyhow::Error) -> u8 {
+    if e.chain().any(|cause| cause.is::<NonInteractive>()) {
+        return exit::NEEDS_CONFIRMATION;
+    }
+    if let Some(failed) = e.downcast_ref::<ExecFailed>() {
+        return failed.code;
+    }
+    if let Some(failed) = e.downcast_ref::<TryFailed>() {
+        return failed.code;
+    }
+    if let Some(outdated) = e.downcast_ref::<Outdated>() {
+        return if outdated.security {
+            exit::SECURITY_UPDATE_AVAILABLE
+        } else {
+            exit::UPDATE_AVAILABLE
+        };
+    }
+
+    match goup_error(e) {
+        Some(
+            GoupError::Network { .. }
+            | GoupError::InvalidIndex { .. }
+            | GoupError::NotAnArchive { .. }
+            | GoupError::DownloadStalled { .. }
+            | GoupError::SizeMismatch { .. }
+            | GoupError::Offline { .. },
+        ) => exit::NETWORK,
+        Some(
+            GoupError::VersionNotAvailable(_)
+            | GoupError::SeriesNotAvailable(_)
+            | GoupError::NoReleaseAsOf { .. }
+            | GoupError::NoVersionsAvailable
+            | GoupError::NoSourceRelease(_)
+            | GoupError::NotInTrash(_),
+        ) => exit::NOT_FOUND,
+        Some(GoupError::NoProjectRequirement(_)) => exit::NOT_FOUND,
+        Some(
+            GoupError::VersionNotInstalled(_)
+            | GoupError::SeriesNotInstalled(_)
+            | GoupError::ToolchainNotLinked(_)
+            | GoupError::RequirementNotInstalled { .. },
+        ) => exit::NOT_INSTALLED,
+        _ => exit::FAILURE,
+    }
+}
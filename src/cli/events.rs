@@ -0,0 +1,165 @@
+//! Newline-delimited JSON events on stderr, for programs that drive goup, with
+//! `--progress json`
+//!
+//! Every line is a JSON object whose `event` field says what happened:
+//!
+//! | `event`             | Fields                                          |
+//! | ------------------- | ----------------------------------------------- |
+//! | `step_start`        | `step`, `total`, `message`                      |
+//! | `step_end`          | `step`, `seconds`                               |
+//! | `download_start`    | `total_bytes`                                   |
+//! | `download_progress` | `bytes`, `total_bytes`                          |
+//! | `download_end`      | `bytes`, `complete`                             |
+//! | `extract_progress`  | `entries`                                       |
+//! | `phase`             | `phase`, `seconds`, and `entries` for `extract` |
+//! | `build_output`      | `line`                                          |
+//! | `message`           | `text`                                          |
+//! | `timings`           | `timings`                                       |
+//! | `result`            | `ok`, `exit_code`, and `error` on failure       |
+
+use crate::cli::report::{self, Reporter};
+use goup::Progress;
+use serde_json::{json, Value};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// How many entries are unpacked between `extract_progress` events
+const ENTRIES_PER_EVENT: u64 = 1000;
+
+/// Send events instead of drawing progress for the rest of the program
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Whether events are being sent
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Write a single event to stderr
+pub fn emit(event: Value) {
+    let mut stderr = io::stderr().lock();
+    let _ = writeln!(stderr, "{}", event);
+}
+
+/// Print a line of diagnostics to stderr, or send it as a `message` event, without any color
+pub fn diagnostic(text: &str) {
+    if enabled() {
+        emit(json!({ "event": "message", "text": console::strip_ansi_codes(text) }))
+    } else {
+        eprintln!("{}", text)
+    }
+}
+
+/// Print a line saying what a command has done to stderr, or send it as a `message` event.
+/// Unlike diagnostics, these are left out with `--quiet`.
+pub fn status(text: &str) {
+    if enabled() {
+        emit(json!({ "event": "message", "text": console::strip_ansi_codes(text) }));
+    } else if !report::quiet() {
+        eprintln!("{}", text);
+    }
+}
+
+/// Send the final event, saying how the command turned out
+pub fn result(exit_code: u8, error: Option<String>) {
+    let mut event = json!({ "event": "result", "ok": exit_code == 0, "exit_code": exit_code });
+    if let Some(error) = error {
+        event["error"] = error.into();
+    }
+    emit(event);
+}
+
+/// Sends events about downloads and extraction
+#[derive(Default)]
+pub struct JsonProgress {
+    download: Mutex<Download>,
+}
+
+#[derive(Default)]
+struct Download {
+    total: u64,
+    received: u64,
+    percent: u64,
+    entries: u64,
+}
+
+impl Progress for JsonProgress {
+    fn start(&self, total: u64) {
+        *self.download.lock().unwrap() = Download {
+            total,
+            ..Default::default()
+        };
+        emit(json!({ "event": "download_start", "total_bytes": total }));
+    }
+
+    fn advance(&self, bytes: u64) {
+        let mut download = self.download.lock().unwrap();
+        download.received += bytes;
+        // One event for each percent is plenty, however small the reads
+        let percent = (download.received * 100)
+            .checked_div(download.total)
+            .unwrap_or(100);
+        if percent > download.percent {
+            download.percent = percent;
+            emit(json!({
+                "event": "download_progress",
+                "bytes": download.received,
+                "total_bytes": download.total,
+            }));
+        }
+    }
+
+    fn finish(&self, complete: bool) {
+        let received = self.download.lock().unwrap().received;
+        emit(json!({ "event": "download_end", "bytes": received, "complete": complete }));
+    }
+
+    fn extracted(&self, entries: u64) {
+        let mut download = self.download.lock().unwrap();
+        download.entries += entries;
+        if download.entries.is_multiple_of(ENTRIES_PER_EVENT) {
+            emit(json!({ "event": "extract_progress", "entries": download.entries }));
+        }
+    }
+
+    fn phase(&self, name: &'static str, elapsed: Duration) {
+        report::record(name, elapsed);
+        let mut event =
+            json!({ "event": "phase", "phase": name, "seconds": elapsed.as_secs_f64() });
+        if name == "extract" {
+            let entries = std::mem::take(&mut self.download.lock().unwrap().entries);
+            event["entries"] = entries.into();
+        }
+        emit(event);
+    }
+
+    fn output(&self, line: &str) {
+        emit(json!({ "event": "build_output", "line": line }));
+    }
+}
+
+/// Sends an event as each step of a command starts and ends
+#[derive(Default)]
+pub struct JsonSteps {
+    current: usize,
+}
+
+impl Reporter for JsonSteps {
+    fn start(&mut self, number: usize, total: usize, message: &str) {
+        self.current = number;
+        emit(json!({ "event": "step_start", "step": number, "total": total, "message": message }));
+    }
+
+    fn end(&mut self, elapsed: Duration) {
+        emit(
+            json!({ "event": "step_end", "step": self.current, "seconds": elapsed.as_secs_f64() }),
+        );
+    }
+
+    fn finish(&mut self, _phases: &[(&'static str, Duration)]) {}
+}
@@ -0,0 +1,21 @@
+//! The exit codes that goup uses to report the category of a failure.
+//!
+//! Documented in the README, so these should be treated as a stable interface.
+
+/// A failure that does not fall into any of the more specific categories
+pub const FAILURE: u8 = 1;
+/// The command line could not be parsed
+pub const USAGE: u8 = 2;
+/// go.dev could not be reached, or returned an error
+pub const NETWORK: u8 = 3;
+/// The requested version is not available for download
+pub const NOT_FOUND: u8 = 4;
+/// The requested version is not installed
+pub const NOT_INSTALLED: u8 = 5;
+/// A question needed answering, but goup was running non-interactively
+pub const NEEDS_CONFIRMATION: u8 = 6;
+/// `goup check` found a newer version
+pub const UPDATE_AVAILABLE: u8 = 7;
+/// `goup check` found a newer version, and at least one of the newer versions includes
+/// security fixes
+pub const SECURITY_UPDATE_AVAILABLE: u8 = 8;
@@ -0,0 +1,118 @@
+//! Templates like `{version}\t{enabled}`, for scripts that want particular fields of a command's
+//! output, such as `goup list --format`
+
+use std::fmt;
+
+/// The fields that `goup list --format` can show
+pub const LIST_FIELDS: &[&str] = &[
+    "version",
+    "status",
+    "installed",
+    "enabled",
+    "pinned",
+    "available",
+    "size",
+    "date",
+    "last_used",
+    "semver",
+];
+
+/// A line of output with `{field}` placeholders, which is checked against the fields it may use
+/// when it is parsed, so that a typo is reported before any work is done.
+///
+/// Write `{{` and `}}` for literal braces. The escapes `\t`, `\n`, and `\\` are understood too,
+/// since shells don't make tabs easy to type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pieces: Vec<Piece>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Text(String),
+    Field(&'static str),
+}
+
+impl Template {
+    /// Parse `template`, which may only name the given `fields`
+    pub fn parse(template: &str, fields: &[&'static str]) -> Result<Self, String> {
+        let mut pieces = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| format!("'{{' at '{{{}' is never closed", rest))?;
+                    let name = &rest[..end];
+                    let Some(field) = fields.iter().find(|f| **f == name) else {
+                        return Err(format!(
+                            "unknown placeholder '{{{}}}'; expected one of {}",
+                            name,
+                            Fields(fields)
+                        ));
+                    };
+                    if !text.is_empty() {
+                        pieces.push(Piece::Text(std::mem::take(&mut text)));
+                    }
+                    pieces.push(Piece::Field(field));
+                    chars = rest[end + 1..].chars();
+                }
+                '}' => return Err("unmatched '}'; write '}}' for a literal brace".to_string()),
+                '\\' => match chars.next() {
+                    Some('t') => text.push('\t'),
+                    Some('n') => text.push('\n'),
+                    Some('\\') => text.push('\\'),
+                    Some(other) => {
+                        text.push('\\');
+                        text.push(other);
+                    }
+                    None => text.push('\\'),
+                },
+                _ => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            pieces.push(Piece::Text(text));
+        }
+        Ok(Self { pieces })
+    }
+
+    /// Whether the template shows `field`, for fields that are expensive to work out
+    pub fn uses(&self, field: &str) -> bool {
+        self.pieces
+            .iter()
+            .any(|p| matches!(p, Piece::Field(f) if *f == field))
+    }
+
+    /// Fill in the template, using `value` to find the value of each field
+    pub fn render(&self, mut value: impl FnMut(&str) -> String) -> String {
+        self.pieces
+            .iter()
+            .map(|piece| match piece {
+                Piece::Text(text) => text.clone(),
+                Piece::Field(field) => value(field),
+            })
+            .collect()
+    }
+}
+
+/// The fields a template may use, as `{a}, {b}, {c}`
+struct Fields<'a>(&'a [&'static str]);
+
+impl fmt::Display for Fields<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<_> = self.0.iter().map(|name| format!("{{{}}}", name)).collect();
+        f.write_str(&names.join(", "))
+    }
+}
@@ -0,0 +1,148 @@
+//! Git hooks that check the version of Go a repository needs is installed after each checkout
+//! and merge, for `goup hook-git`
+
+use anyhow::{bail, Context, Result};
+use goup::GoupError;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// The hooks that run after the files in the working tree change
+pub const HOOKS: [&str; 2] = ["post-checkout", "post-merge"];
+
+/// The first line of goup's part of a hook
+const BEGIN: &str = "# >>> goup >>>";
+/// The last line of goup's part of a hook
+const END: &str = "# <<< goup <<<";
+/// What a hook goup creates starts with, before goup's part
+const SHEBANG: &str = "#!/bin/sh\n";
+
+/// What was done to a hook
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// The hook didn't exist, and was created
+    Created,
+    /// goup's part was added to an existing hook
+    Added,
+    /// The hook was already as it should be
+    Unchanged,
+    /// goup's part was taken out of a hook that does other things too
+    Removed,
+    /// The hook did nothing but run goup, and was deleted
+    Deleted,
+    /// The hook exists but isn't a shell script, so goup can't add itself to it
+    NotShell,
+}
+
+/// goup's part of a hook, which other hooks written as shell scripts can include as is. It
+/// does nothing at all if goup isn't on PATH, and never fails the hook.
+pub fn snippet() -> String {
+    format!(
+        "{}\n\
+         # Added by `goup hook-git install`, and removed by `goup hook-git uninstall`\n\
+         if command -v goup >/dev/null 2>&1; then\n\
+         \x20 goup resolve --ensure >/dev/null || true\n\
+         fi\n\
+         {}\n",
+        BEGIN, END
+    )
+}
+
+/// The directory that git runs the current repository's hooks from, which `core.hooksPath`
+/// may have moved
+pub fn hooks_dir() -> Result<PathBuf> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--git-path", "hooks"])
+        .output()
+        .context("Unable to run git")?;
+    if !output.status.success() {
+        bail!(
+            "Unable to find the git repository's hooks: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+    Ok(PathBuf::from(
+        String::from_utf8_lossy(&output.stdout).trim(),
+    ))
+}
+
+/// Make the hook at `path` run goup, creating it if it doesn't exist. Doing so again changes
+/// nothing.
+pub fn install(path: &Path) -> Result<Change> {
+    let Some(mut script) = read(path)? else {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        fs::write(path, format!("{}{}", SHEBANG, snippet()))
+            .map_err(GoupError::io("write", path))?;
+        make_executable(path)?;
+        return Ok(Change::Created);
+    };
+    if script.lines().any(|line| line == BEGIN) {
+        return Ok(Change::Unchanged);
+    }
+    let shell = script
+        .lines()
+        .next()
+        .is_some_and(|line| line.starts_with("#!") && line.contains("sh"));
+    if !shell {
+        return Ok(Change::NotShell);
+    }
+    if !script.ends_with('\n') {
+        script.push('\n');
+    }
+    script.push_str(&snippet());
+    fs::write(path, script).map_err(GoupError::io("write", path))?;
+    Ok(Change::Added)
+}
+
+/// Take goup's part out of the hook at `path`, deleting the hook if goup created it and
+/// nothing else has been added since
+pub fn uninstall(path: &Path) -> Result<Change> {
+    let Some(script) = read(path)? else {
+        return Ok(Change::Unchanged);
+    };
+    let mut kept = String::new();
+    let mut inside = false;
+    let mut found = false;
+    for line in script.split_inclusive('\n') {
+        match line.trim_end_matches('\n') {
+            BEGIN => (inside, found) = (true, true),
+            END if inside => inside = false,
+            _ if inside => {}
+            _ => kept.push_str(line),
+        }
+    }
+    if !found {
+        return Ok(Change::Unchanged);
+    }
+    if kept.is_empty() || kept == SHEBANG {
+        fs::remove_file(path).map_err(GoupError::io("remove", path))?;
+        return Ok(Change::Deleted);
+    }
+    fs::write(path, kept).map_err(GoupError::io("write", path))?;
+    Ok(Change::Removed)
+}
+
+/// The hook at `path`, or `None` if there isn't one
+fn read(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(script) => Ok(Some(script)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(GoupError::io("read", path)(e).into()),
+    }
+}
+
+#[cfg(unix)]
+fn make_executable(path: &Path) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o755))
+        .map_err(GoupError::io("make executable", path))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn make_executable(_path: &Path) -> Result<()> {
+    Ok(())
+}
@@ -0,0 +1,80 @@
+//! Editor configuration pointing at goup's toolchains
+
+use anyhow::{bail, Context, Result};
+use goup::{GoVersion, Goup, GoupError};
+use serde_json::{json, Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The GOROOT an editor should use: the stable `go` symlink, so that the editor follows
+/// `goup enable`, or a specific version's installation if one is requested.
+pub fn toolchain_root(goup: &Goup, version: Option<GoVersion>) -> Result<PathBuf> {
+    match version {
+        Some(version) => {
+            if !goup.installed()?.contains(&version) {
+                return Err(GoupError::VersionNotInstalled(version).into());
+            }
+            Ok(goup.goroot(version))
+        }
+        None => Ok(goup.link_path()),
+    }
+}
+
+/// The settings the Go extension for VS Code needs to use the given GOROOT
+pub fn vscode_settings(goroot: &Path) -> Map<String, Value> {
+    let go = goroot
+        .join("bin")
+        .join(format!("go{}", std::env::consts::EXE_SUFFIX));
+    let settings = json!({
+        "go.goroot": goroot,
+        "go.alternateTools": { "go": go },
+    });
+
+    match settings {
+        Value::Object(map) => map,
+        _ => unreachable!(),
+    }
+}
+
+/// Merge the given settings into a VS Code settings file, leaving any other keys as they were,
+/// including those of nested objects like `go.alternateTools`
+pub fn merge_vscode_settings(path: &Path, settings: Map<String, Value>) -> Result<()> {
+    let mut existing = match fs::read_to_string(path) {
+        Ok(text) if text.trim().is_empty() => Map::new(),
+        Ok(text) => match serde_json::from_str(&text) {
+            Ok(Value::Object(map)) => map,
+            Ok(_) => bail!("{} does not contain a JSON object", path.display()),
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!(
+                        "Unable to parse {} (comments and trailing commas aren't supported)",
+                        path.display()
+                    )
+                })
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Map::new(),
+        Err(e) => return Err(GoupError::io("read", path)(e).into()),
+    };
+
+    merge(&mut existing, settings);
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+    }
+    let text = serde_json::to_string_pretty(&existing)?;
+    fs::write(path, text + "\n").map_err(GoupError::io("write", path))?;
+    Ok(())
+}
+
+/// Merge `new` into `existing` key by key, descending into objects present in both
+fn merge(existing: &mut Map<String, Value>, new: Map<String, Value>) {
+    for (key, value) in new {
+        match (existing.get_mut(&key), value) {
+            (Some(Value::Object(old)), Value::Object(value)) => merge(old, value),
+            (_, value) => {
+                existing.insert(key, value);
+            }
+        }
+    }
+}
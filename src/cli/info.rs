@@ -0,0 +1,376 @@
+//! Describing versions of Go and their releases, for `goup info`, `goup platforms`,
+//! `goup changelog`, `goup bootstrap-script`, and `goup config`
+
+use super::bootstrap;
+use super::enable::describe_shadow;
+use super::install::newest_installed;
+use super::parse::format_date;
+use crate::Toolchain;
+use anyhow::{bail, Context, Result};
+use console::style;
+use goup::size::format_size;
+use goup::{GoVersion, Goup, GoupError, VersionFile};
+use std::env;
+use std::process::Command;
+use std::time::{Duration, UNIX_EPOCH};
+
+pub fn info(goup: &Goup, toolchain: Option<Toolchain>) -> Result<()> {
+    let state = goup.state()?;
+    let toolchain = match (toolchain, &state.enabled_linked, state.enabled) {
+        (Some(toolchain), _, _) => toolchain,
+        (None, Some(name), _) => Toolchain::Linked(name.clone()),
+        (None, None, Some(version)) => Toolchain::Version(version),
+        (None, None, None) => return Err(GoupError::NoVersionEnabled.into()),
+    };
+    let lines = match toolchain {
+        Toolchain::Version(version) if !state.installed.contains(&version) => {
+            return Err(GoupError::VersionNotInstalled(version).into())
+        }
+        Toolchain::Version(version) => info_lines(goup, &state, version),
+        Toolchain::Series(series) => info_lines(goup, &state, newest_installed(goup, series)?),
+        Toolchain::Linked(name) => linked_info_lines(&state, &name)?,
+    };
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// What `goup info` says about the toolchain called `name`
+fn linked_info_lines(state: &VersionFile, name: &str) -> Result<Vec<String>> {
+    let toolchain = state
+        .linked
+        .get(name)
+        .ok_or_else(|| GoupError::ToolchainNotLinked(name.to_string()))?;
+    let mut status = vec![if toolchain.is_custom() {
+        "custom"
+    } else {
+        "linked"
+    }];
+    if state.enabled_linked.as_deref() == Some(name) {
+        status.push("default");
+    }
+    let days = (UNIX_EPOCH + Duration::from_secs(toolchain.linked_at))
+        .elapsed()
+        .unwrap_or_default()
+        .as_secs()
+        / 86400;
+    let mut lines = vec![
+        format!("Toolchain:    {}", name),
+        format!("GOROOT:       {}", toolchain.path.display()),
+        format!("Status:       {}", status.join(", ")),
+        format!("Go version:   {}", toolchain.go_version),
+    ];
+    match &toolchain.custom {
+        Some(source) => {
+            lines.push(format!("Installed:    {} day(s) ago, from a URL", days));
+            lines.push(format!("Source:       {}", source.url));
+            lines.push(format!(
+                "SHA256:       {}",
+                source
+                    .sha256
+                    .as_deref()
+                    .unwrap_or("not checked (--no-verify)")
+            ));
+        }
+        None => lines.push(format!("Linked:       {} day(s) ago", days)),
+    }
+    Ok(lines)
+}
+
+/// What `goup info` says about the installed `version`, a line at a time
+pub fn info_lines(goup: &Goup, state: &VersionFile, version: GoVersion) -> Vec<String> {
+    let mut lines = Vec::new();
+    let mut status = Vec::new();
+    if state.enabled == Some(version) {
+        status.push(match (state.copied, state.junction) {
+            (true, _) => "default (copied)",
+            (false, true) => "default (junction)",
+            (false, false) => "default",
+        });
+    }
+    if state.pin_expired(version) {
+        status.push("pinned (expired)");
+    } else if state.pinned.contains(&version) {
+        status.push("pinned");
+    }
+    if state.minimal.contains(&version) {
+        status.push("minimal");
+    }
+    lines.push(format!("Version:      {}", version));
+    lines.push(format!("GOROOT:       {}", goup.goroot(version).display()));
+    if !status.is_empty() {
+        lines.push(format!("Status:       {}", status.join(", ")));
+    }
+    if let (true, Some(info)) = (
+        state.pinned.contains(&version),
+        state.pin_info.get(&version),
+    ) {
+        let expiry = match info.expires_at() {
+            Some(t) if info.is_expired() => format!(", expired {}", format_date(t)),
+            Some(t) => format!(", until {}", format_date(t)),
+            None => String::new(),
+        };
+        lines.push(format!(
+            "Pinned:       {}{}{}",
+            format_date(info.pinned_at()),
+            expiry,
+            info.reason
+                .as_ref()
+                .map(|r| format!(", {}", r))
+                .unwrap_or_default()
+        ));
+    }
+
+    if let Some(blocked) = goup.config().blocked(version) {
+        lines.push(format!(
+            "Blocked:      by {} in the config file{}",
+            blocked.pattern(),
+            blocked
+                .reason()
+                .map(|r| format!(", {}", r))
+                .unwrap_or_default()
+        ));
+    }
+
+    if let Some(max) = goup.config().max_version {
+        let beyond = if max.admits(version) {
+            ""
+        } else {
+            ", which this version is newer than"
+        };
+        lines.push(format!(
+            "Max version:  {}, set in the config file{}",
+            max, beyond
+        ));
+    }
+
+    if let (true, Some(link)) = (state.enabled == Some(version), goup.link_status()) {
+        lines.push(format!(
+            "Link:         {} symlink, which {}",
+            if link.relative {
+                "relative"
+            } else {
+                "absolute"
+            },
+            if link.resolves {
+                "resolves"
+            } else {
+                "doesn't resolve"
+            }
+        ));
+    }
+    if state.enabled == Some(version) {
+        for shadow in goup.shadowing() {
+            lines.push(format!("Shadowed by:  {}", describe_shadow(&shadow)));
+        }
+    }
+
+    match goup.provenance(version) {
+        Some(p) => {
+            let days = p.installed_at().elapsed().unwrap_or_default().as_secs() / 86400;
+            lines.push(format!(
+                "Installed:    {} day(s) ago, by goup {}",
+                days, p.goup_version
+            ));
+            lines.push(format!("Source:       {}", p.source));
+            // Versions imported from other managers weren't unpacked from an archive
+            if !p.filename.is_empty() {
+                lines.push(format!(
+                    "Archive:      {} ({}/{})",
+                    p.filename, p.os, p.arch
+                ));
+            }
+            if let Some(bootstrap) = p.bootstrap {
+                lines.push(format!("Built with:   {}, from source", bootstrap));
+            }
+        }
+        None => lines
+            .push("Installed:    by an older version of goup, which kept no record".to_string()),
+    }
+    match state.last_used(version) {
+        Some(time) => lines.push(format!("Last used:    {}", format_date(time))),
+        None => lines.push("Last used:    never recorded".to_string()),
+    }
+    lines
+}
+
+pub fn platforms(goup: &Goup, version: GoVersion, json: bool) -> Result<()> {
+    let releases = goup.releases(Some(goup.config().cache_ttl()))?;
+    let release = releases
+        .into_iter()
+        .find(|r| r.version == version)
+        .ok_or(GoupError::VersionNotAvailable(version))?;
+    let mut archives: Vec<_> = release.files.iter().filter(|f| f.is_archive()).collect();
+    archives.sort_by(|a, b| (&a.os, &a.arch).cmp(&(&b.os, &b.arch)));
+
+    if json {
+        let archives: Vec<_> = archives
+            .iter()
+            .map(|f| {
+                serde_json::json!({
+                    "os": f.os,
+                    "arch": f.arch,
+                    "filename": f.filename,
+                    "size": f.size,
+                    "sha256": f.sha256,
+                    "this_platform": f.is_for_this_platform(),
+                })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&archives)?);
+        return Ok(());
+    }
+    if archives.is_empty() {
+        diagnostic!("{} has no archives for any platform", version);
+        return Ok(());
+    }
+    for f in &archives {
+        let size = match f.size {
+            0 => "?".to_string(),
+            size => format_size(size),
+        };
+        let line = format!(
+            "{} {:<20} {:>10}",
+            if f.is_for_this_platform() { "*" } else { " " },
+            format!("{}/{}", f.os, f.arch),
+            size
+        );
+        if f.is_for_this_platform() {
+            println!("{}", style(line).green())
+        } else {
+            println!("{}", line)
+        }
+    }
+    if !archives.iter().any(|f| f.is_for_this_platform()) {
+        diagnostic!("{} has no archive for this platform", version);
+    }
+    Ok(())
+}
+
+pub fn config_list(goup: &Goup) -> Result<()> {
+    let sources = &goup.config().sources;
+    let width = sources.keys().map(String::len).max().unwrap_or_default();
+    for (key, setting) in sources {
+        println!("{:width$}  {}  ({})", key, setting.value, setting.layer);
+    }
+    if sources.is_empty() {
+        status!("Every setting is at its default")
+    } else {
+        status!("Everything else is at its default")
+    }
+    Ok(())
+}
+
+pub fn changelog(goup: &Goup, version: Option<GoVersion>, open: bool) -> Result<()> {
+    let version = match version {
+        Some(v) => v,
+        None => match goup.index(None) {
+            Ok(index) if !index.offline => *index
+                .versions
+                .keys()
+                .last()
+                .ok_or(GoupError::NoVersionsAvailable)?,
+            // Offline, the version in use, or else the one installed last, is the likeliest to
+            // be of interest; a cached list of versions may be out of date
+            res => {
+                let state = goup.state()?;
+                let newest_install = state.installed.iter().copied().max_by_key(|&v| {
+                    let installed_at = goup.provenance(v).map(|p| p.installed_at());
+                    (installed_at, v)
+                });
+                match (state.enabled.or(newest_install), res) {
+                    (Some(v), _) => v,
+                    (None, Ok(index)) => *index
+                        .versions
+                        .keys()
+                        .last()
+                        .ok_or(GoupError::NoVersionsAvailable)?,
+                    (None, Err(e)) => return Err(e.into()),
+                }
+            }
+        },
+    };
+
+    let url = goup::history::release_notes_url(goup.config().docs_url(), version);
+    println!(
+        "Release notes for go{}.{}: {}",
+        version.major, version.minor, url
+    );
+
+    // Patch releases don't get their own notes, just a line in the release history
+    if version.patch > 0 {
+        match goup.release_history().map(|mut h| h.remove(&version)) {
+            Ok(Some(entry)) => println!("\n{}", entry),
+            Ok(None) => diagnostic!("{} is not in the release history yet", version),
+            Err(_) => {}
+        }
+    }
+
+    if open {
+        open_in_browser(&url)?;
+    }
+    Ok(())
+}
+
+/// Open the given URL with the platform's default handler
+fn open_in_browser(url: &str) -> Result<()> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", "start", ""]);
+        cmd
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    let status = cmd
+        .arg(url)
+        .status()
+        .with_context(|| format!("Unable to open {}", url))?;
+    if !status.success() {
+        bail!("Unable to open {}", url);
+    }
+    Ok(())
+}
+
+pub fn bootstrap_script(
+    goup: &Goup,
+    version: GoVersion,
+    shell: bootstrap::Shell,
+    os: Option<String>,
+    arch: Option<String>,
+    prefix: Option<String>,
+) -> Result<()> {
+    let os = os.unwrap_or_else(|| env::consts::OS.to_string());
+    let arch = match arch.or_else(|| goup::remote::arch().map(str::to_string)) {
+        Some(arch) => arch,
+        None => bail!(
+            "Go doesn't support this machine's architecture, {}; give one with --arch",
+            env::consts::ARCH
+        ),
+    };
+    let releases = goup.releases(Some(goup.config().cache_ttl()))?;
+    let release = releases
+        .into_iter()
+        .find(|r| r.version == version)
+        .ok_or(GoupError::VersionNotAvailable(version))?;
+    let Some(file) = release
+        .files
+        .iter()
+        .find(|f| f.os == os && f.arch == arch && f.is_archive())
+    else {
+        bail!(
+            "{} has no archive for {}/{}; `goup platforms {}` lists those it has",
+            version,
+            os,
+            arch,
+            version
+        );
+    };
+    let url = goup.config().archive_url(&file.filename);
+    let prefix = prefix.as_deref().unwrap_or(shell.default_prefix());
+    print!("{}", bootstrap::script(shell, version, file, &url, prefix));
+    Ok(())
+}
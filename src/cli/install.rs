@@ -0,0 +1,340 @@
+//! Installing versions of Go, for `goup install`
+
+use super::prompt::{self, NonInteractive};
+use super::report::{self, Steps};
+use anyhow::{anyhow, bail, Context, Result};
+use console::style;
+use goup::build;
+use goup::size::format_size;
+use goup::version::VersionRequest;
+use goup::{GoVersion, Goup, GoupError, Index};
+use std::fs;
+use std::path::Path;
+
+/// Refuse to install a version beyond max_version or on the blocklist unless told to, and say
+/// so when overriding
+pub fn check_policy(goup: &Goup, version: GoVersion, override_policy: bool) -> Result<()> {
+    if let Some(blocked) = goup.config().blocked(version) {
+        if !override_policy {
+            return Err(GoupError::VersionBlocked {
+                version,
+                reason: blocked.reason().map(str::to_string),
+            }
+            .into());
+        }
+        diagnostic!(
+            "{} installing {}, which is on the blocklist, because of --override-policy",
+            style("Notice:").yellow(),
+            version
+        );
+    }
+    let Some(max) = goup.config().max_version.filter(|max| !max.admits(version)) else {
+        return Ok(());
+    };
+    if !override_policy {
+        return Err(GoupError::BeyondMaxVersion { version, max }.into());
+    }
+    diagnostic!(
+        "{} installing {}, which is newer than max_version {}, because of --override-policy",
+        style("Notice:").yellow(),
+        version,
+        max
+    );
+    Ok(())
+}
+
+/// Let the user know when the list of versions came from the cache because go.dev couldn't be
+/// reached, or when parts of it couldn't be understood and were left out
+pub fn warn_about_index(goup: &Goup, index: &Index) {
+    for skipped in &index.skipped {
+        diagnostic!("Warning: skipped {} in the list of versions", skipped);
+    }
+    if index.offline {
+        let reason = if goup.config().offline() {
+            "in offline mode"
+        } else {
+            "unable to reach go.dev"
+        };
+        let hours = index.fetched.elapsed().unwrap_or_default().as_secs() / 3600;
+        diagnostic!(
+            "Warning: {}; the list of versions is {} hour(s) old",
+            reason,
+            hours
+        );
+    }
+}
+
+pub fn install(goup: &Goup, version: GoVersion, override_policy: bool) -> Result<()> {
+    check_policy(goup, version, override_policy)?;
+    let mut steps = Steps::new(report::reporter(), 2);
+    let available = steps.run("index", "Fetching the list of versions", || {
+        goup.available()
+    })?;
+    let file = available
+        .get(&version)
+        .ok_or(GoupError::VersionNotAvailable(version))?;
+    let downloading = format!("Downloading {}", version);
+    let omitted = steps.run("download", &downloading, || {
+        goup.transaction(|tx| tx.download(version, file))
+    })?;
+    steps.finish();
+    match omitted {
+        0 => status!("{} installed successfully", version),
+        _ => status!(
+            "{} installed successfully, saving {} by leaving out tests and documentation",
+            version,
+            format_size(omitted)
+        ),
+    }
+    Ok(())
+}
+
+/// Install the newest release of `series`, or of all, that had been published by `date`
+pub fn install_as_of(
+    goup: &Goup,
+    date: &str,
+    series: Option<VersionRequest>,
+    override_policy: bool,
+) -> Result<()> {
+    let request = series.unwrap_or(VersionRequest::Latest);
+    let dates = goup.release_dates()?;
+    let version = goup::history::release_as_of(&dates, request, date).ok_or_else(|| {
+        GoupError::NoReleaseAsOf {
+            request,
+            date: date.to_string(),
+        }
+    })?;
+    status!(
+        "{} is the newest release{} as of {}, released {}",
+        version,
+        match series {
+            Some(series) => format!(" of {}", series),
+            None => String::new(),
+        },
+        date,
+        dates[&version]
+    );
+    install(goup, version, override_policy)
+}
+
+/// Roughly how much disk space a build of Go from source takes, including its build cache
+const BUILD_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Install `version` by building it from source with `bootstrap`, or a suitable installed
+/// version, offering to install one if there isn't any
+pub fn build_from_source(
+    goup: &Goup,
+    version: GoVersion,
+    bootstrap: Option<GoVersion>,
+    override_policy: bool,
+) -> Result<()> {
+    check_policy(goup, version, override_policy)?;
+    if goup.installed()?.contains(&version) {
+        status!("{} is already installed", version);
+        return Ok(());
+    }
+    diagnostic!(
+        "{} building {} from source takes several minutes, and around {} of disk space",
+        style("Warning:").yellow(),
+        version,
+        format_size(BUILD_SIZE)
+    );
+    if !prompt::confirm(&format!("Build {} from source?", version))? {
+        status!("Skipped building {}", version);
+        return Ok(());
+    }
+
+    let bootstrap = match bootstrap {
+        Some(bootstrap) => Some(bootstrap),
+        None => goup.bootstrap_for(version)?,
+    };
+    let bootstrap = match bootstrap {
+        Some(bootstrap) => bootstrap,
+        None => {
+            let needs = build::bootstrap_version(version);
+            let available = goup.available()?;
+            // Any release from `needs` on will do, and the newest one before the version being
+            // built is the most likely to have an archive for this platform
+            let candidate = available
+                .keys()
+                .copied()
+                .rfind(|&v| v >= needs && v < version)
+                .ok_or(GoupError::VersionNotAvailable(needs))?;
+            let question = format!(
+                "Building {} needs {} or newer to bootstrap it. Install {}?",
+                version, needs, candidate
+            );
+            if !prompt::confirm(&question)? {
+                status!("Skipped building {}", version);
+                return Ok(());
+            }
+            install(goup, candidate, true)?;
+            candidate
+        }
+    };
+
+    let mut steps = Steps::new(report::reporter(), 2);
+    let file = steps.run("index", "Fetching the list of versions", || {
+        goup.source_release(version)
+    })?;
+    let building = format!("Building {} with {}", version, bootstrap);
+    steps.run("build", &building, || {
+        goup.transaction(|tx| tx.build(version, &file, bootstrap))
+    })?;
+    steps.finish();
+    status!("{} built from source and installed successfully", version);
+    Ok(())
+}
+
+/// Install each of the versions listed in the file at `path`, carrying on past any that fail,
+/// and then enable the one matching `enable`
+pub fn install_from_file(
+    goup: &Goup,
+    path: &Path,
+    enable: Option<VersionRequest>,
+    override_policy: bool,
+) -> Result<()> {
+    let requests = read_version_list(path)?;
+    let mut steps = Steps::new(report::reporter(), requests.len() + 1);
+    let available = steps.run("index", "Fetching the list of versions", || {
+        goup.available()
+    })?;
+
+    let mut installed = Vec::new();
+    let mut failed = Vec::new();
+    for request in &requests {
+        // Only a release named exactly may go beyond max_version or be on the blocklist
+        let candidates = available
+            .keys()
+            .copied()
+            .filter(|&v| matches!(request, VersionRequest::Exact(_)) || goup.config().allows(v));
+        let Some(version) = request.resolve(candidates) else {
+            steps.skip();
+            failed.push(format!("{}: no release matches", request));
+            continue;
+        };
+        if let Err(e) = check_policy(goup, version, override_policy) {
+            steps.skip();
+            failed.push(format!("{}: {}", version, e));
+            continue;
+        }
+        if installed.contains(&version) {
+            steps.skip();
+            continue;
+        }
+        let downloading = format!("Downloading {}", version);
+        let res = steps.run("download", &downloading, || {
+            goup.transaction(|tx| tx.download(version, &available[&version]))
+        });
+        match res {
+            Ok(_) => installed.push(version),
+            Err(e) => failed.push(format!("{}: {}", version, e)),
+        }
+    }
+    steps.finish();
+
+    for version in &installed {
+        status!("{} installed successfully", version);
+    }
+    for failure in &failed {
+        diagnostic!("{} {}", style("Failed").red(), failure);
+    }
+    if let Some(request) = enable {
+        match request.resolve(installed.iter().copied()) {
+            Some(version) => {
+                goup.enable(version)?;
+                status!("{} enabled", version);
+            }
+            None => failed.push(format!("{}: not installed, so it was not enabled", request)),
+        }
+    }
+    if !failed.is_empty() {
+        bail!(
+            "{} of the {} version(s) in {} could not be installed",
+            failed.len(),
+            requests.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Read a list of versions, one per line, ignoring blank lines and `#` comments
+fn read_version_list(path: &Path) -> Result<Vec<VersionRequest>> {
+    let text =
+        fs::read_to_string(path).with_context(|| format!("Unable to read {}", path.display()))?;
+    let mut requests = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let request = line
+            .parse()
+            .map_err(|e| anyhow!("{}:{}: {} '{}'", path.display(), number + 1, e, line))?;
+        requests.push(request);
+    }
+    if requests.is_empty() {
+        bail!("{} doesn't list any versions", path.display());
+    }
+    Ok(requests)
+}
+
+/// The newest release of `series` that goup may install, for commands given a version without
+/// its patch number. Which release it chose is said, since the version given doesn't name it.
+pub fn newest_available(goup: &Goup, series: VersionRequest) -> Result<GoVersion> {
+    let available = goup.available()?;
+    let allowed = available.into_keys().filter(|&v| goup.config().allows(v));
+    let version = series
+        .resolve(allowed)
+        .ok_or(GoupError::SeriesNotAvailable(series))?;
+    status!("Resolved {} to {}, its newest release", series, version);
+    Ok(version)
+}
+
+/// The newest installed release of `series`, as [`newest_available`] chooses among those
+/// available
+pub fn newest_installed(goup: &Goup, series: VersionRequest) -> Result<GoVersion> {
+    let version = series
+        .resolve(goup.installed()?)
+        .ok_or(GoupError::SeriesNotInstalled(series))?;
+    status!(
+        "Resolved {} to {}, its newest installed release",
+        series,
+        version
+    );
+    Ok(version)
+}
+
+pub fn install_custom(goup: &Goup, name: &str, url: &str, sha256: Option<&str>) -> Result<()> {
+    if sha256.is_none() {
+        diagnostic!(
+            "{} The archive's digest won't be checked (--no-verify)",
+            style("Warning:").yellow()
+        );
+    }
+    let toolchain = goup.install_custom(name, url, sha256)?;
+    status!(
+        "{} installed successfully from {} ({})",
+        name,
+        url,
+        toolchain.go_version
+    );
+    Ok(())
+}
+
+/// Make sure the given version is installed, offering to install it when running interactively
+pub fn ensure_installed(goup: &Goup, version: GoVersion) -> Result<()> {
+    if goup.installed()?.contains(&version) {
+        return Ok(());
+    }
+
+    // Without anyone to offer it to, the version is simply missing
+    let question = format!("{} is not installed. Install it now?", version);
+    match prompt::confirm(&question) {
+        Ok(true) => install(goup, version, false),
+        Ok(false) => Err(GoupError::VersionNotInstalled(version).into()),
+        Err(e) if e.is::<NonInteractive>() => Err(GoupError::VersionNotInstalled(version).into()),
+        Err(e) => Err(e),
+    }
+}
@@ -0,0 +1,24 @@
+//! Noticing Ctrl-C, for commands that should stop cleanly rather than be killed
+
+use std::sync::atomic::AtomicBool;
+#[cfg(unix)]
+use std::sync::atomic::Ordering;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+#[cfg(unix)]
+extern "C" fn on_interrupt(_signal: libc::c_int) {
+    // Storing to an atomic is all that is safe to do in a signal handler
+    INTERRUPTED.store(true, Ordering::Relaxed);
+}
+
+/// Catch Ctrl-C from now on, returning the flag that it sets instead of ending the program.
+/// Elsewhere than Unix, Ctrl-C still ends the program.
+pub fn catch() -> &'static AtomicBool {
+    #[cfg(unix)]
+    unsafe {
+        let handler: extern "C" fn(libc::c_int) = on_interrupt;
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+    &INTERRUPTED
+}
@@ -0,0 +1,333 @@
+//! Listing versions of Go, for `goup list` and `goup du`
+
+use super::format::Template;
+use super::parse::format_date;
+use super::pin;
+use super::update::list_index;
+use anyhow::Result;
+use console::style;
+use goup::series::{self, Status};
+use goup::size::format_size;
+use goup::{GoVersion, Goup, GoupError, VersionFile};
+use indicatif::ProgressBar;
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// List the versions, as a tree if `tree` is given, in which case it says whether to expand
+/// each series
+pub fn versions(
+    goup: &Goup,
+    installed_only: bool,
+    strict: bool,
+    show_size: bool,
+    long: bool,
+    tree: Option<bool>,
+) -> Result<()> {
+    let state = goup.state()?;
+    let VersionFile {
+        enabled,
+        installed,
+        pinned,
+        minimal,
+        linked,
+        enabled_linked,
+        ..
+    } = state.clone();
+    let sizes = if show_size {
+        measure(goup, &installed)?
+    } else {
+        BTreeMap::new()
+    };
+    let index = if installed_only {
+        None
+    } else {
+        list_index(goup, strict)?
+    };
+    // Without a list of versions, nothing can be said about what is available
+    let installed_only = index.is_none();
+    let (available, security) = match index {
+        None => (BTreeSet::new(), BTreeSet::new()),
+        Some(index) => (
+            index.versions.into_keys().collect(),
+            goup.security_releases(),
+        ),
+    };
+
+    // Only worth pointing out where a project or the environment overrides the default
+    let active = active_version(goup).filter(|&v| enabled != Some(v));
+    let statuses: BTreeMap<GoVersion, Status> = installed
+        .union(&available)
+        .map(|v| {
+            let status = Status {
+                installed: installed.contains(v),
+                available: available.contains(v),
+                enabled: enabled == Some(*v),
+                pinned: pinned.contains(v),
+            };
+            (*v, status)
+        })
+        .collect();
+
+    let line = |v: &GoVersion| {
+        let status = statuses[v];
+        let Status {
+            installed: is_installed,
+            available: is_available,
+            enabled: is_enabled,
+            pinned: _,
+        } = status;
+
+        let bullet = if is_enabled {
+            "*"
+        } else if is_installed {
+            "i"
+        } else {
+            " "
+        };
+        let pinned_text = pin::label(&state, *v);
+        let blocked_text = match goup.config().blocked(*v).map(|b| b.reason()) {
+            Some(Some(reason)) => format!(" (BLOCKED: {})", reason),
+            Some(None) => " (BLOCKED)".to_string(),
+            None => String::new(),
+        };
+        let security_text = if security.contains(v) {
+            " (security)"
+        } else {
+            ""
+        };
+        let active_text = if active == Some(*v) {
+            " (active here)"
+        } else {
+            ""
+        };
+        let minimal_text = if minimal.contains(v) {
+            " (minimal)"
+        } else {
+            ""
+        };
+        let size_text = match sizes.get(v) {
+            Some(&size) => format!(" [{}]", format_size(size)),
+            None => String::new(),
+        };
+        let extras = format!(
+            "{}{}{}{}{}{}",
+            active_text, security_text, blocked_text, pinned_text, minimal_text, size_text
+        );
+        // The color only reinforces the status, which is always given in words as well
+        let string = if long {
+            format!(
+                "{} {:<10} {}{}",
+                bullet,
+                v.to_string(),
+                status.label(!installed_only),
+                extras
+            )
+        } else if is_installed && !is_available && !installed_only {
+            format!("{} {} (unavailable upstream){}", bullet, v, extras)
+        } else {
+            format!("{} {}{}", bullet, v, extras)
+        };
+
+        match (is_installed, is_available, is_enabled) {
+            _ if installed_only => style(string),
+            (true, true, _) => style(string).green(),
+            (true, false, true) => style(string).red(),
+            (true, false, false) => style(string).yellow(),
+            _ => style(string),
+        }
+    };
+
+    // Linked toolchains aren't versions, so they come first, outside of any series
+    for (name, toolchain) in &linked {
+        let bullet = if enabled_linked.as_deref() == Some(name.as_str()) {
+            "*"
+        } else {
+            "i"
+        };
+        let origin = match &toolchain.custom {
+            Some(source) => format!("custom, installed from {}", source.url),
+            None => format!("linked to {}", toolchain.path.display()),
+        };
+        let string = if long {
+            format!("{} {:<10} {}", bullet, name, origin)
+        } else {
+            format!("{} {} ({})", bullet, name, origin)
+        };
+        println!("{}", style(string).cyan());
+    }
+
+    let Some(expand) = tree else {
+        for v in statuses.keys().rev() {
+            println!("{}", line(v));
+        }
+        return Ok(());
+    };
+    for series in series::group(&statuses, expand) {
+        println!(
+            "{}",
+            style(format!(
+                "{} — {} release(s), {} installed",
+                series, series.releases, series.installed
+            ))
+            .bold()
+        );
+        for v in &series.shown {
+            println!("  {}", line(v));
+        }
+        if series.hidden > 0 {
+            println!("    … {} more (--expand to show)", series.hidden);
+        }
+    }
+    Ok(())
+}
+
+/// List the versions using `template`, one line each, for scripts
+pub fn formatted(
+    goup: &Goup,
+    installed_only: bool,
+    strict: bool,
+    template: &Template,
+) -> Result<()> {
+    let state = goup.state()?;
+    let VersionFile {
+        enabled,
+        installed,
+        pinned,
+        ..
+    } = state.clone();
+    let sizes = if template.uses("size") {
+        measure(goup, &installed)?
+    } else {
+        BTreeMap::new()
+    };
+    let index = if installed_only {
+        None
+    } else {
+        list_index(goup, strict)?
+    };
+    let known = index.is_some();
+    let available: BTreeSet<GoVersion> = index
+        .map(|index| index.versions.into_keys().collect())
+        .unwrap_or_default();
+
+    let versions: BTreeSet<_> = installed.union(&available).collect();
+    for v in versions.into_iter().rev() {
+        let line = template.render(|field| match field {
+            "version" => v.to_string(),
+            "semver" => v.format_semver(),
+            "status" => Status {
+                installed: installed.contains(v),
+                available: available.contains(v),
+                enabled: enabled == Some(*v),
+                pinned: pinned.contains(v),
+            }
+            .label(known)
+            .to_string(),
+            "installed" => installed.contains(v).to_string(),
+            "enabled" => (enabled == Some(*v)).to_string(),
+            "pinned" => pinned.contains(v).to_string(),
+            "available" => available.contains(v).to_string(),
+            "size" => sizes.get(v).map(u64::to_string).unwrap_or_default(),
+            "date" => goup
+                .provenance(*v)
+                .map(|p| format_date(p.installed_at()))
+                .unwrap_or_default(),
+            "last_used" => state.last_used(*v).map(format_date).unwrap_or_default(),
+            _ => unreachable!("the template was checked against LIST_FIELDS"),
+        });
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// List the versions as JSON, for scripts and widgets
+pub fn json(goup: &Goup, installed_only: bool, strict: bool, show_size: bool) -> Result<()> {
+    let VersionFile {
+        enabled,
+        installed,
+        pinned,
+        ..
+    } = goup.state()?;
+    let sizes = if show_size {
+        measure(goup, &installed)?
+    } else {
+        BTreeMap::new()
+    };
+    let index = if installed_only {
+        None
+    } else {
+        list_index(goup, strict)?
+    };
+    let available: Option<BTreeSet<GoVersion>> =
+        index.map(|index| index.versions.into_keys().collect());
+
+    let known = available.clone().unwrap_or_default();
+    let versions: BTreeSet<_> = installed.union(&known).collect();
+    let versions: Vec<_> = versions
+        .into_iter()
+        .rev()
+        .map(|v| {
+            let mut entry = serde_json::json!({
+                "version": v,
+                "installed": installed.contains(v),
+                "enabled": enabled == Some(*v),
+                "pinned": pinned.contains(v),
+                "available": available.as_ref().map(|a| a.contains(v)),
+            });
+            if show_size {
+                entry["size"] = serde_json::json!(sizes.get(v));
+            }
+            entry
+        })
+        .collect();
+    let list = serde_json::json!({
+        "enabled": enabled,
+        "active_here": active_version(goup),
+        "versions": versions,
+    });
+    println!("{}", serde_json::to_string_pretty(&list)?);
+    Ok(())
+}
+
+/// The installed version active in the current directory, if there is one
+fn active_version(goup: &Goup) -> Option<GoVersion> {
+    let dir = env::current_dir().ok()?;
+    goup.resolve(&dir).ok()?.version
+}
+
+pub fn du(goup: &Goup) -> Result<()> {
+    let sizes = measure(goup, &goup.installed()?)?;
+    for (version, size) in sizes.iter().rev() {
+        println!("{:>10}  {}", format_size(*size), version);
+    }
+    println!("{:>10}  total", format_size(sizes.values().sum()));
+    Ok(())
+}
+
+/// Measure the disk space taken up by each of the given versions, showing a spinner if it
+/// takes a while
+fn measure(goup: &Goup, versions: &BTreeSet<GoVersion>) -> Result<BTreeMap<GoVersion, u64>> {
+    let (done, finished) = mpsc::channel();
+    thread::scope(|s| {
+        s.spawn(move || {
+            let sizes = versions
+                .iter()
+                .map(|&v| Ok((v, goup.size(v)?)))
+                .collect::<Result<BTreeMap<_, _>, GoupError>>();
+            let _ = done.send(sizes);
+        });
+
+        // Quick measurements shouldn't flash a spinner on the screen
+        if let Ok(sizes) = finished.recv_timeout(Duration::from_secs(1)) {
+            return Ok(sizes?);
+        }
+        let spinner = ProgressBar::new_spinner().with_message("Measuring installed versions");
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        let sizes = finished.recv().expect("measurement thread panicked");
+        spinner.finish_and_clear();
+        Ok(sizes?)
+    })
+}
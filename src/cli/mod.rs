@@ -0,0 +1,34 @@
+//! Pieces of the command line interface that have no place in the library
+
+pub mod actions;
+pub mod bootstrap;
+pub mod bundle;
+pub mod cache;
+pub mod crash;
+pub mod debug;
+pub mod enable;
+pub mod errors;
+pub mod events;
+pub mod exit;
+pub mod format;
+pub mod hooks;
+pub mod ide;
+pub mod info;
+pub mod install;
+pub mod interrupt;
+pub mod list;
+pub mod modulefiles;
+pub mod parse;
+pub mod pin;
+pub mod progress;
+pub mod prompt;
+pub mod remove;
+pub mod report;
+pub mod resolve;
+pub mod run;
+pub mod server;
+pub mod setup;
+pub mod startup;
+pub mod tools;
+pub mod ui;
+pub mod update;
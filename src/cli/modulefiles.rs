@@ -0,0 +1,192 @@
+//! Modulefiles for environment modules and Lmod, so that `module load go/1.22.6` makes an
+//! installed version active, for `goup modulefiles`
+
+use anyhow::Result;
+use goup::{GoVersion, Goup, GoupError};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const TCL: &str = include_str!("templates/modulefile.tcl");
+const LUA: &str = include_str!("templates/modulefile.lua");
+/// The file naming the default version of the module, which Lmod reads as well
+const DEFAULT: &str = include_str!("templates/version.tcl");
+
+/// What every file goup writes says near its top, so that files written by hand are never
+/// overwritten or pruned
+const MARKER: &str = "Generated by goup";
+
+/// The name the modulefiles are loaded under, as in `module load go/1.22.6`
+pub const MODULE: &str = "go";
+
+/// The language modulefiles are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    /// Tcl, which both environment modules and Lmod read
+    Tcl,
+    /// Lua, which only Lmod reads
+    Lua,
+}
+
+impl Format {
+    /// The name of the modulefile for `version`
+    fn file_name(self, version: GoVersion) -> String {
+        match self {
+            Format::Tcl => number(version),
+            Format::Lua => format!("{}.lua", number(version)),
+        }
+    }
+
+    /// The line setting the variable `name` to `value`
+    fn setenv(self, name: &str, value: &str) -> String {
+        match self {
+            Format::Tcl => format!("setenv {} \"{}\"", name, self.escape(value)),
+            Format::Lua => format!("setenv(\"{}\", \"{}\")", name, self.escape(value)),
+        }
+    }
+
+    /// `text` as it must be written within double quotes
+    fn escape(self, text: &str) -> String {
+        let special: &[char] = match self {
+            Format::Tcl => &['\\', '"', '$', '[', ']'],
+            Format::Lua => &['\\', '"'],
+        };
+        let mut escaped = String::with_capacity(text.len());
+        for c in text.chars() {
+            if special.contains(&c) {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    }
+}
+
+/// What was done to one file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Created(PathBuf),
+    Updated(PathBuf),
+    Unchanged(PathBuf),
+    /// A file goup wrote earlier, that no longer belongs
+    Removed(PathBuf),
+    /// A file goup wrote earlier for a version that is no longer installed, left in place
+    /// without `--prune`
+    Stale(PathBuf),
+    /// A file in the way that goup didn't write, which is left alone
+    Foreign(PathBuf),
+}
+
+/// The modulefile making `version` active, installed at `goroot`
+fn render(goup: &Goup, format: Format, version: GoVersion, goroot: &Path) -> String {
+    let mut env = vec![format.setenv("GOROOT", &goroot.display().to_string())];
+    if goup.config().gotoolchain_local() {
+        env.push(format.setenv("GOTOOLCHAIN", "local"));
+    }
+    let template = match format {
+        Format::Tcl => TCL,
+        Format::Lua => LUA,
+    };
+    template
+        .replace("{version}", &version.to_string())
+        .replace("{number}", &number(version))
+        .replace("{goroot}", &format.escape(&goroot.display().to_string()))
+        .replace("{env}", &env.join("\n"))
+        .replace(
+            "{bin}",
+            &format.escape(&goroot.join("bin").display().to_string()),
+        )
+}
+
+/// Write a modulefile for every installed version into the `go` directory within `out_dir`,
+/// along with a `.version` file making the enabled version the default. Files goup wrote
+/// before that no longer belong are removed if `prune` is given.
+pub fn write(goup: &Goup, out_dir: &Path, format: Format, prune: bool) -> Result<Vec<Change>> {
+    let state = goup.state()?;
+    let dir = out_dir.join(MODULE);
+    fs::create_dir_all(&dir).map_err(GoupError::io("create", &dir))?;
+
+    let mut changes = Vec::new();
+    let mut wanted = BTreeSet::new();
+    for &version in &state.installed {
+        let path = dir.join(format.file_name(version));
+        let text = render(goup, format, version, &goup.goroot(version));
+        changes.push(put(&path, &text)?);
+        wanted.insert(path);
+    }
+    let default = dir.join(".version");
+    if let Some(enabled) = state.enabled {
+        changes.push(put(
+            &default,
+            &DEFAULT.replace("{number}", &number(enabled)),
+        )?);
+        wanted.insert(default);
+    }
+
+    for entry in fs::read_dir(&dir).map_err(GoupError::io("read", &dir))? {
+        let path = entry.map_err(GoupError::io("read", &dir))?.path();
+        if wanted.contains(&path) || !is_generated(&path)? {
+            continue;
+        }
+        // The default is wrong as soon as nothing is enabled, so it goes whatever `prune` says
+        let installed = version_of(&path).is_some_and(|v| state.installed.contains(&v));
+        if prune || installed || path == dir.join(".version") {
+            fs::remove_file(&path).map_err(GoupError::io("remove", &path))?;
+            changes.push(Change::Removed(path));
+        } else {
+            changes.push(Change::Stale(path));
+        }
+    }
+    Ok(changes)
+}
+
+/// Write `text` to `path`, unless it already holds it or goup didn't write it
+fn put(path: &Path, text: &str) -> Result<Change> {
+    let existing = match fs::read_to_string(path) {
+        Ok(existing) => Some(existing),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(GoupError::io("read", path)(e).into()),
+    };
+    let path = path.to_path_buf();
+    match existing.as_deref() {
+        Some(existing) if existing == text => return Ok(Change::Unchanged(path)),
+        Some(existing) if !has_marker(existing) => return Ok(Change::Foreign(path)),
+        _ => {}
+    }
+    fs::write(&path, text).map_err(GoupError::io("write", &path))?;
+    match existing {
+        Some(_) => Ok(Change::Updated(path)),
+        None => Ok(Change::Created(path)),
+    }
+}
+
+/// Whether the file at `path` was written by goup
+fn is_generated(path: &Path) -> Result<bool> {
+    if !path.is_file() {
+        return Ok(false);
+    }
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(has_marker(&text)),
+        // Binary files aren't modulefiles of goup's
+        Err(e) if e.kind() == io::ErrorKind::InvalidData => Ok(false),
+        Err(e) => Err(GoupError::io("read", path)(e).into()),
+    }
+}
+
+fn has_marker(text: &str) -> bool {
+    text.lines().take(2).any(|l| l.contains(MARKER))
+}
+
+/// The version a modulefile is named for, in either format
+fn version_of(path: &Path) -> Option<GoVersion> {
+    let name = path.file_name()?.to_str()?;
+    let name = name.strip_suffix(".lua").unwrap_or(name);
+    format!("go{}", name).parse().ok()
+}
+
+/// The version without its `go` prefix, as modules name it
+fn number(version: GoVersion) -> String {
+    let name = version.to_string();
+    name.strip_prefix("go").unwrap_or(&name).to_string()
+}
@@ -0,0 +1,159 @@
+//! Reading the dates, durations, and versions given on the command line, and writing dates
+//! and durations back out
+
+use super::format::{Template, LIST_FIELDS};
+use anyhow::Result;
+use goup::version::VersionRequest;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub fn parse_list_format(template: &str) -> Result<Template, String> {
+    Template::parse(template, LIST_FIELDS)
+}
+
+/// Accept only a series, like `go1.21`, for `goup upgrade`
+pub fn parse_platform(platform: &str) -> Result<(String, String), String> {
+    match platform.split_once('/') {
+        Some((os, arch)) if !os.is_empty() && !arch.is_empty() && !arch.contains('/') => {
+            Ok((os.to_string(), arch.to_string()))
+        }
+        _ => Err("expected OS/ARCH, like linux/amd64".to_string()),
+    }
+}
+
+/// A release like go1.22.6, or a series like go1.22 for one of its releases
+pub fn parse_release(version: &str) -> Result<VersionRequest, String> {
+    match version.parse() {
+        Ok(VersionRequest::Latest) => {
+            Err("expected a release like go1.22.6, or a series like go1.22".to_string())
+        }
+        Ok(request) => Ok(request),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+pub fn parse_series(series: &str) -> Result<VersionRequest, String> {
+    match series.parse() {
+        Ok(request @ VersionRequest::Series { .. }) => Ok(request),
+        Ok(_) => Err("expected a series like go1.21, without a patch number".to_string()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// The date of the given time as YYYY-MM-DD, in UTC
+pub fn format_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86400;
+    // Convert days since the epoch to a civil date, after Howard Hinnant's `civil_from_days`
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Parse a date given as YYYY-MM-DD, in UTC, into the last second of that day, so that a pin
+/// until then lasts through it. Dates that have passed are refused.
+pub fn parse_date(date: &str) -> Result<SystemTime, String> {
+    let days = parse_day(
+        date,
+        "a date like 2025-10-01, or --for with a length of time like 90d",
+    )?;
+    let end = UNIX_EPOCH + Duration::from_secs(u64::try_from(days).unwrap_or(0) * 86400 + 86399);
+    if end <= SystemTime::now() {
+        return Err(format!("{} has already passed", date));
+    }
+    Ok(end)
+}
+
+/// Check a date given to --as-of, keeping it as YYYY-MM-DD to compare with release dates
+pub fn parse_as_of(date: &str) -> Result<String, String> {
+    parse_day(date, "a date like 2024-03-15")?;
+    Ok(date.to_string())
+}
+
+/// Parse a date given as YYYY-MM-DD into the number of days since the epoch, describing what
+/// was `expected` if it isn't a date at all
+fn parse_day(date: &str, expected: &str) -> Result<i64, String> {
+    let invalid = || format!("expected {}, not '{}'", expected, date);
+    let fields: Vec<_> = date.split('-').collect();
+    let [year, month, day] = fields[..] else {
+        return Err(invalid());
+    };
+    let number = |field: &str, len: usize| {
+        if field.len() == len && field.bytes().all(|b| b.is_ascii_digit()) {
+            field.parse::<i64>().map_err(|_| invalid())
+        } else {
+            Err(invalid())
+        }
+    };
+    let (year, month, day) = (number(year, 4)?, number(month, 2)?, number(day, 2)?);
+    let leap = year % 4 == 0 && (year % 100 != 0 || year % 400 == 0);
+    let month_days = match month {
+        2 if leap => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        1..=12 => 31,
+        _ => return Err(format!("{} has no month {}", date, month)),
+    };
+    if !(1..=month_days).contains(&day) {
+        return Err(format!("{} is not a day of the month", date));
+    }
+
+    // Convert the civil date to days since the epoch, after Howard Hinnant's
+    // `days_from_civil`
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    Ok(era * 146097 + doe - 719468)
+}
+
+/// Parse a duration given as a number followed by a unit, such as `90d`, `6months`, or `1y`.
+/// A month is taken to be 30 days, and a year 365.
+pub fn parse_age(age: &str) -> Result<Duration, String> {
+    let split = age.find(|c: char| !c.is_ascii_digit()).unwrap_or(age.len());
+    let (count, unit) = age.split_at(split);
+    let count: u64 = count.parse().map_err(|_| {
+        format!(
+            "expected a number followed by a unit, like 30d, not '{}'",
+            age
+        )
+    })?;
+    const DAY: u64 = 24 * 60 * 60;
+    let seconds = match unit.trim() {
+        "s" | "sec" | "secs" | "second" | "seconds" => 1,
+        "m" | "min" | "mins" | "minute" | "minutes" => 60,
+        "h" | "hour" | "hours" => 60 * 60,
+        "d" | "day" | "days" => DAY,
+        "w" | "week" | "weeks" => 7 * DAY,
+        "mo" | "month" | "months" => 30 * DAY,
+        "y" | "year" | "years" => 365 * DAY,
+        _ => {
+            return Err(format!(
+                "unknown unit '{}'; use s, m, h, d, w, months, or y",
+                unit
+            ))
+        }
+    };
+    Ok(Duration::from_secs(count.saturating_mul(seconds)))
+}
+
+/// Describe how long ago something happened in its largest whole unit, like `3d`
+pub fn format_age(age: Duration) -> String {
+    let seconds = age.as_secs();
+    match seconds {
+        0..60 => format!("{}s", seconds),
+        60..3600 => format!("{}m", seconds / 60),
+        3600..86400 => format!("{}h", seconds / 3600),
+        _ => format!("{}d", seconds / 86400),
+    }
+}
@@ -0,0 +1,117 @@
+//! Keeping versions from being cleaned up or installed, for `goup pin` and `goup block`
+
+use super::parse::format_date;
+use anyhow::{bail, Result};
+use goup::config;
+use goup::version::VersionPattern;
+use goup::{GoVersion, Goup, GoupError, VersionFile};
+use std::time::SystemTime;
+
+pub fn pin(
+    goup: &Goup,
+    version: GoVersion,
+    reason: Option<String>,
+    expires: Option<SystemTime>,
+) -> Result<()> {
+    match expires {
+        Some(expires) => {
+            goup.pin_until(version, reason, expires)?;
+            status!("Pinned {} until {}", version, format_date(expires));
+        }
+        None => goup.pin(version, reason)?,
+    }
+    Ok(())
+}
+
+pub fn list(goup: &Goup) -> Result<()> {
+    let state = goup.state()?;
+    if state.pinned.is_empty() {
+        diagnostic!("No versions are pinned");
+    }
+    for &version in &state.pinned {
+        let info = state.pin_info.get(&version);
+        let pinned_at = match info {
+            Some(info) => format_date(info.pinned_at()),
+            None => "unknown date".to_string(),
+        };
+        let expiry = match info.and_then(|i| i.expires_at()) {
+            Some(t) if state.pin_expired(version) => format!(", expired {}", format_date(t)),
+            Some(t) => format!(", until {}", format_date(t)),
+            None => String::new(),
+        };
+        let version_text = version.to_string();
+        match state.pin_reason(version) {
+            Some(reason) => println!(
+                "{:<10}  pinned {}{}  {}",
+                version_text, pinned_at, expiry, reason
+            ),
+            None => println!("{:<10}  pinned {}{}", version_text, pinned_at, expiry),
+        }
+    }
+    Ok(())
+}
+
+pub fn prune_expired(goup: &Goup) -> Result<()> {
+    let pruned = goup.transaction(|tx| Ok::<_, GoupError>(tx.prune_expired_pins()))?;
+    if pruned.is_empty() {
+        status!("No pins have expired");
+    }
+    for version in pruned {
+        status!("Unpinned {}, whose pin had expired", version);
+    }
+    Ok(())
+}
+
+/// How `goup list` describes the pin on `version`, if it has one, like ` (PINNED: reason)`
+pub fn label(state: &VersionFile, version: GoVersion) -> String {
+    if !state.pinned.contains(&version) {
+        return String::new();
+    }
+    let info = state.pin_info.get(&version);
+    let label = match info.and_then(|i| i.expires_at()) {
+        Some(t) if state.pin_expired(version) => format!("PIN EXPIRED {}", format_date(t)),
+        Some(t) => format!("PINNED until {}", format_date(t)),
+        None => "PINNED".to_string(),
+    };
+    match state.pin_reason(version) {
+        Some(reason) => format!(" ({}: {})", label, reason),
+        None => format!(" ({})", label),
+    }
+}
+
+pub fn unpin(goup: &Goup, version: GoVersion) -> Result<()> {
+    Ok(goup.unpin(version)?)
+}
+
+pub fn block(goup: &Goup, pattern: &VersionPattern, reason: Option<&str>) -> Result<()> {
+    let path = goup.config_file();
+    config::block(&path, pattern, reason)?;
+    status!("Added {} to the blocklist in {}", pattern, path.display());
+    let installed: Vec<_> = goup
+        .installed()?
+        .into_iter()
+        .filter(|&v| pattern.matches(v))
+        .map(|v| v.to_string())
+        .collect();
+    if !installed.is_empty() {
+        status!(
+            "Already installed: {}. Use 'goup remove' to remove them",
+            installed.join(", ")
+        );
+    }
+    Ok(())
+}
+
+pub fn unblock(goup: &Goup, pattern: &VersionPattern) -> Result<()> {
+    let path = goup.config_file();
+    if config::unblock(&path, pattern)? {
+        status!(
+            "Removed {} from the blocklist in {}",
+            pattern,
+            path.display()
+        )
+    } else {
+        bail!("{} is not on the blocklist in {}", pattern, path.display())
+    }
+    Ok(())
+}
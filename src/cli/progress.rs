@@ -0,0 +1,66 @@
+//! Progress bars for downloads
+
+use super::report;
+use goup::Progress;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// A progress bar displayed on the terminal while downloading archives, beneath the step it
+/// belongs to
+pub struct DownloadBar {
+    pub multi: MultiProgress,
+    pub bar: Mutex<Option<ProgressBar>>,
+}
+
+impl DownloadBar {
+    pub fn new(multi: MultiProgress) -> Self {
+        Self {
+            multi,
+            bar: Mutex::new(None),
+        }
+    }
+
+    fn bar(&self) -> Option<ProgressBar> {
+        self.bar.lock().unwrap().clone()
+    }
+}
+
+impl Progress for DownloadBar {
+    fn start(&self, total: u64) {
+        let bar = ProgressBar::new(total).with_style(
+            ProgressStyle::with_template(
+                "  [{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes}",
+            )
+            .unwrap()
+            .progress_chars("=> "),
+        );
+        *self.bar.lock().unwrap() = Some(self.multi.add(bar));
+    }
+
+    fn advance(&self, bytes: u64) {
+        if let Some(bar) = self.bar() {
+            bar.inc(bytes);
+        }
+    }
+
+    fn finish(&self, complete: bool) {
+        if let Some(bar) = self.bar.lock().unwrap().take() {
+            if complete {
+                bar.finish()
+            } else {
+                bar.abandon()
+            }
+        }
+    }
+
+    fn phase(&self, name: &'static str, elapsed: Duration) {
+        report::record(name, elapsed);
+    }
+
+    fn output(&self, line: &str) {
+        if !report::quiet() {
+            self.multi.suspend(|| eprintln!("{}", line));
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Asking the user questions, in a way that automation can rely on never hanging
+
+use anyhow::Result;
+use std::io::{self, IsTerminal};
+use std::sync::OnceLock;
+use thiserror::Error;
+
+static MODE: OnceLock<Mode> = OnceLock::new();
+
+/// How questions for the user should be answered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Ask on the terminal
+    Interactive,
+    /// Answer yes to everything without asking
+    AssumeYes,
+    /// Refuse to ask anything, failing instead
+    NonInteractive,
+}
+
+/// A question needed answering, but goup was not allowed to ask it
+#[derive(Debug, Error)]
+#[error("Unable to ask \"{question}\" in non-interactive mode. Pass --yes to answer yes")]
+pub struct NonInteractive {
+    question: String,
+}
+
+/// Choose how questions will be answered for the rest of the program. Without a terminal to
+/// ask on, goup is always non-interactive.
+pub fn init(yes: bool, non_interactive: bool) {
+    let mode = if yes {
+        Mode::AssumeYes
+    } else if non_interactive || !io::stdin().is_terminal() {
+        Mode::NonInteractive
+    } else {
+        Mode::Interactive
+    };
+    let _ = MODE.set(mode);
+}
+
+/// Ask the user a yes or no question, defaulting to no
+pub fn confirm(question: &str) -> Result<bool> {
+    match MODE.get().copied().unwrap_or(Mode::NonInteractive) {
+        Mode::AssumeYes => Ok(true),
+        Mode::NonInteractive => Err(NonInteractive {
+            question: question.to_string(),
+        }
+        .into()),
+        Mode::Interactive => {
+            eprint!("{} [y/N] ", question);
+            let mut answer = String::new();
+            io::stdin().read_line(&mut answer)?;
+            Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+        }
+    }
+}
+
+/// Whether questions are put to the user, rather than answered for them. Some questions are
+/// not ones that `--yes` should answer.
+pub fn interactive() -> bool {
+    MODE.get() == Some(&Mode::Interactive)
+}
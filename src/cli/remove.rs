@@ -0,0 +1,203 @@
+//! Removing versions of Go, for `goup remove`, `goup clean`, and `goup trash`
+
+use super::parse::format_age;
+use super::prompt;
+use anyhow::{bail, Result};
+use console::style;
+use goup::size::format_size;
+use goup::trash::Disposal;
+use goup::{CleanPolicy, GoVersion, Goup, GoupError, Removed};
+use std::time::Duration;
+
+pub fn remove(goup: &Goup, version: GoVersion, ignore_usage: bool) -> Result<()> {
+    let users = if ignore_usage {
+        Vec::new()
+    } else {
+        goup.processes_using(version).unwrap_or_default()
+    };
+    if !users.is_empty() {
+        let list: Vec<_> = users.iter().map(|p| format!("  {}", p)).collect();
+        bail!(
+            "{} is in use by these processes:\n{}\nStop them first, or pass --force to remove \
+             it anyway",
+            version,
+            list.join("\n")
+        );
+    }
+    let (was_enabled, size, disposal) = goup.transaction(|tx| {
+        let was_enabled = tx.state().enabled == Some(version);
+        tx.check_removable(version)?;
+        if was_enabled && !prompt::confirm(&format!("{} is enabled. Remove it anyway?", version))? {
+            bail!("Cancelled, {} was not removed", version);
+        }
+        // Measured beforehand, since a missing version has no size to report
+        let size = if tx.state().installed.contains(&version) {
+            goup.size(version).ok()
+        } else {
+            None
+        };
+        let disposal = tx.remove(version)?;
+        Ok((was_enabled, size, disposal))
+    })?;
+    if was_enabled {
+        status!(
+            "Version {} was the default. Use 'goup default' to choose another.",
+            version
+        );
+    }
+    match disposal {
+        Disposal::Trashed(_) => {
+            status!(
+                "Moved {} to the trash. Use 'goup restore {}' to bring it back",
+                version,
+                version
+            );
+            return Ok(());
+        }
+        Disposal::TrashElsewhere => warn_trash_elsewhere(goup, &[version]),
+        Disposal::Deleted => {}
+    }
+    match size {
+        Some(size) => status!(
+            "{} uninstalled successfully, freeing {}",
+            version,
+            format_size(size)
+        ),
+        None => status!("{} uninstalled successfully", version),
+    }
+    Ok(())
+}
+
+/// Say that `versions` were deleted rather than moved to the trash, since it is on another
+/// filesystem
+fn warn_trash_elsewhere(goup: &Goup, versions: &[GoVersion]) {
+    let names: Vec<_> = versions.iter().map(GoVersion::to_string).collect();
+    diagnostic!(
+        "{} {} is on another filesystem, so {} deleted rather than moved there",
+        style("Warning:").yellow(),
+        goup.trash_dir().display(),
+        match names.len() {
+            1 => format!("{} was", names[0]),
+            _ => format!("{} were", names.join(", ")),
+        }
+    );
+}
+
+pub fn restore(goup: &Goup, version: GoVersion) -> Result<()> {
+    let restored = goup.restore(version)?;
+    status!(
+        "Restored {}, which was put in the trash {} ago",
+        version,
+        format_age(restored.age())
+    );
+    Ok(())
+}
+
+pub fn trash_list(goup: &Goup) -> Result<()> {
+    let trashed = goup.trash()?;
+    if trashed.is_empty() {
+        diagnostic!("The trash is empty");
+    }
+    for t in &trashed {
+        println!(
+            "{:>10}  {:>4}  {}",
+            format_size(t.size),
+            format_age(t.age()),
+            t.version
+        );
+    }
+    Ok(())
+}
+
+pub fn trash_empty(goup: &Goup, older_than: Option<Duration>) -> Result<()> {
+    let deleted = goup.empty_trash(older_than)?;
+    for t in &deleted {
+        status!("Deleted {} ({})", t.version, format_size(t.size));
+    }
+    let freed = deleted.iter().map(|t| t.size).sum();
+    status!("Freed {}", format_size(freed));
+    Ok(())
+}
+
+pub fn clean(goup: &Goup, policy: &CleanPolicy) -> Result<()> {
+    let report = goup.clean(policy)?;
+    let state = goup.state()?;
+    for version in &report.superseded {
+        status!("{} is superseded by {}", version, version.release());
+    }
+    for version in &report.unreleased {
+        status!("Kept {} (its release isn't out yet)", version);
+    }
+    for &version in &report.pinned {
+        match state.pin_reason(version) {
+            Some(reason) => status!("Kept {} (PINNED: {})", version, reason),
+            None => status!("Kept {} (PINNED)", version),
+        }
+    }
+    for version in &report.skipped {
+        status!(
+            "Skipped {} (not created by goup; delete it manually if it isn't needed)",
+            version
+        );
+    }
+    for version in &report.unknown_age {
+        status!(
+            "Kept {} (installed by an older version of goup, which didn't record when)",
+            version
+        );
+    }
+    for version in &report.never_used {
+        status!("Kept {} (use never recorded)", version);
+    }
+    for (version, users) in &report.in_use {
+        let pids: Vec<_> = users.iter().map(|p| p.to_string()).collect();
+        status!(
+            "Kept {} (in use by {}; pass --force to remove it anyway)",
+            version,
+            pids.join(", ")
+        );
+    }
+    summarize_removals(goup, &report.removed, &report.failed)
+}
+
+/// Report the outcome of removing several versions, failing if any of them couldn't be removed
+pub fn summarize_removals(
+    goup: &Goup,
+    removed: &[Removed],
+    failed: &[(GoVersion, GoupError)],
+) -> Result<()> {
+    let (trashed, deleted): (Vec<_>, Vec<_>) = removed
+        .iter()
+        .partition(|r| matches!(r.disposal, Disposal::Trashed(_)));
+    for r in &deleted {
+        status!("Removed {} ({})", r.version, format_size(r.size));
+    }
+    for r in &trashed {
+        status!("Moved {} to the trash ({})", r.version, format_size(r.size));
+    }
+    let elsewhere: Vec<_> = deleted
+        .iter()
+        .filter(|r| r.disposal == Disposal::TrashElsewhere)
+        .map(|r| r.version)
+        .collect();
+    if !elsewhere.is_empty() {
+        warn_trash_elsewhere(goup, &elsewhere);
+    }
+    if !deleted.is_empty() {
+        let freed = deleted.iter().map(|r| r.size).sum();
+        status!("Freed {}", format_size(freed));
+    }
+    if !trashed.is_empty() {
+        status!("Use 'goup restore <version>' to bring one back, or 'goup trash empty' to free the space");
+    }
+    for (version, e) in failed {
+        match std::error::Error::source(e) {
+            Some(cause) => diagnostic!("Failed to remove {}: {}: {}", version, e, cause),
+            None => diagnostic!("Failed to remove {}: {}", version, e),
+        }
+    }
+    match failed.len() {
+        0 => Ok(()),
+        n => bail!("{} version(s) could not be removed", n),
+    }
+}
@@ -0,0 +1,238 @@
+//! Reporting the phases of commands that do several things in turn, like `goup update`
+
+use super::events::{self, JsonSteps};
+use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget, ProgressStyle};
+use std::io::{self, IsTerminal};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+static OUTPUT: OnceLock<(MultiProgress, bool)> = OnceLock::new();
+
+/// How long each phase of work has taken so far, for `--timings`
+static TIMINGS: Mutex<Vec<(&'static str, Duration)>> = Mutex::new(Vec::new());
+
+/// Shows which phase of a command is under way, and how long each one took
+pub trait Reporter {
+    /// Step `number` of `total` is starting
+    fn start(&mut self, number: usize, total: usize, message: &str);
+    /// The step that was last started is done, after `elapsed`
+    fn end(&mut self, elapsed: Duration);
+    /// Every step is done; `phases` names each one along with how long it took
+    fn finish(&mut self, phases: &[(&'static str, Duration)]);
+}
+
+/// Choose how progress will be shown for the rest of the program, returning the display that
+/// download bars should be added to. Nothing at all is drawn when `quiet`, or when sending
+/// [`events`](super::events) instead.
+pub fn init(quiet: bool) -> MultiProgress {
+    let quiet = quiet || events::enabled();
+    let multi = if quiet {
+        MultiProgress::with_draw_target(ProgressDrawTarget::hidden())
+    } else {
+        MultiProgress::new()
+    };
+    let _ = OUTPUT.set((multi.clone(), quiet));
+    multi
+}
+
+/// Take every progress bar off the screen and draw nothing more, before the program ends in a
+/// way that leaves them no chance to finish
+pub fn abandon() {
+    if let Some((multi, _)) = OUTPUT.get() {
+        let _ = multi.clear();
+        multi.set_draw_target(ProgressDrawTarget::hidden());
+    }
+}
+
+/// Whether `--quiet` was given, or events are being sent in place of anything drawn
+pub fn quiet() -> bool {
+    OUTPUT.get().is_none_or(|(_, quiet)| *quiet)
+}
+
+/// A reporter for the next command's steps: silent with `--quiet`, plain lines when stderr
+/// isn't a terminal, and otherwise spinners above any download bar
+pub fn reporter() -> Box<dyn Reporter> {
+    if events::enabled() {
+        return Box::new(JsonSteps::default());
+    }
+    match OUTPUT.get() {
+        None | Some((_, true)) => Box::new(Silent),
+        Some(_) if !io::stderr().is_terminal() => Box::new(Lines),
+        Some((multi, false)) => Box::new(Spinners {
+            multi: multi.clone(),
+            current: None,
+        }),
+    }
+}
+
+/// Runs each phase of a command, reporting it as a numbered step
+pub struct Steps {
+    reporter: Box<dyn Reporter>,
+    total: usize,
+    number: usize,
+    phases: Vec<(&'static str, Duration)>,
+}
+
+impl Steps {
+    pub fn new(reporter: Box<dyn Reporter>, total: usize) -> Self {
+        Self {
+            reporter,
+            total,
+            number: 0,
+            phases: Vec::new(),
+        }
+    }
+
+    /// Run the next step, described by `message` while it runs and by `phase` in the summary
+    pub fn run<T>(&mut self, phase: &'static str, message: &str, f: impl FnOnce() -> T) -> T {
+        self.number += 1;
+        self.reporter.start(self.number, self.total, message);
+        let start = Instant::now();
+        let res = f();
+        let elapsed = start.elapsed();
+        self.reporter.end(elapsed);
+        self.phases.push((phase, elapsed));
+        res
+    }
+
+    /// Pass over the next step, which turned out to be unnecessary
+    pub fn skip(&mut self) {
+        self.number += 1;
+    }
+
+    /// Summarize how long each step took
+    pub fn finish(mut self) {
+        self.reporter.finish(&self.phases);
+    }
+}
+
+/// Record that a phase of work took `elapsed`. Times are always collected, but only shown with
+/// `--timings` or `--verbose`.
+pub fn record(phase: &'static str, elapsed: Duration) {
+    let mut timings = TIMINGS.lock().unwrap_or_else(|e| e.into_inner());
+    match timings.iter_mut().find(|(p, _)| *p == phase) {
+        Some((_, total)) => *total += elapsed,
+        None => timings.push((phase, elapsed)),
+    }
+}
+
+/// The time taken by each phase of work so far, in the order the phases first happened
+pub fn timings() -> Vec<(&'static str, Duration)> {
+    TIMINGS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Report how long each phase took, for `--timings`, as a table or as JSON to go with the
+/// command's own. Without it, `verbose` still lists them on one line.
+pub fn show_timings(wanted: bool, json: bool, verbose: bool) {
+    let timings = timings();
+    if wanted {
+        if events::enabled() {
+            let mut event = timings_json(&timings);
+            event["event"] = "timings".into();
+            events::emit(event);
+        } else if json {
+            eprintln!("{}", timings_json(&timings));
+        } else {
+            eprint!("{}", timings_table(&timings));
+        }
+    } else if verbose && !timings.is_empty() {
+        diagnostic!("Timings: {}", phases_line(&timings));
+    }
+}
+
+/// Lay out the time taken by each phase as a table, ending with the total
+pub fn timings_table(phases: &[(&'static str, Duration)]) -> String {
+    let total: Duration = phases.iter().map(|(_, elapsed)| *elapsed).sum();
+    let mut table = format!("{:<10}{:>9}\n", "Phase", "Time");
+    for (phase, elapsed) in phases.iter().chain([&("total", total)]) {
+        table += &format!("{:<10}{:>8.3}s\n", phase, elapsed.as_secs_f64());
+    }
+    table
+}
+
+/// The time taken by each phase as a JSON object, in seconds
+pub fn timings_json(phases: &[(&'static str, Duration)]) -> serde_json::Value {
+    let seconds = phases
+        .iter()
+        .map(|(phase, elapsed)| (phase.to_string(), elapsed.as_secs_f64().into()))
+        .collect();
+    serde_json::json!({ "timings": serde_json::Value::Object(seconds) })
+}
+
+/// Describe how long each phase took on a single line
+pub fn phases_line(phases: &[(&'static str, Duration)]) -> String {
+    let phases: Vec<_> = phases
+        .iter()
+        .map(|(phase, elapsed)| format!("{} {:.1}s", phase, elapsed.as_secs_f64()))
+        .collect();
+    phases.join(", ")
+}
+
+/// Summarize the steps of a command on a single line
+fn summary(phases: &[(&'static str, Duration)]) -> String {
+    let total: Duration = phases.iter().map(|(_, elapsed)| *elapsed).sum();
+    format!(
+        "Finished in {:.1}s ({})",
+        total.as_secs_f64(),
+        phases_line(phases)
+    )
+}
+
+/// Reports nothing, for `--quiet`
+struct Silent;
+
+impl Reporter for Silent {
+    fn start(&mut self, _number: usize, _total: usize, _message: &str) {}
+    fn end(&mut self, _elapsed: Duration) {}
+    fn finish(&mut self, _phases: &[(&'static str, Duration)]) {}
+}
+
+/// Reports each step as a plain line on stderr, for logs
+struct Lines;
+
+impl Reporter for Lines {
+    fn start(&mut self, number: usize, total: usize, message: &str) {
+        eprintln!("[{}/{}] {}", number, total, message);
+    }
+
+    fn end(&mut self, _elapsed: Duration) {}
+
+    fn finish(&mut self, phases: &[(&'static str, Duration)]) {
+        eprintln!("{}", summary(phases));
+    }
+}
+
+/// Reports the current step with a spinner, above any download bar added to the same
+/// [`MultiProgress`]
+struct Spinners {
+    multi: MultiProgress,
+    current: Option<ProgressBar>,
+}
+
+impl Reporter for Spinners {
+    fn start(&mut self, number: usize, total: usize, message: &str) {
+        let spinner = self.multi.add(
+            ProgressBar::new_spinner()
+                .with_style(ProgressStyle::with_template("{spinner} {msg}").unwrap())
+                .with_message(format!("[{}/{}] {}…", number, total, message)),
+        );
+        spinner.enable_steady_tick(Duration::from_millis(100));
+        self.current = Some(spinner);
+    }
+
+    fn end(&mut self, elapsed: Duration) {
+        if let Some(spinner) = self.current.take() {
+            let message = spinner.message();
+            spinner.set_style(ProgressStyle::with_template("  {msg}").unwrap());
+            spinner.finish_with_message(format!(
+                "{} done in {:.1}s",
+                message,
+                elapsed.as_secs_f64()
+            ));
+        }
+    }
+
+    fn finish(&mut self, phases: &[(&'static str, Duration)]) {
+        let _ = self.multi.println(summary(phases));
+    }
+}
@@ -0,0 +1,259 @@
+//! The version of Go used in each directory, for `goup resolve`, `goup sync`, and
+//! `goup local`, and pointing other tools at it
+
+use super::enable::{active_here, default_of, enable};
+use super::hooks::{self, Change};
+use super::ide;
+use super::install::install;
+use super::modulefiles;
+use super::report;
+use crate::{Editor, HookCommand};
+use anyhow::{anyhow, Context, Result};
+use console::style;
+use goup::project::{self, Need, Requirement, Resolution};
+use goup::version::VersionRequest;
+use goup::{GoVersion, Goup, GoupError};
+use std::env;
+use std::path::{Path, PathBuf};
+
+pub fn resolve(goup: &Goup, dir: Option<PathBuf>, ensure: bool) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => env::current_dir().context("Unable to find the current directory")?,
+    };
+    let resolution = goup.resolve(&dir)?;
+    // The hooks only want the version, as do prompts, with --quiet
+    if !ensure && !report::quiet() {
+        print_resolution(&resolution, &dir);
+    }
+    if let Some(version) = resolution.version {
+        println!("{}", version);
+        return Ok(());
+    }
+    let Some((source, need)) = resolution.decision() else {
+        return if ensure {
+            Ok(())
+        } else {
+            Err(resolution.error().into())
+        };
+    };
+    if !ensure {
+        return Err(resolution.error().into());
+    }
+    if !goup.config().auto_install() {
+        let fix = match need {
+            Need::Request(VersionRequest::Exact(version)) | Need::AtLeast(version) => {
+                format!("goup install {}", version)
+            }
+            Need::Request(_) => "goup list".to_string(),
+        };
+        diagnostic!(
+            "{} {} needs {}, which isn't installed. Run: {}",
+            style("Warning:").yellow(),
+            source,
+            need,
+            fix
+        );
+        return Ok(());
+    }
+
+    let available = goup.available()?;
+    let version = need
+        .choose(available.into_keys().filter(|&v| goup.config().allows(v)))
+        .ok_or_else(|| resolution.error())?;
+    status!("{} needs {}, so installing {}", source, need, version);
+    install(goup, version, false)?;
+    println!("{}", version);
+    Ok(())
+}
+
+/// Install and enable the version that the project in `dir` asks for, or with `dry_run`, only
+/// print it
+pub fn sync(goup: &Goup, dir: Option<PathBuf>, dry_run: bool) -> Result<()> {
+    let dir = match dir {
+        Some(dir) => dir,
+        None => env::current_dir().context("Unable to find the current directory")?,
+    };
+    let Requirement { need, file } =
+        project::requirement(&dir)?.ok_or_else(|| GoupError::NoProjectRequirement(dir))?;
+    let installed = goup.installed()?;
+    let version = match need.choose(installed.iter().copied()) {
+        Some(version) => version,
+        None => {
+            // Only a release named exactly may go beyond max_version or be on the blocklist,
+            // which installing it then refuses without --override-policy
+            let exact = matches!(need, Need::Request(VersionRequest::Exact(_)));
+            let available = goup.available()?;
+            need.choose(
+                available
+                    .into_keys()
+                    .filter(|&v| exact || goup.config().allows(v)),
+            )
+            .ok_or_else(|| anyhow!("{} needs {}, which isn't available", file.display(), need))?
+        }
+    };
+    status!("{} needs {}, so using {}", file.display(), need, version);
+    if dry_run {
+        println!("{}", version);
+        return Ok(());
+    }
+
+    if !installed.contains(&version) {
+        install(goup, version, false)?;
+    }
+    if goup.state()?.enabled == Some(version) {
+        status!("{} is already enabled", version)
+    } else {
+        enable(goup, version, false, false)?
+    }
+    Ok(())
+}
+
+/// Make the project in the current directory ask for `version`
+pub fn local(goup: &Goup, version: GoVersion, tool_versions: bool) -> Result<()> {
+    let dir = env::current_dir().context("Unable to find the current directory")?;
+    let file = project::set_local(&dir, version, tool_versions)?;
+    status!("{} now asks for {}", file.display(), version);
+    if !goup.installed()?.contains(&version) {
+        status!(
+            "{} isn't installed yet. Use 'goup sync' to install it",
+            version
+        );
+    }
+    Ok(())
+}
+
+/// Print the version chosen, and every source consulted in choosing it
+fn print_resolution(resolution: &Resolution, dir: &Path) {
+    match default_of(resolution) {
+        Some(version) => println!("Default: {}", version),
+        None => println!("Default: none"),
+    }
+    println!("Active here: {}", active_here(resolution, dir));
+    println!("From the highest precedence to the lowest, the first to ask for one (*) decides:");
+    let labels: Vec<_> = resolution
+        .consulted
+        .iter()
+        .map(|(source, _)| source.to_string())
+        .collect();
+    let width = labels.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    for (i, (label, (_, found))) in labels.iter().zip(&resolution.consulted).enumerate() {
+        let mark = if resolution.decided_by == Some(i) {
+            '*'
+        } else {
+            ' '
+        };
+        println!("{} {:width$}  {}", mark, label, found, width = width);
+    }
+}
+
+pub fn write_modulefiles(
+    goup: &Goup,
+    out_dir: &Path,
+    format: modulefiles::Format,
+    prune: bool,
+) -> Result<()> {
+    let changes = modulefiles::write(goup, out_dir, format, prune)?;
+    let name = |path: &Path| {
+        path.strip_prefix(out_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    let mut stale = 0;
+    for change in &changes {
+        match change {
+            modulefiles::Change::Created(path) => status!("Created {}", name(path)),
+            modulefiles::Change::Updated(path) => status!("Updated {}", name(path)),
+            modulefiles::Change::Unchanged(_) => {}
+            modulefiles::Change::Removed(path) => status!("Removed {}", name(path)),
+            modulefiles::Change::Stale(_) => stale += 1,
+            modulefiles::Change::Foreign(path) => diagnostic!(
+                "{} {} wasn't written by goup, so it was left alone",
+                style("Warning:").yellow(),
+                path.display()
+            ),
+        }
+    }
+    if stale > 0 {
+        status!(
+            "{} modulefile(s) for versions that are no longer installed remain. Pass --prune to \
+             delete them",
+            stale
+        );
+    }
+    status!(
+        "Load a version with 'module use {}' and 'module load {}/<version>'",
+        out_dir.display(),
+        modulefiles::MODULE
+    );
+    Ok(())
+}
+
+pub fn hook_git(command: HookCommand) -> Result<()> {
+    let dir = hooks::hooks_dir()?;
+    for name in hooks::HOOKS {
+        let path = dir.join(name);
+        let change = match command {
+            HookCommand::Install => hooks::install(&path)?,
+            HookCommand::Uninstall => hooks::uninstall(&path)?,
+        };
+        match change {
+            Change::Created => status!("Created {}", path.display()),
+            Change::Added => status!("Added goup to {}", path.display()),
+            Change::Unchanged => {}
+            Change::Removed => status!("Removed goup from {}", path.display()),
+            Change::Deleted => status!("Deleted {}", path.display()),
+            Change::NotShell => {
+                diagnostic!(
+                    "{} {} isn't a shell script, so goup can't add itself to it. Have it run \
+                     this instead:",
+                    style("Warning:").yellow(),
+                    path.display()
+                );
+                print!("{}", hooks::snippet());
+            }
+        }
+    }
+    Ok(())
+}
+
+pub fn path(goup: &Goup, version: Option<GoVersion>, bin: bool, root: bool) -> Result<()> {
+    if root {
+        println!("{}", goup.root().display());
+        return Ok(());
+    }
+
+    let state = goup.state()?;
+    let version = version
+        .or(state.enabled)
+        .ok_or(GoupError::NoVersionEnabled)?;
+    if !state.installed.contains(&version) {
+        return Err(GoupError::VersionNotInstalled(version).into());
+    }
+
+    let goroot = goup.goroot(version);
+    if bin {
+        println!("{}", goroot.join("bin").display())
+    } else {
+        println!("{}", goroot.display())
+    }
+    Ok(())
+}
+
+pub fn ide(goup: &Goup, editor: Editor) -> Result<()> {
+    match editor {
+        Editor::Vscode { version, write } => {
+            let goroot = ide::toolchain_root(goup, version)?;
+            let settings = ide::vscode_settings(&goroot);
+            if write {
+                let path = std::path::Path::new(".vscode").join("settings.json");
+                ide::merge_vscode_settings(&path, settings)?;
+                status!("Updated {}", path.display());
+            } else {
+                println!("{}", serde_json::to_string_pretty(&settings)?);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,340 @@
+//! Running commands with a version of Go, for `goup run`, `goup exec`, `goup try`, and
+//! `goup shell`
+
+use super::actions;
+use super::errors::{ExecFailed, TryFailed};
+use super::exit;
+use super::install::{check_policy, ensure_installed, install};
+use super::interrupt;
+use super::report::{self, Steps};
+use anyhow::{bail, Context, Result};
+use console::style;
+use goup::project::ACTIVE_SHELL;
+use goup::{GoVersion, Goup, GoupError};
+use std::collections::BTreeMap;
+use std::env;
+use std::io::{self, Write};
+use std::process::Command;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::thread;
+
+pub fn run(goup: &Goup, version: GoVersion, command: Vec<String>) -> Result<()> {
+    ensure_installed(goup, version)?;
+    // Only used to decide what `clean --unused-for` may remove, so not worth failing over
+    let _ = goup.record_use(version);
+
+    let goroot = goup.goroot(version);
+    let (program, args) = match command.split_first() {
+        Some((tool, args)) if goup::exec::tool_path(&goroot, tool).is_some() => {
+            (tool.as_str(), args)
+        }
+        _ => ("go", command.as_slice()),
+    };
+    let mut cmd = goup.command(version, program)?;
+    cmd.args(args);
+    exec(cmd)
+}
+
+pub fn run_linked(goup: &Goup, name: &str, command: Vec<String>) -> Result<()> {
+    let state = goup.state()?;
+    let toolchain = state
+        .linked
+        .get(name)
+        .ok_or_else(|| GoupError::ToolchainNotLinked(name.to_string()))?;
+    let (program, args) = match command.split_first() {
+        Some((tool, args)) if goup::exec::tool_path(&toolchain.path, tool).is_some() => {
+            (tool.as_str(), args)
+        }
+        _ => ("go", command.as_slice()),
+    };
+    let mut cmd = goup.linked_command(name, program)?;
+    cmd.args(args);
+    exec(cmd)
+}
+
+pub fn with(goup: &Goup, version: GoVersion, command: Vec<String>) -> Result<()> {
+    let (program, args) = command.split_first().expect("clap requires a command");
+    let mut cmd = goup.command(version, program)?;
+    cmd.args(args);
+    let _ = goup.record_use(version);
+    exec(cmd)
+}
+
+/// Run `command` with `version`, installing it first if need be. A version installed here is
+/// removed again once the command finishes, or is interrupted, unless `keep` is given.
+pub fn try_version(
+    goup: &Goup,
+    version: GoVersion,
+    keep: bool,
+    command: Vec<String>,
+) -> Result<()> {
+    let (program, args) = command.split_first().expect("clap requires a command");
+    let installed_here = if goup.installed()?.contains(&version) {
+        false
+    } else {
+        check_policy(goup, version, false)?;
+        let mut steps = Steps::new(report::reporter(), 2);
+        let available = steps.run("index", "Fetching the list of versions", || {
+            goup.available()
+        })?;
+        let file = available
+            .get(&version)
+            .ok_or(GoupError::VersionNotAvailable(version))?;
+        let downloading = format!("Downloading {}", version);
+        let installed = steps.run("download", &downloading, || {
+            goup.transaction(|tx| tx.download_ephemeral(version, file))
+        })?;
+        steps.finish();
+        installed
+    };
+    let mut cmd = goup.command(version, program)?;
+    cmd.args(args);
+    if !installed_here {
+        if keep {
+            status!("{} was already installed", version);
+        }
+        return exec(cmd);
+    }
+
+    // Ctrl-C reaches the command as well, which is left to stop it, so that goup is still here
+    // to clean up afterwards
+    interrupt::catch();
+    let status = cmd
+        .status()
+        .with_context(|| format!("Unable to run {}", program));
+    match goup.transaction(|tx| tx.settle_ephemeral(version, keep)) {
+        Ok(true) => status!("Removed {}, which was installed for the command", version),
+        Ok(false) => status!("Kept {}", version),
+        Err(e) => diagnostic!(
+            "{} Unable to remove {}, which was installed for the command: {}",
+            style("Warning:").yellow(),
+            version,
+            e
+        ),
+    }
+    let status = status?;
+    if status.success() {
+        Ok(())
+    } else {
+        Err(TryFailed {
+            program: program.clone(),
+            // A command killed by a signal has no code, but certainly failed
+            code: status.code().map_or(1, |c| c.clamp(1, 255) as u8),
+        }
+        .into())
+    }
+}
+
+/// How running a command with one version turned out, for `goup exec`
+enum Outcome {
+    Passed,
+    Failed(u8),
+    /// Not run, because an earlier version failed and `--fail-fast` was given
+    Skipped,
+}
+
+/// Run `command` with each installed version that passes `filter`, oldest first, with up to
+/// `jobs` at once, then print a table of the outcomes
+pub fn exec_all(
+    goup: &Goup,
+    command: Vec<String>,
+    filter: impl Fn(GoVersion) -> bool,
+    fail_fast: bool,
+    jobs: usize,
+) -> Result<()> {
+    let versions: Vec<_> = goup
+        .installed()?
+        .into_iter()
+        .filter(|&v| filter(v))
+        .collect();
+    if versions.is_empty() {
+        bail!("No installed version matches");
+    }
+    let (program, args) = command.split_first().expect("clap requires a command");
+    let _ = goup.transaction(|tx| versions.iter().try_for_each(|&v| tx.record_use(v)));
+    let failed = AtomicBool::new(false);
+    let queue = Mutex::new(versions.iter().rev().copied().collect::<Vec<_>>());
+    let outcomes = Mutex::new(BTreeMap::new());
+
+    // Run with one version, streaming its output when running one at a time, and otherwise
+    // printing it all at once when it's done
+    let run = |version: GoVersion| -> Outcome {
+        if fail_fast && failed.load(Ordering::Relaxed) {
+            return Outcome::Skipped;
+        }
+        let header = style(format!("==> {}", version)).bold();
+        let status = goup
+            .command(version, program)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut cmd| {
+                cmd.args(args);
+                if jobs == 1 {
+                    diagnostic!("{}", header);
+                    return Ok(cmd.status()?);
+                }
+                let output = cmd.output()?;
+                let mut stdout = io::stdout().lock();
+                diagnostic!("{}", header);
+                stdout.write_all(&output.stdout)?;
+                stdout.flush()?;
+                io::stderr().write_all(&output.stderr)?;
+                Ok(output.status)
+            });
+        let outcome = match status {
+            Ok(status) if status.success() => Outcome::Passed,
+            // A command killed by a signal has no code, but certainly failed
+            Ok(status) => Outcome::Failed(status.code().map_or(1, |c| c.clamp(1, 255) as u8)),
+            Err(e) => {
+                diagnostic!("{} {}: {:#}", style("Error:").red(), version, e);
+                Outcome::Failed(exit::FAILURE)
+            }
+        };
+        if matches!(outcome, Outcome::Failed(_)) {
+            failed.store(true, Ordering::Relaxed);
+        }
+        outcome
+    };
+    thread::scope(|s| {
+        for _ in 0..jobs.min(versions.len()) {
+            s.spawn(|| {
+                while let Some(version) = queue.lock().unwrap().pop() {
+                    let outcome = run(version);
+                    outcomes.lock().unwrap().insert(version, outcome);
+                }
+            });
+        }
+    });
+
+    diagnostic!("");
+    let outcomes = outcomes.into_inner().unwrap();
+    let mut worst = 0;
+    for (version, outcome) in &outcomes {
+        let text = match outcome {
+            Outcome::Passed => style("passed".to_string()).green(),
+            Outcome::Failed(code) => style(format!("failed (exit code {})", code)).red(),
+            Outcome::Skipped => style("skipped".to_string()).dim(),
+        };
+        diagnostic!("{:<10} {}", version.to_string(), text);
+        if let Outcome::Failed(code) = outcome {
+            worst = worst.max(*code);
+        }
+    }
+    match outcomes
+        .values()
+        .filter(|o| matches!(o, Outcome::Failed(_)))
+        .count()
+    {
+        0 => Ok(()),
+        failed => Err(ExecFailed {
+            failed,
+            total: outcomes.len(),
+            code: worst,
+        }
+        .into()),
+    }
+}
+
+pub fn shell(goup: &Goup, version: GoVersion) -> Result<()> {
+    if let Ok(active) = env::var(ACTIVE_SHELL) {
+        diagnostic!("Warning: already inside a goup shell for {}", active);
+    }
+
+    let program = if cfg!(windows) {
+        env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".into())
+    } else {
+        env::var("SHELL").unwrap_or_else(|_| "/bin/sh".into())
+    };
+    let mut cmd = goup.command(version, &program)?;
+    cmd.env(ACTIVE_SHELL, version.to_string());
+    exec(cmd)
+}
+
+pub fn env(
+    goup: &Goup,
+    version: Option<GoVersion>,
+    install_first: bool,
+    github_actions: bool,
+    export_version: bool,
+) -> Result<()> {
+    let state = goup.state()?;
+    // The enabled version is set up through the `go` link, which stays put when another
+    // version is enabled later
+    let named = version.is_some();
+    let (version, goroot) = match version {
+        Some(v) if state.enabled == Some(v) => (v, goup.link_path()),
+        Some(v) => {
+            if install_first && !state.installed.contains(&v) {
+                install(goup, v, false)?;
+            } else if !state.installed.contains(&v) {
+                return Err(GoupError::VersionNotInstalled(v).into());
+            }
+            (v, goup.goroot(v))
+        }
+        None => {
+            let v = state.enabled.ok_or(GoupError::NoVersionEnabled)?;
+            (v, goup.link_path())
+        }
+    };
+
+    let mut setup = actions::Setup {
+        path: vec![goroot.join("bin")],
+        env: vec![("GOROOT".to_string(), goroot.display().to_string())],
+    };
+    if goup.config().gotoolchain_local() {
+        setup
+            .env
+            .push(("GOTOOLCHAIN".to_string(), "local".to_string()));
+    }
+    if export_version {
+        setup
+            .env
+            .push(("GOUP_VERSION".to_string(), version.to_string()));
+    }
+    // Only a version asked for by name gets the variables meant for it, since the enabled
+    // version's environment would go stale when another is enabled
+    if named {
+        setup.env.extend(goup.injected_env(version));
+    }
+
+    if !github_actions {
+        for (name, value) in &setup.env {
+            println!("export {}=\"{}\"", name, value);
+        }
+        for dir in &setup.path {
+            println!("export PATH=\"{}:${{PATH}}\"", dir.display());
+        }
+        return Ok(());
+    }
+    let commands = actions::write(&setup)?;
+    if !commands.is_empty() {
+        diagnostic!(
+            "Note: $GITHUB_PATH or $GITHUB_ENV is not set, so this doesn't seem to be a GitHub \
+             Actions job. These commands would do the same in one:"
+        );
+        for command in commands {
+            println!("{}", command);
+        }
+    }
+    Ok(())
+}
+
+/// Replace goup with the given command, so that its exit status and any signals pass
+/// straight through
+fn exec(mut cmd: Command) -> Result<()> {
+    let program = cmd.get_program().to_string_lossy().into_owned();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        Err(cmd.exec()).with_context(|| format!("Unable to run {}", program))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let status = cmd
+            .status()
+            .with_context(|| format!("Unable to run {}", program))?;
+        std::process::exit(status.code().unwrap_or(exit::FAILURE.into()));
+    }
+}
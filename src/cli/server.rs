@@ -0,0 +1,101 @@
+//! Serving goup's versions to other machines and processes, for `goup serve` and
+//! `goup daemon`
+
+use super::interrupt;
+#[cfg(unix)]
+use super::parse::format_age;
+use anyhow::{bail, Result};
+use console::style;
+use goup::serve;
+use goup::size::format_size;
+use goup::Goup;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+pub fn serve(goup: &Goup, addr: &str, index: bool, limits: serve::Limits) -> Result<()> {
+    let catalog = serve::Catalog::load(goup, index)?;
+    for path in &catalog.unverified {
+        diagnostic!(
+            "{} not serving {}, which doesn't match the checksum published for it",
+            style("Warning:").yellow(),
+            path.display()
+        );
+    }
+    let archives: Vec<_> = catalog.archives().collect();
+    if archives.is_empty() {
+        bail!("There are no cached archives to serve; install a version to download one");
+    }
+
+    let server = serve::Server::bind(addr)?;
+    let stop = interrupt::catch();
+    status!(
+        "Serving {} archive(s) on http://{}",
+        archives.len(),
+        server.local_addr()?
+    );
+    for name in &archives {
+        status!("  {}", name);
+    }
+    if !catalog.has_index() {
+        status!("The list of versions is not served, so clients need another mirror for it");
+    }
+    let stdout = Mutex::new(io::stdout());
+    let log = |served: &serve::Served| {
+        let mut stdout = stdout.lock().unwrap();
+        let _ = writeln!(
+            stdout,
+            "{} {} {} {} {}",
+            served.peer.ip(),
+            served.method,
+            served.path,
+            served.status,
+            format_size(served.bytes)
+        );
+        let _ = stdout.flush();
+    };
+    let stopped = server.run(&catalog, limits, stop, &log)?;
+    match stopped {
+        serve::Stopped::Interrupted => status!("Stopped"),
+        serve::Stopped::TimeLimit => status!("Stopped after reaching the time limit"),
+        serve::Stopped::ByteLimit => status!("Stopped after reaching the byte limit"),
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+pub fn daemon(goup: &Goup, idle_timeout: Duration) -> Result<()> {
+    let path = goup.daemon_socket();
+    let daemon = goup::daemon::Daemon::bind(goup, &path)?;
+    let stop = interrupt::catch();
+    status!("Listening on {}", daemon.path().display());
+    let stdout = Mutex::new(io::stdout());
+    let log = |handled: &goup::daemon::Handled| {
+        let mut stdout = stdout.lock().unwrap();
+        let _ = match &handled.error {
+            Some(error) => writeln!(stdout, "{} failed: {}", handled.method, error),
+            None => writeln!(
+                stdout,
+                "{} ({:.2}s)",
+                handled.method,
+                handled.elapsed.as_secs_f64()
+            ),
+        };
+        let _ = stdout.flush();
+    };
+    match daemon.run(idle_timeout, stop, &log)? {
+        goup::daemon::Stopped::Interrupted => status!("Stopped"),
+        goup::daemon::Stopped::Idle => status!(
+            "Stopped after {} without a client",
+            format_age(idle_timeout)
+        ),
+        goup::daemon::Stopped::Shutdown => status!("Stopped, as a client asked"),
+    }
+    Ok(())
+}
+
+/// Named pipes, which Windows would need in place of the socket, aren't supported yet
+#[cfg(not(unix))]
+pub fn daemon(_: &Goup, _: Duration) -> Result<()> {
+    bail!("goup daemon is only available on Unix-like systems for now")
+}
@@ -0,0 +1,160 @@
+//! Configuring the user's shell to find goup's installation of Go, for `goup setup`
+
+use super::install::warn_about_index;
+use super::prompt;
+use super::update::{newest_allowed, note_policy};
+use anyhow::{Context, Result};
+use goup::{Goup, GoupError};
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Where GOPATH is put when it isn't set, matching install.sh
+pub fn default_gopath() -> Option<PathBuf> {
+    Some(PathBuf::from(env::var_os("HOME")?).join(".go"))
+}
+
+/// Set GOPATH to where it is put by default if it isn't set, returning it if so
+pub fn default_gopath_if_unset() -> Option<PathBuf> {
+    if env::var_os("GOPATH").is_some() {
+        return None;
+    }
+    let gopath = default_gopath()?;
+    env::set_var("GOPATH", &gopath);
+    Some(gopath)
+}
+
+/// Write the `env` file in the goup root, which sets GOPATH, GOROOT, and PATH for a POSIX shell
+/// in the same way as the one written by install.sh, along with GOTOOLCHAIN unless that is
+/// turned off. Returns its path.
+pub fn write_env_file(goup: &Goup) -> Result<PathBuf> {
+    let gopath = goup.root().parent().unwrap_or(goup.root());
+    let goroot = goup.link_path();
+    let mut contents = format!(
+        "export GOPATH=\"{gopath}\" # The global dir for packages and installed binaries\n\
+         export GOROOT=\"{goroot}\" # The installed Go development kit\n\
+         export PATH=\"{goroot}/bin:{gopath}/bin:${{PATH}}\"\n",
+        gopath = gopath.display(),
+        goroot = goroot.display(),
+    );
+    if goup.config().gotoolchain_local() {
+        contents +=
+            "export GOTOOLCHAIN=local # Use goup's Go, rather than letting go download its own\n";
+    }
+    let path = goup.root().join("env");
+    if fs::read_to_string(&path).ok().as_deref() != Some(contents.as_str()) {
+        fs::write(&path, contents)
+            .with_context(|| format!("Unable to write {}", path.display()))?;
+    }
+    Ok(path)
+}
+
+/// The startup file of the user's shell that the `env` file should be sourced from, if the
+/// shell is one that can source it
+pub fn profile() -> Option<PathBuf> {
+    let home = PathBuf::from(env::var_os("HOME")?);
+    let shell = env::var("SHELL").unwrap_or_default();
+    match Path::new(&shell).file_name()?.to_str()? {
+        "bash" => Some(home.join(".bashrc")),
+        "zsh" => Some(home.join(".zshrc")),
+        "sh" | "dash" | "ksh" => Some(home.join(".profile")),
+        _ => None,
+    }
+}
+
+/// The line that sources the `env` file
+pub fn source_line(env_file: &Path) -> String {
+    format!(". \"{}\"", env_file.display())
+}
+
+/// Whether `profile` already sources the `env` file
+pub fn is_configured(profile: &Path, env_file: &Path) -> bool {
+    let line = source_line(env_file);
+    fs::read_to_string(profile).is_ok_and(|text| text.lines().any(|l| l.trim() == line))
+}
+
+/// Add a line sourcing the `env` file to the end of `profile`
+pub fn add_to_profile(profile: &Path, env_file: &Path) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(profile)
+        .with_context(|| format!("Unable to open {}", profile.display()))?;
+    writeln!(file, "\n# Added by goup setup\n{}", source_line(env_file))
+        .with_context(|| format!("Unable to write to {}", profile.display()))
+}
+
+/// Install and enable the latest version, and add it to the PATH, asking about each step
+pub fn run(goup: &Goup, defaults: bool, defaulted_gopath: Option<PathBuf>) -> Result<()> {
+    let ask = |question: &str| {
+        if defaults {
+            Ok(true)
+        } else {
+            prompt::confirm(question)
+        }
+    };
+
+    if let Some(gopath) = defaulted_gopath {
+        status!("GOPATH is not set, so using {}", gopath.display());
+    }
+    let root = goup.root();
+    fs::create_dir_all(root).with_context(|| format!("Unable to create {}", root.display()))?;
+    status!("Go versions will be installed in {}", root.display());
+
+    let index = goup.index(None)?;
+    warn_about_index(goup, &index);
+    note_policy(goup, &index.versions);
+    let (&latest, file) =
+        newest_allowed(goup, &index.versions).ok_or(GoupError::NoVersionsAvailable)?;
+    if goup.installed()?.contains(&latest) {
+        status!("{}, the latest version, is already installed", latest);
+    } else if ask(&format!("Install {}, the latest version of Go?", latest))? {
+        goup.download(latest, file)?;
+        status!("{} installed successfully", latest);
+    } else {
+        status!("Skipped installing {}", latest);
+    }
+
+    let state = goup.state()?;
+    let newest = if state.installed.contains(&latest) {
+        Some(latest)
+    } else {
+        state.installed.last().copied()
+    };
+    match newest {
+        Some(v) if state.enabled == Some(v) => status!("{} is already enabled", v),
+        Some(v) if ask(&format!("Enable {}?", v))? => {
+            goup.enable(v)?;
+            status!("Enabled {}", v);
+        }
+        Some(v) => status!("Skipped enabling {}", v),
+        None => status!("No version is installed to enable"),
+    }
+
+    let env_file = write_env_file(goup)?;
+    let line = source_line(&env_file);
+    match profile() {
+        Some(profile) if is_configured(&profile, &env_file) => {
+            status!("{} already adds Go to your PATH", profile.display())
+        }
+        Some(profile) if ask(&format!("Add Go to your PATH in {}?", profile.display()))? => {
+            add_to_profile(&profile, &env_file)?;
+            status!(
+                "Added Go to your PATH in {}. Open a new shell, or run: {}",
+                profile.display(),
+                line
+            );
+        }
+        _ => status!(
+            "To add Go to your PATH, add this to your shell's startup file: {}",
+            line
+        ),
+    }
+
+    if let Some(enabled) = goup.state()?.enabled {
+        let output = goup.command(enabled, "go")?.arg("version").output()?;
+        status!("{}", String::from_utf8_lossy(&output.stdout).trim_end());
+    }
+    Ok(())
+}
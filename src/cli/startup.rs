@@ -0,0 +1,235 @@
+//! Opening the goup directory with the settings given on the command line, for every command
+
+use super::events::{self, JsonProgress};
+use super::progress::DownloadBar;
+use super::prompt;
+use super::report;
+use super::ui;
+use crate::{Args, Commands, ProgressFormat};
+use anyhow::{Context, Result};
+use console::style;
+use goup::config::ProjectConfig;
+use goup::config::{self, EnableMode, Layer};
+use goup::{CacheMode, Goup, GoupError};
+use std::env;
+use std::path::Path;
+
+/// Open the goup directory with the settings the flags in `args` give, along with those of
+/// the project in the current directory, and bring it up to date with this version of goup
+pub fn open(args: &Args) -> Result<Goup> {
+    if args.progress == ProgressFormat::Json {
+        events::enable();
+    }
+    let multi = report::init(args.quiet);
+    let goup = Goup::from_env()
+        .and_then(|goup| {
+            let Some(project) =
+                env::current_dir().map_or(Ok(None), |dir| ProjectConfig::find(&dir))?
+            else {
+                return Ok(goup);
+            };
+            let trusted = trust_project(&goup, &project);
+            goup.with_project(&project, trusted)
+        })
+        .and_then(|goup| with_flags(goup, args))?
+        .with_cache_mode(match (args.refresh, args.no_cache) {
+            (_, true) => CacheMode::Off,
+            (true, _) => CacheMode::Refresh,
+            _ => CacheMode::Normal,
+        })
+        .with_all_releases(args.command.names_pre_release());
+    let mut goup = match args.progress {
+        // The view draws downloads itself, in place of the bar
+        ProgressFormat::Bar if matches!(args.command, Commands::Ui) => {
+            goup.with_progress(ui::Downloads)
+        }
+        // Installs report to the client that asked for them
+        #[cfg(unix)]
+        _ if matches!(args.command, Commands::Daemon { .. }) => {
+            goup.with_progress(goup::daemon::Relay)
+        }
+        ProgressFormat::Bar => goup.with_progress(DownloadBar::new(multi)),
+        ProgressFormat::Json => goup.with_progress(JsonProgress::default()),
+    };
+    if args.verbose {
+        diagnostic!("Retry policy: {}", goup.config().retry);
+        if let Some(version) = args.command.runs_with() {
+            for (name, value) in goup.injected_env(version) {
+                diagnostic!(
+                    "Setting {}={} for {}, from the config file",
+                    name,
+                    value,
+                    version
+                );
+            }
+        }
+    }
+    if !args.no_migrate {
+        let migration = goup.migrate().with_context(|| {
+            format!(
+                "Unable to move {} into the current layout. Run again to finish, or pass \
+                 --no-migrate to leave it as it is",
+                goup.root().display()
+            )
+        })?;
+        if let Some(migration) = migration {
+            report_migration(&goup, &migration);
+        }
+    }
+    relink(&goup);
+    note_expired_pins(&goup);
+    Ok(goup)
+}
+
+/// Layer the settings given by flags over those from the config files
+fn with_flags(goup: Goup, args: &Args) -> Result<Goup, GoupError> {
+    let mut config = goup.config().clone();
+    if let Some(timeout) = args.stall_timeout {
+        config.stall_timeout = Some(timeout);
+        config.set_by("stall_timeout", Layer::Flag("--stall-timeout"), timeout);
+    }
+    if let Some(cacert) = &args.cacert {
+        config.ca_bundle = Some(cacert.clone());
+        let path = config::quoted(&cacert.display().to_string());
+        config.set_by("ca_bundle", Layer::Flag("--cacert"), path);
+    }
+    if let Some(chunks) = args.chunks {
+        config.download_chunks = Some(usize::from(chunks));
+        config.set_by("download_chunks", Layer::Flag("--chunks"), chunks);
+    }
+    if let Some(attempts) = args.retries {
+        config.retry.attempts = attempts;
+        let retry = format!("{{ attempts = {} }}", attempts);
+        config.set_by("retry", Layer::Flag("--retries"), retry);
+    }
+    if args.offline {
+        config.offline = Some(true);
+        config.set_by("offline", Layer::Flag("--offline"), true);
+    }
+    if args.trace_http {
+        config.trace_http = Some(true);
+        config.set_by("trace_http", Layer::Flag("--trace-http"), true);
+    }
+    if let Commands::Install { minimal: true, .. } = args.command {
+        config.minimal = Some(true);
+        config.set_by("minimal", Layer::Flag("--minimal"), true);
+    }
+    if let Commands::Install {
+        no_verify: true, ..
+    }
+    | Commands::Update {
+        no_verify: true, ..
+    } = args.command
+    {
+        config.verify_checksums = Some(false);
+        config.set_by("verify_checksums", Layer::Flag("--no-verify"), false);
+    }
+    if let Commands::Update { pre: true, .. } = args.command {
+        config.pre_releases = Some(true);
+        config.set_by("pre_releases", Layer::Flag("--pre"), true);
+    }
+    if let Commands::Enable { copy: true, .. } = args.command {
+        config.enable_mode = EnableMode::Copy;
+        config.set_by("enable_mode", Layer::Flag("--copy"), config::quoted("copy"));
+    }
+    goup.with_config(config)
+}
+
+/// Move links made where link_path used to say, the first time goup runs after it changes
+fn relink(goup: &Goup) {
+    if goup.links_moved().unwrap_or(false) {
+        let paths: Vec<_> = goup
+            .link_paths()
+            .iter()
+            .map(|p| p.display().to_string())
+            .collect();
+        match goup.relink() {
+            Ok(true) => status!(
+                "Moved the link to the enabled version to {}",
+                paths.join(", ")
+            ),
+            Ok(false) => {}
+            Err(e) => diagnostic!(
+                "{} Unable to move the link to the enabled version to {}: {}",
+                style("Warning:").yellow(),
+                paths.join(", "),
+                e
+            ),
+        }
+    }
+}
+
+/// Point out each expired pin once, in whichever command notices it first
+fn note_expired_pins(goup: &Goup) {
+    let expired = goup.transaction(|tx| Ok::<_, GoupError>(tx.notice_expired_pins()));
+    for version in expired.unwrap_or_default() {
+        status!(
+            "Note: the pin on {} has expired, so 'goup clean' may remove it. Pin it again to \
+             keep it, or use 'goup pin --prune-expired' to unpin it",
+            version
+        );
+    }
+}
+
+/// Say what moving the goup directory into the current layout moved
+fn report_migration(goup: &Goup, migration: &goup::layout::Migration) {
+    let root = goup.root();
+    let relative = |path: &Path| {
+        path.strip_prefix(root)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+    };
+    status!(
+        "Moved {} into goup's new layout, which older goup binaries can't use:",
+        root.display()
+    );
+    for (from, to) in &migration.moved {
+        status!("  {} -> {}", relative(from), relative(to));
+    }
+    for link in &migration.repointed {
+        status!(
+            "  Pointed {} at the enabled version's new place",
+            link.display()
+        );
+    }
+}
+
+/// Whether every setting in a project's .goup.toml may be used, or only those that can't send
+/// downloads elsewhere. The user is only asked while at the terminal, since `--yes` shouldn't
+/// hand a repository control of where Go comes from.
+fn trust_project(goup: &Goup, project: &ProjectConfig) -> bool {
+    let untrusted = project.untrusted_keys();
+    if untrusted.is_empty()
+        || goup.config().trust_project_config()
+        || goup.project_trusted(project).unwrap_or(false)
+    {
+        return true;
+    }
+    let question = format!(
+        "{} sets {}. Trust it?",
+        project.path.display(),
+        untrusted.join(", ")
+    );
+    if prompt::interactive() && prompt::confirm(&question).unwrap_or(false) {
+        let trusted = goup.transaction(|tx| {
+            tx.trust_project(&project.path, project.digest());
+            Ok::<_, GoupError>(())
+        });
+        if let Err(e) = trusted {
+            diagnostic!(
+                "Unable to remember trusting {}: {}",
+                project.path.display(),
+                e
+            );
+        }
+        return true;
+    }
+    diagnostic!(
+        "Ignoring {} in {}, which isn't trusted; set trust_project_config = true in the config \
+         file, or run goup at a terminal to trust it",
+        untrusted.join(", "),
+        project.path.display()
+    );
+    false
+}
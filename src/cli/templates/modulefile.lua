@@ -0,0 +1,12 @@
+-- Generated by goup for {version}. It is rewritten by `goup modulefiles`, so edits are lost.
+
+help("Go {number}, installed by goup in {goroot}")
+
+whatis("Name: go")
+whatis("Version: {number}")
+whatis("Description: The Go programming language, release {version}")
+
+family("go")
+
+{env}
+prepend_path("PATH", "{bin}")
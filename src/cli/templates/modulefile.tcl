@@ -0,0 +1,15 @@
+#%Module1.0
+## Generated by goup for {version}. It is rewritten by `goup modulefiles`, so edits are lost.
+
+proc ModulesHelp { } {
+    puts stderr "Go {number}, installed by goup in {goroot}"
+}
+
+module-whatis "Name: go"
+module-whatis "Version: {number}"
+module-whatis "Description: The Go programming language, release {version}"
+
+conflict go
+
+{env}
+prepend-path PATH "{bin}"
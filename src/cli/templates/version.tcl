@@ -0,0 +1,3 @@
+#%Module1.0
+## Generated by goup. It is rewritten by `goup modulefiles` to follow the enabled version.
+set ModulesVersion "{number}"
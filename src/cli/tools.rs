@@ -0,0 +1,46 @@
+//! Rebuilding the tools in GOBIN with another version of Go, for `goup tools`
+
+use anyhow::{bail, Context, Result};
+use goup::tools::{Snapshot, Tool};
+use goup::{GoVersion, Goup, GoupError};
+
+pub fn snapshot(goup: &Goup) -> Result<()> {
+    let version = goup.state()?.enabled.ok_or(GoupError::NoVersionEnabled)?;
+    let snapshot = Snapshot::new(goup.find_tools(version)?);
+    snapshot.store(&goup.tools_file())?;
+    status!("Recorded {} tool(s)", snapshot.tools.len());
+    for tool in &snapshot.tools {
+        status!(
+            "  {} ({}, built with {})",
+            tool.name,
+            tool.install_spec(),
+            tool.go
+        );
+    }
+    Ok(())
+}
+
+pub fn rebuild_all(goup: &Goup) -> Result<()> {
+    let version = goup.state()?.enabled.ok_or(GoupError::NoVersionEnabled)?;
+    let snapshot = Snapshot::load(&goup.tools_file())?
+        .context("No tools have been recorded. Use 'goup tools snapshot' first")?;
+    rebuild(goup, version, &snapshot.tools)
+}
+
+/// Rebuild each of the tools with the given version, reporting how each went
+pub fn rebuild(goup: &Goup, version: GoVersion, tools: &[Tool]) -> Result<()> {
+    let mut failed = 0;
+    for tool in tools {
+        match goup.rebuild_tool(version, tool) {
+            Ok(()) => status!("Rebuilt {} with {}", tool.name, version),
+            Err(e) => {
+                diagnostic!("Failed to rebuild {}: {}", tool.name, e);
+                failed += 1;
+            }
+        }
+    }
+    match failed {
+        0 => Ok(()),
+        n => bail!("{} tool(s) could not be rebuilt", n),
+    }
+}
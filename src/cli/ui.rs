@@ -0,0 +1,551 @@
+//! `goup ui`, a full-screen view of the versions to browse and manage from the keyboard
+//!
+//! Each action calls the same library functions as the command that does the same thing, so
+//! the screen is only ever a quicker way to do what `goup install`, `enable`, `pin`, `unpin`,
+//! `remove`, and `info` already do.
+
+use super::enable::describe_shadow;
+use super::info::info_lines;
+use super::install::check_policy;
+use super::interrupt;
+use super::parse::format_date;
+use super::report;
+use anyhow::{bail, Result};
+use goup::series::Status;
+use goup::size::format_size;
+use goup::trash::Disposal;
+use goup::{FileInfo, GoVersion, Goup, GoupError, Progress, VersionFile};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, LineGauge, Paragraph, Row, Table, TableState};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{self, IsTerminal};
+use std::sync::atomic::Ordering;
+use std::sync::{mpsc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// How long to wait for a key before drawing the screen again, to keep download progress
+/// current
+const TICK: Duration = Duration::from_millis(100);
+
+/// How wide the list of versions is, with the details of the selected one beside it
+const LIST_WIDTH: u16 = 44;
+
+/// The keys, as shown at the bottom of the screen
+const KEYS: &str = "↑↓ move  i install  e enable  p pin/unpin  d remove  r refresh  q quit";
+
+/// The download under way, as reported by [`Downloads`]
+static TRANSFER: Mutex<Transfer> = Mutex::new(Transfer {
+    total: 0,
+    received: 0,
+    entries: 0,
+});
+
+#[derive(Clone, Copy)]
+struct Transfer {
+    total: u64,
+    received: u64,
+    entries: u64,
+}
+
+/// Keeps track of the download under way, for the screen to show
+pub struct Downloads;
+
+impl Progress for Downloads {
+    fn start(&self, total: u64) {
+        *TRANSFER.lock().unwrap() = Transfer {
+            total,
+            received: 0,
+            entries: 0,
+        };
+    }
+
+    fn advance(&self, bytes: u64) {
+        TRANSFER.lock().unwrap().received += bytes;
+    }
+
+    fn extracted(&self, entries: u64) {
+        TRANSFER.lock().unwrap().entries += entries;
+    }
+
+    fn phase(&self, name: &'static str, elapsed: Duration) {
+        report::record(name, elapsed);
+    }
+}
+
+/// Show the versions in `goup`'s root until the user quits
+pub fn run(goup: &Goup) -> Result<()> {
+    if !io::stdin().is_terminal() || !io::stdout().is_terminal() {
+        bail!(
+            "goup ui needs a terminal to read keys from and draw on. \
+             Use goup list, info, install, enable, pin, unpin, and remove instead"
+        );
+    }
+    let mut app = App::load(goup)?;
+    app.fetch(false);
+
+    let stop = interrupt::catch();
+    let mut screen = Screen::enter()?;
+    let (sender, installs) = mpsc::channel();
+    thread::scope(|scope| loop {
+        if stop.swap(false, Ordering::Relaxed) {
+            app.quit();
+        }
+        if app.quitting && app.installing.is_none() {
+            return Ok(());
+        }
+        screen.0.draw(|frame| app.draw(frame))?;
+        if let Ok((version, res)) = installs.try_recv() {
+            app.installed(version, res);
+            continue;
+        }
+        if !event::poll(TICK)? {
+            continue;
+        }
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        if let Some((version, file)) = app.key(key) {
+            let done = sender.clone();
+            scope.spawn(move || {
+                let res = goup.transaction(|tx| tx.download(version, &file));
+                let _ = done.send((version, res));
+            });
+        }
+    })
+}
+
+/// What is shown, and what the user is in the middle of
+struct App<'a> {
+    goup: &'a Goup,
+    state: VersionFile,
+    available: BTreeMap<GoVersion, FileInfo>,
+    sizes: BTreeMap<GoVersion, u64>,
+    /// When each installed version was installed, where that was recorded
+    dates: BTreeMap<GoVersion, String>,
+    /// Every installed or available version, newest first
+    rows: Vec<GoVersion>,
+    selected: usize,
+    /// The selection and scroll position of the list as drawn
+    table: TableState,
+    /// How many versions fit in the list, as last drawn
+    page: usize,
+    message: Option<(String, bool)>,
+    /// The version that pressing `y` will remove
+    confirming: Option<GoVersion>,
+    installing: Option<GoVersion>,
+    quitting: bool,
+}
+
+impl<'a> App<'a> {
+    fn load(goup: &'a Goup) -> Result<Self> {
+        let mut app = Self {
+            goup,
+            state: VersionFile::default(),
+            available: BTreeMap::new(),
+            sizes: BTreeMap::new(),
+            dates: BTreeMap::new(),
+            rows: Vec::new(),
+            selected: 0,
+            table: TableState::default(),
+            page: 0,
+            message: None,
+            confirming: None,
+            installing: None,
+            quitting: false,
+        };
+        app.reload()?;
+        Ok(app)
+    }
+
+    /// Read the installed versions again, keeping the same version selected
+    fn reload(&mut self) -> Result<()> {
+        let selected = self.rows.get(self.selected).copied();
+        self.state = self.goup.state()?;
+        self.sizes = self
+            .state
+            .installed
+            .iter()
+            .filter_map(|&v| Some((v, self.goup.size(v).ok()?)))
+            .collect();
+        self.dates = self
+            .state
+            .installed
+            .iter()
+            .filter_map(|&v| Some((v, format_date(self.goup.provenance(v)?.installed_at()))))
+            .collect();
+        let versions: BTreeSet<GoVersion> = self
+            .state
+            .installed
+            .iter()
+            .chain(self.available.keys())
+            .copied()
+            .collect();
+        self.rows = versions.into_iter().rev().collect();
+        self.selected = selected
+            .and_then(|v| self.rows.iter().position(|&r| r == v))
+            .unwrap_or(0);
+        Ok(())
+    }
+
+    /// Look up the available versions, from the cache unless `refresh`
+    fn fetch(&mut self, refresh: bool) {
+        let max_age = if refresh {
+            Duration::ZERO
+        } else {
+            self.goup.config().cache_ttl()
+        };
+        match self.goup.index(Some(max_age)) {
+            Ok(index) => {
+                self.available = index.versions;
+                if index.offline {
+                    self.inform("Couldn't reach go.dev, so the versions available are cached");
+                }
+            }
+            Err(e) => self.fail(format!("Showing installed versions only. {}", e)),
+        }
+        if let Err(e) = self.reload() {
+            self.fail(e);
+        }
+    }
+
+    fn inform(&mut self, text: impl Into<String>) {
+        self.message = Some((text.into(), false));
+    }
+
+    fn fail(&mut self, e: impl std::fmt::Display) {
+        self.message = Some((e.to_string(), true));
+    }
+
+    fn quit(&mut self) {
+        self.quitting = true;
+        if let Some(version) = self.installing {
+            self.inform(format!("Waiting for {} to finish installing", version));
+        }
+    }
+
+    /// Handle a key, returning the version to start installing, if that is what was asked
+    fn key(&mut self, key: KeyEvent) -> Option<(GoVersion, FileInfo)> {
+        if let Some(version) = self.confirming.take() {
+            match key.code {
+                KeyCode::Char('y' | 'Y') => self.remove(version),
+                _ => self.inform(format!("Cancelled, {} was not removed", version)),
+            }
+            return None;
+        }
+        // The terminal is in raw mode, so Ctrl-C arrives as a key rather than a signal
+        if key.modifiers.contains(KeyModifiers::CONTROL) && key.code == KeyCode::Char('c') {
+            self.quit();
+            return None;
+        }
+        let last = self.rows.len().saturating_sub(1);
+        let page = self.page.max(1);
+        match key.code {
+            KeyCode::Up | KeyCode::Char('k') => self.selected = self.selected.saturating_sub(1),
+            KeyCode::Down | KeyCode::Char('j') => self.selected = (self.selected + 1).min(last),
+            KeyCode::PageUp => self.selected = self.selected.saturating_sub(page),
+            KeyCode::PageDown => self.selected = (self.selected + page).min(last),
+            KeyCode::Home | KeyCode::Char('g') => self.selected = 0,
+            KeyCode::End | KeyCode::Char('G') => self.selected = last,
+            KeyCode::Char('q') | KeyCode::Esc => self.quit(),
+            KeyCode::Char('r') => {
+                self.message = None;
+                self.fetch(true);
+            }
+            code => {
+                let version = *self.rows.get(self.selected)?;
+                match code {
+                    KeyCode::Char('i') => return self.install(version),
+                    KeyCode::Char('e') => self.enable(version),
+                    KeyCode::Char('p') => self.toggle_pin(version),
+                    KeyCode::Char('d') | KeyCode::Delete => self.confirm_removal(version),
+                    _ => {}
+                }
+            }
+        }
+        None
+    }
+
+    fn install(&mut self, version: GoVersion) -> Option<(GoVersion, FileInfo)> {
+        if let Some(installing) = self.installing {
+            self.fail(format!("{} is still installing", installing));
+            return None;
+        }
+        if self.state.installed.contains(&version) {
+            self.inform(format!("{} is already installed", version));
+            return None;
+        }
+        let Some(file) = self.available.get(&version).cloned() else {
+            self.fail(GoupError::VersionNotAvailable(version));
+            return None;
+        };
+        if let Err(e) = check_policy(self.goup, version, false) {
+            self.fail(e);
+            return None;
+        }
+        Downloads.start(file.size);
+        self.installing = Some(version);
+        self.message = None;
+        Some((version, file))
+    }
+
+    fn installed(&mut self, version: GoVersion, res: Result<u64, GoupError>) {
+        self.installing = None;
+        match res {
+            Ok(0) => self.inform(format!("{} installed successfully", version)),
+            Ok(omitted) => self.inform(format!(
+                "{} installed successfully, saving {} by leaving out tests and documentation",
+                version,
+                format_size(omitted)
+            )),
+            Err(e) => self.fail(e),
+        }
+        if let Err(e) = self.reload() {
+            self.fail(e);
+        }
+    }
+
+    fn enable(&mut self, version: GoVersion) {
+        match self.goup.enable(version) {
+            Ok(()) => match self.goup.shadowing().first() {
+                Some(shadow) => self.fail(format!(
+                    "Enabled {}, but running go will find {} first",
+                    version,
+                    describe_shadow(shadow)
+                )),
+                None => self.inform(format!("Enabled {}", version)),
+            },
+            Err(e) => self.fail(e),
+        }
+        if let Err(e) = self.reload() {
+            self.fail(e);
+        }
+    }
+
+    fn toggle_pin(&mut self, version: GoVersion) {
+        let res = if self.state.pinned.contains(&version) {
+            self.goup.unpin(version).map(|_| "Unpinned")
+        } else {
+            self.goup.pin(version, None).map(|_| "Pinned")
+        };
+        match res {
+            Ok(done) => self.inform(format!("{} {}", done, version)),
+            Err(e) => self.fail(e),
+        }
+        if let Err(e) = self.reload() {
+            self.fail(e);
+        }
+    }
+
+    fn confirm_removal(&mut self, version: GoVersion) {
+        if !self.state.installed.contains(&version) {
+            self.fail(GoupError::VersionNotInstalled(version));
+            return;
+        }
+        if self.installing == Some(version) {
+            self.fail(format!("{} is still installing", version));
+            return;
+        }
+        self.confirming = Some(version);
+        if self.state.enabled == Some(version) {
+            self.inform(format!("{} is enabled. Remove it anyway? [y/N]", version))
+        } else {
+            self.inform(format!("Remove {}? [y/N]", version))
+        }
+    }
+
+    fn remove(&mut self, version: GoVersion) {
+        let size = self.sizes.get(&version).copied();
+        match self.goup.transaction(|tx| tx.remove(version)) {
+            Ok(Disposal::Trashed(_)) => self.inform(format!(
+                "Moved {} to the trash. Use 'goup restore {}' to bring it back",
+                version, version
+            )),
+            Ok(_) => match size {
+                Some(size) => self.inform(format!(
+                    "{} uninstalled successfully, freeing {}",
+                    version,
+                    format_size(size)
+                )),
+                None => self.inform(format!("{} uninstalled successfully", version)),
+            },
+            Err(e) => self.fail(e),
+        }
+        if let Err(e) = self.reload() {
+            self.fail(e);
+        }
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [title, body, message, keys] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list, details] =
+            Layout::horizontal([Constraint::Length(LIST_WIDTH), Constraint::Min(0)]).areas(body);
+
+        let root = format!("goup ui — {}", self.goup.root().display());
+        frame.render_widget(Line::from(root).bold(), title);
+
+        // One line of the list is taken by the headings
+        self.page = usize::from(list.height).saturating_sub(1);
+        let header = Row::new([
+            Cell::from(""),
+            Cell::from("VERSION"),
+            Cell::from(""),
+            Cell::from(Line::from("SIZE").right_aligned()),
+            Cell::from("INSTALLED"),
+        ])
+        .dim();
+        let widths = [
+            Constraint::Length(1),
+            Constraint::Length(10),
+            Constraint::Length(6),
+            Constraint::Length(10),
+            Constraint::Length(10),
+        ];
+        let rows: Vec<_> = self.rows.iter().map(|&v| self.row(v)).collect();
+        let table = Table::new(rows, widths)
+            .header(header)
+            .row_highlight_style(Style::new().reversed());
+        self.table.select(Some(self.selected));
+        frame.render_stateful_widget(table, list, &mut self.table);
+
+        let lines: Vec<_> = self.details().into_iter().map(Line::from).collect();
+        let pane = Paragraph::new(lines).block(Block::new().borders(Borders::LEFT));
+        frame.render_widget(pane, details);
+
+        match (self.installing, &self.message) {
+            (Some(version), None) => draw_progress(frame, message, version),
+            (_, Some((text, true))) => {
+                frame.render_widget(Line::from(text.as_str()).red(), message)
+            }
+            (_, Some((text, false))) => frame.render_widget(Line::from(text.as_str()), message),
+            (None, None) => {}
+        }
+        frame.render_widget(Line::from(KEYS).dim(), keys);
+    }
+
+    /// The row for `version` in the list, colored like it is by `goup list`
+    fn row(&self, version: GoVersion) -> Row<'static> {
+        let status = Status {
+            installed: self.state.installed.contains(&version),
+            available: self.available.contains_key(&version),
+            enabled: self.state.enabled == Some(version),
+            pinned: self.state.pinned.contains(&version),
+        };
+        let bullet = match (status.enabled, status.installed) {
+            (true, _) => "*",
+            (false, true) => "i",
+            (false, false) => " ",
+        };
+        let size = match self.sizes.get(&version) {
+            Some(&size) => format_size(size),
+            None => String::new(),
+        };
+        let row = Row::new([
+            Cell::from(bullet),
+            Cell::from(version.to_string()),
+            Cell::from(if status.pinned { "pinned" } else { "" }),
+            Cell::from(Line::from(size).right_aligned()),
+            Cell::from(self.dates.get(&version).cloned().unwrap_or_default()),
+        ]);
+        match (status.installed, status.available, status.enabled) {
+            _ if self.available.is_empty() => row,
+            (true, true, _) => row.green(),
+            (true, false, true) => row.red(),
+            (true, false, false) => row.yellow(),
+            _ => row,
+        }
+    }
+
+    /// What `goup info` says about the selected version, or about its archive if it isn't
+    /// installed
+    fn details(&self) -> Vec<String> {
+        let Some(&version) = self.rows.get(self.selected) else {
+            return vec!["No versions are installed or available".to_string()];
+        };
+        if self.state.installed.contains(&version) {
+            return info_lines(self.goup, &self.state, version);
+        }
+        let mut lines = vec![
+            format!("Version:      {}", version),
+            "Status:       available".to_string(),
+        ];
+        if self.state.pinned.contains(&version) {
+            lines.push("Pinned:       yes, though not installed".to_string());
+        }
+        if let Some(file) = self.available.get(&version) {
+            lines.push(format!(
+                "Archive:      {} ({}/{})",
+                file.filename, file.os, file.arch
+            ));
+            lines.push(format!("Download:     {}", format_size(file.size)));
+        }
+        lines
+    }
+}
+
+/// How far the install of `version` has got, as a gauge while it is downloading
+fn draw_progress(frame: &mut Frame, area: Rect, version: GoVersion) {
+    let Transfer {
+        total,
+        received,
+        entries,
+    } = *TRANSFER.lock().unwrap();
+    match (received, entries) {
+        (_, 0) if total > 0 => {
+            let label = format!(
+                "Downloading {}: {} of {}",
+                version,
+                format_size(received),
+                format_size(total)
+            );
+            let gauge = LineGauge::default()
+                .ratio((received as f64 / total as f64).min(1.0))
+                .label(label)
+                .filled_style(Style::new().green());
+            frame.render_widget(gauge, area);
+        }
+        (_, 0) => {
+            let text = format!("Downloading {}: {}", version, format_size(received));
+            frame.render_widget(Line::from(text), area);
+        }
+        _ => {
+            let text = format!("Unpacking {}: {} file(s)", version, entries);
+            frame.render_widget(Line::from(text), area);
+        }
+    }
+}
+
+/// The terminal given over to the screen, which is handed back as it was when dropped. ratatui
+/// does the same on a panic.
+struct Screen(DefaultTerminal);
+
+impl Screen {
+    fn enter() -> io::Result<Self> {
+        match ratatui::try_init() {
+            Ok(terminal) => Ok(Self(terminal)),
+            Err(e) => {
+                ratatui::restore();
+                Err(e)
+            }
+        }
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        ratatui::restore();
+    }
+}
@@ -0,0 +1,374 @@
+//! Keeping up with new releases of Go and of goup, for `goup update`, `goup check`,
+//! `goup upgrade`, and `goup self-update`
+
+use super::enable::warn_about_goroot;
+use super::errors::Outdated;
+use super::install::warn_about_index;
+use super::remove::summarize_removals;
+use super::report::{self, Steps};
+use anyhow::{anyhow, bail, Context, Result};
+use console::style;
+use goup::size::format_size;
+use goup::version::VersionRequest;
+use goup::{self_update, CleanPolicy, FileInfo, GoVersion, Goup, GoupError, Index};
+use std::collections::BTreeMap;
+use std::env;
+
+pub fn check(goup: &Goup) -> Result<()> {
+    let enabled = goup.state()?.enabled.ok_or(GoupError::NoVersionEnabled)?;
+    warn_about_goroot(goup, false);
+    if goup.link_status().is_some_and(|link| !link.resolves) {
+        diagnostic!(
+            "{} {} doesn't resolve, so `go` can't be found. If the goup directory was moved, \
+             run `goup enable {}` to fix it, and set relative_symlinks = true in the config \
+             file so that moving it again doesn't break it.",
+            style("Warning:").yellow(),
+            goup.link_path().display(),
+            enabled
+        );
+    }
+    warn_about_blocked(goup)?;
+    let index = goup.index(Some(goup.config().cache_ttl()))?;
+    warn_about_index(goup, &index);
+    note_policy(goup, &index.versions);
+    let newer: Vec<_> = index
+        .versions
+        .into_keys()
+        .filter(|&v| v > enabled && goup.config().allows(v))
+        .collect();
+    let Some(latest) = newer.last() else {
+        if goup.config().max_version.is_some() || !goup.config().blocklist.is_empty() {
+            status!("{} is the newest version allowed", enabled)
+        } else {
+            status!("{} is the latest version", enabled)
+        }
+        return Ok(());
+    };
+
+    status!("{} is enabled, but {} is available", enabled, latest);
+    let security = goup.security_releases();
+    let fixes: Vec<_> = newer
+        .iter()
+        .filter(|v| security.contains(v))
+        .map(GoVersion::to_string)
+        .collect();
+    if !fixes.is_empty() {
+        status!("Security fixes are included in {}", fixes.join(", "));
+    }
+    Err(Outdated {
+        enabled,
+        security: !fixes.is_empty(),
+    }
+    .into())
+}
+
+pub fn update(goup: &Goup, dry_run: bool) -> Result<()> {
+    let mut steps = Steps::new(report::reporter(), 3);
+    let index = steps.run("index", "Fetching the list of versions", || {
+        goup.index(None)
+    })?;
+    warn_about_index(goup, &index);
+    note_policy(goup, &index.versions);
+    let available = index.versions;
+    let (&latest_version, file_info) =
+        newest_allowed(goup, &available).ok_or(GoupError::NoVersionsAvailable)?;
+    let previous = goup.state()?.enabled;
+
+    if dry_run {
+        steps.finish();
+        if goup.installed()?.contains(&latest_version) {
+            status!("{} is already installed", latest_version)
+        } else {
+            status!("Would install {}", latest_version)
+        }
+        if previous != Some(latest_version) {
+            status!("Would enable {}", latest_version);
+        }
+        return auto_clean(goup, latest_version, previous, true);
+    }
+
+    goup.transaction(|tx| {
+        if tx.state().installed.contains(&latest_version) {
+            steps.skip();
+            let enabling = format!("Enabling {}", latest_version);
+            steps.run("enable", &enabling, || tx.enable(latest_version))?;
+            steps.finish();
+            status!("The latest version is {}", latest_version);
+            status!("Already up to date!");
+            return Ok(());
+        }
+
+        status!("Version {} is available", latest_version);
+        let downloading = format!("Downloading {}", latest_version);
+        steps.run("download", &downloading, || {
+            tx.download(latest_version, file_info)
+        })?;
+        let enabling = format!("Enabling {}", latest_version);
+        steps.run("enable", &enabling, || tx.enable(latest_version))?;
+        steps.finish();
+        status!("Installed and enabled version {}", latest_version);
+        if goup.config().auto_clean() {
+            status!(
+                "Use 'goup enable {}' to roll back",
+                previous.unwrap_or_default()
+            )
+        } else {
+            status!(
+                "Use 'goup clean' to remove old versions, or 'goup enable {}' to roll back",
+                previous.unwrap_or_default()
+            )
+        }
+        Ok::<_, anyhow::Error>(())
+    })?;
+    auto_clean(goup, latest_version, previous, false)
+}
+
+/// With auto_clean set, remove the versions that the retention policy no longer needs now that
+/// `enabled` is enabled. The version enabled before it is kept for rolling back, unless nothing
+/// is to be retained.
+fn auto_clean(
+    goup: &Goup,
+    enabled: GoVersion,
+    previous: Option<GoVersion>,
+    dry_run: bool,
+) -> Result<()> {
+    let config = goup.config();
+    if !config.auto_clean() {
+        return Ok(());
+    }
+    let policy = CleanPolicy {
+        include_available: config.retain.is_some(),
+        retain: config.retain,
+        keep: previous
+            .filter(|_| config.retain != Some(0))
+            .into_iter()
+            .collect(),
+        assume_enabled: Some(enabled),
+        dry_run,
+        ..Default::default()
+    };
+    let report = goup.clean(&policy)?;
+    if dry_run {
+        for r in &report.removed {
+            status!("Would remove {} ({})", r.version, format_size(r.size));
+        }
+        if !report.removed.is_empty() {
+            let freed = report.removed.iter().map(|r| r.size).sum();
+            status!("Would free {}", format_size(freed));
+        }
+        return Ok(());
+    }
+    summarize_removals(goup, &report.removed, &report.failed)
+}
+
+/// The list of versions for `goup list`. Unless `strict`, failing to fetch it isn't an error,
+/// since the installed versions can still be listed: a warning is printed, and `None` given.
+pub fn list_index(goup: &Goup, strict: bool) -> Result<Option<Index>> {
+    match goup.index(Some(goup.config().cache_ttl())) {
+        Ok(index) => {
+            warn_about_index(goup, &index);
+            Ok(Some(index))
+        }
+        Err(e @ (GoupError::Network { .. } | GoupError::Offline { .. })) if !strict => {
+            let reason = match e {
+                GoupError::Offline { .. } => "in offline mode with no cached list of versions",
+                _ => "couldn't reach go.dev",
+            };
+            diagnostic!(
+                "{} {}; availability information omitted",
+                style("Warning:").yellow(),
+                reason
+            );
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// The newest of `versions` that the config's max_version and blocklist allow goup to choose
+pub fn newest_allowed<'a>(
+    goup: &Goup,
+    versions: &'a BTreeMap<GoVersion, FileInfo>,
+) -> Option<(&'a GoVersion, &'a FileInfo)> {
+    versions
+        .iter()
+        .rev()
+        .find(|(&v, _)| goup.config().allows(v))
+}
+
+/// Explain why goup is choosing an older version than the newest, when max_version or the
+/// blocklist holds it back
+pub fn note_policy(goup: &Goup, versions: &BTreeMap<GoVersion, FileInfo>) {
+    let config = goup.config();
+    if let Some(max) = config.max_version {
+        if let Some(first) = versions.keys().find(|&&v| !max.admits(v)) {
+            status!(
+                "Note: max_version {} is set in the config file, so {} and newer are skipped",
+                max,
+                first
+            );
+        }
+    }
+    let Some((&chosen, _)) = newest_allowed(goup, versions) else {
+        return;
+    };
+    let blocked: Vec<_> = versions
+        .keys()
+        .filter(|&&v| v > chosen && config.blocked(v).is_some())
+        .filter(|&&v| config.max_version.is_none_or(|max| max.admits(v)))
+        .map(GoVersion::to_string)
+        .collect();
+    if !blocked.is_empty() {
+        status!(
+            "Note: {} skipped, being on the blocklist in the config file",
+            blocked.join(", ")
+        );
+    }
+}
+
+/// Warn about each installed version that is on the blocklist, since it was installed before
+/// being blocked, or despite it
+fn warn_about_blocked(goup: &Goup) -> Result<()> {
+    for version in goup.installed()? {
+        let Some(blocked) = goup.config().blocked(version) else {
+            continue;
+        };
+        diagnostic!(
+            "{} {} is installed, but on the blocklist in the config file{}",
+            style("Warning:").yellow(),
+            version,
+            blocked
+                .reason()
+                .map(|r| format!(": {}", r))
+                .unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// Replace the installed releases of `series` with its newest, moving their pin and enabled
+/// status over to it. The old releases are only removed once the new one is in place, so a
+/// failure part way leaves both.
+pub fn upgrade(goup: &Goup, series: VersionRequest, keep_old: bool) -> Result<()> {
+    let mut steps = Steps::new(report::reporter(), 4);
+    let available = steps.run("index", "Fetching the list of versions", || {
+        goup.available()
+    })?;
+    let newest = series
+        .resolve(
+            available
+                .keys()
+                .copied()
+                .filter(|&v| goup.config().allows(v)),
+        )
+        .ok_or_else(|| anyhow!("No release of {} is available", series))?;
+    let state = goup.state()?;
+    let old: Vec<_> = state
+        .installed
+        .iter()
+        .copied()
+        .filter(|&v| series.matches(v) && v < newest)
+        .collect();
+    let installed = state.installed.contains(&newest);
+    if old.is_empty() {
+        steps.finish();
+        if installed {
+            status!("{} is already at its newest release, {}", series, newest)
+        } else {
+            bail!(
+                "No release of {} is installed; use 'goup install {}' to install one",
+                series,
+                newest
+            )
+        }
+        return Ok(());
+    }
+
+    let enabled = old.iter().copied().find(|&v| state.enabled == Some(v));
+    let pinned = old.iter().copied().rfind(|v| state.pinned.contains(v));
+    status!("Upgrading {}:", series);
+    if !installed {
+        status!("  install {}", newest);
+    }
+    if let Some(pinned) = pinned {
+        status!("  pin {}, since {} is pinned", newest, pinned);
+    }
+    if let Some(enabled) = enabled {
+        status!("  enable {} in place of {}", newest, enabled);
+    }
+    if !keep_old {
+        for version in &old {
+            status!("  remove {}", version);
+        }
+    }
+
+    goup.transaction(|tx| {
+        if installed {
+            steps.skip()
+        } else {
+            let downloading = format!("Downloading {}", newest);
+            steps.run("download", &downloading, || {
+                tx.download(newest, &available[&newest])
+            })?;
+        }
+        if let Some(pinned) = pinned {
+            let reason = tx.state().pin_reason(pinned).map(str::to_string);
+            match tx
+                .state()
+                .pin_info
+                .get(&pinned)
+                .and_then(|i| i.expires_at())
+            {
+                Some(expires) => tx.pin_until(newest, reason, expires)?,
+                None => tx.pin(newest, reason)?,
+            }
+        }
+        match enabled {
+            Some(_) => {
+                let enabling = format!("Enabling {}", newest);
+                steps.run("enable", &enabling, || tx.enable(newest))?;
+            }
+            None => steps.skip(),
+        }
+        if keep_old {
+            steps.skip();
+        } else {
+            let names: Vec<_> = old.iter().map(GoVersion::to_string).collect();
+            let removing = format!("Removing {}", names.join(", "));
+            steps.run("remove", &removing, || {
+                old.iter().try_for_each(|&version| {
+                    if tx.state().pinned.contains(&version) {
+                        tx.unpin(version)?;
+                    }
+                    tx.remove(version).map(drop)
+                })
+            })?;
+        }
+        Ok::<_, anyhow::Error>(())
+    })?;
+    steps.finish();
+    status!("Upgraded {} to {}", series, newest);
+    Ok(())
+}
+
+pub fn self_update(goup: &Goup, check: bool) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let agent = goup.client("check for a new release of goup")?;
+    let release = self_update::latest_release(agent, goup.config().releases_url())?;
+    if !release.is_newer_than(current) {
+        status!("goup {} is up to date", current);
+        return Ok(());
+    } else if check {
+        status!(
+            "goup {} is available (currently {})",
+            release.version(),
+            current
+        );
+        return Ok(());
+    }
+
+    let exe = env::current_exe().context("Unable to locate the goup executable")?;
+    self_update::install_release(agent, &release, &exe)?;
+    status!("Updated goup from {} to {}", current, release.version());
+    Ok(())
+}
@@ -0,0 +1,825 @@
+use crate::error::GoupError;
+use crate::mirror::{self, MirrorSelection};
+use crate::retry::RetryPolicy;
+use crate::self_update;
+use crate::version::{GoVersion, VersionPattern, VersionRequest};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use std::{fs, io};
+
+/// The location that Go versions are downloaded from by default
+pub const DEFAULT_MIRROR: &str = "https://go.dev/dl";
+
+/// How long a download may go without receiving any data before it is abandoned, by default
+pub const DEFAULT_STALL_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long the list of available versions is reused before checking go.dev again, by default
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// How long a measured ranking of the mirrors is trusted, by default
+pub const DEFAULT_MIRROR_RANKING_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// The location of Go's documentation, including release notes, by default
+pub const DEFAULT_DOCS_URL: &str = "https://go.dev/doc";
+
+/// The name of a project's own settings file, found in the current directory or one above it
+pub const PROJECT_FILE: &str = ".goup.toml";
+
+/// The settings a project's `.goup.toml` may change without being trusted. The others could
+/// redirect downloads, send credentials somewhere, or name paths outside the project, so a
+/// repository could use them against whoever runs goup in it.
+pub const PROJECT_KEYS: &[&str] = &[
+    "max_version",
+    "blocklist",
+    "minimal",
+    "offline",
+    "cache_ttl",
+    "stall_timeout",
+    "retry",
+    "download_chunks",
+    "warn_shadowed",
+    "warn_goroot",
+    "auto_install",
+    "gotoolchain_local",
+];
+
+/// User-provided settings, read from `config.toml` in the goup root
+///
+/// ```
+/// use goup::Config;
+///
+/// let config: Config = toml::from_str(r#"mirror = "https://mirror.example.com/go""#).unwrap();
+/// assert_eq!(config.index_url(), "https://mirror.example.com/go/?mode=json");
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// The base URL serving the version index and archives, in the same layout as go.dev/dl
+    pub mirror: Option<String>,
+    /// More mirrors to fall back to when `mirror` can't be reached
+    pub mirrors: Vec<String>,
+    /// How to choose between the mirrors
+    pub mirror_selection: MirrorSelection,
+    /// Seconds before the mirrors are probed again, with automatic mirror selection
+    pub mirror_ranking_ttl: Option<u64>,
+    /// The endpoint describing the newest release of goup, for `goup self-update`
+    pub releases_url: Option<String>,
+    /// The base URL of Go's documentation, where release notes are found
+    pub docs_url: Option<String>,
+    /// Seconds without receiving data before a download is considered stalled
+    pub stall_timeout: Option<u64>,
+    /// Seconds that `goup list` reuses the list of available versions without checking go.dev
+    pub cache_ttl: Option<u64>,
+    /// Never touch the network, using only cached and local data
+    pub offline: Option<bool>,
+    /// Extra headers to send with requests to the mirror, such as credentials
+    pub headers: BTreeMap<String, HeaderValue>,
+    /// A PEM file of extra certificate authorities to trust, such as a corporate proxy's
+    pub ca_bundle: Option<PathBuf>,
+    /// Trust the platform's certificate store instead of the roots bundled with goup
+    pub native_certs: Option<bool>,
+    /// How failed downloads are retried
+    pub retry: RetryPolicy,
+    /// Log every HTTP request and response to stderr
+    pub trace_http: Option<bool>,
+    /// Leave out tests and documentation when installing versions
+    pub minimal: Option<bool>,
+    /// Check each downloaded archive against the checksum the index lists for it
+    pub verify_checksums: Option<bool>,
+    /// How the enabled version is put in place
+    pub enable_mode: EnableMode,
+    /// Bytes the cache may take up before the least recently used archives are deleted after
+    /// each download. The cache is unbounded by default.
+    pub cache_max_size: Option<u64>,
+    /// Move removed versions into the trash, from where `goup restore` can bring them back,
+    /// rather than deleting them
+    pub use_trash: Option<bool>,
+    /// Bytes the trash may take up before the versions longest in it are deleted for good. The
+    /// trash is unbounded by default.
+    pub trash_max_size: Option<u64>,
+    /// Warn when another `go` comes before goup's on PATH
+    pub warn_shadowed: Option<bool>,
+    /// Warn when GOROOT is set to somewhere other than the enabled version
+    pub warn_goroot: Option<bool>,
+    /// Install the version a project needs when `goup resolve --ensure` finds it missing,
+    /// rather than only warning about it
+    pub auto_install: Option<bool>,
+    /// Make the `go` symlink relative to the goup directory, so that it still resolves when
+    /// the directory is reached by another absolute path
+    pub relative_symlinks: Option<bool>,
+    /// Set GOTOOLCHAIN=local wherever goup sets up Go's environment
+    pub gotoolchain_local: Option<bool>,
+    /// The newest version that may be chosen automatically, as a release like `go1.22.3` or a
+    /// series like `go1.22`. Newer versions must be named and installed with
+    /// `--override-policy`.
+    pub max_version: Option<VersionRequest>,
+    /// Releases known to be broken, which are never installed without `--override-policy`,
+    /// and never chosen automatically
+    pub blocklist: Vec<Blocked>,
+    /// Let betas and release candidates, like `go1.23rc1`, be chosen automatically, as the
+    /// newest version. Otherwise they are only installed by name.
+    pub pre_releases: Option<bool>,
+    /// Remove old versions after `goup update` enables a new one
+    pub auto_clean: Option<bool>,
+    /// Have every clean remove betas and release candidates once their release is out, as
+    /// `goup clean --prerelease` does
+    pub clean_prereleases: Option<bool>,
+    /// How many of the newest versions `auto_clean` keeps, besides pinned versions and the one
+    /// enabled before the update. Without it, versions are removed as by `goup clean`.
+    pub retain: Option<usize>,
+    /// How many connections to download each archive over, in ranges of it. Downloads use a
+    /// single connection by default.
+    pub download_chunks: Option<usize>,
+    /// Where to put the link to the enabled version, instead of `go` in the goup root. Given
+    /// a list, the first is the link that goup's commands report, and the rest are symlinks
+    /// to the same place.
+    pub link_path: Option<LinkPaths>,
+    /// Take every setting from a project's `.goup.toml`, not only those in [`PROJECT_KEYS`].
+    /// Only read from the config file in the goup root.
+    pub trust_project_config: Option<bool>,
+    /// Environment variables to set for commands run with some versions, like
+    /// `[env."go1.21"]`, under the release, series, or pattern they apply to. Variables already
+    /// set in goup's own environment are left alone.
+    #[serde(deserialize_with = "version_env")]
+    pub env: EnvTables,
+    /// Where each setting that isn't at its default was given, by its name in the config file
+    #[serde(skip)]
+    pub sources: BTreeMap<String, Setting>,
+}
+
+/// The `env` tables of the config file: the variables to set, by the versions they apply to
+pub type EnvTables = Vec<(VersionPattern, BTreeMap<String, String>)>;
+
+/// Where a setting was given, from the lowest precedence to the highest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Layer {
+    /// The config file in the goup root
+    Global(PathBuf),
+    /// A project's `.goup.toml`
+    Project(PathBuf),
+    /// A `GOUP_*` environment variable
+    Env(&'static str),
+    /// A command line option
+    Flag(&'static str),
+}
+
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Layer::Global(path) => write!(f, "config file {}", path.display()),
+            Layer::Project(path) => write!(f, "project {}", path.display()),
+            Layer::Env(name) => write!(f, "environment {}", name),
+            Layer::Flag(flag) => write!(f, "command line {}", flag),
+        }
+    }
+}
+
+/// A setting's value, as given by the layer it came from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub layer: Layer,
+    /// The value as it was written, except that header values are left out
+    pub value: String,
+}
+
+/// A project's settings file, which overrides the config file in the goup root for commands
+/// run within the project
+#[derive(Debug, Clone)]
+pub struct ProjectConfig {
+    pub path: PathBuf,
+    text: String,
+    table: toml::Table,
+}
+
+impl ProjectConfig {
+    /// Find the nearest [`PROJECT_FILE`] in `dir` or the directories above it, if there is one
+    pub fn find(dir: &Path) -> Result<Option<ProjectConfig>, GoupError> {
+        let Some(path) = dir
+            .ancestors()
+            .map(|dir| dir.join(PROJECT_FILE))
+            .find(|path| path.is_file())
+        else {
+            return Ok(None);
+        };
+        let text = fs::read_to_string(&path).map_err(GoupError::io("read", &path))?;
+        let invalid = |source| GoupError::ConfigInvalid {
+            path: path.clone(),
+            source,
+        };
+        let table: toml::Table = toml::from_str(&text).map_err(invalid)?;
+        // Unknown and mistyped settings are caught here, where the file can be blamed for them
+        toml::Table::try_into::<Config>(table.clone()).map_err(invalid)?;
+        Ok(Some(ProjectConfig { path, text, table }))
+    }
+
+    /// The settings in the file that are only used once it is trusted, since they aren't
+    /// among [`PROJECT_KEYS`]
+    pub fn untrusted_keys(&self) -> Vec<&str> {
+        self.table
+            .keys()
+            .map(String::as_str)
+            .filter(|key| !PROJECT_KEYS.contains(key) && *key != "trust_project_config")
+            .collect()
+    }
+
+    /// The SHA256 digest of the file, so that trusting it can be tied to what it said
+    pub fn digest(&self) -> String {
+        crate::bundle::sha256(&mut self.text.as_bytes()).expect("reading a string can't fail")
+    }
+}
+
+/// An entry in the blocklist: the versions it names, and optionally why
+///
+/// ```
+/// use goup::Config;
+///
+/// let config: Config = toml::from_str(r#"
+///     blocklist = ["go1.21", { version = "go1.22.5", reason = "breaks cgo" }]
+/// "#).unwrap();
+/// let blocked = config.blocked("go1.22.5".parse().unwrap()).unwrap();
+/// assert_eq!(blocked.reason(), Some("breaks cgo"));
+/// assert!(config.blocked("go1.22.6".parse().unwrap()).is_none());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Blocked {
+    Version(VersionPattern),
+    WithReason {
+        version: VersionPattern,
+        reason: String,
+    },
+}
+
+impl Blocked {
+    /// The versions blocked
+    pub fn pattern(&self) -> &VersionPattern {
+        match self {
+            Self::Version(pattern)
+            | Self::WithReason {
+                version: pattern, ..
+            } => pattern,
+        }
+    }
+
+    /// Why they are blocked, if that was given
+    pub fn reason(&self) -> Option<&str> {
+        match self {
+            Self::Version(_) => None,
+            Self::WithReason { reason, .. } => Some(reason),
+        }
+    }
+}
+
+/// One path, or a list of them
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum LinkPaths {
+    One(PathBuf),
+    Many(Vec<PathBuf>),
+}
+
+/// How `goup enable` puts the enabled version in place at `$GOPATH/goup/go`
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnableMode {
+    /// Make `go` a symlink to the version
+    #[default]
+    Symlink,
+    /// Make `go` a copy of the version, hard linked where possible, for build sandboxes and
+    /// filesystems that don't cope with symlinks
+    Copy,
+}
+
+/// The value of an extra request header, given either directly or as the name of an
+/// environment variable to read it from, so that secrets can be kept out of the config file
+///
+/// ```
+/// use goup::Config;
+///
+/// let config: Config = toml::from_str(r#"
+///     [headers]
+///     X-Team = "platform"
+///     X-Api-Key = { env = "ARTIFACTORY_TOKEN" }
+/// "#).unwrap();
+/// assert_eq!(config.headers["X-Team"].resolve().as_deref(), Some("platform"));
+/// ```
+#[derive(Clone, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum HeaderValue {
+    Literal(String),
+    Env { env: String },
+}
+
+impl HeaderValue {
+    /// The value to send, or `None` if it comes from an environment variable that isn't set
+    pub fn resolve(&self) -> Option<String> {
+        match self {
+            HeaderValue::Literal(value) => Some(value.clone()),
+            HeaderValue::Env { env } => env::var(env).ok(),
+        }
+    }
+}
+
+impl fmt::Debug for HeaderValue {
+    // Header values are often secrets, so keep them out of any debug output
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeaderValue::Literal(_) => f.write_str("Literal(<redacted>)"),
+            HeaderValue::Env { env } => f.debug_struct("Env").field("env", env).finish(),
+        }
+    }
+}
+
+impl Config {
+    /// Read the config file at the given path. A missing file gives the default config.
+    pub fn load(path: &Path) -> Result<Config, GoupError> {
+        Self::layered(path, None, false)
+    }
+
+    /// Read the config file at `path`, with the settings of `project` over it. Unless the
+    /// project is `trusted`, only the settings in [`PROJECT_KEYS`] are taken from it, and
+    /// `trust_project_config` never is.
+    ///
+    /// ```
+    /// use goup::config::{Config, ProjectConfig, PROJECT_FILE};
+    ///
+    /// let dir = tempfile::tempdir().unwrap();
+    /// std::fs::write(dir.path().join("config.toml"), "max_version = \"go1.23\"\n").unwrap();
+    /// let project = dir.path().join("project");
+    /// std::fs::create_dir(&project).unwrap();
+    /// std::fs::write(
+    ///     project.join(PROJECT_FILE),
+    ///     "max_version = \"go1.21\"\nmirror = \"https://evil.example.com\"\n",
+    /// ).unwrap();
+    ///
+    /// let project = ProjectConfig::find(&project.join("src")).unwrap().unwrap();
+    /// assert_eq!(project.untrusted_keys(), ["mirror"]);
+    /// let config = Config::layered(&dir.path().join("config.toml"), Some(&project), false).unwrap();
+    /// assert_eq!(config.max_version, Some("go1.21".parse().unwrap()));
+    /// assert_eq!(config.mirror, None);
+    /// ```
+    pub fn layered(
+        path: &Path,
+        project: Option<&ProjectConfig>,
+        trusted: bool,
+    ) -> Result<Config, GoupError> {
+        let invalid = |path: &Path| {
+            let path = path.to_path_buf();
+            move |source| GoupError::ConfigInvalid { path, source }
+        };
+        let mut table = match fs::read_to_string(path) {
+            Ok(x) => toml::from_str::<toml::Table>(&x).map_err(invalid(path))?,
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => toml::Table::new(),
+            Err(e) => return Err(GoupError::io("read", path)(e)),
+        };
+        let mut sources: BTreeMap<_, _> = table
+            .iter()
+            .map(|(key, value)| (key.clone(), setting(Layer::Global(path.into()), key, value)))
+            .collect();
+        let mut config = toml::Table::try_into::<Config>(table.clone()).map_err(invalid(path))?;
+
+        let Some(project) = project else {
+            config.sources = sources;
+            return Ok(config);
+        };
+        let untrusted = project.untrusted_keys();
+        for (key, value) in &project.table {
+            if key == "trust_project_config" || (!trusted && untrusted.contains(&key.as_str())) {
+                continue;
+            }
+            let layer = Layer::Project(project.path.clone());
+            sources.insert(key.clone(), setting(layer, key, value));
+            table.insert(key.clone(), value.clone());
+        }
+        config = toml::Table::try_into::<Config>(table).map_err(invalid(&project.path))?;
+        config.sources = sources;
+        Ok(config)
+    }
+
+    /// Record that the setting `key` was given by `layer`, as `value`, which is written as it
+    /// would be in the config file
+    pub fn set_by(&mut self, key: &str, layer: Layer, value: impl fmt::Display) {
+        let value = value.to_string();
+        self.sources
+            .insert(key.to_string(), Setting { layer, value });
+    }
+
+    /// Whether to take every setting from a project's `.goup.toml`, without asking
+    pub fn trust_project_config(&self) -> bool {
+        self.trust_project_config.unwrap_or(false)
+    }
+
+    /// Override settings with those given by `GOUP_*` environment variables
+    pub fn apply_env(&mut self) {
+        if let Ok(mirror) = env::var("GOUP_MIRROR") {
+            self.set_by("mirror", Layer::Env("GOUP_MIRROR"), quoted(&mirror));
+            self.mirror = Some(mirror);
+        }
+        if let Ok(url) = env::var("GOUP_RELEASES_URL") {
+            self.set_by(
+                "releases_url",
+                Layer::Env("GOUP_RELEASES_URL"),
+                quoted(&url),
+            );
+            self.releases_url = Some(url);
+        }
+        if let Ok(url) = env::var("GOUP_DOCS_URL") {
+            self.set_by("docs_url", Layer::Env("GOUP_DOCS_URL"), quoted(&url));
+            self.docs_url = Some(url);
+        }
+        if let Ok(offline) = env::var("GOUP_OFFLINE") {
+            self.offline = Some(is_truthy(&offline));
+            self.set_by("offline", Layer::Env("GOUP_OFFLINE"), is_truthy(&offline));
+        }
+        if let Ok(trace) = env::var("GOUP_TRACE_HTTP") {
+            self.trace_http = Some(is_truthy(&trace));
+            self.set_by(
+                "trace_http",
+                Layer::Env("GOUP_TRACE_HTTP"),
+                is_truthy(&trace),
+            );
+        }
+    }
+
+    /// The base URL that versions are downloaded from, without a trailing slash
+    pub fn mirror(&self) -> &str {
+        self.mirror
+            .as_deref()
+            .unwrap_or(DEFAULT_MIRROR)
+            .trim_end_matches('/')
+    }
+
+    /// Every configured mirror, starting with `mirror`, without trailing slashes
+    ///
+    /// ```
+    /// use goup::Config;
+    ///
+    /// let config: Config = toml::from_str(r#"mirrors = ["https://mirror.example.com/go/"]"#)
+    ///     .unwrap();
+    /// assert_eq!(config.mirrors(), ["https://go.dev/dl", "https://mirror.example.com/go"]);
+    /// ```
+    pub fn mirrors(&self) -> Vec<String> {
+        let mut mirrors = vec![self.mirror().to_string()];
+        for mirror in &self.mirrors {
+            let mirror = mirror.trim_end_matches('/').to_string();
+            if !mirrors.contains(&mirror) {
+                mirrors.push(mirror);
+            }
+        }
+        mirrors
+    }
+
+    /// How long a measured ranking of the mirrors is trusted before probing them again
+    pub fn mirror_ranking_ttl(&self) -> Duration {
+        self.mirror_ranking_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_MIRROR_RANKING_TTL)
+    }
+
+    /// The URL of the JSON index listing the available versions, all of them if pre-releases
+    /// may be chosen
+    pub fn index_url(&self) -> String {
+        mirror::index_url(self.mirror(), self.pre_releases())
+    }
+
+    /// The URL that the given archive can be downloaded from
+    pub fn archive_url(&self, filename: &str) -> String {
+        mirror::archive_url(self.mirror(), filename)
+    }
+
+    /// How long a download may go without receiving any data before it is abandoned
+    pub fn stall_timeout(&self) -> Duration {
+        self.stall_timeout
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_STALL_TIMEOUT)
+    }
+
+    /// Whether to trust the platform's certificate store instead of the bundled roots
+    pub fn native_certs(&self) -> bool {
+        self.native_certs.unwrap_or(false)
+    }
+
+    /// Whether every HTTP request and response should be logged to stderr
+    pub fn trace_http(&self) -> bool {
+        self.trace_http.unwrap_or(false)
+    }
+
+    /// Whether installs should leave out tests and documentation, which Go doesn't need to
+    /// build programs
+    pub fn minimal(&self) -> bool {
+        self.minimal.unwrap_or(false)
+    }
+
+    /// Whether downloaded archives must match the checksum the index lists for them. Turning
+    /// this off is only for proxies that alter what they pass on; checksums imported with
+    /// `goup checksums import` are checked regardless.
+    pub fn verify_checksums(&self) -> bool {
+        self.verify_checksums.unwrap_or(true)
+    }
+
+    /// Whether to warn when another `go` comes before goup's on PATH, which some people arrange
+    /// deliberately
+    pub fn warn_shadowed(&self) -> bool {
+        self.warn_shadowed.unwrap_or(true)
+    }
+
+    /// Whether `goup resolve --ensure` installs a missing version, as opposed to warning
+    pub fn auto_install(&self) -> bool {
+        self.auto_install.unwrap_or(false)
+    }
+
+    /// Whether the `go` symlink is made relative to the goup directory, for home directories
+    /// that are synced or mounted at different paths on different machines
+    pub fn relative_symlinks(&self) -> bool {
+        self.relative_symlinks.unwrap_or(false)
+    }
+
+    /// Whether to warn when GOROOT is set to somewhere other than the enabled version, for the
+    /// rare setup that sets it on purpose
+    pub fn warn_goroot(&self) -> bool {
+        self.warn_goroot.unwrap_or(true)
+    }
+
+    /// Whether goup should set GOTOOLCHAIN=local, which stops the go command from downloading
+    /// a newer toolchain of its own when a go.mod asks for one, behind goup's back
+    pub fn gotoolchain_local(&self) -> bool {
+        self.gotoolchain_local.unwrap_or(true)
+    }
+
+    /// How many connections to download an archive over at once, which is never fewer than one
+    pub fn download_chunks(&self) -> usize {
+        self.download_chunks.unwrap_or(1).max(1)
+    }
+
+    /// Where the links to the enabled version go, with `~` standing for the home directory.
+    /// Empty unless `link_path` is set, in which case goup's root is left alone.
+    ///
+    /// ```
+    /// use goup::Config;
+    /// use std::path::PathBuf;
+    ///
+    /// let config: Config = toml::from_str(r#"link_path = ["/opt/go", "/usr/local/go"]"#).unwrap();
+    /// assert_eq!(config.link_paths(), [PathBuf::from("/opt/go"), PathBuf::from("/usr/local/go")]);
+    /// ```
+    pub fn link_paths(&self) -> Vec<PathBuf> {
+        let paths = match &self.link_path {
+            None => return Vec::new(),
+            Some(LinkPaths::One(path)) => vec![path.clone()],
+            Some(LinkPaths::Many(paths)) => paths.clone(),
+        };
+        let home = env::var_os("HOME").map(PathBuf::from);
+        paths
+            .into_iter()
+            .map(|path| match (path.strip_prefix("~"), &home) {
+                (Ok(rest), Some(home)) => home.join(rest),
+                _ => path,
+            })
+            .collect()
+    }
+
+    /// Whether `goup update` should remove old versions after enabling a new one
+    pub fn auto_clean(&self) -> bool {
+        self.auto_clean.unwrap_or(false)
+    }
+
+    /// Whether cleaning removes betas and release candidates that their release supersedes
+    pub fn clean_prereleases(&self) -> bool {
+        self.clean_prereleases.unwrap_or(false)
+    }
+
+    /// Whether betas and release candidates may be chosen automatically
+    pub fn pre_releases(&self) -> bool {
+        self.pre_releases.unwrap_or(false)
+    }
+
+    /// Whether `max_version` and the blocklist let goup choose `version` without being told
+    /// to override them. Pre-releases are only chosen with [`Config::pre_releases`].
+    ///
+    /// ```
+    /// use goup::Config;
+    ///
+    /// let config: Config = toml::from_str(r#"
+    ///     max_version = "go1.22"
+    ///     blocklist = ["go1.22.5"]
+    /// "#).unwrap();
+    /// assert!(config.allows("go1.22.6".parse().unwrap()));
+    /// assert!(!config.allows("go1.22.5".parse().unwrap()));
+    /// assert!(!config.allows("go1.23.0".parse().unwrap()));
+    /// assert!(!config.allows("go1.22rc1".parse().unwrap()));
+    /// ```
+    pub fn allows(&self, version: GoVersion) -> bool {
+        self.max_version.is_none_or(|cap| cap.admits(version))
+            && self.blocked(version).is_none()
+            && (self.pre_releases() || !version.is_prerelease())
+    }
+
+    /// The entry in the blocklist that blocks `version`, if one does
+    pub fn blocked(&self, version: GoVersion) -> Option<&Blocked> {
+        self.blocklist.iter().find(|b| b.pattern().matches(version))
+    }
+
+    /// The environment variables to set for commands run with `version`. Where more than one
+    /// table sets a variable, a release's wins over its series', and a series' over a pattern's.
+    ///
+    /// ```
+    /// use goup::Config;
+    ///
+    /// let config: Config = toml::from_str(r#"
+    ///     [env."go1.21"]
+    ///     GODEBUG = "gotypesalias=0"
+    ///     CGO_ENABLED = "0"
+    ///     [env."go1.21.5"]
+    ///     GODEBUG = "gotypesalias=1"
+    /// "#).unwrap();
+    /// let env = config.env_for("go1.21.5".parse().unwrap());
+    /// assert_eq!(env["GODEBUG"], "gotypesalias=1");
+    /// assert_eq!(env["CGO_ENABLED"], "0");
+    /// assert!(config.env_for("go1.22.0".parse().unwrap()).is_empty());
+    /// ```
+    pub fn env_for(&self, version: GoVersion) -> BTreeMap<&str, &str> {
+        let mut tables: Vec<_> = self
+            .env
+            .iter()
+            .filter(|(pattern, _)| pattern.matches(version))
+            .collect();
+        tables.sort_by_key(|(pattern, _)| specificity(pattern));
+        tables
+            .into_iter()
+            .flat_map(|(_, vars)| vars)
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect()
+    }
+
+    /// Whether removed versions go to the trash rather than being deleted
+    pub fn use_trash(&self) -> bool {
+        self.use_trash.unwrap_or(false)
+    }
+
+    /// Whether goup must avoid all network access
+    pub fn offline(&self) -> bool {
+        self.offline.unwrap_or(false)
+    }
+
+    /// How long read-only commands reuse the list of available versions before checking
+    /// go.dev again
+    pub fn cache_ttl(&self) -> Duration {
+        self.cache_ttl
+            .map(Duration::from_secs)
+            .unwrap_or(DEFAULT_CACHE_TTL)
+    }
+
+    /// The base URL of Go's documentation, without a trailing slash
+    pub fn docs_url(&self) -> &str {
+        self.docs_url
+            .as_deref()
+            .unwrap_or(DEFAULT_DOCS_URL)
+            .trim_end_matches('/')
+    }
+
+    /// The URL of the page describing every release of Go
+    pub fn release_history_url(&self) -> String {
+        format!("{}/devel/release", self.docs_url())
+    }
+
+    /// The endpoint describing the newest release of goup
+    pub fn releases_url(&self) -> &str {
+        self.releases_url
+            .as_deref()
+            .unwrap_or(self_update::RELEASES_URL)
+    }
+}
+
+/// Add `pattern` to the blocklist in the config file at `path`, or change the reason given
+/// for it if it's already there. The rest of the file is left as it is, comments and all.
+pub fn block(path: &Path, pattern: &VersionPattern, reason: Option<&str>) -> Result<(), GoupError> {
+    edit(path, |blocklist| {
+        let entry = match reason {
+            Some(reason) => {
+                let mut table = toml_edit::InlineTable::new();
+                table.insert("version", pattern.to_string().into());
+                table.insert("reason", reason.into());
+                toml_edit::Value::InlineTable(table)
+            }
+            None => pattern.to_string().into(),
+        };
+        match position(blocklist, pattern) {
+            Some(i) => {
+                blocklist.replace(i, entry);
+            }
+            None => blocklist.push(entry),
+        }
+    })
+}
+
+/// Take `pattern` out of the blocklist in the config file at `path`, returning whether it was
+/// there. Only the entry naming exactly that pattern is removed.
+pub fn unblock(path: &Path, pattern: &VersionPattern) -> Result<bool, GoupError> {
+    edit(path, |blocklist| match position(blocklist, pattern) {
+        Some(i) => {
+            blocklist.remove(i);
+            true
+        }
+        None => false,
+    })
+}
+
+/// Where `pattern` is in the blocklist, whether given alone or with a reason
+fn position(blocklist: &toml_edit::Array, pattern: &VersionPattern) -> Option<usize> {
+    blocklist.iter().position(|entry| {
+        let name = match entry {
+            toml_edit::Value::InlineTable(table) => table.get("version").and_then(|v| v.as_str()),
+            value => value.as_str(),
+        };
+        name.and_then(|n| n.parse::<VersionPattern>().ok()).as_ref() == Some(pattern)
+    })
+}
+
+/// Change the blocklist in the config file at `path`, creating either if need be. The file is
+/// only replaced if it is still a valid config afterwards.
+fn edit<T>(path: &Path, change: impl FnOnce(&mut toml_edit::Array) -> T) -> Result<T, GoupError> {
+    let uneditable = |reason: String| GoupError::ConfigUneditable {
+        path: path.to_path_buf(),
+        reason,
+    };
+    Config::load(path)?;
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(GoupError::io("read", path)(e)),
+    };
+    let mut doc: toml_edit::DocumentMut = text
+        .parse()
+        .map_err(|e: toml_edit::TomlError| uneditable(e.to_string()))?;
+    let blocklist = doc
+        .entry("blocklist")
+        .or_insert_with(|| toml_edit::value(toml_edit::Array::new()))
+        .as_array_mut()
+        .ok_or_else(|| uneditable("blocklist isn't a list".to_string()))?;
+    let res = change(blocklist);
+    blocklist.fmt();
+
+    let text = doc.to_string();
+    toml::from_str::<Config>(&text).map_err(|source| GoupError::ConfigInvalid {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+    }
+    let staging = path.with_extension("toml.tmp");
+    fs::write(&staging, text).map_err(GoupError::io("write", &staging))?;
+    fs::rename(&staging, path).map_err(GoupError::io("replace", path))?;
+    Ok(res)
+}
+
+/// `text` as a string in the config file, quoted and escaped
+pub fn quoted(text: &str) -> toml::Value {
+    toml::Value::from(text)
+}
+
+/// The setting `key` given by `layer`, keeping the values of headers out of it
+fn setting(layer: Layer, key: &str, value: &toml::Value) -> Setting {
+    let value = match (key, value) {
+        ("headers", toml::Value::Table(headers)) => {
+            let names: Vec<_> = headers.keys().map(String::as_str).collect();
+            format!("{} (values hidden)", names.join(", "))
+        }
+        _ => value.to_string(),
+    };
+    Setting { layer, value }
+}
+
+/// How narrowly `pattern` names versions, so that the tables of [`Config::env`] naming fewer
+/// versions can take precedence
+fn specificity(pattern: &VersionPattern) -> u8 {
+    match pattern {
+        VersionPattern::Glob(_) => 0,
+        VersionPattern::Request(VersionRequest::Exact(_)) => 2,
+        VersionPattern::Request(_) => 1,
+    }
+}
+
+/// Read the `env` tables, keyed by the versions they apply to
+fn version_env<'de, D>(deserializer: D) -> Result<EnvTables, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    BTreeMap::<String, BTreeMap<String, String>>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, vars)| {
+            let pattern = key
+                .parse()
+                .map_err(|e| serde::de::Error::custom(format!("{}, not '{}'", e, key)))?;
+            Ok((pattern, vars))
+        })
+        .collect()
+}
+
+/// Whether an environment variable used as a switch is turned on
+fn is_truthy(value: &str) -> bool {
+    !matches!(value, "" | "0" | "false")
+}
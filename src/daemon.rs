@@ -0,0 +1,498 @@
+//! A long-running process answering questions about goup over a Unix domain socket, for editors
+//! and other tools that would otherwise run goup for every one of them
+//!
+//! Clients send newline-delimited JSON-RPC 2.0 requests, and get one response per line for each
+//! request with an `id`. These are the methods:
+//!
+//! | `method`    | `params`  | `result`                                                   |
+//! | ----------- | --------- | ---------------------------------------------------------- |
+//! | `version`   |           | `goup`, the version of goup, and `protocol`                |
+//! | `current`   |           | `version`, the enabled version or null                     |
+//! | `installed` |           | an array of objects with `version` and `enabled`           |
+//! | `resolve`   | `dir`     | `version` or null, `decided_by`, `consulted`, and `reason` |
+//! | `install`   | `version` | `version`, `installed`, and `omitted_bytes`                |
+//! | `shutdown`  |           | null, after which the daemon stops                         |
+//!
+//! `resolve` decides as [`Goup::resolve`] does, for `dir`, which must be absolute. Each entry of
+//! `consulted` has `source` and `found`, the reason being given when no installed version
+//! satisfies what was asked for. `install` takes `latest`, a series, or a release, and sends
+//! `progress` notifications while it works, whose params are the `id` of the request and an
+//! `event`, named and with fields as for `--progress json`. Errors from goup have the code
+//! [`GOUP_ERROR`], with the message saying what went wrong.
+//!
+//! Installs take the same locks as goup's commands, so the daemon and the command line can be
+//! used side by side.
+
+use crate::error::GoupError;
+use crate::version::{GoVersion, VersionRequest};
+use crate::{Goup, Progress};
+use serde_json::{json, Value};
+use std::error::Error;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The version of the protocol, raised whenever a method or its result changes incompatibly
+pub const PROTOCOL: u32 = 1;
+
+/// The error code of requests that goup itself failed, rather than that were malformed
+pub const GOUP_ERROR: i64 = -32000;
+
+/// How often the daemon checks whether it should stop, while waiting for connections and
+/// requests
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// The longest request that is accepted
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+/// How many entries are unpacked between `extract_progress` notifications
+const ENTRIES_PER_EVENT: u64 = 1000;
+
+/// Why [`Daemon::run`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stopped {
+    /// The stop flag was set, as when the user presses Ctrl-C
+    Interrupted,
+    /// No client was connected for the idle timeout
+    Idle,
+    /// A client asked it to stop
+    Shutdown,
+}
+
+/// A request that the daemon answered
+#[derive(Debug, Clone)]
+pub struct Handled {
+    pub method: String,
+    /// What went wrong, if it failed
+    pub error: Option<String>,
+    pub elapsed: Duration,
+}
+
+/// A JSON-RPC error
+struct RpcError {
+    code: i64,
+    message: String,
+}
+
+impl RpcError {
+    fn invalid_params(message: impl Into<String>) -> Self {
+        Self {
+            code: -32602,
+            message: message.into(),
+        }
+    }
+}
+
+impl From<GoupError> for RpcError {
+    fn from(e: GoupError) -> Self {
+        // The whole chain, since clients have no other way to learn the cause
+        let mut message = e.to_string();
+        let mut source = e.source();
+        while let Some(cause) = source {
+            message.push_str(": ");
+            message.push_str(&cause.to_string());
+            source = cause.source();
+        }
+        Self {
+            code: GOUP_ERROR,
+            message,
+        }
+    }
+}
+
+/// The client following the install in progress, as reported by [`Relay`]
+static SUBSCRIBER: Mutex<Option<Subscriber>> = Mutex::new(None);
+
+/// Listens on a Unix domain socket and answers requests with a [`Goup`] handle
+pub struct Daemon<'a> {
+    goup: &'a Goup,
+    listener: UnixListener,
+    path: PathBuf,
+    /// Held by the install in progress, so that the relay sends its progress to one client
+    installing: Mutex<()>,
+}
+
+impl<'a> Daemon<'a> {
+    /// Listen on the socket at `path`, usually [`Goup::daemon_socket`], replacing a socket left
+    /// behind by a daemon that is no longer running. Fails if another daemon is listening there.
+    /// Clients are only sent the progress of installs if `goup` reports it to a [`Relay`].
+    pub fn bind(goup: &'a Goup, path: &Path) -> Result<Daemon<'a>, GoupError> {
+        let error = |source| GoupError::Daemon {
+            path: path.to_path_buf(),
+            source,
+        };
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        if path.symlink_metadata().is_ok() {
+            if UnixStream::connect(path).is_ok() {
+                return Err(GoupError::DaemonRunning(path.to_path_buf()));
+            }
+            fs::remove_file(path).map_err(GoupError::io("remove", path))?;
+        }
+        let listener = UnixListener::bind(path).map_err(error)?;
+        // Anyone able to connect could install versions, so only the owner may
+        fs::set_permissions(path, fs::Permissions::from_mode(0o600)).map_err(error)?;
+        listener.set_nonblocking(true).map_err(error)?;
+        Ok(Daemon {
+            goup,
+            listener,
+            path: path.to_path_buf(),
+            installing: Mutex::new(()),
+        })
+    }
+
+    /// The socket the daemon is listening on
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Answer requests until `stop` is set, a client asks the daemon to stop, or no client has
+    /// been connected for `idle_timeout`, calling `log` for each request answered. Requests in
+    /// progress are finished before returning.
+    pub fn run(
+        &self,
+        idle_timeout: Duration,
+        stop: &AtomicBool,
+        log: &(dyn Fn(&Handled) + Sync),
+    ) -> Result<Stopped, GoupError> {
+        let connected = AtomicUsize::new(0);
+        let last_seen = Mutex::new(Instant::now());
+        let ending = AtomicBool::new(false);
+        thread::scope(|s| loop {
+            if stop.load(Ordering::Relaxed) {
+                ending.store(true, Ordering::Relaxed);
+                return Ok(Stopped::Interrupted);
+            }
+            if ending.load(Ordering::Relaxed) {
+                return Ok(Stopped::Shutdown);
+            }
+            if connected.load(Ordering::Relaxed) == 0
+                && last_seen.lock().unwrap().elapsed() >= idle_timeout
+            {
+                return Ok(Stopped::Idle);
+            }
+            match self.listener.accept() {
+                Ok((stream, _)) => {
+                    connected.fetch_add(1, Ordering::Relaxed);
+                    let (connected, last_seen, ending) = (&connected, &last_seen, &ending);
+                    s.spawn(move || {
+                        // A client that goes away only ends its own connection
+                        let _ = self.converse(stream, ending, log);
+                        *last_seen.lock().unwrap() = Instant::now();
+                        connected.fetch_sub(1, Ordering::Relaxed);
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(source) => {
+                    ending.store(true, Ordering::Relaxed);
+                    return Err(GoupError::Daemon {
+                        path: self.path.clone(),
+                        source,
+                    });
+                }
+            }
+        })
+    }
+
+    /// Answer the requests sent over `stream` until the client closes it or the daemon stops
+    fn converse(
+        &self,
+        stream: UnixStream,
+        ending: &AtomicBool,
+        log: &(dyn Fn(&Handled) + Sync),
+    ) -> io::Result<()> {
+        stream.set_nonblocking(false)?;
+        stream.set_read_timeout(Some(POLL_INTERVAL))?;
+        let mut reader = BufReader::new(&stream);
+        let mut line = Vec::new();
+        loop {
+            if ending.load(Ordering::Relaxed) {
+                return Ok(());
+            }
+            // Bytes read before a timeout stay in `line`, so a request can arrive in pieces
+            let closed = match reader.read_until(b'\n', &mut line) {
+                Ok(0) => true,
+                Ok(_) if line.ends_with(b"\n") => false,
+                Ok(_) => continue,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    if line.len() > MAX_REQUEST_BYTES {
+                        return Ok(());
+                    }
+                    continue;
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            if !line.trim_ascii().is_empty() {
+                if let Some(response) = self.answer(&line, &stream, ending, log) {
+                    writeln!(&stream, "{}", response)?;
+                }
+            }
+            if closed {
+                return Ok(());
+            }
+            line.clear();
+        }
+    }
+
+    /// The response to the request in `line`, or `None` for a notification
+    fn answer(
+        &self,
+        line: &[u8],
+        stream: &UnixStream,
+        ending: &AtomicBool,
+        log: &(dyn Fn(&Handled) + Sync),
+    ) -> Option<Value> {
+        let request: Value = match serde_json::from_slice(line) {
+            Ok(request) => request,
+            Err(e) => return Some(error_response(Value::Null, -32700, &e.to_string())),
+        };
+        let id = request.get("id").cloned();
+        let Some(method) = request["method"].as_str() else {
+            let id = id.unwrap_or(Value::Null);
+            return Some(error_response(id, -32600, "the request has no method"));
+        };
+        let params = &request["params"];
+
+        let start = Instant::now();
+        let result = match method {
+            "version" => Ok(json!({ "goup": env!("CARGO_PKG_VERSION"), "protocol": PROTOCOL })),
+            "current" => self.current(),
+            "installed" => self.installed(),
+            "resolve" => self.resolve(params),
+            "install" => {
+                let id = id.clone().unwrap_or(Value::Null);
+                self.install(params, id, stream)
+            }
+            "shutdown" => {
+                ending.store(true, Ordering::Relaxed);
+                Ok(Value::Null)
+            }
+            _ => Err(RpcError {
+                code: -32601,
+                message: format!("there is no method called '{}'", method),
+            }),
+        };
+        log(&Handled {
+            method: method.to_string(),
+            error: result.as_ref().err().map(|e| e.message.clone()),
+            elapsed: start.elapsed(),
+        });
+        let id = id?;
+        Some(match result {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err(e) => error_response(id, e.code, &e.message),
+        })
+    }
+
+    fn current(&self) -> Result<Value, RpcError> {
+        let enabled = self.goup.state()?.enabled;
+        Ok(json!({ "version": enabled.map(|v| v.to_string()) }))
+    }
+
+    fn installed(&self) -> Result<Value, RpcError> {
+        let state = self.goup.state()?;
+        let installed: Vec<_> = state
+            .installed
+            .iter()
+            .map(|&v| json!({ "version": v.to_string(), "enabled": state.enabled == Some(v) }))
+            .collect();
+        Ok(Value::Array(installed))
+    }
+
+    fn resolve(&self, params: &Value) -> Result<Value, RpcError> {
+        let dir = params["dir"]
+            .as_str()
+            .map(Path::new)
+            .filter(|dir| dir.is_absolute())
+            .ok_or_else(|| RpcError::invalid_params("dir must be an absolute path"))?;
+        let resolution = self.goup.resolve(dir)?;
+        let consulted: Vec<_> = resolution
+            .consulted
+            .iter()
+            .map(|(source, found)| json!({ "source": source.to_string(), "found": found.to_string() }))
+            .collect();
+        let decided_by = resolution.decision().map(|(source, _)| source.to_string());
+        let reason = match resolution.version {
+            Some(_) => None,
+            None => Some(resolution.error().to_string()),
+        };
+        Ok(json!({
+            "version": resolution.version.map(|v| v.to_string()),
+            "decided_by": decided_by,
+            "consulted": consulted,
+            "reason": reason,
+        }))
+    }
+
+    fn install(&self, params: &Value, id: Value, stream: &UnixStream) -> Result<Value, RpcError> {
+        let request: VersionRequest = params["version"]
+            .as_str()
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| {
+                RpcError::invalid_params("version must be latest, a series, or a release")
+            })?;
+        let available = self.goup.available()?;
+        let version = match (request.resolve(available.keys().copied()), request) {
+            (Some(version), _) => version,
+            (None, VersionRequest::Exact(version)) => {
+                return Err(GoupError::VersionNotAvailable(version).into())
+            }
+            (None, _) => {
+                return Err(RpcError {
+                    code: GOUP_ERROR,
+                    message: format!("No release of {} is available for download", request),
+                })
+            }
+        };
+        if self.goup.state()?.installed.contains(&version) {
+            return Ok(
+                json!({ "version": version.to_string(), "installed": false, "omitted_bytes": 0 }),
+            );
+        }
+        self.check_policy(version)?;
+
+        let _installing = self.installing.lock().unwrap();
+        let out = stream
+            .try_clone()
+            .map_err(GoupError::io("clone", &self.path))?;
+        Relay::subscribe(id, out);
+        let omitted = self
+            .goup
+            .transaction(|tx| tx.download(version, &available[&version]));
+        Relay::unsubscribe();
+        Ok(json!({ "version": version.to_string(), "installed": true, "omitted_bytes": omitted? }))
+    }
+
+    /// Refuse versions that the config file blocks or that are newer than it allows, as
+    /// `goup install` does without `--override-policy`
+    fn check_policy(&self, version: GoVersion) -> Result<(), GoupError> {
+        let config = self.goup.config();
+        if let Some(blocked) = config.blocked(version) {
+            return Err(GoupError::VersionBlocked {
+                version,
+                reason: blocked.reason().map(str::to_string),
+            });
+        }
+        match config.max_version.filter(|max| !max.admits(version)) {
+            Some(max) => Err(GoupError::BeyondMaxVersion { version, max }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Drop for Daemon<'_> {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Sends the progress of the install in progress to the client that asked for it, as `progress`
+/// notifications
+pub struct Relay;
+
+struct Subscriber {
+    /// The id of the install request
+    id: Value,
+    out: UnixStream,
+    total: u64,
+    received: u64,
+    percent: u64,
+    entries: u64,
+}
+
+impl Relay {
+    fn subscribe(id: Value, out: UnixStream) {
+        *SUBSCRIBER.lock().unwrap() = Some(Subscriber {
+            id,
+            out,
+            total: 0,
+            received: 0,
+            percent: 0,
+            entries: 0,
+        });
+    }
+
+    fn unsubscribe() {
+        *SUBSCRIBER.lock().unwrap() = None;
+    }
+
+    /// Update the subscriber with `f`, and send the event it returns, if any
+    fn send(&self, f: impl FnOnce(&mut Subscriber) -> Option<Value>) {
+        let mut subscriber = SUBSCRIBER.lock().unwrap();
+        let Some(subscriber) = subscriber.as_mut() else {
+            return;
+        };
+        let Some(mut event) = f(subscriber) else {
+            return;
+        };
+        event["id"] = subscriber.id.clone();
+        let notification = json!({ "jsonrpc": "2.0", "method": "progress", "params": event });
+        // A client that has gone away misses its progress, but the install carries on
+        let _ = writeln!(subscriber.out, "{}", notification);
+    }
+}
+
+impl Progress for Relay {
+    fn start(&self, total: u64) {
+        self.send(|s| {
+            (s.total, s.received, s.percent) = (total, 0, 0);
+            Some(json!({ "event": "download_start", "total_bytes": total }))
+        });
+    }
+
+    fn advance(&self, bytes: u64) {
+        self.send(|s| {
+            s.received += bytes;
+            // One notification for each percent is plenty, however small the reads
+            let percent = (s.received * 100).checked_div(s.total).unwrap_or(100);
+            (percent > s.percent).then(|| {
+                s.percent = percent;
+                json!({ "event": "download_progress", "bytes": s.received, "total_bytes": s.total })
+            })
+        });
+    }
+
+    fn finish(&self, complete: bool) {
+        self.send(|s| {
+            Some(json!({ "event": "download_end", "bytes": s.received, "complete": complete }))
+        });
+    }
+
+    fn extracted(&self, entries: u64) {
+        self.send(|s| {
+            s.entries += entries;
+            s.entries
+                .is_multiple_of(ENTRIES_PER_EVENT)
+                .then(|| json!({ "event": "extract_progress", "entries": s.entries }))
+        });
+    }
+
+    fn phase(&self, name: &'static str, elapsed: Duration) {
+        self.send(|s| {
+            let mut event =
+                json!({ "event": "phase", "phase": name, "seconds": elapsed.as_secs_f64() });
+            if name == "extract" {
+                event["entries"] = std::mem::take(&mut s.entries).into();
+            }
+            Some(event)
+        });
+    }
+}
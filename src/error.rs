@@ -0,0 +1,256 @@
+use crate::project::Need;
+use crate::response::Received;
+use crate::version::{GoVersion, VersionRequest};
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+
+/// The things that can go wrong while managing Go installations
+#[derive(Debug, Error)]
+pub enum GoupError {
+    #[error("Version {0} is not installed")]
+    VersionNotInstalled(GoVersion),
+    #[error("Version {0} not available for download")]
+    VersionNotAvailable(GoVersion),
+    #[error("No release of {0} is installed")]
+    SeriesNotInstalled(VersionRequest),
+    #[error("No release of {0} is available for download")]
+    SeriesNotAvailable(VersionRequest),
+    #[error("Version {version} is newer than max_version {max}, set in the config file")]
+    BeyondMaxVersion {
+        version: GoVersion,
+        max: VersionRequest,
+    },
+    #[error(
+        "Version {version} is on the blocklist in the config file{}",
+        .reason.as_ref().map(|r| format!(": {}", r)).unwrap_or_default()
+    )]
+    VersionBlocked {
+        version: GoVersion,
+        reason: Option<String>,
+    },
+    #[error(
+        "No release{} was published on or before {date}",
+        match .request {
+            VersionRequest::Latest => String::new(),
+            request => format!(" of {}", request),
+        }
+    )]
+    NoReleaseAsOf {
+        request: VersionRequest,
+        date: String,
+    },
+    #[error("No toolchain named {0} is linked")]
+    ToolchainNotLinked(String),
+    #[error(
+        "No .go-version file, go.mod with a go line, or .tool-versions with a golang line found \
+         in {} or above it",
+        .0.display()
+    )]
+    NoProjectRequirement(PathBuf),
+    #[error("Unable to read the version of Go needed from {}", .0.display())]
+    InvalidProjectFile(PathBuf),
+    #[error("{} needs {need}, which isn't installed", .file.display())]
+    RequirementNotInstalled { file: PathBuf, need: Need },
+    #[error("Unable to link a toolchain as {name:?}: {reason}")]
+    InvalidToolchainName { name: String, reason: &'static str },
+    #[error("Version {0} is pinned")]
+    VersionPinned(GoVersion),
+    #[error("Version {0} is not pinned")]
+    VersionNotPinned(GoVersion),
+    #[error("{} was not created by goup; delete it manually if it isn't needed", .0.display())]
+    NotCreatedByGoup(PathBuf),
+    #[error("{} is a directory that goup didn't create, so it won't replace it", .path.display())]
+    ForeignLink {
+        path: PathBuf,
+        /// The version of Go the directory holds, if it looks like an installation
+        version: Option<GoVersion>,
+    },
+    #[error("Version {version} is already being installed by pid {pid}")]
+    InstallInProgress { version: GoVersion, pid: u32 },
+    #[error("{name} is already being installed by pid {pid}")]
+    ToolchainInstallInProgress { name: String, pid: u32 },
+    #[error("A toolchain named {0} already exists")]
+    ToolchainExists(String),
+    #[error("Unable to put the go link at {}: {reason}", .path.display())]
+    LinkDirUnusable { path: PathBuf, reason: &'static str },
+    #[error("Timed out waiting for {}, which is held by pid {pid}", .path.display())]
+    Locked { path: PathBuf, pid: u32 },
+    #[error("`{command}` failed: {stderr}")]
+    CommandFailed { command: String, stderr: String },
+    #[error("Version {0} has no source release to build from")]
+    NoSourceRelease(GoVersion),
+    #[error(
+        "Building {version} needs Go {needs} or newer to bootstrap it, but {bootstrap} was given"
+    )]
+    BootstrapTooOld {
+        version: GoVersion,
+        bootstrap: GoVersion,
+        needs: GoVersion,
+    },
+    #[error("Building {version} from source failed. The build log is at {}", .log.display())]
+    BuildFailed { version: GoVersion, log: PathBuf },
+    #[error("Version {0} is not in the trash")]
+    NotInTrash(GoVersion),
+    #[error("Version {0} is already installed")]
+    VersionAlreadyInstalled(GoVersion),
+    #[error("Invalid bundle {}: {reason}", .path.display())]
+    InvalidBundle { path: PathBuf, reason: String },
+    #[error("{} is not a usable installation of Go: {reason}", .path.display())]
+    InvalidGoTree { path: PathBuf, reason: String },
+    #[error("The bundle is for {bundle}, but this machine is {host}")]
+    BundlePlatformMismatch { bundle: String, host: String },
+    #[error("No version of Go is enabled")]
+    NoVersionEnabled,
+    #[error("Found no available go versions")]
+    NoVersionsAvailable,
+    #[error("Failed to request {url}")]
+    Network {
+        url: String,
+        #[source]
+        source: Box<ureq::Error>,
+    },
+    #[error("Unable to {needed} in offline mode")]
+    Offline { needed: String },
+    #[error("Unable to load the platform's certificate store")]
+    NativeCerts(#[source] io::Error),
+    #[error("Invalid certificate in {path}")]
+    InvalidCertificate {
+        path: PathBuf,
+        #[source]
+        source: rustls::Error,
+    },
+    #[error(
+        "Download of {url} was incomplete: expected {expected} bytes, but received {received}"
+    )]
+    SizeMismatch {
+        url: String,
+        expected: u64,
+        received: u64,
+    },
+    #[error("Download of {url} stalled: no data received for {seconds}s")]
+    DownloadStalled { url: String, seconds: u64 },
+    #[error("Unable to parse version info from {url}, which sent {received}")]
+    InvalidIndex {
+        url: String,
+        received: Received,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Expected an archive from {url}, but it sent {received}")]
+    NotAnArchive { url: String, received: Received },
+    #[error(
+        "{} is arranged in layout {layout}, which only a newer goup understands; update goup to \
+         use it",
+        .root.display()
+    )]
+    LayoutUnsupported { root: PathBuf, layout: u32 },
+    #[error("Unable to parse version file {}", .path.display())]
+    VersionFileCorrupt {
+        path: PathBuf,
+        #[source]
+        source: serde_json::Error,
+    },
+    #[error("Unable to parse config file {}", .path.display())]
+    ConfigInvalid {
+        path: PathBuf,
+        #[source]
+        source: toml::de::Error,
+    },
+    #[error("Unable to edit config file {}: {reason}", .path.display())]
+    ConfigUneditable { path: PathBuf, reason: String },
+    #[error("Go does not publish binaries for {os}/{arch}")]
+    Unsupported {
+        os: &'static str,
+        arch: &'static str,
+    },
+    #[error("Unable to {op} {}", .path.display())]
+    Io {
+        path: PathBuf,
+        op: &'static str,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Release {version} of goup does not include {asset}")]
+    NoReleaseAsset { version: String, asset: String },
+    #[error("Checksum mismatch for {file} (expected {expected}, got {actual})")]
+    ChecksumMismatch {
+        file: String,
+        expected: String,
+        actual: String,
+    },
+    #[error(
+        "{file} isn't the official archive: the checksum database records {expected}, from \
+         {recorded}, but it has {actual}"
+    )]
+    UnofficialChecksum {
+        file: String,
+        expected: String,
+        recorded: String,
+        actual: String,
+    },
+    #[error(
+        "{} records {file} as {imported}, but the checksum database already has {recorded}",
+        .path.display()
+    )]
+    ChecksumConflict {
+        file: String,
+        recorded: String,
+        imported: String,
+        path: PathBuf,
+    },
+    #[error("Invalid checksum database {}: {reason}", .path.display())]
+    InvalidChecksums { path: PathBuf, reason: String },
+    #[error(
+        "Unable to replace {}. If goup was installed by a package manager, use it to update goup instead",
+        .path.display()
+    )]
+    ExecutableNotWritable {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Unable to serve on {addr}")]
+    Serve {
+        addr: String,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Unable to listen on {}", .path.display())]
+    Daemon {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+    #[error("goup daemon is already listening on {}", .0.display())]
+    DaemonRunning(PathBuf),
+    #[error("GOPATH variable is not set")]
+    GopathNotSet,
+    #[error("Unable to read GOPATH variable")]
+    GopathNotUnicode,
+}
+
+impl GoupError {
+    /// Wrap an error from ureq with the URL that was being requested
+    pub fn network(url: &str, e: impl Into<ureq::Error>) -> Self {
+        Self::Network {
+            url: url.to_string(),
+            source: Box::new(e.into()),
+        }
+    }
+
+    /// Whether this is a failure to reach the server at all, as happens when the network is
+    /// down, rather than the server answering with an error
+    pub fn is_unreachable(&self) -> bool {
+        match self {
+            GoupError::Network { source, .. } => crate::net::is_unreachable(source),
+            _ => false,
+        }
+    }
+
+    /// Build a closure that wraps an [`io::Error`] with the operation and path that caused it
+    pub fn io(op: &'static str, path: impl Into<PathBuf>) -> impl FnOnce(io::Error) -> Self {
+        let path = path.into();
+        move |source| Self::Io { path, op, source }
+    }
+}
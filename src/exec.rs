@@ -0,0 +1,106 @@
+//! Running commands with a particular Go installation active
+
+use crate::version::GoVersion;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Build a command that runs `program` with the Go installation at `goroot` active: `GOROOT`
+/// points at it, and its `bin` directory comes first on `PATH`. Only the child's environment
+/// is changed.
+///
+/// ```
+/// use goup::exec::toolchain_command;
+/// use std::path::Path;
+///
+/// let cmd = toolchain_command(Path::new("/opt/goup/go1.22.4/go"), "make");
+/// let goroot = cmd.get_envs().find(|(k, _)| *k == "GOROOT").and_then(|(_, v)| v);
+/// assert_eq!(goroot, Some(Path::new("/opt/goup/go1.22.4/go").as_os_str()));
+/// ```
+pub fn toolchain_command(goroot: &Path, program: impl AsRef<OsStr>) -> Command {
+    let mut cmd = Command::new(program);
+    for (key, value) in toolchain_env(goroot) {
+        cmd.env(key, value);
+    }
+    cmd
+}
+
+/// The environment variables that make the Go installation at `goroot` the active one
+pub fn toolchain_env(goroot: &Path) -> Vec<(OsString, OsString)> {
+    let bin = goroot.join("bin");
+    let rest = env::var_os("PATH").unwrap_or_default();
+    let path = env::join_paths(std::iter::once(bin).chain(env::split_paths(&rest))).unwrap_or(rest);
+
+    vec![
+        ("GOROOT".into(), goroot.as_os_str().to_owned()),
+        ("PATH".into(), path),
+    ]
+}
+
+/// The path to the named tool within the Go installation at `goroot`, if there is one
+pub fn tool_path(goroot: &Path, name: &str) -> Option<PathBuf> {
+    tool_path_in(&goroot.join("bin"), name)
+}
+
+/// A `go` executable that comes before goup's on PATH, so that running `go` finds it instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shadow {
+    pub path: PathBuf,
+    /// Its version, if there is a VERSION file beside its `bin` directory
+    pub version: Option<GoVersion>,
+}
+
+/// Find every `go` in the directories of `path` that come before `bin`. Nothing is found when
+/// `bin` isn't in `path` at all, since then no `go` is shadowing goup's.
+pub fn shadowing(bin: &Path, path: &OsStr) -> Vec<Shadow> {
+    let dirs: Vec<PathBuf> = env::split_paths(path).collect();
+    let Some(position) = dirs.iter().position(|dir| same_dir(dir, bin)) else {
+        return Vec::new();
+    };
+    dirs[..position]
+        .iter()
+        .filter_map(|dir| tool_path_in(dir, "go"))
+        .map(|go| {
+            // Often a symlink, like /usr/bin/go -> /usr/lib/go-1.21/bin/go
+            let real = fs::canonicalize(&go).unwrap_or_else(|_| go.clone());
+            let version = real
+                .parent()
+                .and_then(Path::parent)
+                .and_then(|goroot| fs::read_to_string(goroot.join("VERSION")).ok())
+                .and_then(|text| text.lines().next()?.parse().ok());
+            Shadow { path: go, version }
+        })
+        .collect()
+}
+
+/// `path` with `bin` moved to the front, so that goup's `go` is found first
+///
+/// ```
+/// use goup::exec::reordered_path;
+/// use std::path::Path;
+///
+/// let path = reordered_path(Path::new("/g/go/bin"), "/usr/local/go/bin:/g/go/bin:/usr/bin".as_ref());
+/// assert_eq!(path, "/g/go/bin:/usr/local/go/bin:/usr/bin");
+/// ```
+pub fn reordered_path(bin: &Path, path: &OsStr) -> OsString {
+    let rest = env::split_paths(path).filter(|dir| !same_dir(dir, bin));
+    let dirs: Vec<PathBuf> = std::iter::once(bin.to_path_buf()).chain(rest).collect();
+    env::join_paths(dirs).unwrap_or_else(|_| path.to_owned())
+}
+
+/// Whether `a` and `b` name the same directory, perhaps through symlinks
+fn same_dir(a: &Path, b: &Path) -> bool {
+    a == b
+        || matches!(
+            (fs::canonicalize(a), fs::canonicalize(b)),
+            (Ok(a), Ok(b)) if a == b
+        )
+}
+
+/// The path to the named executable in `dir`, if there is one
+fn tool_path_in(dir: &Path, name: &str) -> Option<PathBuf> {
+    let path = dir.join(format!("{}{}", name, env::consts::EXE_SUFFIX));
+    path.is_file().then_some(path)
+}
@@ -0,0 +1,129 @@
+//! Go's release history, as published at <https://go.dev/doc/devel/release>
+
+use crate::version::{GoVersion, VersionRequest};
+use regex::Regex;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::OnceLock;
+
+static ENTRY_REGEX: OnceLock<Regex> = OnceLock::new();
+static TAG_REGEX: OnceLock<Regex> = OnceLock::new();
+static DATE_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// Extract the description of each point release from the release history page, as plain
+/// text keyed by version. Anything that can't be understood is skipped.
+///
+/// ```
+/// use goup::history::parse_release_history;
+///
+/// let html = r#"<p id="go1.22.5">
+/// go1.22.5 (released 2024-07-02) includes security fixes to the <code>net/http</code> package.
+/// </p>"#;
+/// let history = parse_release_history(html);
+/// assert_eq!(
+///     history[&"go1.22.5".parse().unwrap()],
+///     "go1.22.5 (released 2024-07-02) includes security fixes to the net/http package."
+/// );
+/// ```
+pub fn parse_release_history(html: &str) -> BTreeMap<GoVersion, String> {
+    let entry_regex =
+        ENTRY_REGEX.get_or_init(|| Regex::new(r#"(?s)<p id="(go[0-9.]+)">(.*?)</p>"#).unwrap());
+
+    entry_regex
+        .captures_iter(html)
+        .filter_map(|c| Some((c[1].parse().ok()?, plain_text(&c[2]))))
+        .collect()
+}
+
+/// The releases whose description in the release history says they include security fixes
+///
+/// ```
+/// use goup::history::{parse_release_history, security_releases};
+///
+/// let html = r#"<p id="go1.22.4">go1.22.4 includes fixes to the compiler.</p>
+/// <p id="go1.22.5">go1.22.5 includes security fixes to the net/http package.</p>"#;
+/// let security = security_releases(&parse_release_history(html));
+/// assert_eq!(security.into_iter().collect::<Vec<_>>(), ["go1.22.5".parse().unwrap()]);
+/// ```
+pub fn security_releases(history: &BTreeMap<GoVersion, String>) -> BTreeSet<GoVersion> {
+    history
+        .iter()
+        .filter(|(_, text)| text.to_lowercase().contains("security fix"))
+        .map(|(&v, _)| v)
+        .collect()
+}
+
+/// The day each release was published, as YYYY-MM-DD, wherever the release history gives it
+///
+/// ```
+/// use goup::history::parse_release_dates;
+///
+/// let html = r#"<h2 id="go1.22">go1.22.0 (released 2024-02-06)</h2>
+/// <p id="go1.22.1">go1.22.1 (released 2024-03-05) includes fixes to the compiler.</p>"#;
+/// let dates = parse_release_dates(html);
+/// assert_eq!(dates[&"go1.22.0".parse().unwrap()], "2024-02-06");
+/// assert_eq!(dates[&"go1.22.1".parse().unwrap()], "2024-03-05");
+/// ```
+pub fn parse_release_dates(html: &str) -> BTreeMap<GoVersion, String> {
+    let date_regex = DATE_REGEX.get_or_init(|| {
+        Regex::new(r"(go[0-9]+\.[0-9]+(?:\.[0-9]+)?)\s+\(released ([0-9]{4}-[0-9]{2}-[0-9]{2})\)")
+            .unwrap()
+    });
+
+    date_regex
+        .captures_iter(html)
+        .filter_map(|c| Some((c[1].parse().ok()?, c[2].to_string())))
+        .collect()
+}
+
+/// The newest release that `request` could choose which had been published by the end of
+/// `date`, given as YYYY-MM-DD like the release dates themselves
+///
+/// ```
+/// use goup::history::{parse_release_dates, release_as_of};
+///
+/// let html = r#"<h2 id="go1.21">go1.21.0 (released 2023-08-08)</h2>
+/// <p>go1.21.1 (released 2023-09-06) includes security fixes.</p>
+/// <h2 id="go1.22">go1.22.0 (released 2024-02-06)</h2>"#;
+/// let dates = parse_release_dates(html);
+/// let series = "go1.21".parse().unwrap();
+/// assert_eq!(release_as_of(&dates, series, "2024-03-15"), Some("go1.21.1".parse().unwrap()));
+/// assert_eq!(release_as_of(&dates, series, "2023-09-06"), Some("go1.21.1".parse().unwrap()));
+/// assert_eq!(release_as_of(&dates, series, "2023-01-01"), None);
+/// ```
+pub fn release_as_of(
+    dates: &BTreeMap<GoVersion, String>,
+    request: VersionRequest,
+    date: &str,
+) -> Option<GoVersion> {
+    request.resolve(
+        dates
+            .iter()
+            .filter(|(_, released)| released.as_str() <= date)
+            .map(|(&version, _)| version),
+    )
+}
+
+/// The URL of the release notes for the minor version that `version` belongs to
+///
+/// ```
+/// use goup::history::release_notes_url;
+///
+/// let url = release_notes_url("https://go.dev/doc", "go1.23.1".parse().unwrap());
+/// assert_eq!(url, "https://go.dev/doc/go1.23");
+/// ```
+pub fn release_notes_url(docs_url: &str, version: GoVersion) -> String {
+    format!("{}/go{}.{}", docs_url, version.major, version.minor)
+}
+
+/// Strip the markup out of a fragment of HTML, collapsing whitespace
+fn plain_text(html: &str) -> String {
+    let tag_regex = TAG_REGEX.get_or_init(|| Regex::new(r"<[^>]*>").unwrap());
+    let text = tag_regex
+        .replace_all(html, "")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&");
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,127 @@
+//! How the files within goup's root are arranged, and moving a root from one arrangement to
+//! the next
+
+use crate::error::GoupError;
+use crate::state::VersionFile;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The number of the layout this version of goup creates, recorded in the version file
+pub const CURRENT: u32 = 2;
+
+/// What the version file left in the root of a migrated layout says in place of the list of
+/// installed versions. goup binaries from before the migration fail to read it, instead of
+/// taking the root for an empty one.
+const MOVED_NOTICE: &str = "moved to state/versions.json by a newer goup; update goup to read it";
+
+/// How the files within a root are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Layout 1: the installed versions, the version file, the locks, and everything else
+    /// directly in the root
+    Flat,
+    /// Layout 2: installed versions in `versions`, goup's records and locks in `state`,
+    /// files being unpacked in `tmp`, and downloads in `cache`
+    Nested,
+    /// A layout introduced by a newer goup than this one, which this one mustn't touch
+    Unknown(u32),
+}
+
+impl Layout {
+    /// Work out the layout of `root` from the version file. A root without one, such as one
+    /// that doesn't exist yet, gets the current layout.
+    pub fn detect(root: &Path) -> Layout {
+        let nested = root.join("state").join("versions.json");
+        if nested.is_file() {
+            // A file that can't be read is reported once the state is loaded
+            return match VersionFile::load(&nested).ok().and_then(|s| s.layout) {
+                Some(number) if number > CURRENT => Layout::Unknown(number),
+                _ => Layout::Nested,
+            };
+        }
+        // Only the notice left by a migration says which layout it is in the root
+        let Ok(text) = fs::read_to_string(root.join("versions.json")) else {
+            return Layout::Nested;
+        };
+        let number = serde_json::from_str::<serde_json::Value>(&text)
+            .ok()
+            .and_then(|file| file["layout"].as_u64());
+        match number.map(|n| u32::try_from(n).unwrap_or(u32::MAX)) {
+            Some(number) if number > CURRENT => Layout::Unknown(number),
+            Some(number) if number > 1 => Layout::Nested,
+            _ => Layout::Flat,
+        }
+    }
+
+    /// The number of the layout, as recorded in the version file
+    pub fn number(self) -> u32 {
+        match self {
+            Layout::Flat => 1,
+            Layout::Nested => 2,
+            Layout::Unknown(number) => number,
+        }
+    }
+
+    /// The directory within `root` that versions are installed into
+    pub(crate) fn versions_dir(self, root: &Path) -> PathBuf {
+        match self {
+            Layout::Flat => root.to_path_buf(),
+            Layout::Nested | Layout::Unknown(_) => root.join("versions"),
+        }
+    }
+
+    /// The directory within `root` holding the version file, the snapshot of tools, and locks
+    pub(crate) fn state_dir(self, root: &Path) -> PathBuf {
+        match self {
+            Layout::Flat => root.to_path_buf(),
+            Layout::Nested | Layout::Unknown(_) => root.join("state"),
+        }
+    }
+
+    /// The directory within `root` that installations are prepared in before being moved
+    /// into place
+    pub(crate) fn tmp_dir(self, root: &Path) -> PathBuf {
+        match self {
+            Layout::Flat => root.join("staging"),
+            Layout::Nested | Layout::Unknown(_) => root.join("tmp"),
+        }
+    }
+
+    /// The directory within `root` that toolchains installed from a URL are kept in
+    pub(crate) fn custom_dir(self, root: &Path) -> PathBuf {
+        self.versions_dir(root).join("custom")
+    }
+}
+
+/// What [`Goup::migrate`](crate::Goup::migrate) did to bring a root up to the current layout
+#[derive(Debug, Default)]
+pub struct Migration {
+    /// Each file and directory that was moved, from where to where
+    pub moved: Vec<(PathBuf, PathBuf)>,
+    /// The symlinks to the enabled version that were pointed at where it now is
+    pub repointed: Vec<PathBuf>,
+}
+
+impl Migration {
+    /// Move `from` to `to`, if there is anything at `from` and nothing yet at `to`, so that a
+    /// migration that was interrupted can be run again
+    pub(crate) fn rename(&mut self, from: PathBuf, to: PathBuf) -> Result<(), GoupError> {
+        if from.symlink_metadata().is_err() || to.symlink_metadata().is_ok() {
+            return Ok(());
+        }
+        if let Some(dir) = to.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        fs::rename(&from, &to).map_err(GoupError::io("move", &from))?;
+        self.moved.push((from, to));
+        Ok(())
+    }
+}
+
+/// Leave a version file at `path` that goup binaries which only know the flat layout refuse to
+/// read, rather than taking the root for an empty one and installing into it
+pub(crate) fn leave_notice(path: &Path) -> Result<(), GoupError> {
+    let notice = serde_json::json!({ "layout": CURRENT, "installed": MOVED_NOTICE });
+    let text = serde_json::to_string_pretty(&notice).expect("a notice is always serializable");
+    fs::write(path, text).map_err(GoupError::io("write", path))
+}
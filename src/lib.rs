@@ -0,0 +1,1465 @@
+//! Version management for Go a la rustup
+//!
+//! This crate contains the logic behind the `goup` command line tool, so that it can be driven
+//! directly from other Rust programs. Everything revolves around the [`Goup`] handle, which
+//! manages the Go installations within a single root directory.
+//!
+//! ```no_run
+//! use goup::Goup;
+//!
+//! let goup = Goup::from_env()?;
+//! if let Some(&latest) = goup.available()?.keys().last() {
+//!     goup.install(latest)?;
+//!     goup.enable(latest)?;
+//! }
+//! # Ok::<(), goup::GoupError>(())
+//! ```
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::env::{self, var, VarError};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+pub mod build;
+pub mod bundle;
+pub mod cache;
+pub mod checksums;
+mod chunked;
+pub mod config;
+#[cfg(unix)]
+pub mod daemon;
+pub mod error;
+pub mod exec;
+pub mod history;
+pub mod layout;
+mod lock;
+pub mod managers;
+pub mod mirror;
+mod net;
+pub mod progress;
+pub mod project;
+pub mod provenance;
+pub mod remote;
+mod resolution_cache;
+pub mod response;
+pub mod retry;
+pub mod self_update;
+pub mod series;
+pub mod serve;
+pub mod size;
+pub mod state;
+pub mod suggest;
+pub mod tools;
+pub mod transaction;
+pub mod transport;
+pub mod trash;
+pub mod usage;
+pub mod version;
+
+pub use cache::{CacheCleanReport, CacheEntry, CacheMode};
+pub use config::Config;
+pub use error::GoupError;
+pub use progress::{NoProgress, Progress};
+pub use provenance::Provenance;
+pub use remote::{FileInfo, Release};
+pub use retry::RetryPolicy;
+pub use state::{CustomSource, LinkedToolchain, VersionFile};
+pub use transaction::Transaction;
+pub use version::GoVersion;
+
+use config::ProjectConfig;
+use layout::{Layout, Migration};
+use lock::Lock;
+use mirror::{MirrorSelection, Probe, Ranking};
+use resolution_cache::ResolutionCache;
+use retry::RetryClass;
+use tools::Tool;
+use trash::{Disposal, Trashed};
+use version::VersionRequest;
+
+/// How long a mirror has to respond to a probe before it is considered unreachable
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// How long to wait for another process to finish updating the version file. It is only ever
+/// locked briefly, so a long wait means something is wrong.
+const STATE_LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Which installed versions [`Goup::clean`] should remove.
+///
+/// Pinned versions and the enabled version are never removed.
+#[derive(Debug, Default, Clone)]
+pub struct CleanPolicy {
+    /// Also remove versions that are still available for download from go.dev
+    pub include_available: bool,
+    /// How many versions to delete at once, by default [`DEFAULT_CLEAN_JOBS`]
+    pub jobs: Option<usize>,
+    /// Only remove versions installed at least this long ago. Versions installed by older
+    /// versions of goup, which didn't record when, are kept.
+    pub older_than: Option<Duration>,
+    /// Only remove versions that haven't been enabled or run for at least this long. Versions
+    /// that goup has no record of using are kept.
+    pub unused_for: Option<Duration>,
+    /// Keep this many of the newest installed versions, not counting pinned ones, and remove
+    /// the rest even if they are still available
+    pub retain: Option<usize>,
+    /// Also remove betas and release candidates whose release is installed or available,
+    /// though they are still available themselves. Implied by [`Config::clean_prereleases`].
+    pub prereleases: bool,
+    /// Versions to keep as well, which count towards [`CleanPolicy::retain`]
+    pub keep: BTreeSet<GoVersion>,
+    /// Plan as if this version were enabled, to show what enabling it would let be removed
+    pub assume_enabled: Option<GoVersion>,
+    /// Report the versions that would be removed as removed, without deleting anything
+    pub dry_run: bool,
+    /// Remove versions even while running processes use them, rather than keeping them and
+    /// listing them in [`CleanReport::in_use`]
+    pub ignore_usage: bool,
+}
+
+/// How many versions [`Goup::clean`] deletes at once, by default
+pub const DEFAULT_CLEAN_JOBS: usize = 4;
+
+impl CleanPolicy {
+    /// How many versions to delete at once, which is always at least one
+    pub fn jobs(&self) -> usize {
+        self.jobs.unwrap_or(DEFAULT_CLEAN_JOBS).max(1)
+    }
+}
+
+/// A version removed by [`Goup::clean`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Removed {
+    pub version: GoVersion,
+    /// The bytes of disk space freed by removing it, or that it takes up in the trash
+    pub size: u64,
+    /// Whether it was deleted or moved to the trash
+    pub disposal: Disposal,
+}
+
+/// How the `go` symlink at [`Goup::link_path`] points at the enabled version
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LinkStatus {
+    /// Whether it points by a path relative to the goup directory
+    pub relative: bool,
+    /// Whether what it points at exists
+    pub resolves: bool,
+}
+
+/// The outcome of [`Goup::clean`]
+#[derive(Debug, Default)]
+pub struct CleanReport {
+    /// The versions that were removed, oldest first
+    pub removed: Vec<Removed>,
+    /// The versions that should have been removed, but couldn't be, and why
+    pub failed: Vec<(GoVersion, GoupError)>,
+    /// Directories named like versions that were left alone, since goup didn't create them
+    pub skipped: Vec<GoVersion>,
+    /// Versions that were kept only because they are pinned, oldest first
+    pub pinned: Vec<GoVersion>,
+    /// Versions kept because [`CleanPolicy::older_than`] was given, but goup didn't record
+    /// when they were installed
+    pub unknown_age: Vec<GoVersion>,
+    /// Versions kept because [`CleanPolicy::unused_for`] was given, but goup has no record of
+    /// them being used
+    pub never_used: Vec<GoVersion>,
+    /// Versions kept because running processes are using them, along with those processes
+    pub in_use: Vec<(GoVersion, Vec<usage::Process>)>,
+    /// Pre-releases that [`CleanPolicy::prereleases`] let be removed, since their release is
+    /// out. Those that are pinned or otherwise kept stay all the same.
+    pub superseded: Vec<GoVersion>,
+    /// Pre-releases kept despite [`CleanPolicy::prereleases`], since their release isn't out
+    /// yet
+    pub unreleased: Vec<GoVersion>,
+}
+
+/// The versions of Go available for download, as returned by [`Goup::index`]
+#[derive(Debug, Clone)]
+pub struct Index {
+    /// Each available version, along with the archive that would be installed for it
+    pub versions: BTreeMap<GoVersion, FileInfo>,
+    /// When go.dev last confirmed this list was current
+    pub fetched: SystemTime,
+    /// Whether go.dev couldn't be reached, so a cached list was used instead
+    pub offline: bool,
+    /// Releases and archives in the list that goup couldn't understand, and left out
+    pub skipped: Vec<String>,
+}
+
+/// A handle to a goup installation root
+///
+/// The root directory holds the `go` symlink pointing at the enabled version, an optional
+/// `config.toml`, and directories for the installed versions of Go, goup's records, and its
+/// cache. Where each goes depends on the root's [`Layout`].
+pub struct Goup {
+    root: PathBuf,
+    layout: Layout,
+    config: Config,
+    agent: ureq::Agent,
+    progress: Box<dyn Progress>,
+    cache_mode: CacheMode,
+    all_releases: bool,
+}
+
+impl Goup {
+    /// Create a handle managing the given root directory
+    ///
+    /// ```
+    /// use goup::Goup;
+    ///
+    /// let goup = Goup::new("/opt/goup");
+    /// assert_eq!(goup.link_path(), std::path::Path::new("/opt/goup/go"));
+    /// ```
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        Self {
+            layout: Layout::detect(&root),
+            root,
+            agent: net::default_agent(),
+            config: Config::default(),
+            progress: Box::new(NoProgress),
+            cache_mode: CacheMode::Normal,
+            all_releases: false,
+        }
+    }
+
+    /// Create a handle managing `$GOPATH/goup`, which is what the goup binary uses. Where
+    /// GOPATH lists several directories, the first is used, as `go install` does.
+    ///
+    /// The config file within the root is loaded, and overridden by any `GOUP_*` environment
+    /// variables.
+    pub fn from_env() -> Result<Self, GoupError> {
+        let goup = Self::new(gopath()?.join("goup"));
+        let mut config = Config::load(&goup.config_file())?;
+        config.apply_env();
+        goup.with_config(config)
+    }
+
+    /// Layer the settings of `project` over those of the config file, beneath any `GOUP_*`
+    /// environment variables, as for a command run within the project. Unless `trusted`, only
+    /// the settings in [`config::PROJECT_KEYS`] are taken from it.
+    pub fn with_project(self, project: &ProjectConfig, trusted: bool) -> Result<Self, GoupError> {
+        let mut config = Config::layered(&self.config_file(), Some(project), trusted)?;
+        config.apply_env();
+        self.with_config(config)
+    }
+
+    /// Whether the user trusted every setting in `project`, as it is now
+    pub fn project_trusted(&self, project: &ProjectConfig) -> Result<bool, GoupError> {
+        let trusted = self.state()?.trusted_projects.remove(&project.path);
+        Ok(trusted.is_some_and(|digest| digest == project.digest()))
+    }
+
+    /// Use the given settings instead of the defaults. Fails if the certificates named by the
+    /// TLS settings can't be loaded.
+    pub fn with_config(mut self, config: Config) -> Result<Self, GoupError> {
+        self.agent = net::agent(&config)?;
+        self.config = config;
+        Ok(self)
+    }
+
+    /// Report the progress of downloads to the given [`Progress`]
+    pub fn with_progress(mut self, progress: impl Progress + 'static) -> Self {
+        self.progress = Box::new(progress);
+        self
+    }
+
+    /// Control how cached responses from go.dev are used
+    pub fn with_cache_mode(mut self, mode: CacheMode) -> Self {
+        self.cache_mode = mode;
+        self
+    }
+
+    /// Fetch every release that go.dev has published, not just the current stable ones, as
+    /// needed to install a beta or release candidate by name. This is implied by
+    /// [`Config::pre_releases`], and by having a pre-release installed already.
+    pub fn with_all_releases(mut self, all: bool) -> Self {
+        self.all_releases = all;
+        self
+    }
+
+    /// The URL of the JSON index on the given mirror, listing every release if pre-releases
+    /// are wanted
+    pub(crate) fn index_url(&self, mirror: &str) -> String {
+        let all = self.all_releases
+            || self.config.pre_releases()
+            || self
+                .state()
+                .is_ok_and(|s| s.installed.iter().any(|v| v.is_prerelease()));
+        mirror::index_url(mirror, all)
+    }
+
+    /// The directory that goup uses to install Go versions and manage its internal config
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// How the files within the root are arranged
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The directory that the provided Go version is installed into
+    pub fn install_dir(&self, version: GoVersion) -> PathBuf {
+        self.versions_dir().join(format!("{}", version))
+    }
+
+    /// The directory holding an installation directory for each version
+    pub fn versions_dir(&self) -> PathBuf {
+        self.layout.versions_dir(&self.root)
+    }
+
+    /// The directory that removed versions are moved into with
+    /// [`Config::use_trash`](crate::Config::use_trash)
+    pub fn trash_dir(&self) -> PathBuf {
+        self.root.join("trash")
+    }
+
+    /// The directory that installations are unpacked into before they are moved into place
+    pub(crate) fn tmp_dir(&self) -> PathBuf {
+        self.layout.tmp_dir(&self.root)
+    }
+
+    /// The GOROOT of the given version, within its install directory
+    pub fn goroot(&self, version: GoVersion) -> PathBuf {
+        self.install_dir(version).join("go")
+    }
+
+    /// The directory that the toolchain called `name` is installed into by
+    /// [`Goup::install_custom`], apart from the releases
+    pub fn custom_dir(&self, name: &str) -> PathBuf {
+        self.layout.custom_dir(&self.root).join(name)
+    }
+
+    /// The symlink pointing at the enabled version's GOROOT, or with
+    /// [`EnableMode::Copy`](config::EnableMode::Copy), a copy of it. This is `go` in the root,
+    /// unless [`Config::link_path`] puts it elsewhere.
+    pub fn link_path(&self) -> PathBuf {
+        self.link_paths().swap_remove(0)
+    }
+
+    /// Every link to the enabled version: [`Goup::link_path`], followed by the other symlinks
+    /// to the same place that [`Config::link_path`] lists
+    pub fn link_paths(&self) -> Vec<PathBuf> {
+        match self.config.link_paths() {
+            paths if paths.is_empty() => vec![self.root.join("go")],
+            paths => paths,
+        }
+    }
+
+    /// Whether the links to the enabled version were made somewhere other than where
+    /// [`Config::link_path`] now says, so that [`Transaction::relink`] would move them
+    pub fn links_moved(&self) -> Result<bool, GoupError> {
+        let state = self.state()?;
+        if state.enabled.is_none() && state.enabled_linked.is_none() {
+            return Ok(false);
+        }
+        let recorded = if state.links.is_empty() {
+            vec![self.root.join("go")]
+        } else {
+            state.links
+        };
+        Ok(recorded != self.link_paths())
+    }
+
+    /// Put the links to the enabled version where [`Config::link_path`] now says, removing
+    /// those made elsewhere before. See [`Transaction::relink`].
+    pub fn relink(&self) -> Result<bool, GoupError> {
+        if !self.links_moved()? {
+            return Ok(false);
+        }
+        self.transaction(|tx| tx.relink())
+    }
+
+    /// Check that each of [`Goup::link_paths`] can be made: its directory has to exist already,
+    /// and be writable
+    pub fn check_link_dirs(&self) -> Result<(), GoupError> {
+        for link in self.link_paths() {
+            let unusable = |reason: &'static str| GoupError::LinkDirUnusable {
+                path: link.clone(),
+                reason,
+            };
+            let dir = link
+                .parent()
+                .ok_or_else(|| unusable("it has no parent directory"))?;
+            // The root is created along with the first install
+            if dir == self.root {
+                continue;
+            }
+            if !dir.is_dir() {
+                return Err(unusable("its directory doesn't exist"));
+            }
+            if !is_writable(dir) {
+                return Err(unusable("its directory isn't writable"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Every `go` that comes before the enabled version's on PATH, and so runs instead of it
+    pub fn shadowing(&self) -> Vec<exec::Shadow> {
+        let path = env::var_os("PATH").unwrap_or_default();
+        exec::shadowing(&self.link_path().join("bin"), &path)
+    }
+
+    /// The value of the GOROOT environment variable, if it points somewhere other than
+    /// [`Goup::link_path`] or the enabled installation. The go command trusts GOROOT over its
+    /// own location, so it would then run with the standard library of another installation.
+    pub fn conflicting_goroot(&self) -> Option<PathBuf> {
+        let goroot = PathBuf::from(env::var_os("GOROOT").filter(|v| !v.is_empty())?);
+        let state = self.state().ok()?;
+        let enabled = match (state.enabled, &state.enabled_linked) {
+            (_, Some(name)) => state.linked.get(name).map(|t| t.path.clone()),
+            (Some(version), None) => Some(self.goroot(version)),
+            (None, None) => None,
+        };
+        // With nothing enabled, there is nothing for GOROOT to get in the way of
+        let expected: Vec<PathBuf> = self
+            .link_paths()
+            .into_iter()
+            .chain(enabled)
+            .filter_map(|p| p.canonicalize().ok())
+            .collect();
+        if expected.is_empty() {
+            return None;
+        }
+        match goroot.canonicalize() {
+            Ok(actual) if expected.contains(&actual) => None,
+            _ => Some(goroot),
+        }
+    }
+
+    /// How [`Goup::link_path`] points at the enabled version, or `None` if it isn't a symlink
+    pub fn link_status(&self) -> Option<LinkStatus> {
+        let link = self.link_path();
+        let target = fs::read_link(&link).ok()?;
+        Some(LinkStatus {
+            relative: target.is_relative(),
+            resolves: link.exists(),
+        })
+    }
+
+    /// The version that [`Goup::link_path`] is a copy of, if it is a copy made by goup
+    pub fn copied_version(&self) -> Option<GoVersion> {
+        let marker = self.link_path().join(transaction::COPY_MARKER);
+        fs::read_to_string(marker).ok()?.trim().parse().ok()
+    }
+
+    /// The location of the file describing the versions installed and enabled
+    pub fn version_file(&self) -> PathBuf {
+        self.layout.state_dir(&self.root).join("versions.json")
+    }
+
+    /// The lock file guarding the given resource against concurrent goup processes
+    fn lock_file(&self, name: &str) -> PathBuf {
+        self.layout
+            .state_dir(&self.root)
+            .join("locks")
+            .join(format!("{}.lock", name))
+    }
+
+    /// The location of the snapshot of developer tools to rebuild after switching versions
+    pub fn tools_file(&self) -> PathBuf {
+        self.layout.state_dir(&self.root).join("tools.json")
+    }
+
+    /// The socket that [`daemon::Daemon`] listens on
+    pub fn daemon_socket(&self) -> PathBuf {
+        self.layout.state_dir(&self.root).join("daemon.sock")
+    }
+
+    /// The location of the official checksums imported with [`Goup::import_checksums`]
+    pub fn checksums_file(&self) -> PathBuf {
+        self.layout.state_dir(&self.root).join("checksums.json")
+    }
+
+    /// The directory holding cached responses from go.dev and downloaded archives
+    pub fn cache_dir(&self) -> PathBuf {
+        self.root.join("cache")
+    }
+
+    /// The log of the latest attempt to build the given version from source, which is kept
+    /// after a failed build
+    pub fn build_log(&self, version: GoVersion) -> PathBuf {
+        self.cache_dir()
+            .join("builds")
+            .join(format!("{}.log", version))
+    }
+
+    /// The directory within [`Goup::cache_dir`] holding archives kept from earlier downloads
+    pub fn archive_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join(cache::ARCHIVES)
+    }
+
+    /// The location of the user's settings
+    pub fn config_file(&self) -> PathBuf {
+        self.root.join("config.toml")
+    }
+
+    /// The settings in use by this handle
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The HTTP agent to make requests with, configured with the user's network settings.
+    /// Every request goes through here, so that none are made in offline mode; `needed`
+    /// describes what the request is for, to explain the error in that case.
+    pub fn client(&self, needed: &str) -> Result<&ureq::Agent, GoupError> {
+        if self.config.offline() {
+            Err(GoupError::Offline {
+                needed: needed.to_string(),
+            })
+        } else {
+            Ok(&self.agent)
+        }
+    }
+
+    /// Move a root in the flat layout into the current one: each installed version into
+    /// [`Goup::versions_dir`], toolchains installed from URLs along with them, and goup's
+    /// records into `state`, pointing the symlinks to the enabled version at where it now is.
+    /// A version file is left in the root that older goup binaries refuse to read, so that
+    /// they can't mistake the root for an empty one.
+    ///
+    /// Returns `None` if the root was already in the current layout. A migration that was
+    /// interrupted carries on from where it stopped when run again.
+    pub fn migrate(&mut self) -> Result<Option<Migration>, GoupError> {
+        if self.layout != Layout::Flat {
+            return Ok(None);
+        }
+        let flat_file = self.version_file();
+        let flat_tools = self.tools_file();
+        let flat_custom = self.layout.custom_dir(&self.root);
+        let flat_tmp = self.tmp_dir();
+        let lock = Lock::acquire(&self.lock_file("versions"), STATE_LOCK_TIMEOUT)?;
+        let mut state = self.state()?;
+        let owned: BTreeSet<_> = self
+            .version_folders()?
+            .into_iter()
+            .filter(|&v| state.installed.contains(&v) || self.created_by_goup(v))
+            .collect();
+
+        let mut migration = Migration::default();
+        let nested = Layout::Nested;
+        for version in owned {
+            let to = nested.versions_dir(&self.root).join(version.to_string());
+            migration.rename(self.install_dir(version), to)?;
+        }
+        let custom = nested.custom_dir(&self.root);
+        migration.rename(flat_custom.clone(), custom.clone())?;
+        for toolchain in state.linked.values_mut() {
+            if let Ok(within) = toolchain.path.strip_prefix(&flat_custom) {
+                toolchain.path = custom.join(within);
+            }
+        }
+        let state_dir = nested.state_dir(&self.root);
+        migration.rename(flat_tools, state_dir.join("tools.json"))?;
+        // Anything left being unpacked belonged to a process that has since gone
+        let _ = fs::remove_dir_all(flat_tmp);
+
+        // The version file moves last, so that until it does the root is still flat
+        state.layout = Some(layout::CURRENT);
+        let nested_file = state_dir.join("versions.json");
+        state.store(&nested_file)?;
+        layout::leave_notice(&flat_file)?;
+        migration.moved.push((flat_file, nested_file));
+        self.layout = nested;
+        drop(lock);
+        let _ = fs::remove_dir(self.root.join("locks"));
+
+        migration.repointed = self.transaction(|tx| tx.repoint_links())?;
+        Ok(Some(migration))
+    }
+
+    /// Load the current record of installed, enabled, and pinned versions
+    pub fn state(&self) -> Result<VersionFile, GoupError> {
+        if let Layout::Unknown(layout) = self.layout {
+            return Err(GoupError::LayoutUnsupported {
+                root: self.root.clone(),
+                layout,
+            });
+        }
+        VersionFile::load(&self.version_file())
+    }
+
+    /// The versions of Go available for download for this platform, along with the archive
+    /// that would be installed for each.
+    ///
+    /// The index is cached in [`Goup::cache_dir`], and only downloaded again if the server
+    /// reports that it has changed. If go.dev can't be reached, the cached index is used.
+    pub fn available(&self) -> Result<BTreeMap<GoVersion, FileInfo>, GoupError> {
+        Ok(self.index(None)?.versions)
+    }
+
+    /// The versions of Go available for download, like [`Goup::available`], along with where
+    /// that information came from. A cached index younger than `max_age` is used without
+    /// contacting go.dev at all.
+    ///
+    /// ```no_run
+    /// use goup::Goup;
+    ///
+    /// let goup = Goup::from_env()?;
+    /// let index = goup.index(Some(goup.config().cache_ttl()))?;
+    /// if index.offline {
+    ///     eprintln!("go.dev is unreachable, so this list may be out of date");
+    /// }
+    /// # Ok::<(), goup::GoupError>(())
+    /// ```
+    pub fn index(&self, max_age: Option<Duration>) -> Result<Index, GoupError> {
+        self.fetch_index(max_age, |url, cached| {
+            let parsed = remote::parse_index(url, &cached.body)?;
+            Ok(Index {
+                versions: parsed.versions,
+                skipped: parsed.skipped,
+                fetched: cached.fetched,
+                offline: cached.offline,
+            })
+        })
+    }
+
+    /// Every release in the index, with the files published for each platform, fetched and
+    /// cached as for [`Goup::index`]. Files that can't be understood are left out.
+    ///
+    /// ```no_run
+    /// use goup::Goup;
+    ///
+    /// let goup = Goup::from_env()?;
+    /// for release in goup.releases(None)? {
+    ///     let platforms = release.files.iter().filter(|f| f.is_archive()).count();
+    ///     println!("{} ships archives for {} platforms", release.version, platforms);
+    /// }
+    /// # Ok::<(), goup::GoupError>(())
+    /// ```
+    pub fn releases(&self, max_age: Option<Duration>) -> Result<Vec<Release>, GoupError> {
+        self.fetch_index(max_age, |url, cached| {
+            Ok(remote::parse_releases(url, &cached.body)?.releases)
+        })
+    }
+
+    /// Fetch the JSON index from the first mirror that gives one `parse` accepts, going
+    /// through the cache as [`Goup::index`] describes
+    pub(crate) fn fetch_index<T>(
+        &self,
+        max_age: Option<Duration>,
+        parse: impl Fn(&str, cache::Cached) -> Result<T, GoupError>,
+    ) -> Result<T, GoupError> {
+        let cache = cache::HttpCache::new(self.cache_dir().join("index.json"), self.cache_mode);
+        self.timed("index", || {
+            self.with_mirrors(|mirror| {
+                let url = self.index_url(mirror);
+                let get = || {
+                    let client = self.client("fetch the list of available versions");
+                    cache.get(client, &url, max_age)
+                };
+                let fetch = || {
+                    if self.config.retry.index {
+                        self.config.retry.run(get)
+                    } else {
+                        get()
+                    }
+                };
+                let check = |cached: cache::Cached| {
+                    // A web page is never an index, whatever it would make of it
+                    let checked = response::check_json(
+                        &url,
+                        cached.status,
+                        cached.content_type.as_deref(),
+                        &cached.body,
+                    );
+                    checked
+                        .and_then(|()| parse(&url, cached))
+                        .inspect_err(|_| cache.forget(&url))
+                };
+                let cached = fetch()?;
+                // A corrupt cached body would only be confirmed again by the server, so once
+                // it is forgotten, ask for the index afresh
+                let refetch = cached.reused && !cached.offline;
+                match check(cached) {
+                    Err(_) if refetch => check(fetch()?),
+                    res => res,
+                }
+            })
+        })
+    }
+
+    /// Run `f`, reporting how long it took to the handle's [`Progress`] as the given phase
+    pub(crate) fn timed<T>(&self, phase: &'static str, f: impl FnOnce() -> T) -> T {
+        let start = Instant::now();
+        let res = f();
+        self.progress.phase(phase, start.elapsed());
+        res
+    }
+
+    /// The configured mirrors, in the order they should be tried. That is fastest first if
+    /// they have been ranked by probing them; with automatic mirror selection, they are probed
+    /// again whenever the ranking is out of date.
+    pub fn mirrors(&self) -> Vec<String> {
+        let configured = self.config.mirrors();
+        if configured.len() < 2 {
+            return configured;
+        }
+
+        let path = self.mirror_ranking_file();
+        let ranking = Ranking::load(&path).filter(|r| {
+            let mut ranked = r.mirrors.clone();
+            let mut expected = configured.clone();
+            ranked.sort();
+            expected.sort();
+            ranked == expected
+        });
+        let auto = self.config.mirror_selection == MirrorSelection::Auto;
+        match ranking {
+            Some(r) if !auto || r.age() <= self.config.mirror_ranking_ttl() => return r.mirrors,
+            _ if !auto => return configured,
+            _ => {}
+        }
+
+        match self.probe_mirrors() {
+            Ok(probes) => {
+                let ranking = Ranking::new(mirror::rank(&probes));
+                // Not being able to save the ranking only means probing again next time
+                let _ = ranking.store(&path);
+                ranking.mirrors
+            }
+            Err(_) => configured,
+        }
+    }
+
+    /// Measure how quickly each configured mirror responds, all at once
+    pub fn probe_mirrors(&self) -> Result<Vec<Probe>, GoupError> {
+        let agent = self.client("probe the mirrors")?;
+        Ok(mirror::probe_all(
+            agent,
+            &self.config.mirrors(),
+            PROBE_TIMEOUT,
+        ))
+    }
+
+    /// The location of the saved ranking of the mirrors, fastest first
+    pub fn mirror_ranking_file(&self) -> PathBuf {
+        self.cache_dir().join("mirrors.json")
+    }
+
+    /// Run `f` against each mirror in turn, until one of them succeeds or fails for a reason
+    /// other than being unreachable
+    fn with_mirrors<T>(
+        &self,
+        mut f: impl FnMut(&str) -> Result<T, GoupError>,
+    ) -> Result<T, GoupError> {
+        let mirrors = self.mirrors();
+        for (i, mirror) in mirrors.iter().enumerate() {
+            match f(mirror) {
+                Err(e) if RetryClass::of(&e).is_some() && i + 1 < mirrors.len() => {
+                    // The fastest mirror is failing, so it's time to measure them again
+                    if i == 0 && self.config.mirror_selection == MirrorSelection::Auto {
+                        let _ = fs::remove_file(self.mirror_ranking_file());
+                    }
+                }
+                res => return res,
+            }
+        }
+        unreachable!("there is always at least one mirror")
+    }
+
+    /// The description of each point release in Go's release history.
+    ///
+    /// Like the index, the release history is cached in [`Goup::cache_dir`].
+    pub fn release_history(&self) -> Result<BTreeMap<GoVersion, String>, GoupError> {
+        self.cached_release_history(None)
+    }
+
+    /// The releases that include security fixes, according to the release history. This is
+    /// only ever informational, so if the release history can't be fetched or understood, no
+    /// releases are reported rather than failing.
+    pub fn security_releases(&self) -> BTreeSet<GoVersion> {
+        self.cached_release_history(Some(self.config.cache_ttl()))
+            .map(|history| history::security_releases(&history))
+            .unwrap_or_default()
+    }
+
+    /// The day each release was published, as YYYY-MM-DD, according to the release history
+    pub fn release_dates(&self) -> Result<BTreeMap<GoVersion, String>, GoupError> {
+        let page = self.release_history_page(Some(self.config.cache_ttl()))?;
+        Ok(history::parse_release_dates(&page))
+    }
+
+    fn cached_release_history(
+        &self,
+        max_age: Option<Duration>,
+    ) -> Result<BTreeMap<GoVersion, String>, GoupError> {
+        let page = self.release_history_page(max_age)?;
+        Ok(history::parse_release_history(&page))
+    }
+
+    fn release_history_page(&self, max_age: Option<Duration>) -> Result<String, GoupError> {
+        let cache = cache::HttpCache::new(self.cache_dir().join("history.json"), self.cache_mode);
+        let url = self.config.release_history_url();
+        let client = self.client("fetch the release history");
+        Ok(cache.get(client, &url, max_age)?.body)
+    }
+
+    /// The versions of Go that are currently installed
+    pub fn installed(&self) -> Result<BTreeSet<GoVersion>, GoupError> {
+        Ok(self.state()?.installed)
+    }
+
+    /// The installed version to use in `dir`, and how it was decided, as described at
+    /// [`project::resolve`], under goup's own environment
+    ///
+    /// The resolution is remembered for next time, and reused for as long as none of the
+    /// files it was decided from change, unless the cache is turned off with
+    /// [`CacheMode::Off`] or by setting `GOUP_SHIM_DISABLE_CACHE`.
+    pub fn resolve(&self, dir: &Path) -> Result<project::Resolution, GoupError> {
+        let started = SystemTime::now();
+        let mut context = project::Context::from_env(None);
+        let cache = (self.cache_mode != CacheMode::Off
+            && !matches!(self.layout, Layout::Unknown(_))
+            && ResolutionCache::enabled())
+        .then(|| ResolutionCache::new(self.layout.state_dir(&self.root).join("resolved")));
+        if self.cache_mode == CacheMode::Normal {
+            if let Some(resolution) = cache.as_ref().and_then(|c| c.get(dir, &context)) {
+                return Ok(resolution);
+            }
+        }
+        let state = self.state()?;
+        context.enabled = state.enabled;
+        let installed: Vec<_> = state.installed.into_iter().collect();
+        let resolution = project::resolve(dir, &context, &installed)?;
+        if let Some(cache) = cache {
+            cache.put(dir, &context, &self.version_file(), started, &resolution);
+        }
+        Ok(resolution)
+    }
+
+    /// Where the given version's installation came from, if goup recorded it
+    pub fn provenance(&self, version: GoVersion) -> Option<Provenance> {
+        Provenance::load(&self.install_dir(version))
+    }
+
+    /// The running processes executing a program from the given version, or with one of its
+    /// files open, or `None` if that can't be found out on this platform
+    pub fn processes_using(&self, version: GoVersion) -> Option<Vec<usage::Process>> {
+        Some(usage::Snapshot::take()?.using(&self.install_dir(version)))
+    }
+
+    /// The disk space taken up by an installed version, in bytes
+    pub fn size(&self, version: GoVersion) -> Result<u64, GoupError> {
+        let dir = self.install_dir(version);
+        size::dir_size(&dir).map_err(GoupError::io("measure", dir))
+    }
+
+    /// Every file in [`Goup::cache_dir`], least recently used first
+    pub fn cache_entries(&self) -> Result<Vec<CacheEntry>, GoupError> {
+        cache::entries(&self.cache_dir())
+    }
+
+    /// Delete the cached files chosen by `filter`. Archives that another goup process is
+    /// installing from are left alone, and reported as in use.
+    pub fn clean_cache(
+        &self,
+        filter: impl Fn(&CacheEntry) -> bool,
+    ) -> Result<CacheCleanReport, GoupError> {
+        let mut report = CacheCleanReport::default();
+        for entry in self.cache_entries()?.into_iter().filter(|e| filter(e)) {
+            if self.remove_cached(&entry)? {
+                report.removed.push(entry)
+            } else {
+                report.in_use.push(entry)
+            }
+        }
+        Ok(report)
+    }
+
+    /// Delete the least recently used archives until the cache fits within
+    /// [`Config::cache_max_size`], returning those that were deleted
+    pub(crate) fn evict_archives(&self) -> Result<Vec<CacheEntry>, GoupError> {
+        let Some(max) = self.config.cache_max_size else {
+            return Ok(Vec::new());
+        };
+        let entries = self.cache_entries()?;
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+        let mut evicted = Vec::new();
+        for entry in entries.into_iter().filter(|e| e.version.is_some()) {
+            if total <= max {
+                break;
+            }
+            if self.remove_cached(&entry)? {
+                total -= entry.size;
+                evicted.push(entry);
+            }
+        }
+        Ok(evicted)
+    }
+
+    /// Delete a cached file, unless it is an archive of a version that another process holds
+    /// the lock for, and so may be reading. Returns whether the file was deleted.
+    fn remove_cached(&self, entry: &CacheEntry) -> Result<bool, GoupError> {
+        // Holding the lock keeps an install from starting to read the archive mid-deletion
+        let _lock = match entry.version {
+            Some(version) => match Lock::try_acquire(&self.lock_file(&version.to_string()))? {
+                Ok(lock) => Some(lock),
+                Err(_) => return Ok(false),
+            },
+            None => None,
+        };
+        match fs::remove_file(&entry.path) {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(true),
+            Err(e) => Err(GoupError::io("remove", &entry.path)(e)),
+        }
+    }
+
+    /// Download and install the given version of Go, if it is not already installed
+    ///
+    /// ```no_run
+    /// use goup::{Goup, GoupError};
+    ///
+    /// let goup = Goup::from_env()?;
+    /// match goup.install("go1.22.4".parse().unwrap()) {
+    ///     Err(GoupError::VersionNotAvailable(v)) => eprintln!("{} does not exist", v),
+    ///     other => other?,
+    /// }
+    /// # Ok::<(), GoupError>(())
+    /// ```
+    pub fn install(&self, version: GoVersion) -> Result<(), GoupError> {
+        let available = self.available()?;
+        let file = available
+            .get(&version)
+            .ok_or(GoupError::VersionNotAvailable(version))?;
+        self.download(version, file)
+    }
+
+    /// Install the given version of Go from an archive already looked up with
+    /// [`Goup::available`], saving a second request to go.dev.
+    ///
+    /// A download that fails, or stops receiving data for [`Config::stall_timeout`], is
+    /// retried from the start according to [`Config::retry`], and then from the next mirror.
+    pub fn download(&self, version: GoVersion, file: &FileInfo) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.download(version, file))?;
+        Ok(())
+    }
+
+    /// The source release of the given version, for [`Goup::build`]
+    pub fn source_release(&self, version: GoVersion) -> Result<FileInfo, GoupError> {
+        let release = self
+            .releases(Some(self.config.cache_ttl()))?
+            .into_iter()
+            .find(|r| r.version == version)
+            .ok_or(GoupError::VersionNotAvailable(version))?;
+        release
+            .files
+            .into_iter()
+            .find(FileInfo::is_source)
+            .ok_or(GoupError::NoSourceRelease(version))
+    }
+
+    /// The newest installed version that can bootstrap a build of `version` from source, as
+    /// given by [`build::bootstrap_version`]
+    pub fn bootstrap_for(&self, version: GoVersion) -> Result<Option<GoVersion>, GoupError> {
+        let needs = build::bootstrap_version(version);
+        Ok(self
+            .installed()?
+            .into_iter()
+            .rfind(|&v| v >= needs && v != version))
+    }
+
+    /// Install the given version of Go by building it from source with the installed version
+    /// `bootstrap`, for platforms that go.dev publishes no archive for. This takes several
+    /// minutes; everything the build prints is passed to [`Progress::output`] and kept in
+    /// [`Goup::build_log`].
+    pub fn build(&self, version: GoVersion, bootstrap: GoVersion) -> Result<(), GoupError> {
+        let file = self.source_release(version)?;
+        self.transaction(|tx| tx.build(version, &file, bootstrap))
+    }
+
+    /// Build a command that runs `program` with the given version active, without changing
+    /// the enabled version. Tools shipped with Go, like `go` and `gofmt`, are run from the
+    /// version's installation.
+    ///
+    /// ```no_run
+    /// use goup::Goup;
+    ///
+    /// let goup = Goup::from_env()?;
+    /// let status = goup
+    ///     .command("go1.21.13".parse().unwrap(), "go")?
+    ///     .args(["test", "./..."])
+    ///     .status();
+    /// # Ok::<(), goup::GoupError>(())
+    /// ```
+    pub fn command(
+        &self,
+        version: GoVersion,
+        program: &str,
+    ) -> Result<std::process::Command, GoupError> {
+        if !self.installed()?.contains(&version) {
+            return Err(GoupError::VersionNotInstalled(version));
+        }
+
+        let mut cmd = self.toolchain_command(&self.goroot(version), program);
+        cmd.envs(self.injected_env(version));
+        Ok(cmd)
+    }
+
+    /// The variables from the `env` tables of the config file that commands run with `version`
+    /// are given, leaving out any already set in goup's own environment
+    pub fn injected_env(&self, version: GoVersion) -> Vec<(String, String)> {
+        self.config
+            .env_for(version)
+            .into_iter()
+            .filter(|(name, _)| env::var_os(name).is_none())
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    /// A command running `program` with the Go installation at `goroot` active, finding it
+    /// among the installation's tools first
+    fn toolchain_command(&self, goroot: &Path, program: &str) -> std::process::Command {
+        let program = exec::tool_path(goroot, program)
+            .map(PathBuf::into_os_string)
+            .unwrap_or_else(|| program.into());
+        let mut cmd = exec::toolchain_command(goroot, program);
+        // A GOTOOLCHAIN chosen by the user is left alone
+        if self.config.gotoolchain_local() && env::var_os("GOTOOLCHAIN").is_none() {
+            cmd.env("GOTOOLCHAIN", "local");
+        }
+        cmd
+    }
+
+    /// Package an installed version, along with its provenance, into a bundle at `out` that
+    /// [`Goup::import`] can install on another machine of the same platform
+    pub fn export(&self, version: GoVersion, out: &Path) -> Result<(), GoupError> {
+        if !self.installed()?.contains(&version) {
+            return Err(GoupError::VersionNotInstalled(version));
+        }
+        bundle::write(
+            &self.goroot(version),
+            version,
+            self.provenance(version),
+            out,
+        )
+    }
+
+    /// Install the version in a bundle made by [`Goup::export`], returning which version it
+    /// was. Every file is checked against the bundle's manifest before it is installed.
+    pub fn import(&self, bundle: &Path) -> Result<GoVersion, GoupError> {
+        self.transaction(|tx| tx.import(bundle))
+    }
+
+    /// The official checksums of the archives of the releases `series` picks out, or of every
+    /// release if it is empty, for the given platforms, or every platform if that is empty.
+    /// The index is always fetched anew, so that the checksums are as go.dev publishes them.
+    pub fn export_checksums(
+        &self,
+        series: &[VersionRequest],
+        platforms: &[(String, String)],
+    ) -> Result<checksums::Database, GoupError> {
+        self.fetch_index(None, |url, cached| {
+            let releases = remote::parse_releases(url, &cached.body)?.releases;
+            Ok(checksums::Database::export(
+                url,
+                &releases,
+                |release, file| {
+                    (series.is_empty() || series.iter().any(|s| s.matches(release.version)))
+                        && (platforms.is_empty()
+                            || platforms.iter().any(|(os, arch)| {
+                                (os.as_str(), arch.as_str()) == (&file.os, &file.arch)
+                            }))
+                },
+            ))
+        })
+    }
+
+    /// The official checksums imported so far, which installs and bundle imports are checked
+    /// against
+    pub fn checksums(&self) -> Result<checksums::Database, GoupError> {
+        checksums::Database::load(&self.checksums_file())
+    }
+
+    /// Merge the databases exported by [`Goup::export_checksums`] at `paths` into the one in
+    /// [`Goup::checksums_file`], returning how many archives were new. Nothing is imported if
+    /// any of them can't be read, or records a different checksum for an archive than one
+    /// already imported.
+    pub fn import_checksums(&self, paths: &[PathBuf]) -> Result<usize, GoupError> {
+        let _lock = Lock::acquire(&self.lock_file("checksums"), STATE_LOCK_TIMEOUT)?;
+        let mut database = self.checksums()?;
+        let mut added = 0;
+        for path in paths {
+            let text = fs::read_to_string(path).map_err(GoupError::io("read", path))?;
+            added += database.merge(checksums::Database::parse(path, &text)?, path)?;
+        }
+        database.store(&self.checksums_file())?;
+        Ok(added)
+    }
+
+    /// Install a copy of the Go installation at `goroot`, made by another version manager, or
+    /// with `move_tree`, take it over. Use [`managers::find`] to find them.
+    pub fn adopt(
+        &self,
+        goroot: &Path,
+        version: GoVersion,
+        move_tree: bool,
+    ) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.adopt(goroot, version, move_tree))
+    }
+
+    /// Find the developer tools in the Go binaries directory that can be rebuilt, using the
+    /// given version to read their build info
+    pub fn find_tools(&self, version: GoVersion) -> Result<Vec<Tool>, GoupError> {
+        let dir = tools::bin_dir()?;
+        if !dir.is_dir() {
+            return Ok(Vec::new());
+        }
+        let mut cmd = self.command(version, "go")?;
+        cmd.args(["version", "-m"]).arg(&dir);
+        // Other files in the directory give errors, which don't stop the rest being described
+        let output = cmd.output().map_err(GoupError::io("run go in", &dir))?;
+        Ok(tools::parse_build_info(&String::from_utf8_lossy(
+            &output.stdout,
+        )))
+    }
+
+    /// Rebuild a developer tool with the given version of Go
+    pub fn rebuild_tool(&self, version: GoVersion, tool: &Tool) -> Result<(), GoupError> {
+        let spec = tool.install_spec();
+        let mut cmd = self.command(version, "go")?;
+        cmd.args(["install", &spec]);
+        let output = cmd
+            .output()
+            .map_err(GoupError::io("run go from", self.goroot(version)))?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(GoupError::CommandFailed {
+                command: format!("go install {}", spec),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            })
+        }
+    }
+
+    /// Point the `go` symlink at the given version, which must already be installed
+    pub fn enable(&self, version: GoVersion) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.enable(version))
+    }
+
+    /// Record that the given version is being used, as `goup run` does, so that
+    /// [`CleanPolicy::unused_for`] keeps it. This is done at most once a day for each version,
+    /// and otherwise leaves the version file alone.
+    pub fn record_use(&self, version: GoVersion) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.record_use(version))
+    }
+
+    /// Register the Go installation at `path` as a toolchain called `name`, which can then be
+    /// enabled and run like an installed version. See [`Transaction::link`].
+    pub fn link(&self, name: &str, path: &Path) -> Result<LinkedToolchain, GoupError> {
+        self.transaction(|tx| tx.link(name, path).cloned())
+    }
+
+    /// Install the archive at `url` as a toolchain called `name`, for builds of Go that aren't
+    /// in any index. See [`Transaction::install_custom`].
+    pub fn install_custom(
+        &self,
+        name: &str,
+        url: &str,
+        sha256: Option<&str>,
+    ) -> Result<LinkedToolchain, GoupError> {
+        self.transaction(|tx| tx.install_custom(name, url, sha256).cloned())
+    }
+
+    /// Point the `go` symlink at the linked toolchain called `name`
+    pub fn enable_linked(&self, name: &str) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.enable_linked(name))
+    }
+
+    /// Forget the linked toolchain called `name`, deleting it only if it was installed with
+    /// [`Goup::install_custom`]
+    pub fn unlink(&self, name: &str) -> Result<LinkedToolchain, GoupError> {
+        self.transaction(|tx| tx.unlink(name))
+    }
+
+    /// Build a command that runs `program` with the linked toolchain called `name`, as
+    /// [`Goup::command`] does for installed versions
+    pub fn linked_command(
+        &self,
+        name: &str,
+        program: &str,
+    ) -> Result<std::process::Command, GoupError> {
+        let state = self.state()?;
+        let toolchain = state
+            .linked
+            .get(name)
+            .ok_or_else(|| GoupError::ToolchainNotLinked(name.to_string()))?;
+        Ok(self.toolchain_command(&toolchain.path, program))
+    }
+
+    /// Uninstall the given version. Pinned versions cannot be removed.
+    ///
+    /// If the version was enabled, no version will be enabled afterwards.
+    pub fn remove(&self, version: GoVersion) -> Result<Disposal, GoupError> {
+        self.transaction(|tx| tx.remove(version))
+    }
+
+    /// Install the given version again from the trash, from where it was most recently put
+    pub fn restore(&self, version: GoVersion) -> Result<Trashed, GoupError> {
+        self.transaction(|tx| tx.restore(version))
+    }
+
+    /// The versions in the trash, oldest first
+    pub fn trash(&self) -> Result<Vec<Trashed>, GoupError> {
+        trash::list(&self.trash_dir())
+    }
+
+    /// Delete the versions in the trash for good, or only those put there at least
+    /// `older_than` ago, returning them
+    pub fn empty_trash(&self, older_than: Option<Duration>) -> Result<Vec<Trashed>, GoupError> {
+        let _lock = Lock::acquire(&self.lock_file("trash"), STATE_LOCK_TIMEOUT)?;
+        let mut deleted = Vec::new();
+        for trashed in self.trash()? {
+            if older_than.is_some_and(|age| trashed.age() < age) {
+                continue;
+            }
+            trash::delete(&trashed)?;
+            deleted.push(trashed);
+        }
+        Ok(deleted)
+    }
+
+    /// Delete the versions longest in the trash until it fits within
+    /// [`Config::trash_max_size`], returning those that were deleted. What was put there most
+    /// recently is always kept, however large.
+    pub(crate) fn trim_trash(&self) -> Result<Vec<Trashed>, GoupError> {
+        let Some(max) = self.config.trash_max_size else {
+            return Ok(Vec::new());
+        };
+        let _lock = Lock::acquire(&self.lock_file("trash"), STATE_LOCK_TIMEOUT)?;
+        let mut trashed = self.trash()?;
+        let mut total: u64 = trashed.iter().map(|t| t.size).sum();
+        trashed.pop();
+        let mut deleted = Vec::new();
+        for trashed in trashed {
+            if total <= max {
+                break;
+            }
+            trash::delete(&trashed)?;
+            total -= trashed.size;
+            deleted.push(trashed);
+        }
+        Ok(deleted)
+    }
+
+    /// Pin the given version to keep it from being removed
+    pub fn pin(&self, version: GoVersion, reason: Option<String>) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.pin(version, reason))
+    }
+
+    /// Pin the given version to keep it from being removed until `expires`, after which
+    /// [`Goup::clean`] may remove it again
+    pub fn pin_until(
+        &self,
+        version: GoVersion,
+        reason: Option<String>,
+        expires: SystemTime,
+    ) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.pin_until(version, reason, expires))
+    }
+
+    /// Unpin the given version, allowing it to be removed. Fails with
+    /// [`GoupError::VersionNotPinned`] if it wasn't pinned, rather than doing nothing.
+    pub fn unpin(&self, version: GoVersion) -> Result<(), GoupError> {
+        self.transaction(|tx| tx.unpin(version))
+    }
+
+    /// Remove the installed versions selected by the given policy, reporting which versions
+    /// were removed and which couldn't be.
+    ///
+    /// ```no_run
+    /// use goup::{CleanPolicy, Goup};
+    ///
+    /// let goup = Goup::from_env()?;
+    /// for removed in goup.clean(&CleanPolicy::default())?.removed {
+    ///     println!("removed {}", removed.version);
+    /// }
+    /// # Ok::<(), goup::GoupError>(())
+    /// ```
+    pub fn clean(&self, policy: &CleanPolicy) -> Result<CleanReport, GoupError> {
+        self.transaction(|tx| tx.clean(policy))
+    }
+
+    /// Make several changes to the installed versions, loading the version file once and
+    /// storing it once at the end.
+    ///
+    /// Other goup processes may change the version file meanwhile, so the changes are made to
+    /// its latest contents when storing it, while holding a lock. Each version is also locked
+    /// while it is downloaded, so that different versions can be installed concurrently.
+    ///
+    /// Changes that were made before a failure are still recorded, since they have already
+    /// happened on disk. The closure may fail with any error that a [`GoupError`] converts
+    /// into, so callers can mix in their own checks.
+    ///
+    /// ```no_run
+    /// use goup::Goup;
+    ///
+    /// let goup = Goup::from_env()?;
+    /// let available = goup.available()?;
+    /// if let Some((&latest, file)) = available.last_key_value() {
+    ///     goup.transaction(|tx| {
+    ///         tx.download(latest, file)?;
+    ///         tx.enable(latest)
+    ///     })?;
+    /// }
+    /// # Ok::<(), goup::GoupError>(())
+    /// ```
+    pub fn transaction<T, E: From<GoupError>>(
+        &self,
+        f: impl FnOnce(&mut Transaction) -> Result<T, E>,
+    ) -> Result<T, E> {
+        let original = self.state()?;
+        let mut tx = Transaction {
+            goup: self,
+            state: original.clone(),
+        };
+        let res = f(&mut tx);
+        if tx.state != original {
+            self.timed("state", || {
+                let _lock = Lock::acquire(&self.lock_file("versions"), STATE_LOCK_TIMEOUT)?;
+                let mut latest = self.state()?;
+                latest.apply(&original, &tx.state);
+                if self.layout == Layout::Nested {
+                    latest.layout = Some(layout::CURRENT);
+                }
+                latest.store(&self.version_file())
+            })?;
+        }
+        res
+    }
+
+    /// Delete the given versions with up to `jobs` threads, or move them to the trash,
+    /// advancing the progress by the size of each as it goes
+    fn delete_all(
+        &self,
+        versions: Vec<Removed>,
+        jobs: usize,
+    ) -> Vec<(Removed, Result<Disposal, GoupError>)> {
+        let queue = Mutex::new(versions);
+        let next = || queue.lock().unwrap_or_else(|e| e.into_inner()).pop();
+        thread::scope(|s| {
+            let workers: Vec<_> = (0..jobs)
+                .map(|_| {
+                    s.spawn(|| {
+                        let mut results = Vec::new();
+                        while let Some(removed) = next() {
+                            let res = self.dispose(removed.version);
+                            if res.is_ok() {
+                                self.progress.advance(removed.size);
+                            }
+                            results.push((removed, res));
+                        }
+                        results
+                    })
+                })
+                .collect();
+            workers
+                .into_iter()
+                .flat_map(|w| w.join().expect("deletion thread panicked"))
+                .collect()
+        })
+    }
+
+    /// Delete the installation of the given version, or move it to the trash with
+    /// [`Config::use_trash`](crate::Config::use_trash)
+    fn dispose(&self, version: GoVersion) -> Result<Disposal, GoupError> {
+        let dir = self.install_dir(version);
+        if !self.config.use_trash() {
+            remove_tree(&dir)?;
+            return Ok(Disposal::Deleted);
+        }
+        trash::put(&self.trash_dir(), &dir, version)
+    }
+
+    /// Whether the directory for the given version was created by goup, as shown by its
+    /// provenance, or for older installations, by holding a Go release of that version.
+    /// Nothing is ever deleted without this, so that unrelated directories that happen to be
+    /// named like a version are safe.
+    fn created_by_goup(&self, version: GoVersion) -> bool {
+        self.provenance(version).is_some()
+            || fs::read_to_string(self.goroot(version).join("VERSION")).is_ok_and(|contents| {
+                contents.lines().next().and_then(|l| l.parse().ok()) == Some(version)
+            })
+    }
+
+    /// The versions that have a directory within [`Goup::versions_dir`], whether or not they
+    /// are recorded as installed
+    fn version_folders(&self) -> Result<BTreeSet<GoVersion>, GoupError> {
+        let mut versions = BTreeSet::new();
+        let dir = self.versions_dir();
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(versions),
+            Err(e) => return Err(GoupError::io("read", &dir)(e)),
+        };
+        for entry in entries {
+            let version = entry
+                .map_err(GoupError::io("read", &dir))?
+                .file_name()
+                .to_str()
+                .and_then(|name| name.parse::<GoVersion>().ok());
+            if let Some(v) = version {
+                versions.insert(v);
+            }
+        }
+
+        Ok(versions)
+    }
+}
+
+/// Whether this process may create files in `dir`
+#[cfg(unix)]
+fn is_writable(dir: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    let Ok(path) = std::ffi::CString::new(dir.as_os_str().as_bytes()) else {
+        return false;
+    };
+    // SAFETY: the path is a valid C string that outlives the call
+    unsafe { libc::access(path.as_ptr(), libc::W_OK) == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_writable(dir: &Path) -> bool {
+    fs::metadata(dir).is_ok_and(|m| !m.permissions().readonly())
+}
+
+/// The first directory listed in GOPATH, which is where Go puts what `go install` builds. The
+/// list is split as the platform separates paths, with `:` on Unix and `;` on Windows.
+pub(crate) fn gopath() -> Result<PathBuf, GoupError> {
+    let list = match var("GOPATH") {
+        Ok(list) => list,
+        Err(VarError::NotPresent) => return Err(GoupError::GopathNotSet),
+        Err(VarError::NotUnicode(_)) => return Err(GoupError::GopathNotUnicode),
+    };
+    env::split_paths(&list)
+        .find(|dir| !dir.as_os_str().is_empty())
+        .ok_or(GoupError::GopathNotSet)
+}
+
+/// Delete the directory tree at `dir`, naming the exact path that couldn't be deleted if one
+/// fails. The files that show an installation was made by goup are deleted last, so that one
+/// which is only partly deleted is still recognized as goup's, and can be removed again later.
+pub(crate) fn remove_tree(dir: &Path) -> Result<(), GoupError> {
+    let mut markers = Vec::new();
+    for entry in fs::read_dir(dir).map_err(GoupError::io("read", dir))? {
+        let entry = entry.map_err(GoupError::io("read", dir))?;
+        let path = entry.path();
+        if entry.file_name() == "VERSION" || entry.file_name() == provenance::FILE_NAME {
+            markers.push(path);
+        } else if entry.file_type().is_ok_and(|t| t.is_dir()) {
+            remove_tree(&path)?;
+        } else {
+            fs::remove_file(&path).map_err(GoupError::io("remove", &path))?;
+        }
+    }
+    for path in markers {
+        fs::remove_file(&path).map_err(GoupError::io("remove", &path))?;
+    }
+    fs::remove_dir(dir).map_err(GoupError::io("remove", dir))
+}
@@ -0,0 +1,118 @@
+//! Lock files that keep concurrent goup processes from interfering with each other
+
+use crate::error::GoupError;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use std::{fs, io, process, thread};
+
+/// How often a held lock is checked again while waiting for it
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Distinguishes the files of attempts made at once by threads of the same process
+static ATTEMPT: AtomicU64 = AtomicU64::new(0);
+
+/// A lock file naming the process that holds it. The file is removed when the lock is dropped.
+///
+/// A lock left behind by a process that has since exited is stale, and is taken over.
+#[derive(Debug)]
+pub struct Lock {
+    path: PathBuf,
+}
+
+impl Lock {
+    /// Take the lock at `path`, or report the ID of the live process holding it
+    pub fn try_acquire(path: &Path) -> Result<Result<Lock, u32>, GoupError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+
+        // Linking a complete file into place means that the lock never exists without the
+        // holder's ID in it
+        let staging = path.with_extension(format!("{}.tmp", unique_suffix()));
+        fs::write(&staging, process::id().to_string()).map_err(GoupError::io("write", &staging))?;
+        let res = loop {
+            match fs::hard_link(&staging, path) {
+                Ok(()) => break Ok(Ok(Lock { path: path.into() })),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => break Err(GoupError::io("create", path)(e)),
+            }
+            match holder(path) {
+                Some(pid) if is_alive(pid) => break Ok(Err(pid)),
+                // The holder exited without cleaning up, or released the lock just now
+                _ => take_over_stale(path),
+            }
+        };
+        let _ = fs::remove_file(&staging);
+        res
+    }
+
+    /// Take the lock at `path`, waiting up to `timeout` for another process to release it
+    pub fn acquire(path: &Path, timeout: Duration) -> Result<Lock, GoupError> {
+        let start = Instant::now();
+        loop {
+            match Lock::try_acquire(path)? {
+                Ok(lock) => return Ok(lock),
+                Err(pid) if start.elapsed() >= timeout => {
+                    return Err(GoupError::Locked {
+                        path: path.into(),
+                        pid,
+                    })
+                }
+                Err(_) => thread::sleep(POLL_INTERVAL),
+            }
+        }
+    }
+}
+
+impl Drop for Lock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Clear away a stale lock. Another process may have done the same and taken the lock since
+/// it was found stale, so the lock is first moved aside under a name of our own, where it can
+/// be checked again without racing anyone; a live holder's lock is put back.
+fn take_over_stale(path: &Path) {
+    let aside = path.with_extension(format!("{}.stale", unique_suffix()));
+    if fs::rename(path, &aside).is_err() {
+        // Already gone, so there is nothing to take over
+        return;
+    }
+    if let Some(pid) = holder(&aside) {
+        if is_alive(pid) {
+            let _ = fs::hard_link(&aside, path);
+        }
+    }
+    let _ = fs::remove_file(&aside);
+}
+
+/// A suffix for temporary file names that no other attempt to take a lock will use
+fn unique_suffix() -> String {
+    format!(
+        "{}-{}",
+        process::id(),
+        ATTEMPT.fetch_add(1, Ordering::Relaxed)
+    )
+}
+
+/// The ID of the process named in a lock file, if it can be read
+fn holder(path: &Path) -> Option<u32> {
+    fs::read_to_string(path).ok()?.trim().parse().ok()
+}
+
+/// Whether the process with the given ID is still running
+#[cfg(unix)]
+fn is_alive(pid: u32) -> bool {
+    // Sending signal 0 checks that the process exists without disturbing it. A process owned
+    // by another user still exists, even though we aren't permitted to signal it.
+    let res = unsafe { libc::kill(pid as libc::pid_t, 0) };
+    res == 0 || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Whether the process with the given ID is still running. Without a way to tell, assume it is.
+#[cfg(not(unix))]
+fn is_alive(_pid: u32) -> bool {
+    true
+}
@@ -1,11 +1,57 @@
-use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
-use console::style;
-use std::collections::BTreeSet;
-use std::fs;
-use version::{GoVersion, VersionFile};
+use anyhow::Result;
+use clap::error::ErrorKind;
+use clap::{ArgGroup, CommandFactory, Parser, Subcommand};
+use cli::bootstrap;
+use cli::bundle;
+use cli::cache;
+use cli::crash;
+use cli::enable;
+use cli::errors;
+use cli::events;
+use cli::exit;
+use cli::format::Template;
+use cli::info;
+use cli::install;
+use cli::list;
+use cli::modulefiles;
+use cli::parse::{
+    parse_age, parse_as_of, parse_date, parse_list_format, parse_platform, parse_release,
+    parse_series,
+};
+use cli::pin;
+use cli::prompt;
+use cli::remove;
+use cli::report;
+use cli::resolve;
+use cli::run;
+use cli::server;
+use cli::setup;
+use cli::startup;
+use cli::tools;
+use cli::ui;
+use cli::update;
+use goup::serve;
+use goup::version::{VersionGlob, VersionPattern, VersionRequest};
+use goup::{CleanPolicy, GoVersion, LinkedToolchain};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{Duration, SystemTime};
 
-mod version;
+/// Print a line to stderr, or with `--progress json`, send it as an event
+macro_rules! diagnostic {
+    ($($arg:tt)*) => {
+        $crate::cli::events::diagnostic(&format!($($arg)*))
+    };
+}
+
+/// Say what a command has done, on stderr, so that stdout only carries what it was asked for
+macro_rules! status {
+    ($($arg:tt)*) => {
+        $crate::cli::events::status(&format!($($arg)*))
+    };
+}
+
+mod cli;
 
 /// Go version manager and multiplexer
 ///
@@ -14,207 +60,1237 @@ mod version;
 #[derive(Parser, Debug)]
 #[command(author, version, about)]
 struct Args {
+    /// Answer yes to any question goup would ask
+    #[arg(short, long, global = true)]
+    yes: bool,
+    /// Fail rather than ask any questions. Implied when stdin is not a terminal
+    #[arg(long, global = true)]
+    non_interactive: bool,
+    /// Ignore cached responses and fetch everything from go.dev again
+    #[arg(long, global = true)]
+    refresh: bool,
+    /// Attempt failed downloads this many times in total, overriding the config file
+    #[arg(long, global = true, value_name = "ATTEMPTS")]
+    retries: Option<u32>,
+    /// Print details of the settings in effect
+    #[arg(short, long, global = true)]
+    verbose: bool,
+    /// Trust the certificate authorities in this PEM file, as well as the usual ones
+    #[arg(long, global = true, value_name = "PATH")]
+    cacert: Option<PathBuf>,
+    /// Log every HTTP request and response to stderr, with credentials redacted
+    #[arg(long, global = true)]
+    trace_http: bool,
+    /// Never access the network, using only cached and local data
+    #[arg(long, global = true)]
+    offline: bool,
+    /// Neither use nor update the cache of responses from go.dev, or that of the version
+    /// resolved in each directory
+    #[arg(long, global = true, conflicts_with = "refresh")]
+    no_cache: bool,
+    /// Abandon a download after this many seconds without receiving any data
+    #[arg(long, global = true, value_name = "SECONDS")]
+    stall_timeout: Option<u64>,
+    /// Download each archive over this many connections at once, each fetching part of it
+    #[arg(long, global = true, value_name = "N", value_parser = clap::value_parser!(u16).range(1..=16))]
+    chunks: Option<u16>,
+    /// Hide progress bars, the steps of updates and installs, and messages saying what was done
+    #[arg(short, long, global = true)]
+    quiet: bool,
+    /// Report how long each phase of the command took, such as downloading and extracting
+    #[arg(long, global = true)]
+    timings: bool,
+    /// How to show progress: as bars, or as a JSON object per line on stderr, for programs
+    /// that run goup
+    #[arg(long, global = true, value_name = "FORMAT", default_value = "bar")]
+    progress: ProgressFormat,
+    /// Leave a goup directory from an older goup in its old layout, rather than moving the
+    /// installed versions into `versions` and goup's records into `state`
+    #[arg(long, global = true)]
+    no_migrate: bool,
+    /// If goup crashes, only say so, without writing a report to the temporary directory
+    #[arg(long, global = true)]
+    no_crash_report: bool,
     #[command(subcommand)]
     command: Commands,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum ProgressFormat {
+    Bar,
+    Json,
+}
+
+/// A version of Go, or the name of a toolchain registered with `goup link`
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Toolchain {
+    Version(GoVersion),
+    /// A version without its patch number, like `go1.22`, for the newest release of the series
+    Series(VersionRequest),
+    Linked(String),
+}
+
+impl std::str::FromStr for Toolchain {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        // Names are tried first, so that a labeled name like go1.22.6-acme isn't taken for the
+        // version it starts with
+        if LinkedToolchain::check_name(s).is_ok() {
+            return Ok(Toolchain::Linked(s.to_string()));
+        }
+        match s.parse() {
+            Ok(VersionRequest::Exact(version)) => Ok(Toolchain::Version(version)),
+            Ok(series @ VersionRequest::Series { .. }) => Ok(Toolchain::Series(series)),
+            _ => Err(
+                "expected a version like go1.22.4, a series like go1.22, or the name of a linked \
+                 toolchain"
+                    .to_string(),
+            ),
+        }
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// List the set of available Go versions, as well as those that are installed.
-    List,
+    List {
+        /// Only list the versions that are installed, without contacting go.dev
+        #[arg(long)]
+        installed: bool,
+        /// Show how much disk space each installed version takes up
+        #[arg(long)]
+        size: bool,
+        /// Group the versions by series, showing the newest and installed releases of each
+        #[arg(long)]
+        tree: bool,
+        /// Show every release in each series of the tree
+        #[arg(long, requires = "tree")]
+        expand: bool,
+        /// Describe the status of each version in words, such as `installed (unavailable
+        /// upstream)`, rather than only by its color
+        #[arg(short, long)]
+        long: bool,
+        /// Print each version on a line of its own, like '{version}\t{enabled}', without headers
+        /// or colors. The placeholders are {version}, {status} (as shown by --long),
+        /// {installed}, {enabled}, {pinned}, {available}, {size} (in bytes), {date} (of
+        /// installation), {last_used} (when it was last enabled or run, to within a day), and
+        /// {semver} (the version without the `go` prefix, like 1.22.6)
+        #[arg(long, value_name = "TEMPLATE", conflicts_with = "tree", value_parser = parse_list_format)]
+        format: Option<Template>,
+        /// Fail if go.dev can't be reached and no list of versions is cached, instead of
+        /// listing the installed versions alone
+        #[arg(long, conflicts_with = "installed")]
+        fail_on_network_error: bool,
+        /// Print the versions as JSON, along with the default version as `enabled`, and the
+        /// version active in the current directory as `active_here`. Each has a `version`
+        /// and whether it is `installed`, `enabled`, `pinned`, and `available`, which is null
+        /// when the list of available versions is unknown, and with --size, its `size` in bytes
+        #[arg(long, conflicts_with_all = ["tree", "long", "format"])]
+        json: bool,
+    },
+    /// Show how much disk space each installed version takes up
+    Du,
+    /// Get started: install and enable the latest version of Go, and add it to your PATH
+    ///
+    /// Each step is offered in turn and can be skipped. Running setup again only does what
+    /// is left to do.
+    Setup {
+        /// Perform every step without asking, for provisioning scripts
+        #[arg(long)]
+        defaults: bool,
+    },
     /// Automatically install and enable the latest version of Go
-    Update,
+    ///
+    /// With auto_clean set in the config file, old versions are then removed, keeping the
+    /// newest `retain` of them along with the version that was enabled before.
+    Update {
+        /// Show what would be installed, enabled, and removed, without changing anything
+        #[arg(long)]
+        dry_run: bool,
+        /// Install without checking the archive against the checksum go.dev lists for it, for
+        /// proxies that alter what they pass on
+        #[arg(long)]
+        no_verify: bool,
+        /// Consider betas and release candidates, like go1.23rc1, when choosing the latest
+        /// version
+        #[arg(long)]
+        pre: bool,
+    },
+    /// Check whether the enabled version is the latest, exiting with a distinct status if
+    /// newer versions are available, and another if any of them include security fixes
+    Check,
     /// Install a new version of Go.
+    #[command(group(ArgGroup::new("verify").args(["sha256", "no_verify"])))]
     Install {
-        /// The version of Go that will be installed
-        version: GoVersion,
+        /// The version of Go that will be installed, a series like go1.22 for its newest
+        /// release, or with --url, the name to install it under, such as go1.22.6-acme
+        #[arg(
+            required_unless_present_any = ["from_file", "as_of"],
+            conflicts_with_all = ["from_file", "as_of"]
+        )]
+        version: Option<Toolchain>,
+        /// Leave out Go's tests and documentation, which aren't needed to build programs
+        #[arg(long)]
+        minimal: bool,
+        /// Install every version listed in a file, one per line. Blank lines and `#` comments
+        /// are ignored, and `latest` or a series like `go1.22` picks the newest release
+        #[arg(long, value_name = "PATH")]
+        from_file: Option<PathBuf>,
+        /// Enable this version of those in the file once they are installed
+        #[arg(long, value_name = "VERSION", requires = "from_file")]
+        enable: Option<VersionRequest>,
+        /// Install versions newer than the max_version set in the config file, or on its
+        /// blocklist
+        #[arg(long)]
+        override_policy: bool,
+        /// Build the version from its source release, for platforms that go.dev publishes no
+        /// archive for. This takes several minutes, and an installed version of Go to build it
+        /// with, which goup offers to install if there isn't a suitable one.
+        #[arg(long, requires = "version", conflicts_with_all = ["from_file", "minimal"])]
+        build_from_source: bool,
+        /// Build with this installed version, rather than the newest one that can bootstrap
+        /// the build
+        #[arg(long, value_name = "VERSION", requires = "build_from_source")]
+        bootstrap: Option<GoVersion>,
+        /// Install the archive at this URL, such as a patched build hosted internally, under
+        /// the given name rather than as a release. It is enabled, run, and removed by name,
+        /// and `update` and `clean` leave it alone.
+        #[arg(
+            long,
+            requires_all = ["version", "verify"],
+            conflicts_with_all = ["from_file", "build_from_source"]
+        )]
+        url: Option<String>,
+        /// The SHA256 digest the archive from --url must have
+        #[arg(long, value_name = "HEX", requires = "url")]
+        sha256: Option<String>,
+        /// Install without checking the archive against the checksum go.dev lists for it, or
+        /// with --url, against --sha256, for proxies that alter what they pass on
+        #[arg(long)]
+        no_verify: bool,
+        /// Install the newest release published on or before this date, given as YYYY-MM-DD,
+        /// to reproduce the versions of the past
+        #[arg(long, value_name = "DATE", value_parser = parse_as_of, conflicts_with = "from_file")]
+        as_of: Option<String>,
+        /// With --as-of, the newest release of this series, like go1.21, rather than of all
+        #[arg(long, value_parser = parse_series, requires = "as_of")]
+        series: Option<VersionRequest>,
     },
-    /// Enable the given Go version. This can be used to roll back updates, for example.
+    /// Replace the installed releases of a series, like go1.21, with its newest release
+    ///
+    /// The new release takes over the pin and enabled status of the ones it replaces, which are
+    /// then removed.
+    Upgrade {
+        /// The series to upgrade
+        #[arg(value_parser = parse_series)]
+        series: VersionRequest,
+        /// Keep the releases that were replaced, instead of removing them
+        #[arg(long)]
+        keep_old: bool,
+    },
+    /// Set the default version of Go, which `go` runs wherever no project, GOTOOLCHAIN, or
+    /// `goup shell` asks for another. This can be used to roll back updates, for example.
+    ///
+    /// Without a version, print the default, and the version active in the current directory
+    /// with what decided it; with --quiet, only the default is printed. `goup enable` is
+    /// another name for this command.
+    #[command(name = "default", visible_alias = "enable")]
     Enable {
-        /// The version of Go that will be enabled
-        version: GoVersion,
+        /// The version of Go to make the default, a series like go1.22 for its newest installed
+        /// release, or the name of a linked toolchain
+        version: Option<Toolchain>,
+        /// Offer to rebuild the tools in the last snapshot when switching to a new minor version
+        #[arg(long, requires = "version")]
+        rebuild_tools: bool,
+        /// Make $GOPATH/goup/go a copy of the version instead of a symlink to it
+        #[arg(long, requires = "version")]
+        copy: bool,
+        /// Delete whatever is at $GOPATH/goup/go first, even a directory goup didn't create
+        #[arg(long, requires = "version")]
+        force: bool,
     },
-    /// Remove an installed Go version
+    /// Remove an installed Go version, or unlink a linked toolchain without deleting it.
+    /// Toolchains installed with `install --url` are deleted.
     Remove {
-        /// The version of Go that will be removed
+        /// The version of Go that will be removed, a series like go1.22 for its newest installed
+        /// release, or the name of a linked toolchain
+        version: Toolchain,
+        /// Remove the version even while running processes, such as a build or gopls, are
+        /// using it
+        #[arg(long)]
+        force: bool,
+        /// Don't look for running processes using the version, where reading them is slow or
+        /// not allowed
+        #[arg(long)]
+        no_usage_check: bool,
+    },
+    /// Install a version again from the trash, where `remove` and `clean` put versions with
+    /// use_trash set in the config file
+    Restore {
+        /// The version of Go to restore. The copy put in the trash most recently is restored.
         version: GoVersion,
     },
+    /// List or empty the trash of removed versions
+    #[command(subcommand)]
+    Trash(TrashCommand),
+    /// Register a Go installation that goup didn't install, such as a build of Go from source,
+    /// under a name that `enable`, `run`, and `remove` accept in place of a version
+    ///
+    /// goup never deletes a linked toolchain, and `clean` and `update` leave it alone.
+    Link {
+        /// The name to give the toolchain, such as `mygo`
+        name: String,
+        /// Its GOROOT, which must contain bin/go
+        path: PathBuf,
+    },
     /// Pin the given Go version to keep it from being removed
+    #[command(group(ArgGroup::new("expiry").args(["until", "for_"])))]
     Pin {
-        /// The version of Go that will be pinned
-        version: GoVersion,
+        /// The version of Go that will be pinned, or a series like go1.22 for its newest
+        /// installed release
+        #[arg(required_unless_present_any = ["list", "prune_expired"], value_parser = parse_release)]
+        version: Option<VersionRequest>,
+        /// Why the version must be kept, shown by `goup list` and `goup info`
+        #[arg(long, conflicts_with_all = ["list", "prune_expired"])]
+        reason: Option<String>,
+        /// Only keep the version until this date, like 2025-10-01, after which `clean` may
+        /// remove it. Pinning it again without an expiry makes the pin permanent.
+        #[arg(long, value_name = "DATE", value_parser = parse_date, requires = "version")]
+        until: Option<SystemTime>,
+        /// Only keep the version for this long, such as `90d` or `6months`
+        #[arg(long = "for", value_name = "AGE", value_parser = parse_age, requires = "version")]
+        for_: Option<Duration>,
+        /// List the pinned versions, with when and why they were pinned, and when the pins
+        /// expire
+        #[arg(long, conflicts_with_all = ["version", "prune_expired"])]
+        list: bool,
+        /// Unpin every version whose pin has expired
+        #[arg(long, conflicts_with = "version")]
+        prune_expired: bool,
     },
-    /// Unpin the given Go version, allowing it to be removed
+    /// Unpin the given Go version, allowing it to be removed. Fails if it isn't pinned.
     Unpin {
         /// The version of Go that will be unpinned
         version: GoVersion,
     },
+    /// Add versions to the blocklist in the config file, so that goup never chooses them, and
+    /// only installs them with --override-policy
+    Block {
+        /// A release like go1.22.5, a series like go1.22, or a pattern like 'go1.22.[5-7]'
+        version: VersionPattern,
+        /// Why the versions must be avoided, shown by `goup list` and `goup info`
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Take versions off the blocklist in the config file
+    Unblock {
+        /// The release, series, or pattern, exactly as it was blocked
+        version: VersionPattern,
+    },
     /// Remove Go versions that are out of date (no longer available from go.dev)
-    Clean,
+    Clean {
+        /// How many versions to delete at once
+        #[arg(short, long, value_name = "N", value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: Option<u16>,
+        /// Only remove versions installed at least this long ago, such as `90d`, `6months`, or
+        /// `1y`
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+        /// Only remove versions that haven't been enabled or run in this long, such as `120d`.
+        /// Versions that goup never recorded using are kept.
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        unused_for: Option<Duration>,
+        /// Remove versions even while running processes are using them
+        #[arg(long)]
+        force: bool,
+        /// Don't look for running processes using the versions, where reading them is slow or
+        /// not allowed
+        #[arg(long)]
+        no_usage_check: bool,
+        /// Also remove betas and release candidates once their release is installed or
+        /// available, as clean_prereleases in the config file does every time
+        #[arg(long)]
+        prerelease: bool,
+    },
+    /// Run a tool from the given Go version, without enabling it
+    ///
+    /// The tool defaults to `go`, so `goup run go1.21.13 -- build ./...` runs `go build ./...`
+    /// while `goup run go1.21.13 gofmt -l .` runs gofmt.
+    Run {
+        /// The version of Go to run, a series like go1.22 for its newest installed release, or
+        /// the name of a linked toolchain
+        version: Toolchain,
+        /// The tool to run, and the arguments to pass to it
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+        command: Vec<String>,
+    },
+    /// Run any command with the given Go version active, without enabling it
+    ///
+    /// GOROOT and PATH are adjusted for the command alone, so that any `go` it invokes is the
+    /// requested version: `goup with go1.22.6 -- make release`
+    With {
+        /// The version of Go to make active
+        version: GoVersion,
+        /// The command to run, and its arguments
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Run a command with a version that may not be installed, installing it for the length of
+    /// the command and removing it again afterwards
+    ///
+    /// A version that was already installed is left exactly as it was:
+    /// `goup try go1.24rc1 -- go test ./...`
+    Try {
+        /// The version of Go to try
+        version: GoVersion,
+        /// Keep the version installed afterwards, if it was installed for the command
+        #[arg(long)]
+        keep: bool,
+        /// The command to run, and its arguments
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Run a command with each installed version active in turn, such as to test a library
+    /// against every release: `goup exec --all -- go test ./...`
+    ///
+    /// A table of which versions passed is printed at the end, and goup exits with the worst
+    /// of the commands' exit codes.
+    Exec {
+        /// Run the command with every installed version
+        #[arg(long, required = true)]
+        all: bool,
+        /// Only the versions matching this pattern, like 'go1.2[12].*'
+        #[arg(long, value_name = "PATTERN")]
+        only: Option<VersionGlob>,
+        /// Only this version and newer ones
+        #[arg(long, value_name = "VERSION")]
+        since: Option<GoVersion>,
+        /// Stop at the first version the command fails with
+        #[arg(long)]
+        fail_fast: bool,
+        /// How many versions to run the command with at once. Output is held back until each
+        /// finishes, so that it isn't interleaved.
+        #[arg(short, long, value_name = "N", default_value_t = 1, value_parser = clap::value_parser!(u16).range(1..))]
+        jobs: u16,
+        /// The command to run, and its arguments
+        #[arg(last = true, required = true)]
+        command: Vec<String>,
+    },
+    /// Start a new shell with the given Go version active, without enabling it
+    ///
+    /// GOUP_ACTIVE_SHELL is set to the version within the shell, so that it can be shown in
+    /// your prompt. Exit the shell to return to your usual version.
+    Shell {
+        /// The version of Go to make active
+        version: GoVersion,
+    },
+    /// Print the environment that makes a version of Go the active one, as shell commands to
+    /// eval, by default for the enabled version
+    Env {
+        /// Set up this installed version instead of the enabled one
+        #[arg(long)]
+        version: Option<GoVersion>,
+        /// Install the version first if it isn't installed
+        #[arg(long, requires = "version")]
+        install: bool,
+        /// Add Go to PATH and set GOROOT for the later steps of a GitHub Actions job, through
+        /// the files named by $GITHUB_PATH and $GITHUB_ENV
+        #[arg(long)]
+        github_actions: bool,
+        /// Set GOUP_VERSION to the version as well
+        #[arg(long)]
+        export_version: bool,
+    },
+    /// Package an installed version into a single file, to be imported on another machine
+    Export {
+        /// The version of Go to package
+        version: GoVersion,
+        /// Where to write the bundle, a zstd-compressed tar archive. Defaults to a file in the
+        /// current directory named after the version, like go1.22.6-bundle.tar.zst
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Install a version from a bundle made by `goup export`, without any network access
+    Import {
+        /// The bundle to install
+        bundle: PathBuf,
+    },
+    /// Install versions of Go that another version manager has already downloaded
+    ///
+    /// The installations are copied, so the other manager keeps working, unless --move is given.
+    #[command(subcommand)]
+    ImportFrom(ImportSource),
+    /// Describe an installed version, by default the enabled one, and where it came from
+    Info {
+        /// The version of Go to describe, a series like go1.22 for its newest installed release,
+        /// or the name of a linked toolchain
+        version: Option<Toolchain>,
+    },
+    /// Print the installed version to use in a directory, and how it was chosen
+    ///
+    /// GOTOOLCHAIN naming a release wins, then the version of a `goup shell`, then the
+    /// project's .go-version file, the toolchain or go line of its go.mod, or the golang line
+    /// of its .tool-versions, and finally the enabled version. Every source consulted is
+    /// listed; with --quiet, only the version is printed.
+    ///
+    /// The outcome for each directory is cached in goup's state directory until one of the
+    /// files it was decided from changes. --no-cache, or setting GOUP_SHIM_DISABLE_CACHE,
+    /// resolves afresh without the cache.
+    Resolve {
+        /// The directory to resolve for, by default the current one
+        dir: Option<PathBuf>,
+        /// Make sure a suitable version is installed, installing one if auto_install is set in
+        /// the config file, or otherwise warning in a single line. Outside of a project, there
+        /// is nothing to do.
+        #[arg(long)]
+        ensure: bool,
+    },
+    /// Install and enable the version of Go the project in a directory asks for
+    ///
+    /// The nearest .go-version file, go.mod, or .tool-versions up from the directory decides,
+    /// in that order where a directory has several. Of go.mod, its toolchain line decides, or
+    /// failing that, its go line, for which the newest release of that series is chosen. An
+    /// installed version that will do is used rather than downloading another.
+    Sync {
+        /// The directory of the project, by default the current one
+        dir: Option<PathBuf>,
+        /// Only print the version the project asks for, without installing or enabling it
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Make the project in the current directory ask for a version of Go, by writing it to a
+    /// .go-version file there
+    Local {
+        /// The version of Go the project should use
+        version: GoVersion,
+        /// Write the golang line of the .tool-versions file that asdf and mise read instead,
+        /// keeping the other tools listed there
+        #[arg(long)]
+        tool_versions: bool,
+    },
+    /// Write modulefiles for environment modules or Lmod, so that `module load go/1.22.6`
+    /// makes an installed version active
+    ///
+    /// One modulefile is written for each installed version, in a `go` directory within the
+    /// given one, along with a .version file making the enabled version the default. Run it
+    /// again after installing or removing versions to bring the files up to date.
+    Modulefiles {
+        /// The directory of modulefiles to write the `go` module into, as found on MODULEPATH
+        #[arg(long, value_name = "DIR")]
+        out_dir: PathBuf,
+        /// The language to write the modulefiles in
+        #[arg(long, value_enum, default_value = "tcl")]
+        format: modulefiles::Format,
+        /// Delete modulefiles written before for versions that are no longer installed
+        #[arg(long)]
+        prune: bool,
+    },
+    /// Check that the version of Go a git repository needs is installed whenever its files
+    /// change, with post-checkout and post-merge hooks
+    #[command(subcommand)]
+    HookGit(HookCommand),
+    /// Print the GOROOT of the given version, or the enabled version if none is given
+    Path {
+        /// The version of Go to locate
+        version: Option<GoVersion>,
+        /// Print the directory containing the version's binaries instead
+        #[arg(long)]
+        bin: bool,
+        /// Print the directory goup keeps its installations in instead
+        #[arg(long, conflicts_with_all = ["version", "bin"])]
+        root: bool,
+    },
+    /// Configure an editor to use goup's installation of Go
+    #[command(subcommand)]
+    Ide(Editor),
+    /// Manage the mirrors that Go is downloaded from
+    #[command(subcommand)]
+    Mirror(MirrorCommand),
+    /// Rebuild developer tools installed with `go install` after switching versions
+    #[command(subcommand)]
+    Tools(ToolsCommand),
+    /// Inspect and clear the cache of downloaded archives and responses from go.dev
+    #[command(subcommand)]
+    Cache(CacheCommand),
+    /// Carry the official checksums of Go's archives to machines that can't reach go.dev, so
+    /// that installs and bundle imports there are checked against them
+    #[command(subcommand)]
+    Checksums(ChecksumsCommand),
+    /// Show the settings in effect, including those from a project's .goup.toml
+    #[command(subcommand)]
+    Config(ConfigCommand),
+    /// Show the release notes for a version of Go, by default the latest, or when offline the
+    /// enabled or most recently installed one
+    Changelog {
+        /// The version of Go to describe
+        version: Option<GoVersion>,
+        /// Open the release notes in a web browser
+        #[arg(long)]
+        open: bool,
+    },
+    /// Show which platforms a release of Go publishes archives for, and how large they are
+    Platforms {
+        /// The release to look up
+        version: GoVersion,
+        /// Print the archives as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print a script that installs a release of Go on a machine without goup
+    ///
+    /// The script downloads the release's archive for the given platform, checks it against the
+    /// checksum published in the index, and unpacks it into `go` within a prefix, which it
+    /// takes as its argument. It then prints how to add Go to PATH.
+    BootstrapScript {
+        /// The release to install
+        #[arg(long)]
+        version: GoVersion,
+        /// The language to write the script in
+        #[arg(long, value_enum, default_value = "sh")]
+        shell: bootstrap::Shell,
+        /// The operating system of the machine the script is for, as Go names it, such as
+        /// `linux` or `windows`. Defaults to this machine's.
+        #[arg(long)]
+        os: Option<String>,
+        /// The architecture of the machine the script is for, as Go names it, such as `amd64`
+        /// or `arm64`. Defaults to this machine's.
+        #[arg(long)]
+        arch: Option<String>,
+        /// Where the script puts Go when run without an argument, by default /usr/local for sh
+        /// and C:\Program Files for PowerShell
+        #[arg(long, value_name = "DIR")]
+        prefix: Option<String>,
+    },
+    /// Share the cached archives with other machines over HTTP, as a mirror of go.dev/dl
+    ///
+    /// Point the other machines at it with GOUP_MIRROR=http://<this machine>:8800. Only archives
+    /// matching the checksums that go.dev publishes are served. Stop it with Ctrl-C.
+    Serve {
+        /// The address to listen on
+        #[arg(long, default_value = "0.0.0.0:8800")]
+        addr: String,
+        /// Don't serve the list of versions, only the archives
+        #[arg(long)]
+        no_index: bool,
+        /// Stop after this long, such as `90m` or `2h`
+        #[arg(long, value_name = "DURATION", value_parser = parse_age)]
+        time_limit: Option<Duration>,
+        /// Stop once this many bytes have been sent, letting downloads in progress finish
+        #[arg(long, value_name = "BYTES")]
+        byte_limit: Option<u64>,
+    },
+    /// Answer requests from editors and other tools over a Unix domain socket
+    ///
+    /// Requests are newline-delimited JSON-RPC, for the methods described in the README. The
+    /// socket is daemon.sock within goup's state directory, and only its owner may connect.
+    Daemon {
+        /// Stop once no client has been connected for this long, such as `10m`
+        #[arg(long, value_name = "DURATION", value_parser = parse_age, default_value = "30m")]
+        idle_timeout: Duration,
+    },
+    /// Update goup itself to the newest release
+    SelfUpdate {
+        /// Only report whether a newer release is available
+        #[arg(long)]
+        check: bool,
+    },
+    /// Gather a snapshot of goup's setup into a single file, to attach to a bug report
+    ///
+    /// Credentials are left out, and nothing from within the installed versions of Go is
+    /// included.
+    DebugBundle {
+        /// Where to write the bundle. Defaults to goup-debug.tar.gz in the current directory
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Browse the versions in a full-screen view, and install, enable, pin, and remove them
+    /// with the keyboard
+    ///
+    /// Everything the view does can also be done with goup list, info, install, enable, pin,
+    /// unpin, and remove.
+    Ui,
 }
 
-fn main() {
-    let args = Args::parse();
-
-    let res = match args.command {
-        Commands::List => list_versions(),
-        Commands::Update => update(),
-        Commands::Install { version } => install(version),
-        Commands::Enable { version } => enable(version),
-        Commands::Remove { version } => remove(version),
-        Commands::Pin { version } => pin(version),
-        Commands::Unpin { version } => unpin(version),
-        Commands::Clean => clean(),
-    };
+impl Commands {
+    /// Why the toolchain given to `goup install` doesn't suit whether --url was given, which
+    /// clap can't check itself
+    fn misnamed(&self) -> Option<String> {
+        match self {
+            Commands::Install {
+                version: Some(Toolchain::Version(version)),
+                url: Some(_),
+                ..
+            } => Some(format!(
+                "{} is a release; give the archive from --url a name of its own, like {}-custom",
+                version, version
+            )),
+            Commands::Install {
+                version: Some(Toolchain::Series(series)),
+                url: Some(_),
+                ..
+            } => Some(format!(
+                "{} is a series of releases; give the archive from --url a name of its own, like \
+                 {}-custom",
+                series, series
+            )),
+            Commands::Install {
+                version: Some(Toolchain::Linked(name)),
+                url: None,
+                ..
+            } => Some(format!(
+                "{} isn't a version of Go; only an archive installed with --url can be given a name",
+                name
+            )),
+            _ => None,
+        }
+    }
 
-    if let Err(e) = res {
-        eprintln!("Error: {}", e);
+    /// Whether the command was asked for JSON output
+    fn json(&self) -> bool {
+        matches!(
+            self,
+            Commands::List { json: true, .. }
+                | Commands::Cache(
+                    CacheCommand::List { json: true }
+                        | CacheCommand::Clean { json: true, .. }
+                        | CacheCommand::Size { json: true }
+                )
+        )
     }
-}
 
-fn list_versions() -> Result<()> {
-    let VersionFile {
-        enabled,
-        installed,
-        pinned,
-    } = VersionFile::load()?;
-    let available = version::available_go_versions()?
-        .into_keys()
-        .collect::<BTreeSet<_>>();
-
-    let mut versions = Vec::new();
-    for v in installed.union(&available) {
-        let is_installed = installed.contains(v);
-        let is_available = available.contains(v);
-        let is_enabled = enabled.is_some() && *v == enabled.unwrap();
-        let is_pinned = pinned.contains(v);
-
-        let bullet = if is_enabled {
-            "*"
-        } else if is_installed {
-            "i"
-        } else {
-            " "
+    /// Whether the command names a beta or release candidate, which only go.dev's list of
+    /// every release includes
+    fn names_pre_release(&self) -> bool {
+        let named = match *self {
+            Commands::Install {
+                version: Some(Toolchain::Version(version)),
+                ..
+            }
+            | Commands::Info {
+                version: Some(Toolchain::Version(version)),
+                ..
+            }
+            | Commands::Changelog {
+                version: Some(version),
+                ..
+            }
+            | Commands::Platforms { version, .. }
+            | Commands::BootstrapScript { version, .. } => Some(version),
+            _ => self.runs_with(),
         };
-        let pinned_text = if is_pinned { " (PINNED)" } else { "" };
-        let string = format!("{} {}{}", bullet, v, pinned_text);
-
-        let paint = match (is_installed, is_available, is_enabled) {
-            (true, true, _) => style(string).green(),
-            (true, false, true) => style(string).red(),
-            (true, false, false) => style(string).yellow(),
-            _ => style(string),
-        };
-
-        versions.push(paint);
+        named.is_some_and(|v| v.is_prerelease())
     }
 
-    for v in versions.iter().rev() {
-        println!("{}", v);
+    /// The version the command runs something with, for listing the variables the config
+    /// file's `env` tables give it
+    fn runs_with(&self) -> Option<GoVersion> {
+        match *self {
+            Commands::Run {
+                version: Toolchain::Version(version),
+                ..
+            }
+            | Commands::With { version, .. }
+            | Commands::Try { version, .. }
+            | Commands::Shell { version }
+            | Commands::Env {
+                version: Some(version),
+                ..
+            } => Some(version),
+            _ => None,
+        }
     }
+}
 
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum Editor {
+    /// Print the settings for the Go extension for VS Code
+    Vscode {
+        /// Point at a specific version instead of following the enabled version
+        #[arg(long)]
+        version: Option<GoVersion>,
+        /// Merge the settings into .vscode/settings.json in the current directory
+        #[arg(long)]
+        write: bool,
+    },
 }
 
-fn update() -> Result<()> {
-    let records = VersionFile::load()?;
-    let available = version::available_go_versions()?;
-    let (&latest_version, file_info) = available
-        .last_key_value()
-        .ok_or_else(|| anyhow!("Found no available go versions"))?;
+#[derive(Debug, Subcommand)]
+enum ToolsCommand {
+    /// Record the tools in $GOBIN (or $GOPATH/bin), so that they can be rebuilt later
+    Snapshot,
+    /// Reinstall every recorded tool with the enabled version of Go
+    Rebuild,
+}
 
-    if records.installed.contains(&latest_version) {
-        enable(latest_version)?;
-        println!("The latest version is {}", latest_version);
-        println!("Already up to date!");
-        return Ok(());
-    } else {
-        println!("Version {} is available", latest_version);
-    }
+#[derive(Debug, Subcommand)]
+enum TrashCommand {
+    /// List the versions in the trash, with how long ago they were put there and their sizes
+    List,
+    /// Delete the versions in the trash for good, by default all of them
+    Empty {
+        /// Only delete versions put in the trash at least this long ago, such as `30d`
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+    },
+}
 
-    version::download_version(latest_version, file_info)?;
-    version::enable_version(latest_version)?;
-    println!("Installed and enabled version {}", latest_version);
-    println!(
-        "Use 'goup clean' to remove old versions, or 'goup enable {}' to roll back",
-        records.enabled.unwrap_or_default()
-    );
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum CacheCommand {
+    /// List the cached files, with their sizes, versions, and how long ago they were used
+    List {
+        /// Print the list as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Delete cached files, by default all of them
+    Clean {
+        /// Only delete files that haven't been used for this long, such as `30d` or `12h`
+        #[arg(long, value_name = "AGE", value_parser = parse_age)]
+        older_than: Option<Duration>,
+        /// Only delete archives of versions that aren't installed
+        #[arg(long)]
+        uninstalled: bool,
+        /// Print what was deleted as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Print the disk space taken up by the cache
+    Size {
+        /// Print the size as JSON
+        #[arg(long)]
+        json: bool,
+    },
 }
 
-fn install(v: GoVersion) -> Result<()> {
-    let mut rf = VersionFile::load()?;
-    rf.installed.insert(v);
+#[derive(Debug, Subcommand)]
+enum ChecksumsCommand {
+    /// Write the checksums published by go.dev for the archives of the chosen releases
+    Export {
+        /// Only the releases of this series, like go1.22. May be given more than once; by
+        /// default every release in the index is included
+        #[arg(long, value_parser = parse_series)]
+        series: Vec<VersionRequest>,
+        /// Only the archives for this platform, given as OS/ARCH like linux/amd64. May be given
+        /// more than once; by default every platform is included
+        #[arg(long, value_name = "OS/ARCH", value_parser = parse_platform)]
+        platform: Vec<(String, String)>,
+        /// Where to write the checksums, rather than to stdout
+        #[arg(long, value_name = "PATH")]
+        out: Option<PathBuf>,
+    },
+    /// Add checksums written by `goup checksums export` to those that archives are checked
+    /// against
+    Import {
+        /// The files to import
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+    },
+}
 
-    version::available_go_versions()?
-        .get(&v)
-        .ok_or_else(|| anyhow!("Version {} not available for download", v))
-        .and_then(|f| version::download_version(v, f))?;
+#[derive(Debug, Subcommand)]
+enum ConfigCommand {
+    /// List each setting that isn't at its default, and where its value came from
+    List,
+}
 
-    rf.store()
-        .with_context(|| "Unable to write out version file")?;
+#[derive(Debug, Subcommand)]
+enum ImportSource {
+    /// Import the versions installed by g, from ~/.g/versions or $G_HOME/versions
+    G {
+        /// Where g keeps its versions, if not the usual place
+        #[arg(long, value_name = "PATH")]
+        root: Option<PathBuf>,
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+    /// Import the versions installed by gvm, from ~/.gvm/gos or $GVM_ROOT/gos
+    Gvm {
+        /// Where gvm keeps its versions, if not the usual place
+        #[arg(long, value_name = "PATH")]
+        root: Option<PathBuf>,
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+    /// Import the versions installed by asdf, from ~/.asdf/installs/golang or
+    /// $ASDF_DATA_DIR/installs/golang
+    Asdf {
+        /// Where asdf keeps its Go versions, if not the usual place
+        #[arg(long, value_name = "PATH")]
+        root: Option<PathBuf>,
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+    /// Import an installation of Go, or each of those in a directory
+    Dir {
+        /// The installation, or the directory holding them
+        path: PathBuf,
+        #[command(flatten)]
+        options: ImportOptions,
+    },
+}
 
-    println!("{} installed successfully", v);
-    Ok(())
+#[derive(Debug, clap::Args)]
+struct ImportOptions {
+    /// Only list what would be imported
+    #[arg(long)]
+    dry_run: bool,
+    /// Move the installations instead of copying them, so the other manager no longer has them
+    #[arg(long = "move")]
+    move_trees: bool,
 }
 
-fn enable(version: GoVersion) -> Result<()> {
-    version::enable_version(version)
+#[derive(Debug, Clone, Copy, Subcommand)]
+enum HookCommand {
+    /// Add the hooks to the repository in the current directory, or add goup to hooks it
+    /// already has
+    Install,
+    /// Take out exactly what install added
+    Uninstall,
 }
 
-fn remove(version: GoVersion) -> Result<()> {
-    version::remove_version(version)?;
-    println!("{} uninstalled successfully", version);
-    Ok(())
+#[derive(Debug, Subcommand)]
+enum MirrorCommand {
+    /// Measure how quickly each configured mirror responds
+    Bench {
+        /// Record the ranking, so that downloads try the fastest mirror first
+        #[arg(long)]
+        save: bool,
+    },
 }
 
-fn pin(version: GoVersion) -> Result<()> {
-    let mut version_file = VersionFile::load()?;
-    if !version_file.installed.contains(&version) {
-        return Err(anyhow!("Version {} is not installed.", version));
+fn main() -> ExitCode {
+    let args = match Args::try_parse() {
+        Ok(args) => args,
+        Err(e) => {
+            let _ = e.print();
+            return if e.use_stderr() {
+                ExitCode::from(exit::USAGE)
+            } else {
+                ExitCode::SUCCESS
+            };
+        }
+    };
+    crash::install(!args.no_crash_report);
+    if let Some(message) = args.command.misnamed() {
+        let _ = Args::command()
+            .error(ErrorKind::InvalidValue, message)
+            .print();
+        return ExitCode::from(exit::USAGE);
     }
+    prompt::init(args.yes, args.non_interactive);
+    // Setup is where GOPATH gets chosen, so it can't insist on one already being set
+    let defaulted_gopath = match args.command {
+        Commands::Setup { .. } => setup::default_gopath_if_unset(),
+        _ => None,
+    };
+    let goup = match startup::open(&args) {
+        Ok(goup) => goup,
+        Err(e) => return errors::report(&e, None, args.verbose),
+    };
 
-    version_file.pinned.insert(version);
-    version_file.store()?;
-    Ok(())
-}
-
-fn unpin(version: GoVersion) -> Result<()> {
-    let mut version_file = VersionFile::load()?;
-    version_file.pinned.remove(&version);
-    version_file.store()?;
-    Ok(())
-}
-
-fn clean() -> Result<()> {
-    let mut version_file = VersionFile::load()?;
-    let folder_versions = version::version_folders()?;
-
-    // Fix our list of installed versions to only include those that are actually on disk.
-    // This would indicate that someone was tampering with our .goup directory.
-    version_file.installed = version_file
-        .installed
-        .intersection(&folder_versions)
-        .copied()
-        .collect();
-    version_file.pinned = version_file
-        .installed
-        .intersection(&version_file.pinned)
-        .copied()
-        .collect();
-
-    // Keep any version of Go that is still available, that is pinned, or enabled.
-    let allowlist: BTreeSet<_> = version::available_go_versions()?
-        .into_keys()
-        .chain(version_file.pinned.iter().copied())
-        .chain(version_file.enabled)
-        .collect();
-
-    for version in folder_versions.difference(&allowlist) {
-        version_file.installed.remove(version);
-        fs::remove_dir_all(version::install_dir(*version)?)?;
-    }
+    let json = args.command.json();
+    let res = match args.command {
+        Commands::List {
+            installed,
+            format: Some(template),
+            fail_on_network_error,
+            ..
+        } => list::formatted(&goup, installed, fail_on_network_error, &template),
+        Commands::List {
+            installed,
+            size,
+            fail_on_network_error,
+            json: true,
+            ..
+        } => list::json(&goup, installed, fail_on_network_error, size),
+        Commands::List {
+            installed,
+            size,
+            tree,
+            expand,
+            long,
+            format: None,
+            fail_on_network_error,
+            json: false,
+        } => list::versions(
+            &goup,
+            installed,
+            fail_on_network_error,
+            size,
+            long,
+            tree.then_some(expand),
+        ),
+        Commands::Du => list::du(&goup),
+        Commands::Setup { defaults } => setup::run(&goup, defaults, defaulted_gopath),
+        Commands::Update { dry_run, .. } => update::update(&goup, dry_run),
+        Commands::Check => update::check(&goup),
+        Commands::Install {
+            version: Some(Toolchain::Linked(name)),
+            url: Some(url),
+            sha256,
+            ..
+        } => install::install_custom(&goup, &name, &url, sha256.as_deref()),
+        Commands::Install {
+            version: Some(Toolchain::Version(version)),
+            override_policy,
+            build_from_source: true,
+            bootstrap,
+            ..
+        } => install::build_from_source(&goup, version, bootstrap, override_policy),
+        Commands::Install {
+            version: Some(Toolchain::Version(version)),
+            override_policy,
+            ..
+        } => install::install(&goup, version, override_policy),
+        Commands::Install {
+            version: Some(Toolchain::Series(series)),
+            override_policy,
+            build_from_source: from_source,
+            bootstrap,
+            ..
+        } => install::newest_available(&goup, series).and_then(|version| {
+            if from_source {
+                install::build_from_source(&goup, version, bootstrap, override_policy)
+            } else {
+                install::install(&goup, version, override_policy)
+            }
+        }),
+        Commands::Install {
+            from_file: Some(path),
+            enable,
+            override_policy,
+            ..
+        } => install::install_from_file(&goup, &path, enable, override_policy),
+        Commands::Install {
+            as_of: Some(date),
+            series,
+            override_policy,
+            ..
+        } => install::install_as_of(&goup, &date, series, override_policy),
+        Commands::Install { .. } => {
+            unreachable!(
+                "clap requires a version, a file, or a date, and names are checked against --url"
+            )
+        }
+        Commands::Upgrade { series, keep_old } => update::upgrade(&goup, series, keep_old),
+        Commands::Enable { version: None, .. } => enable::show_default(&goup),
+        Commands::Enable {
+            version: Some(Toolchain::Version(version)),
+            rebuild_tools,
+            force,
+            ..
+        } => enable::enable(&goup, version, rebuild_tools, force),
+        Commands::Enable {
+            version: Some(Toolchain::Series(series)),
+            rebuild_tools,
+            force,
+            ..
+        } => install::newest_installed(&goup, series)
+            .and_then(|version| enable::enable(&goup, version, rebuild_tools, force)),
+        Commands::Enable {
+            version: Some(Toolchain::Linked(name)),
+            force,
+            ..
+        } => enable::enable_linked(&goup, &name, force),
+        Commands::Remove {
+            version: Toolchain::Version(version),
+            force,
+            no_usage_check,
+        } => remove::remove(&goup, version, force || no_usage_check),
+        Commands::Remove {
+            version: Toolchain::Series(series),
+            force,
+            no_usage_check,
+        } => install::newest_installed(&goup, series)
+            .and_then(|version| remove::remove(&goup, version, force || no_usage_check)),
+        Commands::Remove {
+            version: Toolchain::Linked(name),
+            ..
+        } => enable::unlink(&goup, &name),
+        Commands::Restore { version } => remove::restore(&goup, version),
+        Commands::Trash(TrashCommand::List) => remove::trash_list(&goup),
+        Commands::Trash(TrashCommand::Empty { older_than }) => {
+            remove::trash_empty(&goup, older_than)
+        }
+        Commands::Link { name, path } => enable::link(&goup, &name, &path),
+        Commands::Pin {
+            version: Some(request),
+            reason,
+            until,
+            for_,
+            ..
+        } => {
+            let version = match request {
+                VersionRequest::Exact(version) => Ok(version),
+                series => install::newest_installed(&goup, series),
+            };
+            version.and_then(|version| {
+                pin::pin(
+                    &goup,
+                    version,
+                    reason,
+                    until.or(for_.map(|d| SystemTime::now() + d)),
+                )
+            })
+        }
+        Commands::Pin {
+            prune_expired: true,
+            ..
+        } => pin::prune_expired(&goup),
+        Commands::Pin { version: None, .. } => pin::list(&goup),
+        Commands::Unpin { version } => pin::unpin(&goup, version),
+        Commands::Block { version, reason } => pin::block(&goup, &version, reason.as_deref()),
+        Commands::Unblock { version } => pin::unblock(&goup, &version),
+        Commands::Clean {
+            jobs,
+            older_than,
+            unused_for,
+            force,
+            no_usage_check,
+            prerelease,
+        } => {
+            let policy = CleanPolicy {
+                jobs: jobs.map(usize::from),
+                older_than,
+                unused_for,
+                ignore_usage: force || no_usage_check,
+                prereleases: prerelease,
+                ..Default::default()
+            };
+            remove::clean(&goup, &policy)
+        }
+        Commands::Run {
+            version: Toolchain::Version(version),
+            command,
+        } => run::run(&goup, version, command),
+        Commands::Run {
+            version: Toolchain::Series(series),
+            command,
+        } => install::newest_installed(&goup, series)
+            .and_then(|version| run::run(&goup, version, command)),
+        Commands::Run {
+            version: Toolchain::Linked(name),
+            command,
+        } => run::run_linked(&goup, &name, command),
+        Commands::With { version, command } => run::with(&goup, version, command),
+        Commands::Try {
+            version,
+            keep,
+            command,
+        } => run::try_version(&goup, version, keep, command),
+        Commands::Exec {
+            only,
+            since,
+            fail_fast,
+            jobs,
+            command,
+            ..
+        } => {
+            let filter = |v: GoVersion| {
+                only.as_ref().is_none_or(|glob| glob.matches(v)) && since.is_none_or(|s| v >= s)
+            };
+            run::exec_all(&goup, command, filter, fail_fast, usize::from(jobs))
+        }
+        Commands::Shell { version } => run::shell(&goup, version),
+        Commands::Env {
+            version,
+            install: install_first,
+            github_actions,
+            export_version,
+        } => run::env(
+            &goup,
+            version,
+            install_first,
+            github_actions,
+            export_version,
+        ),
+        Commands::Export { version, out } => bundle::export(&goup, version, out),
+        Commands::Import { bundle } => bundle::import(&goup, bundle),
+        Commands::ImportFrom(source) => bundle::import_from(&goup, source),
+        Commands::Info { version } => info::info(&goup, version),
+        Commands::Resolve { dir, ensure } => resolve::resolve(&goup, dir, ensure),
+        Commands::Sync { dir, dry_run } => resolve::sync(&goup, dir, dry_run),
+        Commands::Local {
+            version,
+            tool_versions,
+        } => resolve::local(&goup, version, tool_versions),
+        Commands::Modulefiles {
+            out_dir,
+            format,
+            prune,
+        } => resolve::write_modulefiles(&goup, &out_dir, format, prune),
+        Commands::HookGit(command) => resolve::hook_git(command),
+        Commands::Path { version, bin, root } => resolve::path(&goup, version, bin, root),
+        Commands::Ide(editor) => resolve::ide(&goup, editor),
+        Commands::Mirror(MirrorCommand::Bench { save }) => cache::mirror_bench(&goup, save),
+        Commands::Tools(ToolsCommand::Snapshot) => tools::snapshot(&goup),
+        Commands::Tools(ToolsCommand::Rebuild) => tools::rebuild_all(&goup),
+        Commands::Cache(CacheCommand::List { json }) => cache::list(&goup, json),
+        Commands::Cache(CacheCommand::Clean {
+            older_than,
+            uninstalled,
+            json,
+        }) => cache::clean(&goup, older_than, uninstalled, json),
+        Commands::Cache(CacheCommand::Size { json }) => cache::size(&goup, json),
+        Commands::Checksums(ChecksumsCommand::Export {
+            series,
+            platform,
+            out,
+        }) => bundle::checksums_export(&goup, &series, &platform, out),
+        Commands::Checksums(ChecksumsCommand::Import { files }) => {
+            bundle::checksums_import(&goup, &files)
+        }
+        Commands::Config(ConfigCommand::List) => info::config_list(&goup),
+        Commands::Changelog { version, open } => info::changelog(&goup, version, open),
+        Commands::Platforms { version, json } => info::platforms(&goup, version, json),
+        Commands::BootstrapScript {
+            version,
+            shell,
+            os,
+            arch,
+            prefix,
+        } => info::bootstrap_script(&goup, version, shell, os, arch, prefix),
+        Commands::Serve {
+            addr,
+            no_index,
+            time_limit,
+            byte_limit,
+        } => {
+            let limits = serve::Limits {
+                time: time_limit,
+                bytes: byte_limit,
+            };
+            server::serve(&goup, &addr, !no_index, limits)
+        }
+        Commands::Daemon { idle_timeout } => server::daemon(&goup, idle_timeout),
+        Commands::SelfUpdate { check } => update::self_update(&goup, check),
+        Commands::DebugBundle { out } => {
+            let flags: Vec<_> = [
+                (args.stall_timeout.is_some(), "--stall-timeout"),
+                (args.cacert.is_some(), "--cacert"),
+                (args.chunks.is_some(), "--chunks"),
+                (args.retries.is_some(), "--retries"),
+                (args.offline, "--offline"),
+                (args.trace_http, "--trace-http"),
+            ]
+            .into_iter()
+            .filter_map(|(given, flag)| given.then_some(flag))
+            .collect();
+            bundle::debug_bundle(&goup, &flags, out)
+        }
+        Commands::Ui => ui::run(&goup),
+    };
+
+    report::show_timings(args.timings, json, args.verbose);
 
-    version_file.store()?;
-    Ok(())
+    match res {
+        Ok(()) => {
+            if events::enabled() {
+                events::result(0, None);
+            }
+            ExitCode::SUCCESS
+        }
+        Err(e) => errors::report(&e, Some(&goup), args.verbose),
+    }
 }
@@ -0,0 +1,125 @@
+//! Installations of Go made by other version managers, which goup can adopt rather than
+//! download again
+
+use crate::error::GoupError;
+use crate::version::GoVersion;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Another Go version manager, whose installations can be imported
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Manager {
+    /// [g](https://github.com/voidint/g), which keeps versions in `~/.g/versions`
+    G,
+    /// [gvm](https://github.com/moovweb/gvm), which keeps versions in `~/.gvm/gos`
+    Gvm,
+    /// [asdf](https://asdf-vm.com), which keeps versions in `~/.asdf/installs/golang`
+    Asdf,
+}
+
+impl Manager {
+    /// Where the manager keeps its installations, honoring the variables it uses to move them
+    pub fn default_root(&self) -> Option<PathBuf> {
+        let home = || env::var_os("HOME").map(PathBuf::from);
+        let var = |name: &str| {
+            env::var_os(name)
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        };
+        match self {
+            Self::G => Some(
+                var("G_HOME")
+                    .or_else(|| Some(home()?.join(".g")))?
+                    .join("versions"),
+            ),
+            Self::Gvm => Some(
+                var("GVM_ROOT")
+                    .or_else(|| Some(home()?.join(".gvm")))?
+                    .join("gos"),
+            ),
+            Self::Asdf => Some(
+                var("ASDF_DATA_DIR")
+                    .or_else(|| Some(home()?.join(".asdf")))?
+                    .join("installs")
+                    .join("golang"),
+            ),
+        }
+    }
+}
+
+/// A directory that may hold an installation of Go
+#[derive(Debug)]
+pub struct Found {
+    /// The directory that was found
+    pub path: PathBuf,
+    /// The GOROOT within it and the version it holds, or why it can't be imported
+    pub tree: Result<(PathBuf, GoVersion), GoupError>,
+}
+
+/// Look through `root` for installations of Go. Each directory within it is checked, as is
+/// `root` itself if it is an installation. Nothing is ever modified.
+pub fn find(root: &Path) -> Result<Vec<Found>, GoupError> {
+    if go_tree(root).is_some() {
+        return Ok(vec![Found {
+            path: root.to_path_buf(),
+            tree: validate(root),
+        }]);
+    }
+    let entries = fs::read_dir(root).map_err(GoupError::io("read", root))?;
+    let mut found: Vec<Found> = entries
+        .flatten()
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| Found {
+            path: entry.path(),
+            tree: validate(&entry.path()),
+        })
+        .collect();
+    found.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(found)
+}
+
+/// The GOROOT within `dir`, which is either `dir` itself or a `go` directory inside it
+fn go_tree(dir: &Path) -> Option<PathBuf> {
+    [dir.to_path_buf(), dir.join("go")]
+        .into_iter()
+        .find(|candidate| candidate.join("VERSION").is_file())
+}
+
+/// Check that `dir` holds a complete installation of Go, returning its GOROOT and version
+fn validate(dir: &Path) -> Result<(PathBuf, GoVersion), GoupError> {
+    let invalid = |reason: String| GoupError::InvalidGoTree {
+        path: dir.to_path_buf(),
+        reason,
+    };
+    let goroot = go_tree(dir).ok_or_else(|| invalid("it has no VERSION file".to_string()))?;
+    let text = fs::read_to_string(goroot.join("VERSION"))
+        .map_err(GoupError::io("read", goroot.join("VERSION")))?;
+    let first = text.lines().next().unwrap_or_default().trim();
+    let version: GoVersion = first
+        .parse()
+        .map_err(|_| invalid(format!("its VERSION file names '{}'", first)))?;
+
+    // Managers name directories after the version, with or without the `go` prefix
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    let named = name
+        .parse::<GoVersion>()
+        .or_else(|_| format!("go{}", name).parse());
+    if let Ok(named) = named {
+        if named != version {
+            return Err(invalid(format!(
+                "it is named {}, but its VERSION file says {}",
+                named, version
+            )));
+        }
+    }
+    let go = goroot
+        .join("bin")
+        .join(format!("go{}", env::consts::EXE_SUFFIX));
+    if !go.is_file() {
+        return Err(invalid(
+            "it has no bin/go, so it may not have been built".to_string(),
+        ));
+    }
+    Ok((goroot, version))
+}
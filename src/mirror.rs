@@ -0,0 +1,155 @@
+//! Choosing between several mirrors of go.dev/dl by measuring how quickly they respond
+
+use crate::error::GoupError;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use ureq::Agent;
+
+/// How much of the index a probe downloads to estimate a mirror's throughput
+const PROBE_BYTES: u64 = 64 * 1024;
+
+/// How the configured mirrors are ordered when downloading
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MirrorSelection {
+    /// Try the mirrors in the order they are configured
+    #[default]
+    Ordered,
+    /// Try the mirrors fastest first, as measured by probing each of them
+    Auto,
+}
+
+/// The URL of the JSON index listing the versions available from a mirror. go.dev lists only
+/// the current stable releases unless `all` releases are asked for, betas and release
+/// candidates included.
+pub fn index_url(mirror: &str, all: bool) -> String {
+    if all {
+        format!("{}/?mode=json&include=all", mirror)
+    } else {
+        format!("{}/?mode=json", mirror)
+    }
+}
+
+/// The URL that the given archive can be downloaded from a mirror
+pub fn archive_url(mirror: &str, filename: &str) -> String {
+    format!("{}/{}", mirror, filename)
+}
+
+/// How quickly a mirror responded to a probe
+#[derive(Debug, Clone, Copy)]
+pub struct Measurement {
+    /// The time until the response headers arrived
+    pub latency: Duration,
+    /// The rate at which the body arrived, in bytes per second
+    pub throughput: f64,
+}
+
+/// The outcome of probing a single mirror
+#[derive(Debug)]
+pub struct Probe {
+    pub mirror: String,
+    pub result: Result<Measurement, GoupError>,
+}
+
+/// Measure the latency and throughput of a mirror by fetching the start of its index. The
+/// whole probe is abandoned after `timeout`.
+pub fn probe(agent: &Agent, mirror: &str, timeout: Duration) -> Probe {
+    let url = index_url(mirror, false);
+    let measure = || {
+        let start = Instant::now();
+        let response = agent
+            .get(&url)
+            .timeout(timeout)
+            .set("Range", &format!("bytes=0-{}", PROBE_BYTES - 1))
+            .call()
+            .map_err(|e| GoupError::network(&url, e))?;
+        let latency = start.elapsed();
+
+        let mut body = Vec::new();
+        response
+            .into_reader()
+            .take(PROBE_BYTES)
+            .read_to_end(&mut body)
+            .map_err(|e| GoupError::network(&url, e))?;
+        let transfer = (start.elapsed() - latency).as_secs_f64().max(1e-6);
+        Ok(Measurement {
+            latency,
+            throughput: body.len() as f64 / transfer,
+        })
+    };
+
+    Probe {
+        mirror: mirror.to_string(),
+        result: measure(),
+    }
+}
+
+/// Probe every mirror at once, returning the results in the same order as the mirrors
+pub fn probe_all(agent: &Agent, mirrors: &[String], timeout: Duration) -> Vec<Probe> {
+    thread::scope(|s| {
+        let handles: Vec<_> = mirrors
+            .iter()
+            .map(|mirror| s.spawn(move || probe(agent, mirror, timeout)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|h| h.join().expect("mirror probe panicked"))
+            .collect()
+    })
+}
+
+/// Order the mirrors fastest first. Mirrors that couldn't be probed go last, in the order
+/// they were configured, so if every probe fails the configured order is kept.
+pub fn rank(probes: &[Probe]) -> Vec<String> {
+    let mut ranked: Vec<_> = probes.iter().enumerate().collect();
+    ranked.sort_by(|(i, a), (j, b)| match (&a.result, &b.result) {
+        (Ok(a), Ok(b)) => a.latency.cmp(&b.latency),
+        (Ok(_), Err(_)) => std::cmp::Ordering::Less,
+        (Err(_), Ok(_)) => std::cmp::Ordering::Greater,
+        (Err(_), Err(_)) => i.cmp(j),
+    });
+    ranked.into_iter().map(|(_, p)| p.mirror.clone()).collect()
+}
+
+/// A ranking of the mirrors from an earlier probe, saved in the cache directory
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Ranking {
+    /// Seconds since the Unix epoch at which the mirrors were probed
+    pub measured: u64,
+    /// The mirrors, fastest first
+    pub mirrors: Vec<String>,
+}
+
+impl Ranking {
+    pub fn new(mirrors: Vec<String>) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        Self {
+            measured: now.unwrap_or_default().as_secs(),
+            mirrors,
+        }
+    }
+
+    /// How long ago the mirrors were probed
+    pub fn age(&self) -> Duration {
+        let measured = UNIX_EPOCH + Duration::from_secs(self.measured);
+        measured.elapsed().unwrap_or_default()
+    }
+
+    /// Read a saved ranking. A missing or corrupt file gives `None`, so that the mirrors are
+    /// probed again.
+    pub fn load(path: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn store(&self, path: &Path) -> Result<(), GoupError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        let text = serde_json::to_string(self).expect("a ranking can always be serialized");
+        fs::write(path, text).map_err(GoupError::io("write", path))
+    }
+}
@@ -0,0 +1,210 @@
+use crate::config::Config;
+use crate::error::GoupError;
+use rustls::{ClientConfig, RootCertStore};
+use std::env;
+use std::error::Error as _;
+use std::fs::File;
+use std::io::{self, BufReader};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use ureq::{Agent, AgentBuilder, Error, ErrorKind, Middleware, MiddlewareNext, Request, Response};
+
+/// How long to wait for a connection to be established before giving up
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// The User-Agent sent with every request, identifying goup and the platform it runs on
+fn user_agent() -> String {
+    format!(
+        "goup/{} ({}/{})",
+        env!("CARGO_PKG_VERSION"),
+        env::consts::OS,
+        env::consts::ARCH
+    )
+}
+
+/// Build the HTTP agent shared by every request goup makes, so that proxy, timeout, and TLS
+/// settings apply uniformly.
+pub(crate) fn agent(config: &Config) -> Result<Agent, GoupError> {
+    let builder = builder(config);
+    if config.ca_bundle.is_some() || config.native_certs() {
+        Ok(builder.tls_config(Arc::new(tls_config(config)?)).build())
+    } else {
+        Ok(builder.build())
+    }
+}
+
+/// The agent for the default settings, which can't fail to load any certificates
+pub(crate) fn default_agent() -> Agent {
+    builder(&Config::default()).build()
+}
+
+fn builder(config: &Config) -> AgentBuilder {
+    let headers = MirrorHeaders {
+        mirrors: config.mirrors().iter().map(|m| format!("{}/", m)).collect(),
+        headers: config
+            .headers
+            .iter()
+            .filter_map(|(name, value)| Some((name.clone(), value.resolve()?)))
+            .collect(),
+    };
+
+    let trace = TraceHttp {
+        secrets: config
+            .headers
+            .keys()
+            .map(|h| h.to_ascii_lowercase())
+            .collect(),
+    };
+
+    // A read that blocks for this long means the connection has stalled. Without this, a
+    // connection that is silently dropped mid-download would hang forever.
+    let builder = AgentBuilder::new()
+        .try_proxy_from_env(true)
+        .timeout_connect(CONNECT_TIMEOUT)
+        .timeout_read(config.stall_timeout())
+        .user_agent(&user_agent())
+        .middleware(headers);
+    if config.trace_http() {
+        builder.middleware(trace)
+    } else {
+        builder
+    }
+}
+
+/// The TLS settings trusting either the bundled or the platform's root certificates, along
+/// with any from the configured CA bundle
+fn tls_config(config: &Config) -> Result<ClientConfig, GoupError> {
+    let mut roots = RootCertStore::empty();
+    if config.native_certs() {
+        let certs = rustls_native_certs::load_native_certs().map_err(GoupError::NativeCerts)?;
+        // The platform store commonly contains a few certificates that rustls can't parse,
+        // which shouldn't stop the rest from being used
+        roots.add_parsable_certificates(certs);
+    } else {
+        roots.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    }
+
+    if let Some(path) = &config.ca_bundle {
+        let file = File::open(path).map_err(GoupError::io("read", path))?;
+        for cert in rustls_pemfile::certs(&mut BufReader::new(file)) {
+            let cert = cert.map_err(GoupError::io("read", path))?;
+            roots
+                .add(cert)
+                .map_err(|source| GoupError::InvalidCertificate {
+                    path: path.clone(),
+                    source,
+                })?;
+        }
+    }
+
+    Ok(ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+/// Whether the error means the server couldn't be reached at all, because the name didn't
+/// resolve, or the connection failed or timed out before a response arrived, as happens when
+/// the network is down. Every check for being offline goes through here, so that they all
+/// agree.
+pub(crate) fn is_unreachable(error: &ureq::Error) -> bool {
+    let transport = match error {
+        ureq::Error::Transport(t) => t,
+        ureq::Error::Status(..) => return false,
+    };
+
+    match transport.kind() {
+        ErrorKind::Dns | ErrorKind::ConnectionFailed | ErrorKind::ProxyConnect => true,
+        // Other I/O errors, like certificates failing validation, aren't about reachability
+        ErrorKind::Io => transport
+            .source()
+            .and_then(|e| e.downcast_ref::<io::Error>())
+            .is_some_and(|e| {
+                matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut
+                        | io::ErrorKind::ConnectionRefused
+                        | io::ErrorKind::ConnectionReset
+                        | io::ErrorKind::ConnectionAborted
+                        | io::ErrorKind::NotConnected
+                        | io::ErrorKind::UnexpectedEof
+                        | io::ErrorKind::HostUnreachable
+                        | io::ErrorKind::NetworkUnreachable
+                )
+            }),
+        _ => false,
+    }
+}
+
+/// Headers whose values are never traced, since they carry credentials
+const REDACTED_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie"];
+
+/// Logs every request and response to stderr, without their bodies, for debugging proxies
+/// and mirrors
+struct TraceHttp {
+    /// The lowercased names of the configured extra headers, which are also redacted
+    secrets: Vec<String>,
+}
+
+impl TraceHttp {
+    fn value<'a>(&self, name: &str, value: &'a str) -> &'a str {
+        let name = name.to_ascii_lowercase();
+        if REDACTED_HEADERS.contains(&name.as_str()) || self.secrets.contains(&name) {
+            "<redacted>"
+        } else {
+            value
+        }
+    }
+}
+
+impl Middleware for TraceHttp {
+    fn handle(&self, request: Request, next: MiddlewareNext) -> Result<Response, Error> {
+        let mut trace = format!("> {} {}\n", request.method(), request.url());
+        for name in request.header_names() {
+            for value in request.all(&name) {
+                trace += &format!("> {}: {}\n", name, self.value(&name, value));
+            }
+        }
+
+        let start = Instant::now();
+        let res = next.handle(request);
+        let elapsed = start.elapsed().as_millis();
+        match &res {
+            Ok(response) | Err(Error::Status(_, response)) => {
+                trace += &format!(
+                    "< {} {} ({}ms)\n",
+                    response.status(),
+                    response.status_text(),
+                    elapsed
+                );
+                for name in response.headers_names() {
+                    for value in response.all(&name) {
+                        trace += &format!("< {}: {}\n", name, self.value(&name, value));
+                    }
+                }
+            }
+            Err(e) => trace += &format!("< {} ({}ms)\n", e, elapsed),
+        }
+
+        // Written at once, so that the traces of concurrent requests don't interleave
+        eprint!("{}", trace);
+        res
+    }
+}
+
+/// Adds the configured extra headers to requests bound for the mirror. They often carry
+/// credentials, so they must not be sent anywhere else, like GitHub during a self-update.
+struct MirrorHeaders {
+    mirrors: Vec<String>,
+    headers: Vec<(String, String)>,
+}
+
+impl Middleware for MirrorHeaders {
+    fn handle(&self, mut request: Request, next: MiddlewareNext) -> Result<Response, Error> {
+        if self.mirrors.iter().any(|m| request.url().starts_with(m)) {
+            for (name, value) in &self.headers {
+                request = request.set(name, value);
+            }
+        }
+        next.handle(request)
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::{self, Read};
+use std::time::{Duration, Instant};
+
+/// Receives updates about the progress of a download.
+///
+/// All methods have empty default implementations, so implementors only need to handle the
+/// events they care about.
+///
+/// ```
+/// use goup::Progress;
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// #[derive(Default)]
+/// struct Counter(AtomicU64);
+///
+/// impl Progress for Counter {
+///     fn advance(&self, bytes: u64) {
+///         self.0.fetch_add(bytes, Ordering::Relaxed);
+///     }
+/// }
+/// ```
+pub trait Progress: Send + Sync {
+    /// A download of `total` bytes is starting
+    fn start(&self, _total: u64) {}
+    /// Another `bytes` bytes have been received
+    fn advance(&self, _bytes: u64) {}
+    /// The download has stopped. `complete` is false if it was cut short.
+    fn finish(&self, _complete: bool) {}
+    /// Another `entries` files and directories have been unpacked from the archive
+    fn extracted(&self, _entries: u64) {}
+    /// A phase of work took `elapsed`. The phases are `index`, `download`, `checksum`,
+    /// `extract`, `build`, `enable`, and `state`, and each may be reported more than once,
+    /// such as when a download is retried.
+    fn phase(&self, _name: &'static str, _elapsed: Duration) {}
+    /// A build of Go from source printed `line`
+    fn output(&self, _line: &str) {}
+}
+
+/// A [`Progress`] that ignores every update
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
+
+/// A shim that will count the number of bytes read out of the given reader and report it
+/// to a [`Progress`].
+pub(crate) struct ByteCounter<'a, R: Read> {
+    inner: R,
+    progress: &'a dyn Progress,
+    read: u64,
+    total: u64,
+    stalled: bool,
+    waited: Duration,
+}
+
+impl<'a, R: Read> ByteCounter<'a, R> {
+    pub fn new(inner: R, total: u64, progress: &'a dyn Progress) -> Self {
+        progress.start(total);
+        Self {
+            inner,
+            progress,
+            read: 0,
+            total,
+            stalled: false,
+            waited: Duration::ZERO,
+        }
+    }
+
+    /// The number of bytes read so far
+    pub fn received(&self) -> u64 {
+        self.read
+    }
+
+    /// Whether a read gave up because no data arrived within the agent's read timeout
+    pub fn stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// How long was spent waiting on the inner reader
+    pub fn waited(&self) -> Duration {
+        self.waited
+    }
+}
+
+impl<R: Read> Read for ByteCounter<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let start = Instant::now();
+        let res = self.inner.read(buf);
+        self.waited += start.elapsed();
+        match &res {
+            Ok(size) => {
+                self.read += *size as u64;
+                self.progress.advance(*size as u64);
+            }
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+                ) =>
+            {
+                self.stalled = true;
+            }
+            Err(_) => {}
+        }
+        res
+    }
+}
+
+impl<R: Read> Drop for ByteCounter<'_, R> {
+    fn drop(&mut self) {
+        self.progress.finish(self.read >= self.total);
+    }
+}
@@ -0,0 +1,411 @@
+//! The version of Go a project asks for, in its `.go-version` file, its go.mod, or the
+//! `.tool-versions` file of asdf or mise
+
+use crate::error::GoupError;
+use crate::version::{GoVersion, VersionRequest};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// What a project needs from the version of Go that builds it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Need {
+    /// A release or series named by `.go-version` or `.tool-versions`, or a release named by
+    /// go.mod's `toolchain` line
+    Request(VersionRequest),
+    /// The oldest release that will do, from go.mod's `go` line
+    AtLeast(GoVersion),
+}
+
+impl Need {
+    /// Whether `version` will build the project
+    pub fn matches(&self, version: GoVersion) -> bool {
+        match self {
+            Self::Request(request) => request.matches(version),
+            Self::AtLeast(oldest) => version >= *oldest,
+        }
+    }
+
+    /// The release to use from `versions`, if any of them will do. The newest of them is
+    /// chosen, except that a project that only gives the oldest release it needs gets the
+    /// newest of the same series where there is one, rather than a whole new series.
+    ///
+    /// ```
+    /// use goup::project::Need;
+    ///
+    /// let v = |s: &str| s.parse().unwrap();
+    /// let versions = [v("go1.21.13"), v("go1.22.0"), v("go1.22.6"), v("go1.23.1")];
+    /// assert_eq!(Need::AtLeast(v("go1.22.1")).choose(versions), Some(v("go1.22.6")));
+    /// assert_eq!(Need::AtLeast(v("go1.22.7")).choose(versions), Some(v("go1.23.1")));
+    /// assert_eq!(Need::Request("go1.21".parse().unwrap()).choose(versions), Some(v("go1.21.13")));
+    /// ```
+    pub fn choose(&self, versions: impl IntoIterator<Item = GoVersion>) -> Option<GoVersion> {
+        let matching: Vec<_> = versions.into_iter().filter(|&v| self.matches(v)).collect();
+        let same_series = |v: &GoVersion| match self {
+            Self::AtLeast(oldest) => (v.major, v.minor) == (oldest.major, oldest.minor),
+            Self::Request(_) => true,
+        };
+        let newest = matching.iter().copied().max();
+        matching.into_iter().filter(same_series).max().or(newest)
+    }
+}
+
+impl fmt::Display for Need {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(VersionRequest::Latest) => f.write_str("the latest release"),
+            Self::Request(VersionRequest::Series { major, minor }) => {
+                write!(f, "a release of go{}.{}", major, minor)
+            }
+            Self::Request(VersionRequest::Exact(version)) => write!(f, "{}", version),
+            Self::AtLeast(oldest) => write!(f, "{} or newer", oldest),
+        }
+    }
+}
+
+/// What a project needs, and the file that says so
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Requirement {
+    pub need: Need,
+    pub file: PathBuf,
+}
+
+/// The version needed by the project that `dir` is in, found in the nearest directory up from
+/// it that has a `.go-version` file, a go.mod, or a `.tool-versions` file with a `golang` line.
+/// Within a directory, `.go-version` wins over go.mod, which wins over `.tool-versions`, and
+/// within go.mod a `toolchain` line wins over the `go` line. A go.mod with neither asks for
+/// nothing in particular.
+pub fn requirement(dir: &Path) -> Result<Option<Requirement>, GoupError> {
+    let files = project_files(dir)?;
+    let first = files.into_iter().next();
+    Ok(first.and_then(|(file, need)| need.map(|need| Requirement { need, file })))
+}
+
+/// The name of the file in which asdf and mise record the versions of each tool
+pub const TOOL_VERSIONS: &str = ".tool-versions";
+
+/// The names of the files that say what a project needs, in order of precedence
+pub const PROJECT_FILES: [&str; 3] = [".go-version", "go.mod", TOOL_VERSIONS];
+
+/// The project files of the nearest directory up from `dir` that has any, in order of
+/// precedence, with what each asks for. A `.tool-versions` file without Go in it is for other
+/// tools, and isn't one.
+fn project_files(dir: &Path) -> Result<Vec<(PathBuf, Option<Need>)>, GoupError> {
+    for dir in dir.ancestors() {
+        let mut found = Vec::new();
+        let parsers = [
+            parse_go_version as fn(&str) -> Result<Option<Need>, ()>,
+            parse_go_mod,
+            parse_tool_versions,
+        ];
+        for (name, parse) in PROJECT_FILES.into_iter().zip(parsers) {
+            let file = dir.join(name);
+            let text = match fs::read_to_string(&file) {
+                Ok(text) => text,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(GoupError::io("read", file)(e)),
+            };
+            match parse(&text) {
+                Ok(None) if name == TOOL_VERSIONS => {}
+                Ok(need) => found.push((file, need)),
+                Err(()) => return Err(GoupError::InvalidProjectFile(file)),
+            }
+        }
+        if !found.is_empty() {
+            return Ok(found);
+        }
+    }
+    Ok(Vec::new())
+}
+
+/// The variable `goup shell` sets to the version active within the shell
+pub const ACTIVE_SHELL: &str = "GOUP_ACTIVE_SHELL";
+
+/// Where the version to use can come from, from the highest precedence to the lowest
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Source {
+    /// The GOTOOLCHAIN environment variable, which go itself obeys
+    Gotoolchain,
+    /// [`ACTIVE_SHELL`], within a shell started by `goup shell`
+    Shell,
+    /// A project's `.go-version` file, go.mod, or `.tool-versions` file
+    File(PathBuf),
+    /// The project files looked for from the given directory up, where none were found
+    NoProject(PathBuf),
+    /// The machine's default version, set with `goup default` and kept in the `enabled` field
+    /// of versions.json
+    Enabled,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Gotoolchain => f.write_str("GOTOOLCHAIN"),
+            Self::Shell => f.write_str(ACTIVE_SHELL),
+            Self::File(path) => write!(f, "{}", path.display()),
+            Self::NoProject(dir) => {
+                write!(
+                    f,
+                    ".go-version, go.mod, or .tool-versions in {} or above",
+                    dir.display()
+                )
+            }
+            Self::Enabled => f.write_str("the default version"),
+        }
+    }
+}
+
+/// What a source held when it was consulted
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Found {
+    /// It asks for a version
+    Need(Need),
+    /// It asks for none, for the reason given
+    Nothing(String),
+}
+
+impl fmt::Display for Found {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Need(need) => need.fmt(f),
+            Self::Nothing(reason) => f.write_str(reason),
+        }
+    }
+}
+
+/// Everything besides a project's own files that decides which version is used
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Context {
+    /// The value of GOTOOLCHAIN
+    pub gotoolchain: Option<String>,
+    /// The value of [`ACTIVE_SHELL`]
+    pub shell: Option<String>,
+    /// The machine's default version
+    pub enabled: Option<GoVersion>,
+}
+
+impl Context {
+    /// The context given by goup's own environment, with `enabled` enabled
+    pub fn from_env(enabled: Option<GoVersion>) -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v| !v.is_empty());
+        Self {
+            gotoolchain: var("GOTOOLCHAIN"),
+            shell: var(ACTIVE_SHELL),
+            enabled,
+        }
+    }
+}
+
+/// The version used in a directory, and how it was decided
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resolution {
+    /// Every source consulted, from the highest precedence to the lowest
+    pub consulted: Vec<(Source, Found)>,
+    /// The position in `consulted` of the source that decided, which is the first to ask for
+    /// a version
+    pub decided_by: Option<usize>,
+    /// The installed version that satisfies it, if one does
+    pub version: Option<GoVersion>,
+}
+
+impl Resolution {
+    /// The source that decided, and what it asks for
+    pub fn decision(&self) -> Option<(&Source, Need)> {
+        match self.consulted.get(self.decided_by?)? {
+            (source, Found::Need(need)) => Some((source, *need)),
+            (_, Found::Nothing(_)) => None,
+        }
+    }
+
+    /// Why no version could be chosen: nothing asked for one, or nothing installed satisfies
+    /// what was asked for
+    pub fn error(&self) -> GoupError {
+        match self.decision() {
+            None => GoupError::NoVersionEnabled,
+            Some((Source::File(file), need)) => GoupError::RequirementNotInstalled {
+                file: file.clone(),
+                need,
+            },
+            Some((_, Need::Request(VersionRequest::Exact(version)))) => {
+                GoupError::VersionNotInstalled(version)
+            }
+            Some(_) => unreachable!("only project files ask for anything but a release"),
+        }
+    }
+}
+
+/// Decide the version of Go to use in `dir`, from among `installed`. This is the one place
+/// that decides it, so that every command agrees.
+///
+/// GOTOOLCHAIN wins when it names a release, since go switches to it whatever else asks for,
+/// followed by the version of a `goup shell`, the project's files as [`requirement`] reads
+/// them, and finally the machine's default version.
+///
+/// ```
+/// use goup::project::{resolve, Context, Source};
+///
+/// let v = |s: &str| s.parse().unwrap();
+/// let dir = tempfile::tempdir().unwrap();
+/// std::fs::write(dir.path().join(".go-version"), "1.21\n").unwrap();
+/// let context = Context { enabled: Some(v("go1.22.0")), ..Context::default() };
+/// let resolution = resolve(dir.path(), &context, &[v("go1.21.3"), v("go1.22.0")]).unwrap();
+/// assert_eq!(resolution.version, Some(v("go1.21.3")));
+/// assert_eq!(resolution.decision().unwrap().0, &Source::File(dir.path().join(".go-version")));
+/// ```
+pub fn resolve(
+    dir: &Path,
+    context: &Context,
+    installed: &[GoVersion],
+) -> Result<Resolution, GoupError> {
+    let not_set = || Found::Nothing("not set".to_string());
+    let gotoolchain = match context.gotoolchain.as_deref() {
+        None => not_set(),
+        Some(value) => parse_gotoolchain(value),
+    };
+    let shell = match context.shell.as_deref().map(str::parse) {
+        None => not_set(),
+        Some(Ok(version)) => Found::Need(Need::Request(VersionRequest::Exact(version))),
+        Some(Err(_)) => Found::Nothing("not a release of Go".to_string()),
+    };
+    let mut consulted = vec![(Source::Gotoolchain, gotoolchain), (Source::Shell, shell)];
+    let files = project_files(dir)?;
+    if files.is_empty() {
+        let none = Found::Nothing("none found".to_string());
+        consulted.push((Source::NoProject(dir.to_path_buf()), none));
+    }
+    for (file, need) in files {
+        let found = match need {
+            Some(need) => Found::Need(need),
+            None => Found::Nothing("has neither a go nor a toolchain line".to_string()),
+        };
+        consulted.push((Source::File(file), found));
+    }
+    let enabled = match context.enabled {
+        Some(version) => Found::Need(Need::Request(VersionRequest::Exact(version))),
+        None => Found::Nothing("none".to_string()),
+    };
+    consulted.push((Source::Enabled, enabled));
+
+    let decided_by = consulted
+        .iter()
+        .position(|(_, found)| matches!(found, Found::Need(_)));
+    let mut resolution = Resolution {
+        consulted,
+        decided_by,
+        version: None,
+    };
+    resolution.version = resolution
+        .decision()
+        .and_then(|(_, need)| need.choose(installed.iter().copied()));
+    Ok(resolution)
+}
+
+/// What GOTOOLCHAIN asks for. Only a release, alone or with `+path`, pins go to it; with
+/// `+auto`, go moves to a newer one when the project asks, so that is left to the project.
+fn parse_gotoolchain(value: &str) -> Found {
+    let (name, suffix) = match value.split_once('+') {
+        Some((name, suffix)) => (name, Some(suffix)),
+        None => (value, None),
+    };
+    match (name.parse::<GoVersion>(), suffix) {
+        (Ok(version), None | Some("path")) => {
+            Found::Need(Need::Request(VersionRequest::Exact(version)))
+        }
+        (Ok(_), _) => Found::Nothing(format!("{}, which only sets the oldest release", value)),
+        (Err(_), _) => Found::Nothing(format!("{}, which names no release", value)),
+    }
+}
+
+/// Read a `.go-version` file, which holds a single version, with or without the `go` prefix
+fn parse_go_version(text: &str) -> Result<Option<Need>, ()> {
+    let version = text
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .ok_or(())?;
+    parse_request(version).map(Some)
+}
+
+/// A version written with or without the `go` prefix
+fn parse_request(version: &str) -> Result<Need, ()> {
+    let version = if version.starts_with("go") {
+        version.to_string()
+    } else {
+        format!("go{}", version)
+    };
+    version.parse().map(Need::Request).map_err(|_| ())
+}
+
+/// Read the `golang` line of a `.tool-versions` file, or the `go` line that mise also accepts,
+/// like `golang 1.22.6`. Where a line lists several versions, the first is used, as asdf does.
+fn parse_tool_versions(text: &str) -> Result<Option<Need>, ()> {
+    let Some(line) = text.lines().map(tool_line).find(|l| is_go_line(l)) else {
+        return Ok(None);
+    };
+    let version = line.split_whitespace().nth(1).ok_or(())?;
+    parse_request(version).map(Some)
+}
+
+/// A line of a `.tool-versions` file, without its comment
+fn tool_line(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default().trim()
+}
+
+/// Whether a line of a `.tool-versions` file is the one for Go
+fn is_go_line(line: &str) -> bool {
+    matches!(line.split_whitespace().next(), Some("golang" | "go"))
+}
+
+/// Make `version` the one the project in `dir` asks for, writing it to the `.go-version` file
+/// there, or with `tool_versions`, to the `golang` line of its `.tool-versions` file. Any other
+/// tools in `.tool-versions` are left as they were. Returns the file written.
+pub fn set_local(
+    dir: &Path,
+    version: GoVersion,
+    tool_versions: bool,
+) -> Result<PathBuf, GoupError> {
+    let number = version.to_string();
+    let number = number.trim_start_matches("go");
+    let (file, text) = if tool_versions {
+        let file = dir.join(TOOL_VERSIONS);
+        let existing = match fs::read_to_string(&file) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(GoupError::io("read", file)(e)),
+        };
+        let golang = format!("golang {}", number);
+        let mut lines: Vec<_> = existing.lines().map(str::to_string).collect();
+        match lines.iter_mut().find(|l| is_go_line(tool_line(l))) {
+            Some(line) => *line = golang,
+            None => lines.push(golang),
+        }
+        (file, lines.join("\n") + "\n")
+    } else {
+        (dir.join(".go-version"), format!("{}\n", number))
+    };
+    fs::write(&file, text).map_err(GoupError::io("write", &file))?;
+    Ok(file)
+}
+
+/// Read the `toolchain` line of a go.mod, or failing that its `go` line. Versions written
+/// without a patch number, like `go 1.22`, ask for the first release of that series.
+fn parse_go_mod(text: &str) -> Result<Option<Need>, ()> {
+    let directive = |name: &str| {
+        text.lines().find_map(|line| {
+            let line = line.split("//").next()?.trim();
+            let value = line.strip_prefix(name)?;
+            value.starts_with(char::is_whitespace).then(|| value.trim())
+        })
+    };
+    if let Some(toolchain) = directive("toolchain").filter(|t| *t != "default") {
+        let version = toolchain.parse::<GoVersion>().map_err(|_| ())?;
+        return Ok(Some(Need::Request(VersionRequest::Exact(version))));
+    }
+    let Some(go) = directive("go") else {
+        return Ok(None);
+    };
+    let version = format!("go{}", go).parse().map_err(|_| ())?;
+    Ok(Some(Need::AtLeast(version)))
+}
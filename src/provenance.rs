@@ -0,0 +1,72 @@
+//! A record of where each installation came from, kept alongside it
+
+use crate::error::GoupError;
+use crate::remote::FileInfo;
+use crate::version::GoVersion;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// The name of the provenance file within each install directory
+pub const FILE_NAME: &str = ".goup-meta.json";
+
+/// Where an installation came from, saved as [`FILE_NAME`] in its install directory.
+///
+/// Installations made by older versions of goup have no provenance, and fields added later are
+/// given defaults, so that every installation can be described.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Provenance {
+    /// The version of goup that installed it
+    pub goup_version: String,
+    /// Seconds since the Unix epoch at which it was installed
+    pub installed_at: u64,
+    /// The URL that the archive was downloaded from
+    pub source: String,
+    /// The name of the archive, as published by go.dev
+    pub filename: String,
+    /// The SHA256 digest of the archive, which it was checked against before unpacking it
+    pub sha256: String,
+    pub os: String,
+    pub arch: String,
+    /// Whether tests and documentation were left out
+    pub minimal: bool,
+    /// The version of Go that built it, if it was built from source
+    pub bootstrap: Option<GoVersion>,
+}
+
+impl Provenance {
+    /// The provenance of an archive downloaded from `source` just now, by this version of goup
+    pub fn new(source: &str, file: &FileInfo, minimal: bool) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        Self {
+            goup_version: env!("CARGO_PKG_VERSION").to_string(),
+            installed_at: now.unwrap_or_default().as_secs(),
+            source: source.to_string(),
+            filename: file.filename.clone(),
+            sha256: file.sha256.to_lowercase(),
+            os: file.os.clone(),
+            arch: file.arch.clone(),
+            minimal,
+            bootstrap: None,
+        }
+    }
+
+    /// When the installation was made
+    pub fn installed_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.installed_at)
+    }
+
+    /// Read the provenance from the given install directory. An installation without any, or
+    /// with an unreadable file, gives `None`.
+    pub fn load(install_dir: &Path) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(install_dir.join(FILE_NAME)).ok()?).ok()
+    }
+
+    pub fn store(&self, install_dir: &Path) -> Result<(), GoupError> {
+        let path = install_dir.join(FILE_NAME);
+        let text = serde_json::to_string_pretty(self).expect("provenance can always be serialized");
+        fs::write(&path, text).map_err(GoupError::io("write", path))
+    }
+}
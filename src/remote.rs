@@ -0,0 +1,738 @@
+use crate::bundle;
+use crate::chunked;
+use crate::config::Config;
+use crate::error::GoupError;
+use crate::mirror;
+use crate::progress::{ByteCounter, Progress};
+use crate::response::{self, Received};
+use crate::version::GoVersion;
+use flate2::read::GzDecoder;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Read, Seek, Write};
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use tar::Archive;
+use ureq::Agent;
+use zip::ZipArchive;
+
+/// A release in the index. Its files are parsed one at a time, so that one go.dev describes in
+/// a way goup doesn't understand doesn't spoil the rest.
+#[derive(Debug, Deserialize)]
+struct VersionInfo {
+    version: GoVersion,
+    // stable: bool,
+    #[serde(default)]
+    files: Vec<Value>,
+}
+
+/// A downloadable archive, as described by go.dev. Fields that goup can do without are
+/// optional, and fields it doesn't know are ignored.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FileInfo {
+    pub filename: String,
+    pub os: String,
+    pub arch: String,
+    #[serde(default)]
+    pub version: String,
+    pub sha256: String,
+    /// The size of the archive in bytes, or 0 if the index doesn't give it
+    #[serde(default, deserialize_with = "null_as_default")]
+    pub size: u64,
+    /// `archive`, `installer`, or `source`, or empty if the index doesn't say
+    #[serde(default)]
+    pub kind: String,
+}
+
+/// Deserialize a `null` as the type's default, as for a missing field
+fn null_as_default<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Default + Deserialize<'de>,
+{
+    Ok(Option::<T>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// The archives in an index for this platform, along with the entries that had to be skipped
+#[derive(Debug, Default)]
+pub(crate) struct ParsedIndex {
+    pub versions: BTreeMap<GoVersion, FileInfo>,
+    /// A description of each release or file that couldn't be understood
+    pub skipped: Vec<String>,
+}
+
+/// The top-level directories of a Go release left out of minimal installs. They hold Go's own
+/// test suite and documentation, and make up a large share of the release.
+pub const MINIMAL_OMITTED: &[&str] = &["test", "doc", "api"];
+
+/// Get the set of available versions of Go from Go's website, or the configured mirror.
+pub fn available_go_versions(
+    agent: &Agent,
+    config: &Config,
+) -> Result<BTreeMap<GoVersion, FileInfo>, GoupError> {
+    let url = config.index_url();
+    let response = agent
+        .get(&url)
+        .call()
+        .map_err(|e| GoupError::network(&url, e))?;
+    let (status, content_type) = (response.status(), response.header("Content-Type"));
+    let content_type = content_type.map(str::to_string);
+    let body = response
+        .into_string()
+        .map_err(|e| GoupError::network(&url, e))?;
+    response::check_json(&url, Some(status), content_type.as_deref(), &body)?;
+    Ok(parse_index(&url, &body)?.versions)
+}
+
+/// Parse the JSON index served at `url`, keeping the archive for this platform from each
+/// version
+pub(crate) fn parse_index(url: &str, body: &str) -> Result<ParsedIndex, GoupError> {
+    let arch = arch().ok_or(GoupError::Unsupported {
+        os: env::consts::OS,
+        arch: env::consts::ARCH,
+    })?;
+    let ours = |os: &str, a: &str| os == env::consts::OS && a == arch;
+
+    let parsed = parse_releases(url, body)?;
+    let versions = parsed
+        .releases
+        .into_iter()
+        .filter_map(|release| {
+            release
+                .files
+                .into_iter()
+                .find(|f| ours(&f.os, &f.arch) && f.is_archive())
+                .map(|f| (release.version, f))
+        })
+        .collect();
+    // Files for other platforms are never used, so whether they make sense doesn't matter
+    let skipped = parsed
+        .skipped
+        .into_iter()
+        .filter(|s| s.platform.as_ref().is_none_or(|(os, a)| ours(os, a)))
+        .map(|s| s.description)
+        .collect();
+    Ok(ParsedIndex { versions, skipped })
+}
+
+/// A release in the index, with every file published for it
+#[derive(Debug, Clone)]
+pub struct Release {
+    pub version: GoVersion,
+    pub files: Vec<FileInfo>,
+}
+
+impl FileInfo {
+    /// Whether this is an archive that goup can install, rather than an installer or source
+    pub fn is_archive(&self) -> bool {
+        matches!(self.kind.as_str(), "" | "archive")
+    }
+
+    /// Whether this is a zip archive, as published for Windows, rather than a gzipped tarball
+    pub fn is_zip(&self) -> bool {
+        self.filename.ends_with(".zip")
+    }
+
+    /// Whether this is the release's source code, which can be built where no archive is
+    /// published
+    pub fn is_source(&self) -> bool {
+        self.kind == "source"
+    }
+
+    /// Whether this file is for the platform goup is running on
+    pub fn is_for_this_platform(&self) -> bool {
+        self.os == env::consts::OS && arch() == Some(self.arch.as_str())
+    }
+}
+
+/// Every release in an index, along with the entries that had to be skipped
+#[derive(Debug, Default)]
+pub(crate) struct ParsedReleases {
+    pub releases: Vec<Release>,
+    pub skipped: Vec<Skipped>,
+}
+
+/// A release or file in the index that couldn't be understood
+#[derive(Debug)]
+pub(crate) struct Skipped {
+    /// The OS and architecture of a skipped file, if they could be made out
+    platform: Option<(String, String)>,
+    description: String,
+}
+
+/// Parse the JSON index served at `url`, keeping every file of every release.
+///
+/// Releases and files that can't be understood are skipped, so that a change to the format
+/// doesn't make every version unavailable. Only if no release at all can be understood is the
+/// index considered invalid.
+pub(crate) fn parse_releases(url: &str, body: &str) -> Result<ParsedReleases, GoupError> {
+    let invalid = |source: io::Error| response::invalid_index(url, body, source);
+
+    let releases = serde_json::from_str::<Vec<Value>>(body).map_err(|e| invalid(e.into()))?;
+    let mut parsed = ParsedReleases::default();
+    for (i, release) in releases.iter().enumerate() {
+        let group = match VersionInfo::deserialize(release) {
+            Ok(group) => group,
+            Err(e) => {
+                let name = release["version"].as_str().map_or_else(
+                    || format!("release {}", i + 1),
+                    |v| format!("release {}", v),
+                );
+                parsed.skipped.push(Skipped {
+                    platform: None,
+                    description: format!("{}: {}", name, e),
+                });
+                continue;
+            }
+        };
+
+        let mut files = Vec::new();
+        for file in &group.files {
+            match FileInfo::deserialize(file) {
+                Ok(f) => files.push(f),
+                Err(e) => {
+                    let platform = file["os"].as_str().zip(file["arch"].as_str());
+                    let name = file["filename"].as_str().unwrap_or("a file");
+                    parsed.skipped.push(Skipped {
+                        platform: platform.map(|(os, arch)| (os.to_string(), arch.to_string())),
+                        description: format!("{} of {}: {}", name, group.version, e),
+                    });
+                }
+            }
+        }
+        parsed.releases.push(Release {
+            version: group.version,
+            files,
+        });
+    }
+
+    if parsed.releases.is_empty() && !releases.is_empty() {
+        let first = parsed
+            .skipped
+            .first()
+            .map(|s| s.description.clone())
+            .unwrap_or_default();
+        return Err(invalid(io::Error::new(io::ErrorKind::InvalidData, first)));
+    }
+    Ok(parsed)
+}
+
+/// Download the given archive from `mirror` and unpack it into `target`, returning the number of
+/// bytes left out because of [`Config::minimal`]. If `keep` is given, a copy of the archive is
+/// saved there once it has arrived intact.
+///
+/// The archive is hashed as it arrives, and with `verify`, nothing is left in `target` unless
+/// it matches the checksum in `file`. With [`Config::download_chunks`] and somewhere to keep
+/// the archive, it is downloaded in ranges over several connections, and checked before being
+/// unpacked. Servers that don't serve ranges are downloaded from in one piece, as usual.
+#[allow(clippy::too_many_arguments)]
+pub fn download_archive(
+    agent: &Agent,
+    config: &Config,
+    mirror: &str,
+    file: &FileInfo,
+    target: &Path,
+    keep: Option<&Path>,
+    verify: bool,
+    progress: &dyn Progress,
+) -> Result<u64, GoupError> {
+    let chunks = match file.size {
+        0 => 1,
+        _ => config.download_chunks(),
+    };
+    if let (Some(keep), connections @ 2..) = (keep, chunks) {
+        let chunked = download_chunked(
+            agent,
+            config,
+            mirror,
+            file,
+            target,
+            keep,
+            connections,
+            verify,
+            progress,
+        )?;
+        if let Some(omitted) = chunked {
+            return Ok(omitted);
+        }
+    }
+
+    if file.is_zip() {
+        return download_zip(agent, config, mirror, file, target, keep, verify, progress);
+    }
+
+    let partial = keep.map(|path| with_suffix(path, ".partial"));
+    // The copy is only a convenience, so a cache that can't be written doesn't stop the install
+    let mut copy = partial.as_deref().and_then(|path| {
+        fs::create_dir_all(path.parent()?).ok()?;
+        File::create(path).ok()
+    });
+    let res = receive(
+        agent, config, mirror, file, target, &mut copy, verify, progress,
+    );
+
+    if let (Some(partial), Some(keep)) = (&partial, keep) {
+        let saved = res.is_ok() && copy.take().is_some_and(|mut f| f.flush().is_ok());
+        if !saved || fs::rename(partial, keep).is_err() {
+            let _ = fs::remove_file(partial);
+        }
+    }
+    res
+}
+
+/// `path` with `suffix` added to its filename
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+/// Download the zip archive from `mirror` into `keep`, or beside `target` if it isn't to be
+/// kept, then unpack it into `target`. A zip archive lists what it holds at its end, so unlike a
+/// tarball it can't be unpacked as it arrives.
+#[allow(clippy::too_many_arguments)]
+fn download_zip(
+    agent: &Agent,
+    config: &Config,
+    mirror: &str,
+    file: &FileInfo,
+    target: &Path,
+    keep: Option<&Path>,
+    verify: bool,
+    progress: &dyn Progress,
+) -> Result<u64, GoupError> {
+    let url = mirror::archive_url(mirror, &file.filename);
+    let saved = keep.map_or_else(|| with_suffix(target, ".zip"), Path::to_path_buf);
+    // Without a checksum, download_file takes whatever arrives
+    let unverified = FileInfo {
+        sha256: String::new(),
+        ..file.clone()
+    };
+    download_file(
+        agent,
+        config,
+        &url,
+        if verify { file } else { &unverified },
+        &saved,
+        progress,
+    )?;
+
+    let start = Instant::now();
+    let res = unpack_saved(config, &saved, target, progress);
+    progress.phase("extract", start.elapsed());
+    if keep.is_none() {
+        let _ = fs::remove_file(&saved);
+    }
+    res
+}
+
+/// Download the archive from `mirror` over `connections` connections into `keep`, then unpack
+/// it into `target`, once it is checked with `verify`. Returns `None` if the mirror doesn't
+/// serve ranges of the archive.
+#[allow(clippy::too_many_arguments)]
+fn download_chunked(
+    agent: &Agent,
+    config: &Config,
+    mirror: &str,
+    file: &FileInfo,
+    target: &Path,
+    keep: &Path,
+    connections: usize,
+    verify: bool,
+    progress: &dyn Progress,
+) -> Result<Option<u64>, GoupError> {
+    let url = mirror::archive_url(mirror, &file.filename);
+    // Kept apart from the finished archive until it is verified, and left behind on failure so
+    // that the next attempt only fetches the chunks that are missing
+    let parts = with_suffix(keep, ".chunked");
+    let start = Instant::now();
+    if !chunked::download(
+        agent,
+        config,
+        &url,
+        file.size,
+        &parts,
+        connections,
+        progress,
+    )? {
+        return Ok(None);
+    }
+    progress.phase("download", start.elapsed());
+
+    let actual = File::open(&parts)
+        .and_then(|mut f| bundle::sha256(&mut f))
+        .map_err(GoupError::io("read", &parts))?;
+    if verify && !actual.eq_ignore_ascii_case(&file.sha256) {
+        let _ = fs::remove_file(&parts);
+        return Err(GoupError::ChecksumMismatch {
+            file: file.filename.clone(),
+            expected: file.sha256.clone(),
+            actual,
+        });
+    }
+    fs::rename(&parts, keep).map_err(GoupError::io("move", keep))?;
+
+    let start = Instant::now();
+    let omitted = unpack_saved(config, keep, target, progress)?;
+    progress.phase("extract", start.elapsed());
+    Ok(Some(omitted))
+}
+
+/// Stream the archive from `mirror` into `target`, writing a copy of it to `copy`
+#[allow(clippy::too_many_arguments)]
+fn receive(
+    agent: &Agent,
+    config: &Config,
+    mirror: &str,
+    file: &FileInfo,
+    target: &Path,
+    copy: &mut Option<File>,
+    verify: bool,
+    progress: &dyn Progress,
+) -> Result<u64, GoupError> {
+    let url = mirror::archive_url(mirror, &file.filename);
+    let response = agent
+        .get(&url)
+        .call()
+        .map_err(|e| GoupError::network(&url, e))?;
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok());
+    let response = check_archive(&url, response)?;
+    let mut digest = Sha256::new();
+    let tee = Tee {
+        inner: response,
+        copy,
+        digest: &mut digest,
+    };
+
+    let start = Instant::now();
+    let mut counter = ByteCounter::new(tee, file.size, progress);
+    let res = unpack(&mut counter, target, omitted_dirs(config), progress);
+    let received = counter.received();
+    // The archive is unpacked as it arrives, so whatever time wasn't spent waiting for data
+    // went on extracting it
+    progress.phase("download", counter.waited());
+    progress.phase("extract", start.elapsed().saturating_sub(counter.waited()));
+
+    if counter.stalled() {
+        return Err(GoupError::DownloadStalled {
+            url,
+            seconds: config.stall_timeout().as_secs(),
+        });
+    }
+    drop(counter);
+
+    // A connection dropped at just the wrong moment can leave an archive that unpacks
+    // without complaint, so make sure everything that was promised actually arrived
+    let listed = Some(file.size).filter(|&size| size > 0);
+    for expected in [listed, content_length].into_iter().flatten() {
+        if received != expected {
+            return Err(GoupError::SizeMismatch {
+                url,
+                expected,
+                received,
+            });
+        }
+    }
+    let omitted = res.map_err(GoupError::io("unpack archive into", target))?;
+    let actual = format!("{:x}", digest.finalize());
+    if verify && !actual.eq_ignore_ascii_case(&file.sha256) {
+        return Err(GoupError::ChecksumMismatch {
+            file: file.filename.clone(),
+            expected: file.sha256.clone(),
+            actual,
+        });
+    }
+    Ok(omitted)
+}
+
+/// The body of `response`, unless it is plainly not a gzipped archive, such as a web page sent
+/// by a captive portal or a proxy. Those are refused before the gzip decoder can fail on them
+/// with a message that says nothing of what arrived.
+fn check_archive(url: &str, response: ureq::Response) -> Result<impl Read, GoupError> {
+    if response
+        .header("Content-Type")
+        .is_some_and(response::is_html)
+    {
+        return Err(GoupError::NotAnArchive {
+            url: url.to_string(),
+            received: Received::read(response),
+        });
+    }
+    let (status, content_type) = (response.status(), response.header("Content-Type"));
+    let content_type = content_type.map(str::to_string);
+    let mut reader = BufReader::new(response.into_reader());
+    let start = reader.fill_buf().map_err(|e| GoupError::network(url, e))?;
+    // However few bytes arrived first, they must be the start of the gzip header
+    if start.is_empty() || !GZIP_MAGIC.starts_with(&start[..start.len().min(2)]) {
+        let mut body = Vec::new();
+        let _ = reader.take(4096).read_to_end(&mut body);
+        return Err(GoupError::NotAnArchive {
+            url: url.to_string(),
+            received: Received::new(Some(status), content_type.as_deref(), &body),
+        });
+    }
+    Ok(reader)
+}
+
+/// The bytes every gzipped file starts with
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// Unpack an archive saved by an earlier download into `target`, returning the number of bytes
+/// left out because of [`Config::minimal`]
+pub(crate) fn unpack_saved(
+    config: &Config,
+    archive: &Path,
+    target: &Path,
+    progress: &dyn Progress,
+) -> Result<u64, GoupError> {
+    let mut file = File::open(archive).map_err(GoupError::io("open", archive))?;
+    unpack_file(&mut file, target, omitted_dirs(config), progress)
+        .map_err(GoupError::io("unpack archive into", target))
+}
+
+/// Download the source release `file` from `mirror` into `dest`, checking it against its
+/// checksum. Nothing is left at `dest` unless it arrived intact.
+pub(crate) fn download_source(
+    agent: &Agent,
+    config: &Config,
+    mirror: &str,
+    file: &FileInfo,
+    dest: &Path,
+    progress: &dyn Progress,
+) -> Result<(), GoupError> {
+    let url = mirror::archive_url(mirror, &file.filename);
+    download_file(agent, config, &url, file, dest, progress)
+}
+
+/// Download `file` from `url` into `dest`, checking it against its checksum unless it has
+/// none, as for archives installed with `--no-verify`. Nothing is left at `dest` unless it
+/// arrived intact.
+pub(crate) fn download_file(
+    agent: &Agent,
+    config: &Config,
+    url: &str,
+    file: &FileInfo,
+    dest: &Path,
+    progress: &dyn Progress,
+) -> Result<(), GoupError> {
+    let url = url.to_string();
+    let response = agent
+        .get(&url)
+        .call()
+        .map_err(|e| GoupError::network(&url, e))?;
+    // Other kinds of file may be downloaded here, but never a web page
+    if response
+        .header("Content-Type")
+        .is_some_and(response::is_html)
+    {
+        return Err(GoupError::NotAnArchive {
+            url,
+            received: Received::read(response),
+        });
+    }
+    if let Some(dir) = dest.parent() {
+        fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+    }
+    let partial = with_suffix(dest, ".partial");
+    let mut out = File::create(&partial).map_err(GoupError::io("create", &partial))?;
+
+    let start = Instant::now();
+    let mut counter = ByteCounter::new(response.into_reader(), file.size, progress);
+    let res = io::copy(&mut counter, &mut out);
+    let (received, stalled) = (counter.received(), counter.stalled());
+    drop(counter);
+    progress.phase("download", start.elapsed());
+    let checked = match res {
+        Err(_) if stalled => Err(GoupError::DownloadStalled {
+            url: url.clone(),
+            seconds: config.stall_timeout().as_secs(),
+        }),
+        Err(e) => Err(GoupError::network(&url, e)),
+        Ok(_) if file.size > 0 && received != file.size => Err(GoupError::SizeMismatch {
+            url: url.clone(),
+            expected: file.size,
+            received,
+        }),
+        Ok(_) if file.sha256.is_empty() => Ok(()),
+        Ok(_) => File::open(&partial)
+            .and_then(|mut f| bundle::sha256(&mut f))
+            .map_err(GoupError::io("read", &partial))
+            .and_then(|actual| {
+                if actual.eq_ignore_ascii_case(&file.sha256) {
+                    Ok(())
+                } else {
+                    Err(GoupError::ChecksumMismatch {
+                        file: file.filename.clone(),
+                        expected: file.sha256.clone(),
+                        actual,
+                    })
+                }
+            }),
+    };
+    if let Err(e) = checked {
+        let _ = fs::remove_file(&partial);
+        return Err(e);
+    }
+    fs::rename(&partial, dest).map_err(GoupError::io("move", dest))
+}
+
+/// Unpack a source release saved at `archive` into `target`, leaving nothing out, since the
+/// build may need any of it
+pub(crate) fn unpack_source(
+    archive: &Path,
+    target: &Path,
+    progress: &dyn Progress,
+) -> Result<(), GoupError> {
+    let mut file = File::open(archive).map_err(GoupError::io("open", archive))?;
+    unpack_file(&mut file, target, &[], progress)
+        .map(|_| ())
+        .map_err(GoupError::io("unpack archive into", target))
+}
+
+/// The top-level directories of the `go` tree to leave out when unpacking
+fn omitted_dirs(config: &Config) -> &'static [&'static str] {
+    if config.minimal() {
+        MINIMAL_OMITTED
+    } else {
+        &[]
+    }
+}
+
+/// A reader that writes a copy of everything read through it, and hashes it. If the copy can't
+/// be written, it is abandoned, and reading carries on regardless.
+struct Tee<'a, R> {
+    inner: R,
+    copy: &'a mut Option<File>,
+    digest: &'a mut Sha256,
+}
+
+impl<R: Read> Read for Tee<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let size = self.inner.read(buf)?;
+        self.digest.update(&buf[..size]);
+        if let Some(copy) = self.copy {
+            if copy.write_all(&buf[..size]).is_err() {
+                *self.copy = None;
+            }
+        }
+        Ok(size)
+    }
+}
+
+/// The bytes every zip archive starts with
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+/// Unpack the archive in `file` into `target` as [`unpack`] does, whether it is a gzipped
+/// tarball or a zip archive
+fn unpack_file(
+    file: &mut File,
+    target: &Path,
+    omit: &[&str],
+    progress: &dyn Progress,
+) -> io::Result<u64> {
+    let mut magic = [0; 4];
+    let is_zip = file.read_exact(&mut magic).is_ok() && magic == ZIP_MAGIC;
+    file.rewind()?;
+    if is_zip {
+        unpack_zip(file, target, omit, progress)
+    } else {
+        unpack(file, target, omit, progress)
+    }
+}
+
+/// Unpack the gzipped tarball into `target`, except for the entries within the `omit`ted
+/// top-level directories of the `go` tree, returning the number of bytes left out. Each entry
+/// unpacked is reported to `progress`.
+///
+/// The stream is read through to its end so that the gzip trailer is checked and every byte
+/// of the download is counted.
+fn unpack(
+    reader: &mut impl Read,
+    target: &Path,
+    omit: &[&str],
+    progress: &dyn Progress,
+) -> io::Result<u64> {
+    fs::create_dir_all(target)?;
+    let mut archive = Archive::new(GzDecoder::new(&mut *reader));
+    let mut omitted = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if is_omitted(&entry.path()?, omit) {
+            omitted += entry.header().size()?
+        } else {
+            entry.unpack_in(target)?;
+            progress.extracted(1);
+        }
+    }
+    io::copy(&mut archive.into_inner(), &mut io::sink())?;
+    io::copy(reader, &mut io::sink())?;
+    Ok(omitted)
+}
+
+/// Unpack the zip archive in `file` into `target`, as [`unpack`] does a tarball. Entries whose
+/// names would take them outside of `target` are skipped.
+fn unpack_zip(
+    file: &mut File,
+    target: &Path,
+    omit: &[&str],
+    progress: &dyn Progress,
+) -> io::Result<u64> {
+    fs::create_dir_all(target)?;
+    let mut archive = ZipArchive::new(file)?;
+    let mut omitted = 0;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i)?;
+        let Some(path) = entry.enclosed_name() else {
+            continue;
+        };
+        if is_omitted(&path, omit) {
+            omitted += entry.size();
+            continue;
+        }
+        let dest = target.join(path);
+        if entry.is_dir() {
+            fs::create_dir_all(&dest)?;
+        } else {
+            if let Some(dir) = dest.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            io::copy(&mut entry, &mut File::create(&dest)?)?;
+            #[cfg(unix)]
+            if let Some(mode) = entry.unix_mode() {
+                use std::os::unix::fs::PermissionsExt;
+                fs::set_permissions(&dest, fs::Permissions::from_mode(mode & 0o777))?;
+            }
+        }
+        progress.extracted(1);
+    }
+    Ok(omitted)
+}
+
+/// Whether the entry at `path` in an archive is within one of the `omit`ted top-level
+/// directories of the `go` tree
+fn is_omitted(path: &Path, omit: &[&str]) -> bool {
+    let mut components = path.components().map(|c| c.as_os_str());
+    let top = match (components.next(), components.next()) {
+        (Some(root), Some(top)) if root == "go" => top.to_str(),
+        _ => None,
+    };
+    top.is_some_and(|top| omit.contains(&top))
+}
+
+/// What Go calls the architecture goup is running on, if Go supports it
+pub fn arch() -> Option<&'static str> {
+    match env::consts::ARCH {
+        "x86" => Some("386"),
+        "x86_64" => Some("amd64"),
+        "aarch64" => Some("arm64"),
+        "powerpc64" => Some("ppc64le"),
+        "s390x" => Some("s390x"),
+        _ => None,
+    }
+}
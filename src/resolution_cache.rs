@@ -0,0 +1,139 @@
+//! Resolutions remembered per directory, so that deciding again which version of Go to use in
+//! a directory only needs to check that nothing it depended on has changed
+//!
+//! Each entry is a small file under goup's state directory, named for the directory it was
+//! made in. It records the modification time and length of every file that could have changed
+//! the outcome: versions.json, and each project file looked for from the directory up to the
+//! one where some were found, including those that didn't exist. If any of them differ, or
+//! GOTOOLCHAIN or the version of a `goup shell` do, the directory is resolved afresh.
+
+use crate::project::{Context, Resolution, Source, PROJECT_FILES};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// The variable that, set to anything, makes goup resolve every directory afresh
+pub const DISABLE_VAR: &str = "GOUP_SHIM_DISABLE_CACHE";
+
+/// A file's modification time in nanoseconds and its length, or nothing if it didn't exist
+type Stamp = Option<(u128, u64)>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    dir: PathBuf,
+    gotoolchain: Option<String>,
+    shell: Option<String>,
+    inputs: Vec<(PathBuf, Stamp)>,
+    resolution: Resolution,
+}
+
+/// The cached resolutions in a directory
+pub(crate) struct ResolutionCache {
+    dir: PathBuf,
+}
+
+impl ResolutionCache {
+    pub fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Whether the cache may be used at all, which it may not when [`DISABLE_VAR`] is set
+    pub fn enabled() -> bool {
+        std::env::var_os(DISABLE_VAR).is_none_or(|v| v.is_empty())
+    }
+
+    /// The resolution made in `dir` under `context`, if one was and none of its inputs have
+    /// changed since. Only absolute directories are cached, since a relative one means
+    /// something else from elsewhere.
+    pub fn get(&self, dir: &Path, context: &Context) -> Option<Resolution> {
+        if !dir.is_absolute() {
+            return None;
+        }
+        let text = fs::read_to_string(self.path(dir)).ok()?;
+        let entry: Entry = serde_json::from_str(&text).ok()?;
+        let fresh = entry.dir == dir
+            && entry.gotoolchain == context.gotoolchain
+            && entry.shell == context.shell
+            && entry.inputs.iter().all(|(path, was)| stamp(path) == *was);
+        fresh.then_some(entry.resolution)
+    }
+
+    /// Remember the resolution made in `dir`, unless one of its inputs changed after
+    /// `started`, in which case it may have been read half-way through the change. Failures
+    /// are ignored, since the cache is only an optimization.
+    pub fn put(
+        &self,
+        dir: &Path,
+        context: &Context,
+        version_file: &Path,
+        started: SystemTime,
+        resolution: &Resolution,
+    ) {
+        if !dir.is_absolute() {
+            return;
+        }
+        let since_epoch = |time: SystemTime| time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let started = since_epoch(started).as_nanos();
+        let inputs: Vec<_> = inputs(dir, version_file, resolution)
+            .into_iter()
+            .map(|path| {
+                let stamp = stamp(&path);
+                (path, stamp)
+            })
+            .collect();
+        if inputs
+            .iter()
+            .any(|(_, stamp)| stamp.is_some_and(|(modified, _)| modified >= started))
+        {
+            return;
+        }
+        let entry = Entry {
+            dir: dir.to_path_buf(),
+            gotoolchain: context.gotoolchain.clone(),
+            shell: context.shell.clone(),
+            inputs,
+            resolution: resolution.clone(),
+        };
+        let _ = fs::create_dir_all(&self.dir);
+        if let Ok(text) = serde_json::to_string(&entry) {
+            let path = self.path(dir);
+            let staging = path.with_extension(format!("{}.tmp", std::process::id()));
+            if fs::write(&staging, text).is_ok() && fs::rename(&staging, &path).is_err() {
+                let _ = fs::remove_file(&staging);
+            }
+        }
+    }
+
+    /// The file holding the entry for `dir`
+    fn path(&self, dir: &Path) -> PathBuf {
+        let digest = Sha256::digest(dir.to_string_lossy().as_bytes());
+        self.dir.join(format!("{:x}.json", digest))
+    }
+}
+
+/// Every file whose contents, or whether it exists, could change the resolution made in `dir`
+fn inputs(dir: &Path, version_file: &Path, resolution: &Resolution) -> Vec<PathBuf> {
+    let project = resolution
+        .consulted
+        .iter()
+        .find_map(|(source, _)| match source {
+            Source::File(file) => file.parent(),
+            _ => None,
+        });
+    let mut paths = vec![version_file.to_path_buf()];
+    for ancestor in dir.ancestors() {
+        paths.extend(PROJECT_FILES.iter().map(|name| ancestor.join(name)));
+        if Some(ancestor) == project {
+            break;
+        }
+    }
+    paths
+}
+
+fn stamp(path: &Path) -> Stamp {
+    let metadata = fs::metadata(path).ok()?;
+    let modified = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+    Some((modified.as_nanos(), metadata.len()))
+}
@@ -0,0 +1,240 @@
+//! Describing responses that weren't what goup asked for, such as the login page of a captive
+//! portal or a proxy's error page sent in place of go.dev's index or an archive
+
+use crate::error::GoupError;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::io::{self, Read};
+
+/// How much of an unexpected body is read to describe it
+const MAX_SNIFF_BYTES: u64 = 16 * 1024;
+
+/// The longest excerpt of a body quoted in an error
+const MAX_EXCERPT_CHARS: usize = 100;
+
+/// Words that the login pages of captive portals tend to use, and go.dev and its mirrors don't
+const LOGIN_WORDS: &[&str] = &[
+    "captive",
+    "portal",
+    "hotspot",
+    "wi-fi",
+    "wifi",
+    "log in",
+    "login",
+    "sign in",
+    "accept the terms",
+];
+
+/// Words that error pages from proxies, CDNs, and web servers tend to use
+const ERROR_WORDS: &[&str] = &[
+    "error",
+    "not found",
+    "forbidden",
+    "unavailable",
+    "bad gateway",
+    "timed out",
+    "denied",
+    "blocked",
+];
+
+/// What a response seems to be, judging by its headers and body
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// A web page asking to log in, as captive portals on hotel and airport Wi-Fi send
+    LoginPage,
+    /// A web page saying something went wrong, as proxies and CDNs send
+    ErrorPage,
+    /// Some other web page
+    Page,
+    /// Nothing at all
+    Empty,
+    /// Something other than a web page
+    Other,
+}
+
+/// A response that goup couldn't use, as far as it is worth describing in an error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Received {
+    /// The HTTP status, where it is known
+    pub status: Option<u16>,
+    pub content_type: Option<String>,
+    pub kind: Kind,
+    /// The start of the body as text, or the title of a web page, without markup or control
+    /// characters
+    pub excerpt: String,
+}
+
+impl Received {
+    /// Describe a response from what is known of it
+    pub fn new(status: Option<u16>, content_type: Option<&str>, body: &[u8]) -> Self {
+        let text = String::from_utf8_lossy(body);
+        let html = content_type.is_some_and(is_html) || looks_like_html(&text);
+        let excerpt = if html {
+            title(&text).unwrap_or_else(|| strip_tags(&text))
+        } else {
+            text.to_string()
+        };
+        let kind = match (html, text.trim().is_empty()) {
+            (_, true) => Kind::Empty,
+            (false, false) => Kind::Other,
+            (true, false) => {
+                let lower = text.to_lowercase();
+                if LOGIN_WORDS.iter().any(|w| lower.contains(w)) {
+                    Kind::LoginPage
+                } else if status.is_some_and(|s| s >= 400)
+                    || ERROR_WORDS.iter().any(|w| lower.contains(w))
+                {
+                    Kind::ErrorPage
+                } else {
+                    Kind::Page
+                }
+            }
+        };
+        Self {
+            status,
+            content_type: content_type.map(str::to_string),
+            kind,
+            excerpt: sanitize(&excerpt),
+        }
+    }
+
+    /// Describe `response`, reading the start of its body
+    pub(crate) fn read(response: ureq::Response) -> Self {
+        let status = response.status();
+        let content_type = response.header("Content-Type").map(str::to_string);
+        let mut body = Vec::new();
+        // Whatever can be read is enough to describe it
+        let _ = response
+            .into_reader()
+            .take(MAX_SNIFF_BYTES)
+            .read_to_end(&mut body);
+        Self::new(Some(status), content_type.as_deref(), &body)
+    }
+
+    /// Whether it is plainly not what goup asked for: a web page, or nothing at all
+    pub fn is_page(&self) -> bool {
+        self.kind != Kind::Other
+    }
+}
+
+impl fmt::Display for Received {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self.kind {
+            Kind::LoginPage => "a login page, like those of captive portals",
+            Kind::ErrorPage => "an error page",
+            Kind::Page => "a web page",
+            Kind::Empty => "an empty response",
+            Kind::Other => "a response",
+        })?;
+        let details: Vec<_> = self
+            .status
+            .map(|s| format!("HTTP {}", s))
+            .into_iter()
+            .chain(self.content_type.clone())
+            .collect();
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        if !self.excerpt.is_empty() {
+            write!(f, ": \"{}\"", self.excerpt)?;
+        }
+        Ok(())
+    }
+}
+
+/// Read `response` as JSON from `url`, as an index of versions or releases. Web pages are
+/// refused without trying, whatever their status.
+pub(crate) fn json<T: DeserializeOwned>(
+    url: &str,
+    response: ureq::Response,
+) -> Result<T, GoupError> {
+    let status = response.status();
+    let content_type = response.header("Content-Type").map(str::to_string);
+    let body = response
+        .into_string()
+        .map_err(|e| GoupError::network(url, e))?;
+    check_json(url, Some(status), content_type.as_deref(), &body)?;
+    serde_json::from_str(&body).map_err(|e| invalid_index(url, &body, e.into()))
+}
+
+/// Refuse a body sent as a web page, or that is one, where JSON was expected
+pub(crate) fn check_json(
+    url: &str,
+    status: Option<u16>,
+    content_type: Option<&str>,
+    body: &str,
+) -> Result<(), GoupError> {
+    let received = Received::new(status, content_type, body.as_bytes());
+    if received.is_page() {
+        Err(GoupError::InvalidIndex {
+            url: url.to_string(),
+            received,
+            source: io::Error::new(io::ErrorKind::InvalidData, "expected JSON"),
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// The error for a body from `url` that isn't a valid index, for the reason given by `source`
+pub(crate) fn invalid_index(url: &str, body: &str, source: io::Error) -> GoupError {
+    GoupError::InvalidIndex {
+        url: url.to_string(),
+        received: Received::new(None, None, body.as_bytes()),
+        source,
+    }
+}
+
+/// Whether `content_type` is that of a web page
+pub(crate) fn is_html(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or_default().trim();
+    mime.eq_ignore_ascii_case("text/html") || mime.eq_ignore_ascii_case("application/xhtml+xml")
+}
+
+fn looks_like_html(text: &str) -> bool {
+    let start = text.trim_start().to_lowercase();
+    ["<!doctype html", "<html", "<head", "<body", "<title"]
+        .iter()
+        .any(|tag| start.starts_with(tag))
+}
+
+/// The contents of the page's `<title>`, if it has one
+fn title(html: &str) -> Option<String> {
+    let lower = html.to_lowercase();
+    let open = lower.find("<title")?;
+    let start = open + lower[open..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    let title = html.get(start..end)?.trim();
+    (!title.is_empty()).then(|| title.to_string())
+}
+
+/// The text of `html`, without its tags
+fn strip_tags(html: &str) -> String {
+    let mut text = String::new();
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    text
+}
+
+/// `text` on one line, without control characters, and cut short if it is long
+fn sanitize(text: &str) -> String {
+    let words: Vec<_> = text
+        .split(|c: char| c.is_whitespace() || c.is_control())
+        .filter(|w| !w.is_empty())
+        .collect();
+    let line = words.join(" ");
+    match line.char_indices().nth(MAX_EXCERPT_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line,
+    }
+}
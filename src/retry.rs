@@ -0,0 +1,150 @@
+//! When and how often failed requests are retried
+
+use crate::error::GoupError;
+use serde::Deserialize;
+use std::fmt;
+use std::thread;
+use std::time::Duration;
+
+/// The longest that goup ever waits between attempts, whatever the policy asks for
+const LONGEST_DELAY: Duration = Duration::from_secs(60 * 60);
+
+/// The kinds of failure that can be retried
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RetryClass {
+    /// A download stopped receiving data
+    Stall,
+    /// The connection couldn't be made, or was lost
+    Connection,
+    /// The server answered with a 5xx error, or asked us to slow down with a 429
+    Server,
+}
+
+impl RetryClass {
+    /// The class that the error falls into, if it is one that may be retried at all
+    pub fn of(error: &GoupError) -> Option<Self> {
+        match error {
+            GoupError::DownloadStalled { .. } => Some(Self::Stall),
+            GoupError::SizeMismatch { .. } => Some(Self::Connection),
+            GoupError::Network { source, .. } => match source.as_ref() {
+                ureq::Error::Transport(_) => Some(Self::Connection),
+                ureq::Error::Status(429 | 500..=599, _) => Some(Self::Server),
+                ureq::Error::Status(..) => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for RetryClass {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Stall => "stall",
+            Self::Connection => "connection",
+            Self::Server => "server",
+        })
+    }
+}
+
+/// How failed requests are retried, set by the `[retry]` table of the config file
+///
+/// ```
+/// use goup::Config;
+/// use std::time::Duration;
+///
+/// let config: Config = toml::from_str("retry = { attempts = 5, initial_delay = 0.5 }").unwrap();
+/// assert_eq!(config.retry.attempts, 5);
+/// assert_eq!(config.retry.delay(2), Duration::from_secs(1));
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetryPolicy {
+    /// How many times a request is attempted in total, including the first
+    pub attempts: u32,
+    /// Seconds to wait before the first retry
+    pub initial_delay: f64,
+    /// How much longer to wait before each subsequent retry
+    pub multiplier: f64,
+    /// The longest to ever wait between attempts, in seconds
+    pub max_delay: f64,
+    /// Which kinds of failure are retried
+    pub on: Vec<RetryClass>,
+    /// Whether fetching the version index is retried, as well as archive downloads
+    pub index: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: 3,
+            initial_delay: 1.0,
+            multiplier: 2.0,
+            max_delay: 30.0,
+            on: vec![
+                RetryClass::Stall,
+                RetryClass::Connection,
+                RetryClass::Server,
+            ],
+            index: false,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// How long to wait after the given attempt fails, counting from 1. This never exceeds
+    /// an hour, even if the policy has no `max_delay` to speak of.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.initial_delay * self.multiplier.powi(attempt as i32 - 1);
+        Duration::try_from_secs_f64(delay.min(self.max_delay).max(0.0))
+            .map_or(LONGEST_DELAY, |d| d.min(LONGEST_DELAY))
+    }
+
+    /// Whether an attempt that failed with the given error should be followed by another
+    pub fn should_retry(&self, error: &GoupError, attempt: u32) -> bool {
+        attempt < self.attempts && RetryClass::of(error).is_some_and(|c| self.on.contains(&c))
+    }
+
+    /// Run `f` until it succeeds, fails in a way that can't be retried, or runs out of
+    /// attempts
+    pub(crate) fn run<T>(
+        &self,
+        mut f: impl FnMut() -> Result<T, GoupError>,
+    ) -> Result<T, GoupError> {
+        let mut attempt = 1;
+        loop {
+            match f() {
+                Err(e) if self.should_retry(&e, attempt) => {
+                    thread::sleep(self.delay(attempt));
+                    attempt += 1;
+                }
+                res => return res,
+            }
+        }
+    }
+}
+
+impl fmt::Display for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let on: Vec<_> = self.on.iter().map(RetryClass::to_string).collect();
+        write!(
+            f,
+            "{} attempt(s), waiting {}s then {}x longer each time (at most {}s), on {} errors, \
+             for {}",
+            self.attempts,
+            self.initial_delay,
+            self.multiplier,
+            self.max_delay,
+            if on.is_empty() {
+                "no".to_string()
+            } else {
+                on.join("/")
+            },
+            if self.index {
+                "archives and the version index"
+            } else {
+                "archives only"
+            }
+        )
+    }
+}
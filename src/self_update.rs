@@ -0,0 +1,176 @@
+//! Updating the goup binary itself from the project's GitHub releases
+
+use crate::error::GoupError;
+use crate::response;
+use flate2::read::GzDecoder;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use tar::Archive;
+use ureq::Agent;
+
+/// The GitHub API endpoint describing the newest release of goup
+pub const RELEASES_URL: &str = "https://api.github.com/repos/skubalj/goup/releases/latest";
+
+/// A published release of goup
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub tag_name: String,
+    pub assets: Vec<Asset>,
+}
+
+/// A file attached to a [`Release`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct Asset {
+    pub name: String,
+    pub browser_download_url: String,
+}
+
+impl Release {
+    /// The release's version number, without any leading `v`
+    pub fn version(&self) -> &str {
+        self.tag_name.trim_start_matches('v')
+    }
+
+    /// Whether this release is newer than the given version number
+    ///
+    /// ```
+    /// use goup::self_update::Release;
+    ///
+    /// let release = Release { tag_name: "v0.2.0".into(), assets: vec![] };
+    /// assert!(release.is_newer_than("0.1.10"));
+    /// assert!(!release.is_newer_than("0.2.0"));
+    /// ```
+    pub fn is_newer_than(&self, version: &str) -> bool {
+        parse_semver(self.version()) > parse_semver(version)
+    }
+
+    fn asset(&self, name: &str) -> Option<&Asset> {
+        self.assets.iter().find(|a| a.name == name)
+    }
+}
+
+/// The name of the release asset built for this platform
+pub fn asset_name() -> String {
+    format!("goup-{}-{}.tar.gz", env::consts::ARCH, env::consts::OS)
+}
+
+/// Look up the newest release of goup
+pub fn latest_release(agent: &Agent, url: &str) -> Result<Release, GoupError> {
+    let response = agent
+        .get(url)
+        .call()
+        .map_err(|e| GoupError::network(url, e))?;
+    response::json(url, response)
+}
+
+/// Download the given release and use it to replace the executable at `exe`
+///
+/// The archive is verified against the checksum published alongside it before anything on
+/// disk is touched.
+pub fn install_release(agent: &Agent, release: &Release, exe: &Path) -> Result<(), GoupError> {
+    let name = asset_name();
+    let no_asset = |asset: &str| GoupError::NoReleaseAsset {
+        version: release.version().to_string(),
+        asset: asset.to_string(),
+    };
+    let archive = release.asset(&name).ok_or_else(|| no_asset(&name))?;
+    let checksum_name = format!("{}.sha256", name);
+    let checksum = release
+        .asset(&checksum_name)
+        .ok_or_else(|| no_asset(&checksum_name))?;
+
+    let expected = download(agent, &checksum.browser_download_url)?;
+    let expected = String::from_utf8_lossy(&expected)
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    let payload = download(agent, &archive.browser_download_url)?;
+    let actual = format!("{:x}", Sha256::digest(&payload));
+    if actual != expected {
+        return Err(GoupError::ChecksumMismatch {
+            file: name,
+            expected,
+            actual,
+        });
+    }
+
+    let binary = extract_binary(&payload).ok_or_else(|| no_asset(&name))?;
+    replace_executable(exe, &binary)
+}
+
+fn download(agent: &Agent, url: &str) -> Result<Vec<u8>, GoupError> {
+    let mut buf = Vec::new();
+    agent
+        .get(url)
+        .call()
+        .map_err(|e| GoupError::network(url, e))?
+        .into_reader()
+        .read_to_end(&mut buf)
+        .map_err(|e| GoupError::network(url, e))?;
+    Ok(buf)
+}
+
+/// Find the goup executable within a release archive
+fn extract_binary(archive: &[u8]) -> Option<Vec<u8>> {
+    let exe_name = format!("goup{}", env::consts::EXE_SUFFIX);
+    let mut archive = Archive::new(GzDecoder::new(archive));
+    for entry in archive.entries().ok()? {
+        let mut entry = entry.ok()?;
+        let is_exe = entry
+            .path()
+            .ok()
+            .is_some_and(|p| p.file_name().is_some_and(|n| n == exe_name.as_str()));
+        if is_exe {
+            let mut buf = Vec::new();
+            entry.read_to_end(&mut buf).ok()?;
+            return Some(buf);
+        }
+    }
+    None
+}
+
+/// Swap the executable at `exe` for one with the given contents, without ever leaving a
+/// partially written file at that path.
+fn replace_executable(exe: &Path, contents: &[u8]) -> Result<(), GoupError> {
+    let not_writable = |source| GoupError::ExecutableNotWritable {
+        path: exe.to_path_buf(),
+        source,
+    };
+    let file_name = exe.file_name().unwrap_or_default().to_string_lossy();
+    let staged = exe.with_file_name(format!(".{}.new", file_name));
+    fs::write(&staged, contents).map_err(not_writable)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))
+            .map_err(GoupError::io("set permissions on", &staged))?;
+    }
+
+    // Windows won't let us overwrite a running executable, but it will let us rename it
+    #[cfg(windows)]
+    {
+        let old = exe.with_file_name(format!("{}.old", file_name));
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old).map_err(not_writable)?;
+    }
+
+    fs::rename(&staged, exe).map_err(not_writable)
+}
+
+/// Split a `major.minor.patch` version number into comparable parts
+fn parse_semver(version: &str) -> (u64, u64, u64) {
+    let mut parts = version
+        .split(['.', '-', '+'])
+        .map(|p| p.parse::<u64>().unwrap_or_default());
+    (
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+        parts.next().unwrap_or_default(),
+    )
+}
@@ -0,0 +1,114 @@
+//! Grouping versions by their release series, for `goup list --tree`
+
+use crate::version::GoVersion;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// What is known about a single version, merged from the version file and the index
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Status {
+    pub installed: bool,
+    pub available: bool,
+    pub enabled: bool,
+    pub pinned: bool,
+}
+
+impl Status {
+    /// Describe the version in words, for output that can't rely on color. Whether a version
+    /// is available upstream is only mentioned if `availability_known`.
+    ///
+    /// ```
+    /// use goup::series::Status;
+    ///
+    /// let status = Status { installed: true, ..Default::default() };
+    /// assert_eq!(status.label(true), "installed (unavailable upstream)");
+    /// assert_eq!(status.label(false), "installed");
+    /// ```
+    pub fn label(&self, availability_known: bool) -> &'static str {
+        let unavailable = availability_known && !self.available;
+        match (self.enabled, self.installed, unavailable) {
+            (true, _, true) => "default (unavailable upstream)",
+            (true, _, false) => "default",
+            (false, true, true) => "installed (unavailable upstream)",
+            (false, true, false) => "installed",
+            (false, false, _) => "available",
+        }
+    }
+}
+
+/// The releases of a single major.minor version of Go, such as go1.22
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Series {
+    pub major: u32,
+    pub minor: u32,
+    /// How many releases the series has
+    pub releases: usize,
+    /// How many of them are installed
+    pub installed: usize,
+    /// The releases to display, newest first
+    pub shown: Vec<GoVersion>,
+    /// How many releases were left out of `shown`
+    pub hidden: usize,
+}
+
+impl fmt::Display for Series {
+    /// The name of the series, like `go1.22`
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "go{}.{}", self.major, self.minor)
+    }
+}
+
+/// Group `versions` by series, newest series first.
+///
+/// Unless `expand` is set, each series shows only its newest release along with any that are
+/// installed.
+///
+/// ```
+/// use goup::series::{group, Status};
+/// use goup::GoVersion;
+///
+/// let v = |s: &str| s.parse::<GoVersion>().unwrap();
+/// let available = Status { available: true, ..Default::default() };
+/// let installed = Status { installed: true, ..available };
+/// let versions = [
+///     (v("go1.21.0"), installed),
+///     (v("go1.21.1"), available),
+///     (v("go1.21.2"), available),
+///     (v("go1.22.0"), available),
+/// ];
+/// let series = group(&versions.into_iter().collect(), false);
+/// assert_eq!(series[0].to_string(), "go1.22");
+/// assert_eq!(series[1].shown, [v("go1.21.2"), v("go1.21.0")]);
+/// assert_eq!(series[1].hidden, 1);
+/// ```
+pub fn group(versions: &BTreeMap<GoVersion, Status>, expand: bool) -> Vec<Series> {
+    let mut series: Vec<Series> = Vec::new();
+    for (&version, status) in versions.iter().rev() {
+        let current = match series.last_mut() {
+            Some(s) if (s.major, s.minor) == (version.major, version.minor) => s,
+            _ => {
+                series.push(Series {
+                    major: version.major,
+                    minor: version.minor,
+                    releases: 0,
+                    installed: 0,
+                    shown: Vec::new(),
+                    hidden: 0,
+                });
+                series.last_mut().unwrap()
+            }
+        };
+
+        let newest = current.releases == 0;
+        current.releases += 1;
+        if status.installed {
+            current.installed += 1;
+        }
+        if expand || newest || status.installed || status.enabled || status.pinned {
+            current.shown.push(version)
+        } else {
+            current.hidden += 1
+        }
+    }
+    series
+}
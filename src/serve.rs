@@ -0,0 +1,327 @@
+//! Sharing the cache of downloaded archives with other machines over plain HTTP, in the same
+//! layout as go.dev/dl, so that they can use this machine as their mirror
+
+use crate::bundle;
+use crate::cache;
+use crate::error::GoupError;
+use crate::response;
+use crate::Goup;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the server checks whether it should stop, while waiting for connections
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How long a client may take to send its request before the connection is dropped
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The longest request line and headers that are accepted
+const MAX_REQUEST_BYTES: u64 = 8 * 1024;
+
+/// The files that a server may send: the cached archives that match the checksums published
+/// for them, and optionally the index listing them
+#[derive(Debug, Default)]
+pub struct Catalog {
+    archives: BTreeMap<String, PathBuf>,
+    index: Option<String>,
+    /// Cached archives that are never served, since the index doesn't list them or they don't
+    /// match the checksum it gives
+    pub unverified: Vec<PathBuf>,
+}
+
+impl Catalog {
+    /// Check each cached archive against the index, which is fetched as for
+    /// [`Goup::index`]. With `include_index`, the index is served too, listing only the
+    /// archives that were verified, so that clients never choose a version they can't get here.
+    pub fn load(goup: &Goup, include_index: bool) -> Result<Catalog, GoupError> {
+        let releases = goup.fetch_index(None, |url, cached| {
+            serde_json::from_str::<Vec<Value>>(&cached.body)
+                .map_err(|e| response::invalid_index(url, &cached.body, e.into()))
+        })?;
+        let checksums: BTreeMap<&str, &str> = releases
+            .iter()
+            .flat_map(files)
+            .filter_map(|f| Some((f["filename"].as_str()?, f["sha256"].as_str()?)))
+            .collect();
+
+        let mut catalog = Catalog::default();
+        for entry in cache::entries(&goup.archive_cache_dir())? {
+            let Some(name) = entry.path.file_name().and_then(|n| n.to_str()) else {
+                continue;
+            };
+            let verified = checksums.get(name).is_some_and(|expected| {
+                File::open(&entry.path)
+                    .and_then(|mut f| bundle::sha256(&mut f))
+                    .is_ok_and(|digest| digest.eq_ignore_ascii_case(expected))
+            });
+            if verified {
+                catalog.archives.insert(name.to_string(), entry.path);
+            } else {
+                catalog.unverified.push(entry.path)
+            }
+        }
+
+        if include_index {
+            let listed: Vec<Value> = releases
+                .into_iter()
+                .filter_map(|mut release| {
+                    let kept: Vec<Value> = files(&release)
+                        .filter(|f| {
+                            f["filename"]
+                                .as_str()
+                                .is_some_and(|name| catalog.archives.contains_key(name))
+                        })
+                        .cloned()
+                        .collect();
+                    (!kept.is_empty()).then(|| {
+                        release["files"] = Value::Array(kept);
+                        release
+                    })
+                })
+                .collect();
+            catalog.index = Some(Value::Array(listed).to_string());
+        }
+        Ok(catalog)
+    }
+
+    /// The filenames of the archives that are served
+    pub fn archives(&self) -> impl Iterator<Item = &str> {
+        self.archives.keys().map(String::as_str)
+    }
+
+    /// Whether the index is served
+    pub fn has_index(&self) -> bool {
+        self.index.is_some()
+    }
+}
+
+/// The files listed for a release in the index
+fn files(release: &Value) -> impl Iterator<Item = &Value> {
+    release["files"].as_array().into_iter().flatten()
+}
+
+/// When a server should stop by itself, for sharing archives briefly
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Limits {
+    /// Stop accepting connections after this long
+    pub time: Option<Duration>,
+    /// Stop accepting connections once this many bytes have been sent
+    pub bytes: Option<u64>,
+}
+
+/// Why [`Server::run`] returned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stopped {
+    /// The stop flag was set, as when the user presses Ctrl-C
+    Interrupted,
+    /// [`Limits::time`] was reached
+    TimeLimit,
+    /// [`Limits::bytes`] was reached
+    ByteLimit,
+}
+
+/// A request that the server answered
+#[derive(Debug, Clone)]
+pub struct Served {
+    pub peer: SocketAddr,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    /// The bytes of the body that were sent
+    pub bytes: u64,
+}
+
+/// An HTTP server for a [`Catalog`]. It only answers `GET` and `HEAD` requests, for the index
+/// at `/?mode=json` and for archives by their exact filenames.
+pub struct Server {
+    listener: TcpListener,
+    addr: String,
+}
+
+impl Server {
+    /// Listen on `addr`, such as `0.0.0.0:8800`
+    pub fn bind(addr: &str) -> Result<Server, GoupError> {
+        let error = |source| GoupError::Serve {
+            addr: addr.to_string(),
+            source,
+        };
+        let listener = TcpListener::bind(addr).map_err(error)?;
+        listener.set_nonblocking(true).map_err(error)?;
+        Ok(Server {
+            listener,
+            addr: addr.to_string(),
+        })
+    }
+
+    /// The address the server is listening on, with the port it was given if it asked for 0
+    pub fn local_addr(&self) -> Result<SocketAddr, GoupError> {
+        self.listener
+            .local_addr()
+            .map_err(|source| GoupError::Serve {
+                addr: self.addr.clone(),
+                source,
+            })
+    }
+
+    /// Answer requests until `stop` is set or a limit is reached, calling `log` for each
+    /// request answered. Requests in progress are finished before returning, unless `stop` is
+    /// set, which cuts them short.
+    pub fn run(
+        &self,
+        catalog: &Catalog,
+        limits: Limits,
+        stop: &AtomicBool,
+        log: &(dyn Fn(&Served) + Sync),
+    ) -> Result<Stopped, GoupError> {
+        let start = Instant::now();
+        let sent = AtomicU64::new(0);
+        thread::scope(|s| loop {
+            if stop.load(Ordering::Relaxed) {
+                return Ok(Stopped::Interrupted);
+            }
+            if limits.time.is_some_and(|time| start.elapsed() >= time) {
+                return Ok(Stopped::TimeLimit);
+            }
+            if limits
+                .bytes
+                .is_some_and(|bytes| sent.load(Ordering::Relaxed) >= bytes)
+            {
+                return Ok(Stopped::ByteLimit);
+            }
+            match self.listener.accept() {
+                Ok((stream, peer)) => {
+                    let sent = &sent;
+                    s.spawn(move || {
+                        // A client that goes away only ends its own request
+                        if let Ok(Some(served)) = respond(stream, peer, catalog, stop, sent) {
+                            log(&served);
+                        }
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => thread::sleep(POLL_INTERVAL),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(source) => {
+                    return Err(GoupError::Serve {
+                        addr: self.addr.clone(),
+                        source,
+                    })
+                }
+            }
+        })
+    }
+}
+
+/// Read a request from `stream` and answer it, returning what was done. Connections that
+/// close without sending a request give `None`.
+fn respond(
+    stream: TcpStream,
+    peer: SocketAddr,
+    catalog: &Catalog,
+    stop: &AtomicBool,
+    sent: &AtomicU64,
+) -> io::Result<Option<Served>> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_REQUEST_BYTES));
+    let mut line = String::new();
+    if reader.read_line(&mut line)? == 0 {
+        return Ok(None);
+    }
+    // Skip the headers, which change nothing
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let target = parts.next().unwrap_or_default();
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut served = Served {
+        peer,
+        method,
+        path: target.to_string(),
+        status: 200,
+        bytes: 0,
+    };
+    let head = served.method == "HEAD";
+    let mut stream = &stream;
+
+    if !matches!(served.method.as_str(), "GET" | "HEAD") {
+        served.status = 405;
+        write_head(stream, 405, "text/plain", 0, &[("Allow", "GET, HEAD")])?;
+        return Ok(Some(served));
+    }
+    let wants_index = path == "/" && query.split('&').any(|p| p == "mode=json");
+    if let (true, Some(index)) = (wants_index, &catalog.index) {
+        write_head(stream, 200, "application/json", index.len() as u64, &[])?;
+        if !head {
+            stream.write_all(index.as_bytes())?;
+            served.bytes = index.len() as u64;
+            sent.fetch_add(served.bytes, Ordering::Relaxed);
+        }
+        return Ok(Some(served));
+    }
+    // Archives are found by exact name, so no path can reach anything else on disk
+    let archive = path
+        .strip_prefix('/')
+        .and_then(|name| catalog.archives.get(name));
+    let Some(archive) = archive else {
+        served.status = 404;
+        write_head(stream, 404, "text/plain", 0, &[])?;
+        return Ok(Some(served));
+    };
+
+    let mut file = File::open(archive)?;
+    let length = file.metadata()?.len();
+    write_head(stream, 200, "application/octet-stream", length, &[])?;
+    if head {
+        return Ok(Some(served));
+    }
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        stream.write_all(&buf[..n])?;
+        served.bytes += n as u64;
+        sent.fetch_add(n as u64, Ordering::Relaxed);
+    }
+    Ok(Some(served))
+}
+
+/// Write the status line and headers of a response, which always closes the connection
+fn write_head(
+    mut stream: &TcpStream,
+    status: u16,
+    content_type: &str,
+    length: u64,
+    extra: &[(&str, &str)],
+) -> io::Result<()> {
+    let reason = match status {
+        200 => "OK",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        _ => "",
+    };
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n",
+        status, reason, content_type, length
+    );
+    for (name, value) in extra {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())
+}
@@ -0,0 +1,106 @@
+//! Measuring how much disk space installations take up
+
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::{fs, io, thread};
+
+/// The most threads that will walk a directory at once. Beyond this, the disk is the
+/// bottleneck rather than the number of outstanding requests.
+const MAX_THREADS: usize = 8;
+
+/// The total size of the files within `path`, walking its subdirectories in parallel.
+///
+/// Symlinks are counted as links, rather than followed.
+pub fn dir_size(path: &Path) -> io::Result<u64> {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get().min(MAX_THREADS));
+    dir_size_with(path, threads)
+}
+
+/// The total size of the files within `path`, walking the top-level subdirectories with up to
+/// `threads` threads. With a single thread, the walk happens serially on the calling thread.
+pub fn dir_size_with(path: &Path, threads: usize) -> io::Result<u64> {
+    let mut total = 0;
+    let mut subdirs = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            subdirs.push(entry.path())
+        } else {
+            total += metadata.len()
+        }
+    }
+
+    if threads <= 1 || subdirs.len() <= 1 {
+        for dir in &subdirs {
+            total += walk(dir)?;
+        }
+        return Ok(total);
+    }
+
+    // The threads take directories from a shared queue, so that one huge directory doesn't
+    // leave the others idle
+    let queue = Mutex::new(subdirs);
+    let next = || queue.lock().unwrap_or_else(|e| e.into_inner()).pop();
+    let sizes = thread::scope(|s| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut size = 0;
+                    while let Some(dir) = next() {
+                        size += walk(&dir)?;
+                    }
+                    Ok::<_, io::Error>(size)
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().expect("size walker panicked"))
+            .collect::<Vec<_>>()
+    });
+    for size in sizes {
+        total += size?;
+    }
+    Ok(total)
+}
+
+/// Serially total the size of everything within a directory
+fn walk(path: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    let mut pending: Vec<PathBuf> = vec![path.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                pending.push(entry.path())
+            } else {
+                total += metadata.len()
+            }
+        }
+    }
+    Ok(total)
+}
+
+/// Format a number of bytes for people to read, like `1.4 GiB`
+///
+/// ```
+/// use goup::size::format_size;
+///
+/// assert_eq!(format_size(512), "512 B");
+/// assert_eq!(format_size(1536 * 1024), "1.5 MiB");
+/// ```
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
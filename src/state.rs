@@ -0,0 +1,315 @@
+use crate::error::GoupError;
+use crate::version::GoVersion;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{fs, io};
+
+/// The record of which versions of Go are installed, enabled, and pinned
+#[derive(Debug, Default, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct VersionFile {
+    pub enabled: Option<GoVersion>,
+    pub installed: BTreeSet<GoVersion>,
+    pub pinned: BTreeSet<GoVersion>,
+    /// Installed versions that were unpacked without their tests and documentation
+    #[serde(default)]
+    pub minimal: BTreeSet<GoVersion>,
+    /// Whether the enabled version was copied into place, rather than symlinked
+    #[serde(default)]
+    pub copied: bool,
+    /// Whether the links to the enabled version are junctions, made on Windows where symlinks
+    /// can't be
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub junction: bool,
+    /// Why and when each pinned version was pinned. Pins made by older versions of goup have
+    /// no entry.
+    #[serde(default)]
+    pub pin_info: BTreeMap<GoVersion, PinInfo>,
+    /// Seconds since the Unix epoch at which each version was last enabled or run. Only
+    /// recorded once a day, so it may be up to a day out of date.
+    #[serde(default)]
+    pub last_used: BTreeMap<GoVersion, u64>,
+    /// Toolchains registered by name: those outside goup's root linked with `goup link`, such
+    /// as builds of Go from source, which goup switches to but never deletes, and those
+    /// installed from a URL with `goup install --url`, which goup owns
+    #[serde(default)]
+    pub linked: BTreeMap<String, LinkedToolchain>,
+    /// The name of the linked toolchain that is enabled, if one is, in which case `enabled` is
+    /// `None`
+    #[serde(default)]
+    pub enabled_linked: Option<String>,
+    /// Where the links to the enabled version were made, so that they can be moved when
+    /// `link_path` changes. Empty when nothing is enabled, and when the only link is the
+    /// default, `go` in the root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<PathBuf>,
+    /// Versions installed by `goup try` for the length of one command, which it removes again
+    /// once the command finishes. `clean` leaves them to it.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub ephemeral: BTreeSet<GoVersion>,
+    /// Projects whose `.goup.toml` the user trusted with every setting, by the path of the
+    /// file, with the SHA256 digest of what it said then. Changing the file revokes the trust.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub trusted_projects: BTreeMap<PathBuf, String>,
+    /// The [layout](crate::layout) of the root this file belongs to. Files in the flat layout
+    /// don't say.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub layout: Option<u32>,
+}
+
+/// A Go installation registered under a name of its own, rather than installed as a release
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct LinkedToolchain {
+    /// The toolchain's GOROOT
+    pub path: PathBuf,
+    /// What its `go version` printed when it was linked
+    pub go_version: String,
+    /// Seconds since the Unix epoch at which it was linked
+    pub linked_at: u64,
+    /// Where goup downloaded it from, if it was installed with `goup install --url` rather
+    /// than linked. goup deletes such a toolchain when it is removed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub custom: Option<CustomSource>,
+}
+
+/// The archive that a toolchain installed with `goup install --url` was unpacked from
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct CustomSource {
+    pub url: String,
+    /// The SHA256 digest the archive was checked against, or `None` if it was installed with
+    /// `--no-verify`
+    pub sha256: Option<String>,
+}
+
+impl LinkedToolchain {
+    /// Check that `name` can be used for a linked toolchain: it must start with a letter, use
+    /// only letters, digits, `.`, `_`, and `-`, and not be mistakable for a version of Go. A
+    /// version followed by a label, like `go1.22.6-acme`, is a name rather than a version.
+    ///
+    /// ```
+    /// use goup::state::LinkedToolchain;
+    ///
+    /// assert!(LinkedToolchain::check_name("mygo").is_ok());
+    /// assert!(LinkedToolchain::check_name("go1.22-patched").is_ok());
+    /// assert!(LinkedToolchain::check_name("go1.22").is_err());
+    /// assert!(LinkedToolchain::check_name("go1.22-").is_err());
+    /// ```
+    pub fn check_name(name: &str) -> Result<(), &'static str> {
+        let mut chars = name.chars();
+        if !chars.next().is_some_and(|c| c.is_ascii_alphabetic()) {
+            return Err("it must start with a letter");
+        }
+        if !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-')) {
+            return Err("it may only contain letters, digits, '.', '_', and '-'");
+        }
+        // A version with nothing after its dash is still just a version
+        let unlabeled = match name.split_once('-') {
+            Some((version, "")) => version,
+            _ => name,
+        };
+        if unlabeled.parse::<GoVersion>().is_ok() || name == "latest" {
+            return Err("it could be mistaken for a version of Go");
+        }
+        Ok(())
+    }
+
+    /// Whether goup installed this toolchain from a URL, rather than it being linked
+    pub fn is_custom(&self) -> bool {
+        self.custom.is_some()
+    }
+}
+
+/// How often the use of a version is recorded, so that running it often doesn't mean writing
+/// the version file each time
+pub const USE_RECORD_INTERVAL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Why and when a version was pinned, and until when
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct PinInfo {
+    /// The user's explanation of why the version must be kept
+    pub reason: Option<String>,
+    /// Seconds since the Unix epoch at which the version was pinned
+    pub pinned_at: u64,
+    /// Seconds since the Unix epoch after which the pin no longer keeps the version from being
+    /// cleaned, for a pin made only for a while
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires: Option<u64>,
+    /// Whether the user has been told that the pin expired
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub expiry_noticed: bool,
+}
+
+impl PinInfo {
+    /// A pin made just now, for the given reason, lasting until `expires` if that is given
+    pub fn new(reason: Option<String>, expires: Option<SystemTime>) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        let mut info = Self {
+            reason,
+            pinned_at: now.unwrap_or_default().as_secs(),
+            expires: None,
+            expiry_noticed: false,
+        };
+        info.set_expiry(expires);
+        info
+    }
+
+    /// Make the pin last until `expires`, or for good if that is `None`
+    pub fn set_expiry(&mut self, expires: Option<SystemTime>) {
+        self.expires = expires.map(|t| t.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs());
+        self.expiry_noticed = false;
+    }
+
+    /// When the version was pinned
+    pub fn pinned_at(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.pinned_at)
+    }
+
+    /// When the pin stops keeping the version from being cleaned, if it ever does
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        self.expires
+            .map(|secs| UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Whether the pin has expired
+    pub fn is_expired(&self) -> bool {
+        self.expires_at().is_some_and(|t| t <= SystemTime::now())
+    }
+}
+
+impl VersionFile {
+    /// Read the version file at the given path. A missing file is treated as empty.
+    pub fn load(path: &Path) -> Result<VersionFile, GoupError> {
+        match fs::read_to_string(path) {
+            Ok(x) => serde_json::from_str(&x).map_err(|source| GoupError::VersionFileCorrupt {
+                path: path.to_path_buf(),
+                source,
+            }),
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => Ok(Default::default()),
+            Err(e) => Err(GoupError::io("read", path)(e)),
+        }
+    }
+
+    /// Make the changes that turned `before` into `after` to this record as well, keeping
+    /// any other changes it has, such as those made meanwhile by another process
+    pub fn apply(&mut self, before: &VersionFile, after: &VersionFile) {
+        let enabled = |f: &VersionFile| {
+            let links = f.links.clone();
+            (
+                f.enabled,
+                f.copied,
+                f.junction,
+                f.enabled_linked.clone(),
+                links,
+            )
+        };
+        if enabled(before) != enabled(after) {
+            self.enabled = after.enabled;
+            self.copied = after.copied;
+            self.junction = after.junction;
+            self.enabled_linked = after.enabled_linked.clone();
+            self.links = after.links.clone();
+        }
+        apply_set(&mut self.installed, &before.installed, &after.installed);
+        apply_set(&mut self.pinned, &before.pinned, &after.pinned);
+        apply_set(&mut self.minimal, &before.minimal, &after.minimal);
+        apply_set(&mut self.ephemeral, &before.ephemeral, &after.ephemeral);
+        apply_map(&mut self.pin_info, &before.pin_info, &after.pin_info);
+        apply_map(&mut self.last_used, &before.last_used, &after.last_used);
+        apply_map(&mut self.linked, &before.linked, &after.linked);
+        apply_map(
+            &mut self.trusted_projects,
+            &before.trusted_projects,
+            &after.trusted_projects,
+        );
+        if before.layout != after.layout {
+            self.layout = after.layout;
+        }
+    }
+
+    /// When the given version was last enabled or run, if goup has recorded it
+    pub fn last_used(&self, version: GoVersion) -> Option<SystemTime> {
+        let secs = *self.last_used.get(&version)?;
+        Some(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Record that the given version is being used now, unless that was already recorded
+    /// within [`USE_RECORD_INTERVAL`]. Returns whether anything changed.
+    pub fn record_use(&mut self, version: GoVersion) -> bool {
+        let fresh = self
+            .last_used(version)
+            .and_then(|t| t.elapsed().ok())
+            .is_some_and(|age| age < USE_RECORD_INTERVAL);
+        if !fresh {
+            let now = SystemTime::now().duration_since(UNIX_EPOCH);
+            self.last_used
+                .insert(version, now.unwrap_or_default().as_secs());
+        }
+        !fresh
+    }
+
+    /// Whether the given version is pinned, by a pin that hasn't expired
+    pub fn pin_holds(&self, version: GoVersion) -> bool {
+        self.pinned.contains(&version) && !self.pin_expired(version)
+    }
+
+    /// Whether the given version is pinned, but the pin has expired
+    pub fn pin_expired(&self, version: GoVersion) -> bool {
+        self.pinned.contains(&version)
+            && self.pin_info.get(&version).is_some_and(PinInfo::is_expired)
+    }
+
+    /// Why the given version was pinned, if it is pinned and a reason was given
+    pub fn pin_reason(&self, version: GoVersion) -> Option<&str> {
+        if self.pinned.contains(&version) {
+            self.pin_info.get(&version)?.reason.as_deref()
+        } else {
+            None
+        }
+    }
+
+    /// Write the version file out to the given path, creating its directory if need be. The
+    /// file is replaced atomically, so it is never left half-written.
+    pub fn store(&self, path: &Path) -> Result<(), GoupError> {
+        let payload = serde_json::to_string_pretty(&self)
+            .map_err(|e| GoupError::io("serialize", path)(e.into()))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        let staging = path.with_extension("json.tmp");
+        fs::write(&staging, payload).map_err(GoupError::io("write", &staging))?;
+        fs::rename(&staging, path).map_err(GoupError::io("replace", path))
+    }
+}
+
+/// Make the same insertions, changes, and removals to `map` as were made to `before` to give
+/// `after`
+fn apply_map<K: Ord + Clone, V: Clone + PartialEq>(
+    map: &mut BTreeMap<K, V>,
+    before: &BTreeMap<K, V>,
+    after: &BTreeMap<K, V>,
+) {
+    for k in before.keys() {
+        if !after.contains_key(k) {
+            map.remove(k);
+        }
+    }
+    for (k, value) in after {
+        if before.get(k) != Some(value) {
+            map.insert(k.clone(), value.clone());
+        }
+    }
+}
+
+/// Add and remove the same elements from `set` as were added and removed from `before` to
+/// give `after`
+fn apply_set(
+    set: &mut BTreeSet<GoVersion>,
+    before: &BTreeSet<GoVersion>,
+    after: &BTreeSet<GoVersion>,
+) {
+    for v in before.difference(after) {
+        set.remove(v);
+    }
+    set.extend(after.difference(before));
+}
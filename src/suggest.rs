@@ -0,0 +1,70 @@
+//! Suggestions for the version a user may have meant when they ask for one that doesn't exist
+
+use crate::version::GoVersion;
+use std::cmp::Reverse;
+
+/// The most suggestions that will be offered at once
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Up to three versions from `candidates` that the user may have meant when they asked for
+/// `target`, best match first.
+///
+/// Other patches of the same minor version are preferred, followed by the neighbouring minor
+/// versions, and then anything that is only a typo or two away.
+///
+/// ```
+/// use goup::suggest::suggestions;
+/// use goup::GoVersion;
+///
+/// let v = |s: &str| s.parse::<GoVersion>().unwrap();
+/// let installed = [v("go1.21.13"), v("go1.22.5"), v("go1.22.6")];
+/// assert_eq!(suggestions(v("go1.22.7"), installed), vec![v("go1.22.6"), v("go1.22.5"), v("go1.21.13")]);
+/// ```
+pub fn suggestions(
+    target: GoVersion,
+    candidates: impl IntoIterator<Item = GoVersion>,
+) -> Vec<GoVersion> {
+    let mut ranked: Vec<_> = candidates
+        .into_iter()
+        .filter_map(|c| rank(target, c).map(|r| (r, Reverse(c))))
+        .collect();
+    ranked.sort();
+    ranked.dedup();
+    ranked
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, Reverse(c))| c)
+        .collect()
+}
+
+/// How good a suggestion `candidate` is for `target`, lower being better, or `None` if it is
+/// not worth suggesting at all
+fn rank(target: GoVersion, candidate: GoVersion) -> Option<(u32, u32)> {
+    let same_major = candidate.major == target.major;
+    if candidate == target {
+        None
+    } else if same_major && candidate.minor == target.minor {
+        Some((0, candidate.patch.abs_diff(target.patch)))
+    } else if same_major && candidate.minor.abs_diff(target.minor) == 1 {
+        Some((1, candidate.patch.abs_diff(target.patch)))
+    } else {
+        let distance = edit_distance(&target.to_string(), &candidate.to_string());
+        (distance <= 2).then_some((2, distance))
+    }
+}
+
+/// The Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> u32 {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<u32> = (0..=b.len() as u32).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i as u32 + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let substitution = diagonal + u32::from(ca != cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
@@ -0,0 +1,143 @@
+//! Developer tools built with `go install`, like gopls, which are tied to the toolchain that
+//! built them and need rebuilding after switching versions
+
+use crate::error::GoupError;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A binary built by `go install`, as described by the build info embedded in it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tool {
+    /// The name of the binary
+    pub name: String,
+    /// The package it was built from, like `golang.org/x/tools/gopls`
+    pub path: String,
+    /// The version of the module containing the package, like `v0.15.3`
+    pub version: String,
+    /// The version of Go that built it
+    pub go: String,
+}
+
+impl Tool {
+    /// The argument to `go install` that builds this tool again
+    pub fn install_spec(&self) -> String {
+        format!("{}@{}", self.path, self.version)
+    }
+}
+
+/// The tools found in the Go binaries directory at some point, saved so that they can be
+/// rebuilt later
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Snapshot {
+    /// Seconds since the Unix epoch at which the snapshot was taken
+    pub taken: u64,
+    pub tools: Vec<Tool>,
+}
+
+impl Snapshot {
+    pub fn new(tools: Vec<Tool>) -> Self {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH);
+        Self {
+            taken: now.unwrap_or_default().as_secs(),
+            tools,
+        }
+    }
+
+    /// Read a saved snapshot. A missing file gives `None`.
+    pub fn load(path: &Path) -> Result<Option<Self>, GoupError> {
+        match fs::read_to_string(path) {
+            Ok(x) => serde_json::from_str(&x)
+                .map(Some)
+                .map_err(|e| GoupError::io("parse", path)(e.into())),
+            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => Ok(None),
+            Err(e) => Err(GoupError::io("read", path)(e)),
+        }
+    }
+
+    pub fn store(&self, path: &Path) -> Result<(), GoupError> {
+        let text = serde_json::to_string_pretty(self).expect("a snapshot can always be serialized");
+        fs::write(path, text).map_err(GoupError::io("write", path))
+    }
+}
+
+/// The directory that `go install` puts binaries in: `$GOBIN`, or else `bin` within the first
+/// directory in `$GOPATH`
+pub fn bin_dir() -> Result<PathBuf, GoupError> {
+    if let Some(gobin) = env::var_os("GOBIN").filter(|v| !v.is_empty()) {
+        return Ok(gobin.into());
+    }
+    Ok(crate::gopath()?.join("bin"))
+}
+
+/// Parse the output of `go version -m`, keeping the binaries that can be installed again.
+/// Those built from a local checkout, rather than a published module version, can't be.
+///
+/// ```
+/// use goup::tools::parse_build_info;
+///
+/// let output = "/home/gopher/go/bin/gopls: go1.22.1
+/// \tpath\tgolang.org/x/tools/gopls
+/// \tmod\tgolang.org/x/tools/gopls\tv0.15.3\th1:abc=
+/// \tdep\tgolang.org/x/mod\tv0.17.0\th1:def=
+/// ";
+/// let tools = parse_build_info(output);
+/// assert_eq!(tools[0].name, "gopls");
+/// assert_eq!(tools[0].install_spec(), "golang.org/x/tools/gopls@v0.15.3");
+/// ```
+pub fn parse_build_info(output: &str) -> Vec<Tool> {
+    let mut tools = Vec::new();
+    let mut current: Option<Partial> = None;
+    for line in output.lines() {
+        match line.strip_prefix('\t') {
+            None => {
+                tools.extend(current.take().and_then(Partial::finish));
+                current = line.rsplit_once(": ").map(|(binary, go)| Partial {
+                    name: Path::new(binary)
+                        .file_stem()
+                        .map(|s| s.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                    go: go.trim().to_string(),
+                    path: None,
+                    version: None,
+                });
+            }
+            Some(field) => {
+                let Some(partial) = current.as_mut() else {
+                    continue;
+                };
+                let mut parts = field.split('\t');
+                match (parts.next(), parts.next(), parts.next()) {
+                    (Some("path"), Some(path), _) => partial.path = Some(path.to_string()),
+                    (Some("mod"), Some(_), Some(v)) => partial.version = Some(v.to_string()),
+                    _ => {}
+                }
+            }
+        }
+    }
+    tools.extend(current.and_then(Partial::finish));
+    tools
+}
+
+/// A binary from `go version -m` whose build info hasn't all been read yet
+struct Partial {
+    name: String,
+    go: String,
+    path: Option<String>,
+    version: Option<String>,
+}
+
+impl Partial {
+    fn finish(self) -> Option<Tool> {
+        let version = self.version.filter(|v| v != "(devel)")?;
+        Some(Tool {
+            name: self.name,
+            path: self.path?,
+            version,
+            go: self.go,
+        })
+    }
+}
@@ -0,0 +1,1269 @@
+//! Changes to the installed versions, made against a single load of the version file
+
+use crate::build;
+use crate::bundle;
+use crate::cache::{self, CacheMode};
+use crate::config::EnableMode;
+use crate::error::GoupError;
+use crate::exec;
+use crate::lock::Lock;
+use crate::mirror;
+use crate::provenance::Provenance;
+use crate::remote::{self, FileInfo};
+use crate::state::{CustomSource, LinkedToolchain, PinInfo, VersionFile};
+use crate::trash::{Disposal, Trashed};
+use crate::usage::Snapshot;
+use crate::version::GoVersion;
+use crate::{remove_tree, CleanPolicy, CleanReport, Goup, Removed, STATE_LOCK_TIMEOUT};
+use std::collections::BTreeSet;
+use std::path::{Component, Path, PathBuf};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use std::{env, fs, io, process};
+
+/// A set of changes to the installed versions, made through [`Goup::transaction`]
+///
+/// Each change updates the record of installed versions only once the change on disk has
+/// succeeded, so the record always describes what is actually installed.
+pub struct Transaction<'a> {
+    pub(crate) goup: &'a Goup,
+    pub(crate) state: VersionFile,
+}
+
+impl Transaction<'_> {
+    /// The record of installed, enabled, and pinned versions, including changes made so far
+    pub fn state(&self) -> &VersionFile {
+        &self.state
+    }
+
+    /// Install the given version of Go from an archive looked up with [`Goup::available`],
+    /// if it isn't installed already. Returns the number of bytes left out of a minimal
+    /// install, per [`Config::minimal`](crate::Config::minimal).
+    pub fn download(&mut self, version: GoVersion, file: &FileInfo) -> Result<u64, GoupError> {
+        let Some(_lock) = self.lock_install(version)? else {
+            return Ok(0);
+        };
+
+        let goup = self.goup;
+        // Where official checksums were imported, the archive must match those rather than
+        // whatever the index says
+        let checksums = goup.checksums()?;
+        let official = checksums.official(file);
+        let file = official.as_ref().unwrap_or(file);
+        let target = goup.install_dir(version);
+        let minimal = goup.config.minimal();
+        let verify = official.is_some() || goup.config.verify_checksums();
+        let omitted = match self.saved_archive(file) {
+            Some(archive) => cleanup_on_error(&target, || {
+                let omitted = goup.timed("extract", || {
+                    remote::unpack_saved(&goup.config, &archive, &target, goup.progress.as_ref())
+                })?;
+                let source = archive.display().to_string();
+                Provenance::new(&source, file, minimal).store(&target)?;
+                Ok(omitted)
+            })?,
+            None => {
+                let agent = goup.client(&format!("download {}", version))?;
+                let keep = match goup.cache_mode {
+                    CacheMode::Normal | CacheMode::Refresh => {
+                        Some(goup.archive_cache_dir().join(&file.filename))
+                    }
+                    CacheMode::Off => None,
+                };
+                let progress = goup.progress.as_ref();
+                let omitted = goup.with_mirrors(|mirror| {
+                    goup.config.retry.run(|| {
+                        cleanup_on_error(&target, || {
+                            let omitted = remote::download_archive(
+                                agent,
+                                &goup.config,
+                                mirror,
+                                file,
+                                &target,
+                                keep.as_deref(),
+                                verify,
+                                progress,
+                            )?;
+                            let source = mirror::archive_url(mirror, &file.filename);
+                            Provenance::new(&source, file, minimal).store(&target)?;
+                            Ok(omitted)
+                        })
+                    })
+                });
+                let omitted = omitted.map_err(|e| checksums.explain(e))?;
+                // The cache is only an optimization, so failing to trim it isn't worth
+                // failing the install over
+                let _ = goup.evict_archives();
+                omitted
+            }
+        };
+        self.state.installed.insert(version);
+        if goup.config.minimal() {
+            self.state.minimal.insert(version);
+        }
+        Ok(omitted)
+    }
+
+    /// Install the given version for the length of one command, as `goup try` does, marking
+    /// it ephemeral so that `clean` leaves it alone. Returns whether it was installed here,
+    /// rather than being installed already, in which case it isn't marked.
+    pub fn download_ephemeral(
+        &mut self,
+        version: GoVersion,
+        file: &FileInfo,
+    ) -> Result<bool, GoupError> {
+        if self.state.installed.contains(&version)
+            || self.goup.state()?.installed.contains(&version)
+        {
+            return Ok(false);
+        }
+        self.download(version, file)?;
+        self.state.ephemeral.insert(version);
+        Ok(true)
+    }
+
+    /// Finish with a version installed by [`Transaction::download_ephemeral`], removing it
+    /// unless `keep` is given, or it was pinned or enabled in the meantime. Either way it is no
+    /// longer ephemeral. Returns whether it was removed.
+    pub fn settle_ephemeral(&mut self, version: GoVersion, keep: bool) -> Result<bool, GoupError> {
+        if !self.state.ephemeral.remove(&version) {
+            return Ok(false);
+        }
+        if keep || self.state.pinned.contains(&version) || self.state.enabled == Some(version) {
+            return Ok(false);
+        }
+        self.remove(version)?;
+        Ok(true)
+    }
+
+    /// Take the lock on installing `version`, unless it is installed already, whether by this
+    /// transaction or by another process since it began
+    fn lock_install(&mut self, version: GoVersion) -> Result<Option<Lock>, GoupError> {
+        if self.state.installed.contains(&version) {
+            return Ok(None);
+        }
+        let lock = match Lock::try_acquire(&self.goup.lock_file(&version.to_string()))? {
+            Ok(lock) => lock,
+            Err(pid) => return Err(GoupError::InstallInProgress { version, pid }),
+        };
+        if self.goup.state()?.installed.contains(&version) {
+            self.state.installed.insert(version);
+            return Ok(None);
+        }
+        Ok(Some(lock))
+    }
+
+    /// Install the given version of Go by building its source release, `file`, with the
+    /// installed version `bootstrap`, if it isn't installed already. Everything the build
+    /// prints is kept in [`Goup::build_log`], and passed to the [`Progress`](crate::Progress).
+    ///
+    /// A failed build leaves nothing behind but its log.
+    pub fn build(
+        &mut self,
+        version: GoVersion,
+        file: &FileInfo,
+        bootstrap: GoVersion,
+    ) -> Result<(), GoupError> {
+        if !self.state.installed.contains(&bootstrap) {
+            return Err(GoupError::VersionNotInstalled(bootstrap));
+        }
+        let needs = build::bootstrap_version(version);
+        if bootstrap < needs {
+            return Err(GoupError::BootstrapTooOld {
+                version,
+                bootstrap,
+                needs,
+            });
+        }
+        let Some(_lock) = self.lock_install(version)? else {
+            return Ok(());
+        };
+
+        let goup = self.goup;
+        let progress = goup.progress.as_ref();
+        let archive = match self.saved_archive(file) {
+            Some(archive) => archive,
+            None => {
+                let agent = goup.client(&format!("download the source of {}", version))?;
+                let dest = goup.archive_cache_dir().join(&file.filename);
+                goup.with_mirrors(|mirror| {
+                    goup.config.retry.run(|| {
+                        remote::download_source(agent, &goup.config, mirror, file, &dest, progress)
+                    })
+                })?;
+                dest
+            }
+        };
+
+        let target = goup.install_dir(version);
+        let built = cleanup_on_error(&target, || {
+            goup.timed("extract", || {
+                remote::unpack_source(&archive, &target, progress)
+            })?;
+            build::make(
+                version,
+                &goup.goroot(version),
+                &goup.goroot(bootstrap),
+                &goup.build_log(version),
+                progress,
+            )?;
+            let mut provenance = Provenance::new(&archive.display().to_string(), file, false);
+            provenance.os = env::consts::OS.to_string();
+            provenance.arch = remote::arch().unwrap_or(env::consts::ARCH).to_string();
+            provenance.bootstrap = Some(bootstrap);
+            provenance.store(&target)
+        });
+        if goup.cache_mode == CacheMode::Off {
+            let _ = fs::remove_file(&archive);
+        }
+        built?;
+        self.state.installed.insert(version);
+        Ok(())
+    }
+
+    /// The archive kept from an earlier download of `file`, if the cache may be read and the
+    /// archive still matches the checksum published for it. A corrupt archive is deleted.
+    fn saved_archive(&self, file: &FileInfo) -> Option<PathBuf> {
+        if self.goup.cache_mode != CacheMode::Normal {
+            return None;
+        }
+        let path = self.goup.archive_cache_dir().join(&file.filename);
+        let mut archive = fs::File::open(&path).ok()?;
+        let digest = self
+            .goup
+            .timed("checksum", || bundle::sha256(&mut archive))
+            .ok()?;
+        if !digest.eq_ignore_ascii_case(&file.sha256) {
+            let _ = fs::remove_file(&path);
+            return None;
+        }
+        cache::touch(&path);
+        Some(path)
+    }
+
+    /// Install the version in a bundle made by [`Goup::export`], returning which version it was
+    pub fn import(&mut self, path: &Path) -> Result<GoVersion, GoupError> {
+        let goup = self.goup;
+        // Unpacked beside the installations, so that it can be moved into place at once
+        let staging = goup.tmp_dir().join(process::id().to_string());
+        let _ = fs::remove_dir_all(&staging);
+        let res = self.import_staged(path, &staging);
+        let _ = fs::remove_dir_all(&staging);
+        res
+    }
+
+    fn import_staged(&mut self, path: &Path, staging: &Path) -> Result<GoVersion, GoupError> {
+        let goup = self.goup;
+        let manifest = bundle::read(path, staging)?;
+        // A bundle only says which archive it was installed from, so that is what is checked
+        if let Some(provenance) = &manifest.provenance {
+            if !provenance.sha256.is_empty() {
+                goup.checksums()?
+                    .verify(&provenance.filename, &provenance.sha256)?;
+            }
+        }
+        let version = manifest.version;
+        let _lock = match Lock::try_acquire(&goup.lock_file(&version.to_string()))? {
+            Ok(lock) => lock,
+            Err(pid) => return Err(GoupError::InstallInProgress { version, pid }),
+        };
+        let target = goup.install_dir(version);
+        if self.state.installed.contains(&version) || target.exists() {
+            return Err(GoupError::VersionAlreadyInstalled(version));
+        }
+
+        let provenance = manifest.provenance.unwrap_or_else(|| Provenance {
+            goup_version: env!("CARGO_PKG_VERSION").to_string(),
+            source: path.display().to_string(),
+            filename: path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            os: manifest.os,
+            arch: manifest.arch,
+            ..Default::default()
+        });
+        provenance.store(staging)?;
+        let dir = goup.versions_dir();
+        fs::create_dir_all(&dir).map_err(GoupError::io("create", &dir))?;
+        fs::rename(staging, &target).map_err(GoupError::io("create", &target))?;
+        self.state.installed.insert(version);
+        if provenance.minimal {
+            self.state.minimal.insert(version);
+        }
+        Ok(version)
+    }
+
+    /// Install the Go installation at `goroot`, which holds `version`, by copying it into place,
+    /// or with `move_tree`, by moving it. Files are hard linked rather than copied where
+    /// possible, so the original can be changed or deleted without affecting the copy.
+    pub fn adopt(
+        &mut self,
+        goroot: &Path,
+        version: GoVersion,
+        move_tree: bool,
+    ) -> Result<(), GoupError> {
+        let goup = self.goup;
+        let _lock = match Lock::try_acquire(&goup.lock_file(&version.to_string()))? {
+            Ok(lock) => lock,
+            Err(pid) => return Err(GoupError::InstallInProgress { version, pid }),
+        };
+        let target = goup.install_dir(version);
+        if self.state.installed.contains(&version) || target.exists() {
+            return Err(GoupError::VersionAlreadyInstalled(version));
+        }
+
+        // Assembled beside the installations, so that it can be moved into place at once
+        let staging = goup.tmp_dir().join(process::id().to_string());
+        let _ = fs::remove_dir_all(&staging);
+        cleanup_on_error(&staging, || {
+            fs::create_dir_all(&staging).map_err(GoupError::io("create", &staging))?;
+            let staged = staging.join("go");
+            if !move_tree || fs::rename(goroot, &staged).is_err() {
+                copy_tree(goroot, &staged).map_err(GoupError::io("copy", goroot))?;
+            }
+            let provenance = Provenance {
+                goup_version: env!("CARGO_PKG_VERSION").to_string(),
+                installed_at: SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+                source: goroot.display().to_string(),
+                ..Default::default()
+            };
+            provenance.store(&staging)?;
+            let dir = goup.versions_dir();
+            fs::create_dir_all(&dir).map_err(GoupError::io("create", &dir))?;
+            fs::rename(&staging, &target).map_err(GoupError::io("create", &target))
+        })?;
+        // A tree that couldn't be renamed was copied, and is only removed once that succeeded
+        if move_tree && goroot.exists() {
+            fs::remove_dir_all(goroot).map_err(GoupError::io("remove", goroot))?;
+        }
+        self.state.installed.insert(version);
+        Ok(())
+    }
+
+    /// Point the `go` symlink at the given version, which must already be installed. With
+    /// [`EnableMode::Copy`], `go` is made a copy of the version instead.
+    pub fn enable(&mut self, version: GoVersion) -> Result<(), GoupError> {
+        if !self.state.installed.contains(&version) {
+            return Err(GoupError::VersionNotInstalled(version));
+        }
+
+        let start = Instant::now();
+        self.goup.check_link_dirs()?;
+        let link = self.goup.link_path();
+        match self.goup.config.enable_mode {
+            EnableMode::Symlink => {
+                self.disable()?;
+                let goroot = self.goup.goroot(version);
+                match self.symlink(&goroot, &link) {
+                    Ok(()) => self.place_other_links(&goroot)?,
+                    // Where not even a junction can be made, such as on some network drives,
+                    // a copy still gives a working `go`
+                    Err(_) if cfg!(windows) => self.enable_copy(version, &link)?,
+                    Err(e) => return Err(e),
+                }
+            }
+            EnableMode::Copy => self.enable_copy(version, &link)?,
+        }
+        self.state.enabled = Some(version);
+        self.state.enabled_linked = None;
+        self.state.record_use(version);
+        self.goup.progress.phase("enable", start.elapsed());
+        Ok(())
+    }
+
+    /// Make `link` a copy of the given version, as [`EnableMode::Copy`] does
+    fn enable_copy(&mut self, version: GoVersion, link: &Path) -> Result<(), GoupError> {
+        // Made beside the link, so that it can be moved into place, unless that's the root
+        // itself
+        let copy = match (&self.goup.config.link_path, link.parent()) {
+            (Some(_), Some(dir)) => dir.join(format!(".goup-{}.go", process::id())),
+            _ => self.goup.tmp_dir().join(format!("{}.go", process::id())),
+        };
+        let _ = fs::remove_dir_all(&copy);
+        let res = copy_tree(&self.goup.goroot(version), &copy)
+            .and_then(|()| fs::write(copy.join(COPY_MARKER), version.to_string()))
+            .map_err(GoupError::io("copy into", &copy))
+            .and_then(|()| self.replace_enabled(&copy));
+        let _ = fs::remove_dir_all(&copy);
+        res?;
+        self.place_other_links(link)?;
+        self.state.copied = true;
+        Ok(())
+    }
+
+    /// Record that the given version is being used, for [`CleanPolicy::unused_for`]. This is
+    /// done at most once a day for each version.
+    pub fn record_use(&mut self, version: GoVersion) -> Result<(), GoupError> {
+        if !self.state.installed.contains(&version) {
+            return Err(GoupError::VersionNotInstalled(version));
+        }
+        self.state.record_use(version);
+        Ok(())
+    }
+
+    /// Remove the `go` symlink, or the copy of the enabled version, so that no version is
+    /// enabled
+    fn disable(&mut self) -> Result<(), GoupError> {
+        let link = self.goup.link_path();
+        let res = match fs::symlink_metadata(&link) {
+            Ok(metadata) if metadata.is_dir() => match self.goup.copied_version() {
+                Some(_) => fs::remove_dir_all(&link),
+                None => return Err(foreign_link(&link)),
+            },
+            Ok(_) => remove_link(&link),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e),
+        };
+        res.map_err(GoupError::io("remove", &link))?;
+        let others = self.goup.link_paths().into_iter().skip(1);
+        for other in others.chain(self.recorded_links()) {
+            remove_old_link(&other)?;
+        }
+        self.state.enabled = None;
+        self.state.enabled_linked = None;
+        self.state.copied = false;
+        self.state.junction = false;
+        self.state.links.clear();
+        Ok(())
+    }
+
+    /// Where the links to the enabled version were made, as recorded in the version file.
+    /// Files written before the links were recorded had them in the root.
+    fn recorded_links(&self) -> Vec<PathBuf> {
+        let enabled = self.state.enabled.is_some() || self.state.enabled_linked.is_some();
+        match (self.state.links.is_empty(), enabled) {
+            (true, true) => vec![self.goup.root.join("go")],
+            _ => self.state.links.clone(),
+        }
+    }
+
+    /// Once [`Goup::link_path`](crate::Goup::link_path) is in place, remove the links that
+    /// `link_path` no longer lists, point the others it lists at `target`, and record where
+    /// they all are if they aren't in the default place
+    fn place_other_links(&mut self, target: &Path) -> Result<(), GoupError> {
+        let links = self.goup.link_paths();
+        for old in self.recorded_links() {
+            if !links.contains(&old) {
+                remove_old_link(&old)?;
+            }
+        }
+        for link in &links[1..] {
+            remove_old_link(link)?;
+            if link.symlink_metadata().is_ok() {
+                return Err(foreign_link(link));
+            }
+            self.symlink(target, link)?;
+        }
+        // The default link moves along with the root, so there's no need to record it
+        self.state.links = match self.goup.config.link_path {
+            Some(_) => links,
+            None => Vec::new(),
+        };
+        Ok(())
+    }
+
+    /// Put the links to the enabled version where `link_path` now says, if they were made
+    /// somewhere else. Returns whether anything was moved.
+    pub fn relink(&mut self) -> Result<bool, GoupError> {
+        if !self.goup.links_moved()? {
+            return Ok(false);
+        }
+        match (self.state.enabled, self.state.enabled_linked.clone()) {
+            (_, Some(name)) => self.enable_linked(&name)?,
+            (Some(version), None) => self.enable(version)?,
+            (None, None) => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Point the symlinks to the enabled version or toolchain at where it is now, after it was
+    /// moved by [`Goup::migrate`](crate::Goup::migrate). Copies are left as they are. Returns
+    /// the links that were changed.
+    pub(crate) fn repoint_links(&mut self) -> Result<Vec<PathBuf>, GoupError> {
+        let target = match (self.state.enabled, &self.state.enabled_linked) {
+            (_, Some(name)) => match self.state.linked.get(name) {
+                Some(toolchain) => toolchain.path.clone(),
+                None => return Ok(Vec::new()),
+            },
+            (Some(version), None) => self.goup.goroot(version),
+            (None, None) => return Ok(Vec::new()),
+        };
+        let mut repointed = Vec::new();
+        for link in self.recorded_links() {
+            let is_symlink = fs::symlink_metadata(&link).is_ok_and(|m| m.is_symlink());
+            if !is_symlink || link.canonicalize().ok() == target.canonicalize().ok() {
+                continue;
+            }
+            remove_link(&link).map_err(GoupError::io("remove", &link))?;
+            self.symlink(&target, &link)?;
+            repointed.push(link);
+        }
+        Ok(repointed)
+    }
+
+    /// Register the Go installation at `path`, such as a build of Go from source, as a
+    /// toolchain called `name`, replacing any linked under that name before. It can then be
+    /// enabled and run like an installed version, but goup never deletes it.
+    pub fn link(&mut self, name: &str, path: &Path) -> Result<&LinkedToolchain, GoupError> {
+        LinkedToolchain::check_name(name).map_err(|reason| GoupError::InvalidToolchainName {
+            name: name.to_string(),
+            reason,
+        })?;
+        let path = path.canonicalize().map_err(|e| GoupError::InvalidGoTree {
+            path: path.to_path_buf(),
+            reason: format!("unable to find it: {}", e),
+        })?;
+        let toolchain = toolchain(path, None)?;
+        self.state.linked.insert(name.to_string(), toolchain);
+        Ok(&self.state.linked[name])
+    }
+
+    /// Install the archive at `url` as a toolchain called `name`, which can then be enabled,
+    /// run, and removed like a linked one. The archive is checked against `sha256` unless it
+    /// is `None`, and otherwise downloaded and unpacked just as a release would be, into
+    /// [`Goup::custom_dir`](crate::Goup::custom_dir). `clean` and `update` leave it alone.
+    pub fn install_custom(
+        &mut self,
+        name: &str,
+        url: &str,
+        sha256: Option<&str>,
+    ) -> Result<&LinkedToolchain, GoupError> {
+        LinkedToolchain::check_name(name).map_err(|reason| GoupError::InvalidToolchainName {
+            name: name.to_string(),
+            reason,
+        })?;
+        let goup = self.goup;
+        let _lock = match Lock::try_acquire(&goup.lock_file(name))? {
+            Ok(lock) => lock,
+            Err(pid) => {
+                return Err(GoupError::ToolchainInstallInProgress {
+                    name: name.to_string(),
+                    pid,
+                })
+            }
+        };
+        if self.state.linked.contains_key(name) || goup.state()?.linked.contains_key(name) {
+            return Err(GoupError::ToolchainExists(name.to_string()));
+        }
+
+        let filename = url
+            .rsplit('/')
+            .next()
+            .and_then(|last| last.split(['?', '#']).next())
+            .filter(|last| !last.is_empty())
+            .unwrap_or(name);
+        let file = FileInfo {
+            filename: filename.to_string(),
+            os: env::consts::OS.to_string(),
+            arch: remote::arch().unwrap_or(env::consts::ARCH).to_string(),
+            version: name.to_string(),
+            sha256: sha256.unwrap_or_default().to_string(),
+            size: 0,
+            kind: "archive".to_string(),
+        };
+        let agent = goup.client(&format!("download {}", name))?;
+        let dir = goup.custom_dir(name);
+        let archive = dir.with_file_name(format!("{}.tar.gz", name));
+        let progress = goup.progress.as_ref();
+        goup.config
+            .retry
+            .run(|| remote::download_file(agent, &goup.config, url, &file, &archive, progress))?;
+        let installed = cleanup_on_error(&dir, || {
+            goup.timed("extract", || {
+                remote::unpack_saved(&goup.config, &archive, &dir, progress)
+            })?;
+            let source = CustomSource {
+                url: url.to_string(),
+                sha256: sha256.map(str::to_lowercase),
+            };
+            toolchain(dir.join("go"), Some(source))
+        });
+        let _ = fs::remove_file(&archive);
+        self.state.linked.insert(name.to_string(), installed?);
+        Ok(&self.state.linked[name])
+    }
+
+    /// Point the `go` symlink at the linked toolchain called `name`. Linked toolchains are
+    /// always symlinked, whatever the [`EnableMode`].
+    pub fn enable_linked(&mut self, name: &str) -> Result<(), GoupError> {
+        let path = match self.state.linked.get(name) {
+            Some(toolchain) => toolchain.path.clone(),
+            None => return Err(GoupError::ToolchainNotLinked(name.to_string())),
+        };
+
+        let start = Instant::now();
+        self.goup.check_link_dirs()?;
+        let link = self.goup.link_path();
+        self.disable()?;
+        self.symlink(&path, &link)?;
+        self.place_other_links(&path)?;
+        self.state.enabled_linked = Some(name.to_string());
+        self.goup.progress.phase("enable", start.elapsed());
+        Ok(())
+    }
+
+    /// Forget the linked toolchain called `name`, leaving its directory alone, unless goup
+    /// installed it with [`Transaction::install_custom`], in which case it is deleted. If it
+    /// was enabled, no version will be enabled afterwards.
+    pub fn unlink(&mut self, name: &str) -> Result<LinkedToolchain, GoupError> {
+        let Some(toolchain) = self.state.linked.get(name) else {
+            return Err(GoupError::ToolchainNotLinked(name.to_string()));
+        };
+        let custom = toolchain.is_custom();
+        if self.state.enabled_linked.as_deref() == Some(name) {
+            self.disable()?;
+        }
+        if custom {
+            remove_tree(&self.goup.custom_dir(name))?;
+        }
+        Ok(self.state.linked.remove(name).expect("checked above"))
+    }
+
+    /// Delete whatever is at [`Goup::link_path`](crate::Goup::link_path), even a directory
+    /// that goup didn't create, so that a version can be enabled there. Only for when the user
+    /// has asked for it; returns whether such a directory was deleted.
+    pub fn clear_link(&mut self) -> Result<bool, GoupError> {
+        let link = self.goup.link_path();
+        let foreign = fs::symlink_metadata(&link).is_ok_and(|m| m.is_dir())
+            && self.goup.copied_version().is_none();
+        if foreign {
+            fs::remove_dir_all(&link).map_err(GoupError::io("remove", &link))?;
+        }
+        self.disable()?;
+        Ok(foreign)
+    }
+
+    /// Create a symlink at `link` pointing at `target`, by a path relative to the link's
+    /// directory if [`Config::relative_symlinks`](crate::Config::relative_symlinks) is set
+    #[cfg(unix)]
+    fn symlink(&mut self, target: &Path, link: &Path) -> Result<(), GoupError> {
+        let target = self.link_target(target, link);
+        std::os::unix::fs::symlink(target, link).map_err(GoupError::io("create symlink at", link))
+    }
+
+    /// Create a symlink at `link` pointing at `target`, as on Unix. Windows only lets
+    /// symlinks be made in Developer Mode or from an elevated prompt, so otherwise a
+    /// junction is made instead, and recorded in [`VersionFile::junction`].
+    #[cfg(windows)]
+    fn symlink(&mut self, target: &Path, link: &Path) -> Result<(), GoupError> {
+        let relative = self.link_target(target, link);
+        match std::os::windows::fs::symlink_dir(relative, link) {
+            Ok(()) => Ok(()),
+            Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => {
+                junction(target, link).map_err(GoupError::io("create junction at", link))?;
+                self.state.junction = true;
+                Ok(())
+            }
+            Err(e) => Err(GoupError::io("create symlink at", link)(e)),
+        }
+    }
+
+    /// What a symlink at `link` should hold to point at `target`: a path relative to the
+    /// link's directory if [`Config::relative_symlinks`](crate::Config::relative_symlinks) is
+    /// set, and otherwise `target` itself
+    fn link_target(&self, target: &Path, link: &Path) -> PathBuf {
+        match (self.goup.config.relative_symlinks(), link.parent()) {
+            // Both are resolved, as the link will be resolved from where its directory
+            // really is
+            (true, Some(dir)) => match (dir.canonicalize(), target.canonicalize()) {
+                (Ok(dir), Ok(target)) => relative_path(&dir, &target),
+                _ => target.to_path_buf(),
+            },
+            _ => target.to_path_buf(),
+        }
+    }
+
+    /// Put the freshly prepared `copy` in place of the `go` symlink or the previous copy. Where
+    /// the platform allows, the two are exchanged in a single step, so that `go` never goes
+    /// missing; `copy` then holds whatever was there before.
+    fn replace_enabled(&mut self, copy: &Path) -> Result<(), GoupError> {
+        let link = self.goup.link_path();
+        let is_copy = fs::symlink_metadata(&link).is_ok_and(|m| m.is_dir());
+        if is_copy && self.goup.copied_version().is_none() {
+            return Err(foreign_link(&link));
+        }
+
+        if link.symlink_metadata().is_ok() && exchange(copy, &link) {
+            let previous = if is_copy {
+                fs::remove_dir_all(copy)
+            } else {
+                remove_link(copy)
+            };
+            return previous.map_err(GoupError::io("remove", copy));
+        }
+        self.disable()?;
+        fs::rename(copy, &link).map_err(GoupError::io("create", link))
+    }
+
+    /// Fail the way [`Transaction::remove`] would if the version can't be removed at all, so
+    /// that the user isn't asked to confirm a removal that would be refused anyway
+    pub fn check_removable(&self, version: GoVersion) -> Result<(), GoupError> {
+        if !self.state.installed.contains(&version) {
+            return Err(GoupError::VersionNotInstalled(version));
+        } else if self.state.pinned.contains(&version) {
+            return Err(GoupError::VersionPinned(version));
+        }
+
+        let dir = self.goup.install_dir(version);
+        if dir.exists() && !self.goup.created_by_goup(version) {
+            return Err(GoupError::NotCreatedByGoup(dir));
+        }
+        Ok(())
+    }
+
+    /// Uninstall the given version. Pinned versions cannot be removed. With
+    /// [`Config::use_trash`](crate::Config::use_trash), it is moved to the trash instead of
+    /// being deleted, and is no longer installed all the same.
+    ///
+    /// If the version was enabled, no version will be enabled afterwards.
+    pub fn remove(&mut self, version: GoVersion) -> Result<Disposal, GoupError> {
+        self.check_removable(version)?;
+        // A copy of the version would otherwise outlive it, along with its disk space
+        if self.state.enabled == Some(version) && self.state.copied {
+            self.disable()?;
+        }
+        let disposal = self.goup.dispose(version)?;
+        self.state.installed.remove(&version);
+        self.state.minimal.remove(&version);
+        self.state.ephemeral.remove(&version);
+        self.state.last_used.remove(&version);
+        if self.state.enabled == Some(version) {
+            self.state.enabled = None;
+        }
+        if let Disposal::Trashed(_) = disposal {
+            // The trash only holds what could be restored, so trimming it isn't worth failing
+            // the removal over
+            let _ = self.goup.trim_trash();
+        }
+        Ok(disposal)
+    }
+
+    /// Install the given version again from the trash, from where it was most recently put,
+    /// returning what was taken out of the trash
+    pub fn restore(&mut self, version: GoVersion) -> Result<Trashed, GoupError> {
+        let Some(_install) = self.lock_install(version)? else {
+            return Err(GoupError::VersionAlreadyInstalled(version));
+        };
+        let _lock = Lock::acquire(&self.goup.lock_file("trash"), STATE_LOCK_TIMEOUT)?;
+        let trashed = self
+            .goup
+            .trash()?
+            .into_iter()
+            .rfind(|t| t.version == version)
+            .ok_or(GoupError::NotInTrash(version))?;
+        let dir = self.goup.install_dir(version);
+        if let Some(parent) = dir.parent() {
+            fs::create_dir_all(parent).map_err(GoupError::io("create", parent))?;
+        }
+        fs::rename(&trashed.path, &dir).map_err(GoupError::io("move", &trashed.path))?;
+        self.state.installed.insert(version);
+        if self.goup.provenance(version).is_some_and(|p| p.minimal) {
+            self.state.minimal.insert(version);
+        }
+        Ok(trashed)
+    }
+
+    /// Pin the given version to keep it from being removed
+    ///
+    /// Pinning a version again with a `reason` replaces the reason given before, and makes a
+    /// pin that was to expire permanent.
+    pub fn pin(&mut self, version: GoVersion, reason: Option<String>) -> Result<(), GoupError> {
+        self.pin_expiring(version, reason, None)
+    }
+
+    /// Pin the given version to keep it from being removed until `expires`, after which
+    /// [`Transaction::clean`] may remove it again. Pinning a version again replaces its expiry.
+    pub fn pin_until(
+        &mut self,
+        version: GoVersion,
+        reason: Option<String>,
+        expires: SystemTime,
+    ) -> Result<(), GoupError> {
+        self.pin_expiring(version, reason, Some(expires))
+    }
+
+    fn pin_expiring(
+        &mut self,
+        version: GoVersion,
+        reason: Option<String>,
+        expires: Option<SystemTime>,
+    ) -> Result<(), GoupError> {
+        if !self.state.installed.contains(&version) {
+            return Err(GoupError::VersionNotInstalled(version));
+        }
+        let newly_pinned = self.state.pinned.insert(version);
+        match self.state.pin_info.get_mut(&version) {
+            Some(info) if !newly_pinned => {
+                if reason.is_some() {
+                    info.reason = reason;
+                }
+                info.set_expiry(expires);
+            }
+            _ => {
+                self.state
+                    .pin_info
+                    .insert(version, PinInfo::new(reason, expires));
+            }
+        }
+        Ok(())
+    }
+
+    /// Mark each expired pin that the user hasn't been told about as told, returning the
+    /// versions they pin
+    pub fn notice_expired_pins(&mut self) -> Vec<GoVersion> {
+        let mut noticed = Vec::new();
+        for (&version, info) in &mut self.state.pin_info {
+            if info.is_expired() && !info.expiry_noticed && self.state.pinned.contains(&version) {
+                info.expiry_noticed = true;
+                noticed.push(version);
+            }
+        }
+        noticed
+    }
+
+    /// Unpin every version whose pin has expired, returning them
+    pub fn prune_expired_pins(&mut self) -> Vec<GoVersion> {
+        let expired: Vec<_> = self
+            .state
+            .pinned
+            .iter()
+            .copied()
+            .filter(|&v| self.state.pin_expired(v))
+            .collect();
+        for &version in &expired {
+            self.state.pinned.remove(&version);
+            self.state.pin_info.remove(&version);
+        }
+        expired
+    }
+
+    /// Unpin the given version, allowing it to be removed. Fails with
+    /// [`GoupError::VersionNotPinned`] if it wasn't pinned, rather than doing nothing.
+    pub fn unpin(&mut self, version: GoVersion) -> Result<(), GoupError> {
+        self.state.pin_info.remove(&version);
+        if self.state.pinned.remove(&version) {
+            Ok(())
+        } else {
+            Err(GoupError::VersionNotPinned(version))
+        }
+    }
+
+    /// Trust every setting in the project config file at `path` while it still has the given
+    /// digest, as [`ProjectConfig::digest`](crate::config::ProjectConfig::digest) gives it
+    pub fn trust_project(&mut self, path: &Path, digest: String) {
+        self.state
+            .trusted_projects
+            .insert(path.to_path_buf(), digest);
+    }
+
+    /// Remove the installed versions selected by the given policy, returning the versions
+    /// that were removed
+    ///
+    /// Versions are deleted concurrently, reporting the space freed to the handle's
+    /// [`Progress`](crate::Progress). A version that can't be deleted doesn't stop the others,
+    /// and is listed among the failures instead.
+    pub fn clean(&mut self, policy: &CleanPolicy) -> Result<CleanReport, GoupError> {
+        let folder_versions = self.goup.version_folders()?;
+        // A dry run mustn't record anything, not even the corrections below
+        let mut scratch;
+        let state = if policy.dry_run {
+            scratch = self.state.clone();
+            &mut scratch
+        } else {
+            &mut self.state
+        };
+        let enabled = policy.assume_enabled.or(state.enabled);
+
+        // Fix our list of installed versions to only include those that are actually on disk.
+        // This would indicate that someone was tampering with our .goup directory.
+        state.installed = state
+            .installed
+            .intersection(&folder_versions)
+            .copied()
+            .collect();
+        state.pinned = state
+            .installed
+            .intersection(&state.pinned)
+            .copied()
+            .collect();
+        state.minimal = state
+            .installed
+            .intersection(&state.minimal)
+            .copied()
+            .collect();
+        state.ephemeral = state
+            .installed
+            .intersection(&state.ephemeral)
+            .copied()
+            .collect();
+        let pinned = &state.pinned;
+        state.pin_info.retain(|v, _| pinned.contains(v));
+        let installed = &state.installed;
+        state.last_used.retain(|v, _| installed.contains(v));
+        // An expired pin no longer keeps its version
+        let held: BTreeSet<_> = state
+            .pinned
+            .iter()
+            .copied()
+            .filter(|&v| state.pin_holds(v))
+            .collect();
+
+        // Keep any version of Go that is still available, that is pinned, or enabled.
+        let prereleases = policy.prereleases || self.goup.config.clean_prereleases();
+        let listed: BTreeSet<_> = if policy.include_available && !prereleases {
+            BTreeSet::new()
+        } else {
+            self.goup.available()?.into_keys().collect()
+        };
+        // A pre-release is superseded once its release is out, wherever it is
+        let (superseded, unreleased): (BTreeSet<_>, BTreeSet<_>) = if prereleases {
+            state
+                .installed
+                .iter()
+                .filter(|v| v.is_prerelease())
+                .partition(|v| {
+                    let release = v.release();
+                    listed.contains(&release) || state.installed.contains(&release)
+                })
+        } else {
+            Default::default()
+        };
+        let available: BTreeSet<_> = if policy.include_available {
+            BTreeSet::new()
+        } else {
+            listed.difference(&superseded).copied().collect()
+        };
+        let mut report = CleanReport {
+            superseded: superseded.into_iter().collect(),
+            unreleased: unreleased.into_iter().collect(),
+            pinned: held
+                .iter()
+                .filter(|v| !available.contains(v) && enabled != Some(**v))
+                .copied()
+                .collect(),
+            ..Default::default()
+        };
+        let retained: Vec<_> = match policy.retain {
+            Some(retain) => state
+                .installed
+                .iter()
+                .chain(&policy.keep)
+                .chain(&enabled)
+                .filter(|v| !held.contains(v) && !state.ephemeral.contains(v))
+                .copied()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .rev()
+                .take(retain)
+                .collect(),
+            None => Vec::new(),
+        };
+        // A version that `goup try` installed is its to remove, even if it was installed since
+        // this transaction began
+        let ephemeral = self.goup.state()?.ephemeral;
+        let allowlist: BTreeSet<_> = available
+            .into_iter()
+            .chain(held)
+            .chain(state.ephemeral.iter().copied())
+            .chain(ephemeral)
+            .chain(enabled)
+            .chain(policy.keep.iter().copied())
+            .chain(retained)
+            .collect();
+
+        let mut candidates = Vec::new();
+        // Only looked at once there is something to remove, since it reads every process
+        let mut snapshot = None;
+        for &version in folder_versions.difference(&allowlist) {
+            if !self.goup.created_by_goup(version) {
+                report.skipped.push(version);
+                continue;
+            }
+            if let Some(older_than) = policy.older_than {
+                match self.goup.provenance(version) {
+                    Some(p) if p.installed_at().elapsed().unwrap_or_default() < older_than => {
+                        continue
+                    }
+                    Some(_) => {}
+                    None => {
+                        report.unknown_age.push(version);
+                        continue;
+                    }
+                }
+            }
+            if let Some(unused_for) = policy.unused_for {
+                match state.last_used(version) {
+                    Some(t) if t.elapsed().unwrap_or_default() < unused_for => continue,
+                    Some(_) => {}
+                    None => {
+                        report.never_used.push(version);
+                        continue;
+                    }
+                }
+            }
+            if !policy.ignore_usage {
+                let users = snapshot
+                    .get_or_insert_with(Snapshot::take)
+                    .as_ref()
+                    .map(|s| s.using(&self.goup.install_dir(version)))
+                    .unwrap_or_default();
+                if !users.is_empty() {
+                    report.in_use.push((version, users));
+                    continue;
+                }
+            }
+            match self.goup.size(version) {
+                Ok(size) => candidates.push(Removed {
+                    version,
+                    size,
+                    disposal: Disposal::Deleted,
+                }),
+                Err(e) => report.failed.push((version, e)),
+            }
+        }
+
+        if policy.dry_run {
+            report.removed = candidates;
+        } else {
+            let progress = self.goup.progress.as_ref();
+            progress.start(candidates.iter().map(|c| c.size).sum());
+            for (mut removed, res) in self.goup.delete_all(candidates, policy.jobs()) {
+                match res {
+                    Ok(disposal) => {
+                        removed.disposal = disposal;
+                        state.installed.remove(&removed.version);
+                        state.minimal.remove(&removed.version);
+                        state.last_used.remove(&removed.version);
+                        report.removed.push(removed);
+                    }
+                    Err(e) => report.failed.push((removed.version, e)),
+                }
+            }
+            progress.finish(report.failed.is_empty());
+            if report
+                .removed
+                .iter()
+                .any(|r| matches!(r.disposal, Disposal::Trashed(_)))
+            {
+                let _ = self.goup.trim_trash();
+            }
+        }
+
+        report.removed.sort_by_key(|r| r.version);
+        report.failed.sort_by_key(|(v, _)| *v);
+        Ok(report)
+    }
+}
+
+/// Remove the symlink or copy of a version that goup made at `link`, leaving anything else
+fn remove_old_link(link: &Path) -> Result<(), GoupError> {
+    let res = match fs::symlink_metadata(link) {
+        Ok(metadata) if metadata.is_symlink() => remove_link(link),
+        Ok(metadata) if metadata.is_dir() && link.join(COPY_MARKER).is_file() => {
+            fs::remove_dir_all(link)
+        }
+        _ => Ok(()),
+    };
+    res.map_err(GoupError::io("remove", link))
+}
+
+/// The error for a directory at `link` that goup didn't create, noting the version of Go it
+/// holds if it looks like an installation
+fn foreign_link(link: &Path) -> GoupError {
+    let version = fs::read_to_string(link.join("VERSION"))
+        .ok()
+        .and_then(|text| text.lines().next()?.trim().parse().ok());
+    GoupError::ForeignLink {
+        path: link.to_path_buf(),
+        version,
+    }
+}
+
+/// The path to `target` from the directory `base`, going up through `..` as far as the two
+/// have nothing in common. Both must be absolute, without any `.` or `..` within them.
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+    let mut relative: PathBuf = base[common..]
+        .iter()
+        .map(|_| Component::ParentDir)
+        .collect();
+    relative.extend(&target[common..]);
+    if relative.as_os_str().is_empty() {
+        relative.push(Component::CurDir);
+    }
+    relative
+}
+
+/// The file within a copied `go` directory naming the version it is a copy of
+pub(crate) const COPY_MARKER: &str = ".goup-enabled";
+
+/// Copy the directory tree at `from` to `to`, hard linking files rather than copying them where
+/// possible. Symlinks within the tree are recreated as they are.
+fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let (source, target) = (entry.path(), to.join(entry.file_name()));
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            copy_tree(&source, &target)?;
+        } else if file_type.is_symlink() {
+            copy_symlink(&source, &target)?;
+        } else if fs::hard_link(&source, &target).is_err() {
+            fs::copy(&source, &target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(fs::read_link(source)?, target)
+}
+
+/// Recreate the symlink at `source` at `target`, as a copy of what it points to if that can't
+/// be done, since it takes Developer Mode or an elevated prompt. Go's own releases hold none.
+#[cfg(windows)]
+fn copy_symlink(source: &Path, target: &Path) -> io::Result<()> {
+    use std::os::windows::fs::{symlink_dir, symlink_file};
+    let points_to = fs::read_link(source)?;
+    let res = if source.is_dir() {
+        symlink_dir(&points_to, target)
+    } else {
+        symlink_file(&points_to, target)
+    };
+    match res {
+        Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) && source.is_dir() => {
+            copy_tree(source, target)
+        }
+        Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => {
+            fs::copy(source, target).map(|_| ())
+        }
+        res => res,
+    }
+}
+
+/// Remove the symlink at `link`. On Windows, a symlink or junction to a directory is removed
+/// as a directory.
+fn remove_link(link: &Path) -> io::Result<()> {
+    #[cfg(windows)]
+    {
+        use std::os::windows::fs::FileTypeExt;
+        if fs::symlink_metadata(link)?.file_type().is_symlink_dir() {
+            return fs::remove_dir(link);
+        }
+    }
+    fs::remove_file(link)
+}
+
+/// The error Windows gives for a symlink made without the privilege to make one
+#[cfg(windows)]
+const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+
+/// Make a directory junction at `link` pointing at `target`, with `mklink`, which comes with
+/// Windows. Unlike a symlink, a junction needs no special privilege, but it can only point at
+/// an absolute path on a local drive.
+#[cfg(windows)]
+fn junction(target: &Path, link: &Path) -> io::Result<()> {
+    use std::os::windows::process::CommandExt;
+    let target = std::path::absolute(target)?;
+    // Windows paths can't hold quotes, so quoting each keeps cmd from taking anything in
+    // them for syntax of its own
+    let output = process::Command::new("cmd")
+        .raw_arg(format!(
+            "/C mklink /J \"{}\" \"{}\"",
+            link.display(),
+            target.display()
+        ))
+        .output()?;
+    if output.status.success() {
+        return Ok(());
+    }
+    let message = if output.stderr.is_empty() {
+        output.stdout
+    } else {
+        output.stderr
+    };
+    Err(io::Error::other(
+        String::from_utf8_lossy(&message).trim().to_string(),
+    ))
+}
+
+/// Swap the files or directories at `a` and `b` in a single step, returning false if the
+/// platform or filesystem can't do that
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+fn exchange(a: &Path, b: &Path) -> bool {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let (Ok(a), Ok(b)) = (
+        CString::new(a.as_os_str().as_bytes()),
+        CString::new(b.as_os_str().as_bytes()),
+    ) else {
+        return false;
+    };
+    // SAFETY: both paths are valid NUL-terminated strings that outlive the call
+    let res = unsafe {
+        libc::renameat2(
+            libc::AT_FDCWD,
+            a.as_ptr(),
+            libc::AT_FDCWD,
+            b.as_ptr(),
+            libc::RENAME_EXCHANGE,
+        )
+    };
+    res == 0
+}
+
+#[cfg(not(all(target_os = "linux", target_env = "gnu")))]
+fn exchange(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+/// Describe the Go installation at `path` as a toolchain, with what its `go version` prints
+fn toolchain(path: PathBuf, custom: Option<CustomSource>) -> Result<LinkedToolchain, GoupError> {
+    let invalid = |reason: String| GoupError::InvalidGoTree {
+        path: path.clone(),
+        reason,
+    };
+    let go = path.join("bin").join("go");
+    if !go.is_file() {
+        return Err(invalid(
+            "it has no bin/go, so it may not have been built".to_string(),
+        ));
+    }
+    let output = exec::toolchain_command(&path, &go)
+        .arg("version")
+        .output()
+        .map_err(|e| invalid(format!("unable to run bin/go: {}", e)))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(invalid(format!("`go version` failed: {}", stderr.trim())));
+    }
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+    Ok(LinkedToolchain {
+        go_version: String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        path,
+        linked_at: now.unwrap_or_default().as_secs(),
+        custom,
+    })
+}
+
+/// Run `unpack`, removing `target` if it fails, so that a half-unpacked version isn't left
+/// lying around to be mistaken for an install
+fn cleanup_on_error<T>(
+    target: &Path,
+    unpack: impl FnOnce() -> Result<T, GoupError>,
+) -> Result<T, GoupError> {
+    let res = unpack();
+    if res.is_err() {
+        let _ = fs::remove_dir_all(target);
+    }
+    res
+}
@@ -0,0 +1,455 @@
+//! Driving goup's downloads from async code, through an HTTP client of the caller's choosing
+//!
+//! goup normally makes blocking requests with ureq. [`Goup::available_async`] and
+//! [`Goup::download_async`] instead make them through an [`AsyncTransport`], which can wrap
+//! whichever async client the caller already uses. [`ThreadedTransport`] works on any
+//! executor, by making goup's usual requests on threads of their own.
+//!
+//! Progress is reported to the handle's [`Progress`] from the task awaiting the operation, so
+//! a `Progress` that sends each update down a channel is all it takes to follow along from
+//! elsewhere.
+
+use crate::cache::CacheMode;
+use crate::config::Config;
+use crate::error::GoupError;
+use crate::lock::Lock;
+use crate::mirror;
+use crate::progress::Progress;
+use crate::provenance::Provenance;
+use crate::remote::{self, FileInfo};
+use crate::retry::RetryClass;
+use crate::version::GoVersion;
+use crate::Goup;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, VecDeque};
+use std::fs::{self, File};
+use std::future::{self, Future};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+use std::task::{Poll, Waker};
+use std::thread;
+use std::time::Instant;
+use ureq::Agent;
+
+/// How many parts of a response [`ThreadedTransport`] reads ahead of the task awaiting them
+const READ_AHEAD: usize = 16;
+
+/// The most [`ThreadedTransport`] reads at once
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// An HTTP client that goup can make requests through from async code.
+///
+/// Unlike goup's own requests, requests made through a transport of the caller's don't get the
+/// headers, proxy, and TLS settings from [`Config`]; the client is expected to be set up as
+/// needed already.
+///
+/// ```
+/// use goup::transport::{AsyncBody, AsyncTransport};
+/// use std::io;
+///
+/// /// Serves the same bytes for every URL, in one piece
+/// struct Canned(Vec<u8>);
+///
+/// struct CannedBody(Option<Vec<u8>>);
+///
+/// impl AsyncTransport for Canned {
+///     type Body = CannedBody;
+///
+///     async fn get(&self, _url: &str) -> io::Result<CannedBody> {
+///         Ok(CannedBody(Some(self.0.clone())))
+///     }
+/// }
+///
+/// impl AsyncBody for CannedBody {
+///     async fn chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+///         Ok(self.0.take())
+///     }
+/// }
+/// ```
+pub trait AsyncTransport: Send + Sync {
+    type Body: AsyncBody;
+
+    /// Request `url`. Any status but 200 is an error, which may be returned here or from the
+    /// first [`AsyncBody::chunk`].
+    fn get(&self, url: &str) -> impl Future<Output = io::Result<Self::Body>> + Send;
+}
+
+/// The body of a response to an [`AsyncTransport`] request, read a part at a time
+pub trait AsyncBody: Send {
+    /// The next part of the body, or `None` once all of it has been read
+    fn chunk(&mut self) -> impl Future<Output = io::Result<Option<Vec<u8>>>> + Send;
+}
+
+/// An [`AsyncTransport`] that makes goup's usual blocking requests, each on a thread of its
+/// own, so that nothing blocks the task awaiting them. It needs no particular executor.
+#[derive(Clone)]
+pub struct ThreadedTransport {
+    agent: Agent,
+}
+
+impl ThreadedTransport {
+    /// A transport making requests with the same settings as `goup`
+    pub fn new(goup: &Goup) -> Self {
+        Self {
+            agent: goup.agent.clone(),
+        }
+    }
+}
+
+impl AsyncTransport for ThreadedTransport {
+    type Body = ThreadedBody;
+
+    fn get(&self, url: &str) -> impl Future<Output = io::Result<ThreadedBody>> + Send {
+        let (sender, receiver) = channel(READ_AHEAD);
+        let request = self.agent.get(url);
+        thread::spawn(move || {
+            let mut reader = match request.call() {
+                Ok(response) => response.into_reader(),
+                Err(e) => {
+                    sender.send(Err(io::Error::other(e)));
+                    return;
+                }
+            };
+            let mut buf = vec![0; CHUNK_SIZE];
+            loop {
+                match reader.read(&mut buf) {
+                    Ok(0) => break,
+                    // Stop reading once the body is abandoned
+                    Ok(n) if !sender.send(Ok(buf[..n].to_vec())) => break,
+                    Ok(_) => {}
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    Err(e) => {
+                        sender.send(Err(e));
+                        break;
+                    }
+                }
+            }
+        });
+        future::ready(Ok(ThreadedBody { receiver }))
+    }
+}
+
+/// The body of a response to a [`ThreadedTransport`] request
+pub struct ThreadedBody {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+}
+
+impl AsyncBody for ThreadedBody {
+    async fn chunk(&mut self) -> io::Result<Option<Vec<u8>>> {
+        self.receiver.recv().await.transpose()
+    }
+}
+
+impl Goup {
+    /// Like [`Goup::available`], but making requests through `transport`. The list of
+    /// versions is always fetched, rather than taken from the cache.
+    pub async fn available_async<T: AsyncTransport>(
+        &self,
+        transport: &T,
+    ) -> Result<BTreeMap<GoVersion, FileInfo>, GoupError> {
+        self.client("fetch the list of available versions")?;
+        let mirrors = self.mirrors();
+        for (i, mirror) in mirrors.iter().enumerate() {
+            let url = self.index_url(mirror);
+            let mut body = Vec::new();
+            let res = receive(transport, &url, |chunk| {
+                body.extend_from_slice(chunk);
+                Ok(())
+            })
+            .await
+            .and_then(|_| remote::parse_index(&url, &String::from_utf8_lossy(&body)));
+            match res {
+                Err(e) if RetryClass::of(&e).is_some() && i + 1 < mirrors.len() => {}
+                res => return res.map(|parsed| parsed.versions),
+            }
+        }
+        unreachable!("there is always at least one mirror")
+    }
+
+    /// Like [`Transaction::download`](crate::Transaction::download), but making requests
+    /// through `transport`, and unpacking the archive on a thread of its own. The archive is
+    /// checked against its checksum before anything is unpacked. Each mirror is tried in turn,
+    /// but failed downloads aren't retried.
+    pub async fn download_async<T: AsyncTransport>(
+        &self,
+        transport: &T,
+        version: GoVersion,
+        file: &FileInfo,
+    ) -> Result<u64, GoupError> {
+        if self.installed()?.contains(&version) {
+            return Ok(0);
+        }
+        self.client(&format!("download {}", version))?;
+        let _lock = match Lock::try_acquire(&self.lock_file(&version.to_string()))? {
+            Ok(lock) => lock,
+            Err(pid) => return Err(GoupError::InstallInProgress { version, pid }),
+        };
+        if self.installed()?.contains(&version) {
+            return Ok(0);
+        }
+
+        let archive = self.archive_cache_dir().join(&file.filename);
+        let mirrors = self.mirrors();
+        let mut source = None;
+        for (i, mirror) in mirrors.iter().enumerate() {
+            match self.fetch_archive(transport, mirror, file, &archive).await {
+                Ok(()) => source = Some(mirror::archive_url(mirror, &file.filename)),
+                Err(e) if RetryClass::of(&e).is_some() && i + 1 < mirrors.len() => continue,
+                Err(e) => return Err(e),
+            }
+            break;
+        }
+        let source = source.expect("there is always at least one mirror");
+
+        let target = self.install_dir(version);
+        let minimal = self.config.minimal();
+        let unpacked = match self.unpack_async(&archive, &target).await {
+            Ok(omitted) => Provenance::new(&source, file, minimal)
+                .store(&target)
+                .map(|_| omitted),
+            Err(e) => Err(e),
+        };
+        if self.cache_mode == CacheMode::Off {
+            let _ = fs::remove_file(&archive);
+        }
+        let omitted = match unpacked {
+            Ok(omitted) => omitted,
+            Err(e) => {
+                let _ = fs::remove_dir_all(&target);
+                return Err(e);
+            }
+        };
+        self.transaction(|tx| {
+            tx.state.installed.insert(version);
+            if minimal {
+                tx.state.minimal.insert(version);
+            }
+            Ok(())
+        })?;
+        // The cache is only an optimization, so failing to trim it isn't worth failing over
+        let _ = self.evict_archives();
+        Ok(omitted)
+    }
+
+    /// Download `file` from `mirror` into `dest`, leaving nothing there unless it arrived
+    /// intact
+    async fn fetch_archive<T: AsyncTransport>(
+        &self,
+        transport: &T,
+        mirror: &str,
+        file: &FileInfo,
+        dest: &Path,
+    ) -> Result<(), GoupError> {
+        let url = mirror::archive_url(mirror, &file.filename);
+        if let Some(dir) = dest.parent() {
+            fs::create_dir_all(dir).map_err(GoupError::io("create", dir))?;
+        }
+        let partial = dest.with_extension("partial");
+        let mut out = File::create(&partial).map_err(GoupError::io("create", &partial))?;
+        let mut digest = Sha256::new();
+
+        let start = Instant::now();
+        self.progress.start(file.size);
+        let res = receive(transport, &url, |chunk| {
+            digest.update(chunk);
+            self.progress.advance(chunk.len() as u64);
+            out.write_all(chunk)
+                .map_err(GoupError::io("write", &partial))
+        })
+        .await;
+        self.progress.finish(res.is_ok());
+        self.progress.phase("download", start.elapsed());
+
+        let checked = res.and_then(|received| {
+            if file.size > 0 && received != file.size {
+                return Err(GoupError::SizeMismatch {
+                    url: url.clone(),
+                    expected: file.size,
+                    received,
+                });
+            }
+            let actual = format!("{:x}", digest.finalize());
+            if actual.eq_ignore_ascii_case(&file.sha256) {
+                Ok(())
+            } else {
+                Err(GoupError::ChecksumMismatch {
+                    file: file.filename.clone(),
+                    expected: file.sha256.clone(),
+                    actual,
+                })
+            }
+        });
+        if let Err(e) = checked {
+            let _ = fs::remove_file(&partial);
+            return Err(e);
+        }
+        fs::rename(&partial, dest).map_err(GoupError::io("move", dest))
+    }
+
+    /// Unpack `archive` into `target` on a thread of its own, reporting each entry to the
+    /// handle's [`Progress`] from this task
+    async fn unpack_async(&self, archive: &Path, target: &Path) -> Result<u64, GoupError> {
+        let (sender, mut receiver) = channel(READ_AHEAD);
+        let config = self.config.clone();
+        let (archive, target_dir) = (archive.to_path_buf(), target.to_path_buf());
+        thread::spawn(move || unpack_thread(config, archive, target_dir, sender));
+
+        let start = Instant::now();
+        let res = loop {
+            match receiver.recv().await {
+                Some(Unpacked::Entries(entries)) => self.progress.extracted(entries),
+                Some(Unpacked::Done(res)) => break res,
+                None => {
+                    break Err(GoupError::io("unpack archive into", target)(
+                        io::Error::other("unpacking stopped unexpectedly"),
+                    ))
+                }
+            }
+        };
+        self.progress.phase("extract", start.elapsed());
+        res
+    }
+}
+
+/// Read the body at `url` through `transport`, handing each part to `sink`, and returning how
+/// many bytes there were
+async fn receive<T: AsyncTransport>(
+    transport: &T,
+    url: &str,
+    mut sink: impl FnMut(&[u8]) -> Result<(), GoupError>,
+) -> Result<u64, GoupError> {
+    let mut body = transport.get(url).await.map_err(network(url))?;
+    let mut received = 0;
+    while let Some(chunk) = body.chunk().await.map_err(network(url))? {
+        received += chunk.len() as u64;
+        sink(&chunk)?;
+    }
+    Ok(received)
+}
+
+/// Wrap an error from a transport with the URL that was being requested. An error from ureq
+/// passed along by [`ThreadedTransport`] is unwrapped, so a 404 isn't mistaken for a lost
+/// connection.
+fn network(url: &str) -> impl FnOnce(io::Error) -> GoupError + '_ {
+    move |e| {
+        if e.get_ref().is_some_and(|inner| inner.is::<ureq::Error>()) {
+            let inner = e.into_inner().expect("checked above");
+            GoupError::network(
+                url,
+                *inner.downcast::<ureq::Error>().expect("checked above"),
+            )
+        } else {
+            GoupError::network(url, e)
+        }
+    }
+}
+
+/// News from the thread unpacking an archive
+enum Unpacked {
+    Entries(u64),
+    Done(Result<u64, GoupError>),
+}
+
+/// Passes each entry unpacked on to the task awaiting the unpacking
+struct Forward(Sender<Unpacked>);
+
+impl Progress for Forward {
+    fn extracted(&self, entries: u64) {
+        self.0.send(Unpacked::Entries(entries));
+    }
+}
+
+fn unpack_thread(config: Config, archive: PathBuf, target: PathBuf, sender: Sender<Unpacked>) {
+    let forward = Forward(sender);
+    let res = remote::unpack_saved(&config, &archive, &target, &forward);
+    forward.0.send(Unpacked::Done(res));
+}
+
+/// A bounded channel from a thread to an async task. The thread waits while the channel is
+/// full, and the task is woken as each item arrives.
+fn channel<T>(capacity: usize) -> (Sender<T>, Receiver<T>) {
+    let shared = Arc::new(Shared {
+        state: Mutex::new(State {
+            queue: VecDeque::new(),
+            sender_gone: false,
+            receiver_gone: false,
+            waker: None,
+        }),
+        space: Condvar::new(),
+        capacity,
+    });
+    (Sender(shared.clone()), Receiver(shared))
+}
+
+struct Shared<T> {
+    state: Mutex<State<T>>,
+    space: Condvar,
+    capacity: usize,
+}
+
+struct State<T> {
+    queue: VecDeque<T>,
+    sender_gone: bool,
+    receiver_gone: bool,
+    waker: Option<Waker>,
+}
+
+struct Sender<T>(Arc<Shared<T>>);
+
+impl<T> Sender<T> {
+    /// Send `item`, waiting for room. Returns false if the receiver is gone, so the item is
+    /// unwanted.
+    fn send(&self, item: T) -> bool {
+        let mut state = self.0.state.lock().unwrap();
+        while state.queue.len() >= self.0.capacity && !state.receiver_gone {
+            state = self.0.space.wait(state).unwrap();
+        }
+        if state.receiver_gone {
+            return false;
+        }
+        state.queue.push_back(item);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+        true
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut state = self.0.state.lock().unwrap();
+        state.sender_gone = true;
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+struct Receiver<T>(Arc<Shared<T>>);
+
+impl<T> Receiver<T> {
+    /// The next item, or `None` once the sender is gone and every item has been received
+    async fn recv(&mut self) -> Option<T> {
+        future::poll_fn(|cx| {
+            let mut state = self.0.state.lock().unwrap();
+            if let Some(item) = state.queue.pop_front() {
+                self.0.space.notify_one();
+                return Poll::Ready(Some(item));
+            }
+            if state.sender_gone {
+                return Poll::Ready(None);
+            }
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        })
+        .await
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.0.state.lock().unwrap().receiver_gone = true;
+        self.0.space.notify_all();
+    }
+}
@@ -0,0 +1,118 @@
+//! Versions that were removed with `use_trash` set, kept aside until the trash is emptied so
+//! that they can be restored
+
+use crate::error::GoupError;
+use crate::remove_tree;
+use crate::size;
+use crate::version::GoVersion;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// A version in the trash, as listed by [`Goup::trash`](crate::Goup::trash)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trashed {
+    pub version: GoVersion,
+    pub path: PathBuf,
+    /// When it was moved to the trash
+    pub trashed_at: SystemTime,
+    /// The disk space it takes up, in bytes
+    pub size: u64,
+}
+
+impl Trashed {
+    /// How long ago it was moved to the trash
+    pub fn age(&self) -> Duration {
+        self.trashed_at.elapsed().unwrap_or_default()
+    }
+}
+
+/// What became of a version that was removed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Disposal {
+    /// It was deleted
+    Deleted,
+    /// It was moved into the trash, to the given path
+    Trashed(PathBuf),
+    /// It was deleted, despite `use_trash`, since the trash is on another filesystem and it
+    /// couldn't be moved there without copying it
+    TrashElsewhere,
+}
+
+/// Move the installation at `dir` into the trash at `trash`, or delete it where the trash is on
+/// another filesystem
+pub(crate) fn put(trash: &Path, dir: &Path, version: GoVersion) -> Result<Disposal, GoupError> {
+    fs::create_dir_all(trash).map_err(GoupError::io("create", trash))?;
+    if !same_filesystem(trash, dir) {
+        remove_tree(dir)?;
+        return Ok(Disposal::TrashElsewhere);
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH);
+    let mut seconds = now.unwrap_or_default().as_secs();
+    // Removing a version twice within a second mustn't overwrite the first copy
+    let mut target = trash.join(format!("{}-{}", version, seconds));
+    while target.symlink_metadata().is_ok() {
+        seconds += 1;
+        target = trash.join(format!("{}-{}", version, seconds));
+    }
+    match fs::rename(dir, &target) {
+        Ok(()) => Ok(Disposal::Trashed(target)),
+        Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+            remove_tree(dir)?;
+            Ok(Disposal::TrashElsewhere)
+        }
+        Err(e) => Err(GoupError::io("move", dir)(e)),
+    }
+}
+
+/// Every version in the trash at `dir`, oldest first. A missing trash is empty.
+pub(crate) fn list(dir: &Path) -> Result<Vec<Trashed>, GoupError> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(GoupError::io("read", dir)(e)),
+    };
+    let mut trashed = Vec::new();
+    for entry in entries {
+        let path = entry.map_err(GoupError::io("read", dir))?.path();
+        // Anything not named as goup names what it trashes isn't goup's to list or delete
+        let Some((version, seconds)) = path
+            .file_name()
+            .and_then(|name| name.to_str()?.rsplit_once('-'))
+            .and_then(|(v, s)| Some((v.parse().ok()?, s.parse().ok()?)))
+        else {
+            continue;
+        };
+        let size = size::dir_size(&path).map_err(GoupError::io("measure", &path))?;
+        trashed.push(Trashed {
+            version,
+            path,
+            trashed_at: UNIX_EPOCH + Duration::from_secs(seconds),
+            size,
+        });
+    }
+    trashed.sort_by_key(|t| (t.trashed_at, t.version));
+    Ok(trashed)
+}
+
+/// Delete a version from the trash for good
+pub(crate) fn delete(trashed: &Trashed) -> Result<(), GoupError> {
+    remove_tree(&trashed.path)
+}
+
+/// Whether `a` and `b` are on the same filesystem, so that one can be renamed into the other
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => true,
+    }
+}
+
+/// Elsewhere, the rename itself is left to find out
+#[cfg(not(unix))]
+fn same_filesystem(_: &Path, _: &Path) -> bool {
+    true
+}
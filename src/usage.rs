@@ -0,0 +1,103 @@
+//! Finding the running processes that are using an installed version, so that it isn't deleted
+//! out from under them
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// A running process, and the files it is executing or has open
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Process {
+    pub pid: u32,
+    /// The command line it was started with
+    pub command: String,
+    files: Vec<PathBuf>,
+}
+
+impl fmt::Display for Process {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.pid, self.command)
+    }
+}
+
+/// The processes running at one moment, as far as they can be seen. Processes belonging to
+/// other users can usually only be seen running, not what they have open.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    processes: Vec<Process>,
+}
+
+impl Snapshot {
+    /// Look at the running processes, or `None` where that isn't possible, such as on platforms
+    /// other than Linux, or where /proc can't be read
+    pub fn take() -> Option<Snapshot> {
+        #[cfg(target_os = "linux")]
+        {
+            linux::snapshot()
+        }
+        #[cfg(not(target_os = "linux"))]
+        {
+            None
+        }
+    }
+
+    /// The processes executing a program from within `dir`, or with a file within it open
+    pub fn using(&self, dir: &Path) -> Vec<Process> {
+        let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+        self.processes
+            .iter()
+            .filter(|p| p.files.iter().any(|f| f.starts_with(&dir)))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use super::{Process, Snapshot};
+    use std::fs;
+    use std::process;
+
+    pub fn snapshot() -> Option<Snapshot> {
+        let own = process::id();
+        let mut processes = Vec::new();
+        for entry in fs::read_dir("/proc").ok()?.flatten() {
+            let Some(pid) = entry.file_name().to_str().and_then(|n| n.parse().ok()) else {
+                continue;
+            };
+            if pid == own {
+                continue;
+            }
+            let dir = entry.path();
+            // Processes can exit at any moment, and those of other users hide what they have
+            // open, so whatever can't be read is passed over
+            let mut files: Vec<_> = fs::read_link(dir.join("exe")).into_iter().collect();
+            if let Ok(fds) = fs::read_dir(dir.join("fd")) {
+                files.extend(fds.flatten().filter_map(|fd| fs::read_link(fd.path()).ok()));
+            }
+            files.retain(|f| f.is_absolute());
+            if files.is_empty() {
+                continue;
+            }
+            let cmdline = fs::read(dir.join("cmdline")).unwrap_or_default();
+            let command = String::from_utf8_lossy(&cmdline)
+                .split('\0')
+                .filter(|arg| !arg.is_empty())
+                .collect::<Vec<_>>()
+                .join(" ");
+            let command = if command.is_empty() {
+                fs::read_to_string(dir.join("comm"))
+                    .unwrap_or_default()
+                    .trim()
+                    .to_string()
+            } else {
+                command
+            };
+            processes.push(Process {
+                pid,
+                command,
+                files,
+            });
+        }
+        Some(Snapshot { processes })
+    }
+}
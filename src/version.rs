@@ -1,29 +1,103 @@
-use anyhow::{anyhow, Context, Result};
-use flate2::read::GzDecoder;
-use indicatif::{ProgressBar, ProgressStyle};
 use regex::Regex;
 use serde::de::Visitor;
 use serde::{Deserialize, Serialize};
-use std::collections::{BTreeMap, BTreeSet};
-use std::env::{var, VarError};
+use std::cmp::Ordering;
 use std::fmt::{self, Display};
-use std::io::Read;
-#[cfg(unix)]
-use std::os::unix::fs::symlink;
-use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::sync::OnceLock;
-use std::{env, fs, io};
-use tar::Archive;
 
 static PARSING_REGEX: OnceLock<Regex> = OnceLock::new();
-
-/// A semantic version tag, in Go format
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+static SEMVER_REGEX: OnceLock<Regex> = OnceLock::new();
+
+/// A semantic version tag, in Go format. Pre-releases, like `go1.23rc1`, come before the
+/// release they lead up to.
+///
+/// ```
+/// use goup::GoVersion;
+///
+/// let version: GoVersion = "go1.22.4".parse().unwrap();
+/// assert_eq!(version.minor, 22);
+/// assert_eq!(version.to_string(), "go1.22.4");
+///
+/// let rc: GoVersion = "go1.23rc1".parse().unwrap();
+/// assert!(rc < "go1.23.0".parse().unwrap());
+/// assert_eq!(rc.to_string(), "go1.23rc1");
+/// ```
+#[derive(Debug, Default, Clone, Copy)]
 pub struct GoVersion {
     pub major: u32,
     pub minor: u32,
     pub patch: u32,
+    /// Which beta or release candidate this is, for versions that aren't final releases
+    pub pre: Option<PreRelease>,
+    /// Whether the patch number was written out, as in `go1.22.0` but not `go1.22`, so that a
+    /// request can tell a series from its first release. Versions compare equal either way.
+    pub explicit_patch: bool,
+}
+
+/// The beta or release candidate of a version, as in `go1.22beta2` or `go1.23rc1`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PreRelease {
+    pub stage: Stage,
+    pub number: u32,
+}
+
+/// How far along a pre-release is. Betas come before release candidates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Stage {
+    Beta,
+    Rc,
+}
+
+impl Stage {
+    /// The stage named `beta` or `rc`, as the version regexes only match those
+    fn from_name(name: &str) -> Self {
+        match name {
+            "beta" => Stage::Beta,
+            _ => Stage::Rc,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Stage::Beta => "beta",
+            Stage::Rc => "rc",
+        }
+    }
+}
+
+impl Display for PreRelease {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.stage.as_str(), self.number)
+    }
+}
+
+impl Ord for GoVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let release = (self.major, self.minor, self.patch);
+        release
+            .cmp(&(other.major, other.minor, other.patch))
+            .then_with(|| match (self.pre, other.pre) {
+                (None, None) => Ordering::Equal,
+                (None, Some(_)) => Ordering::Greater,
+                (Some(_), None) => Ordering::Less,
+                (Some(a), Some(b)) => a.cmp(&b),
+            })
+    }
+}
+
+impl PartialEq for GoVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for GoVersion {}
+
+impl PartialOrd for GoVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
 }
 
 impl Serialize for GoVersion {
@@ -66,241 +140,364 @@ impl FromStr for GoVersion {
     type Err = &'static str;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let regex =
-            PARSING_REGEX.get_or_init(|| Regex::new(r"go(\d+)\.(\d+)(?:\.(\d+))?").unwrap());
-
-        match regex.captures(s) {
-            Some(x) => Ok(Self {
-                major: x
-                    .get(1)
-                    .map(|x| x.as_str().parse::<u32>().unwrap())
-                    .unwrap_or_default(),
-                minor: x
-                    .get(2)
-                    .map(|x| x.as_str().parse::<u32>().unwrap())
-                    .unwrap_or_default(),
-                patch: x
-                    .get(3)
-                    .map(|x| x.as_str().parse::<u32>().unwrap())
-                    .unwrap_or_default(),
+        let regex = PARSING_REGEX.get_or_init(|| {
+            Regex::new(r"^go(\d+)\.(\d+)(?:\.(\d+))?(?:(beta|rc)(\d+))?$").unwrap()
+        });
+        let x = regex.captures(s).ok_or("unable to parse go version")?;
+        let number = |m: regex::Match| {
+            m.as_str()
+                .parse::<u32>()
+                .map_err(|_| "version number out of range")
+        };
+        let part = |i| x.get(i).map_or(Ok(0), number);
+        let pre = match x.get(4).zip(x.get(5)) {
+            Some((stage, n)) => Some(PreRelease {
+                stage: Stage::from_name(stage.as_str()),
+                number: number(n)?,
             }),
-            None => Err("unable to parse go version"),
-        }
+            None => None,
+        };
+        Ok(Self {
+            major: part(1)?,
+            minor: part(2)?,
+            patch: part(3)?,
+            pre,
+            explicit_patch: x.get(3).is_some(),
+        })
     }
 }
 
-impl Display for GoVersion {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "go{}.{}.{}", self.major, self.minor, self.patch)
+impl GoVersion {
+    /// Whether this is a beta or release candidate, rather than a final release
+    pub fn is_prerelease(&self) -> bool {
+        self.pre.is_some()
     }
-}
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct VersionFile {
-    pub enabled: Option<GoVersion>,
-    pub installed: BTreeSet<GoVersion>,
-    pub pinned: BTreeSet<GoVersion>,
-}
+    /// The release that a beta or release candidate leads up to, like `go1.23.0` for
+    /// `go1.23rc1`. A release is its own.
+    pub fn release(self) -> GoVersion {
+        GoVersion { pre: None, ..self }
+    }
 
-impl VersionFile {
-    pub fn load() -> Result<VersionFile> {
-        match fs::read_to_string(version_file()?) {
-            Ok(x) => serde_json::from_str(&x).with_context(|| "Unable to parse version file"),
-            Err(e) if matches!(e.kind(), io::ErrorKind::NotFound) => Ok(Default::default()),
-            Err(e) => Err(anyhow!(e)),
+    /// The version without the `go` prefix, like `1.22.6`, as tools that expect semantic
+    /// versions want it. Releases named without a patch number, like `go1.20`, get a zero, and
+    /// pre-releases are marked as semantic versions mark them, like `1.23.0-rc1`.
+    ///
+    /// ```
+    /// use goup::GoVersion;
+    ///
+    /// let version: GoVersion = "go1.20".parse().unwrap();
+    /// assert_eq!(version.format_semver(), "1.20.0");
+    /// assert_eq!(GoVersion::parse_semver("1.20.0"), Ok(version));
+    /// let rc: GoVersion = "go1.23rc1".parse().unwrap();
+    /// assert_eq!(rc.format_semver(), "1.23.0-rc1");
+    /// ```
+    pub fn format_semver(&self) -> String {
+        let release = format!("{}.{}.{}", self.major, self.minor, self.patch);
+        match self.pre {
+            Some(pre) => format!("{}-{}", release, pre),
+            None => release,
         }
     }
 
-    pub fn store(&self) -> Result<()> {
-        let payload = serde_json::to_string_pretty(&self)
-            .with_context(|| "Unable to serialize version file")?;
-        fs::write(version_file()?, payload).with_context(|| "Unable to write version file")
+    /// Parse a version as written by [`GoVersion::format_semver`], also accepting a leading
+    /// `v` and a missing patch number. Only final releases are accepted.
+    pub fn parse_semver(s: &str) -> Result<Self, &'static str> {
+        let regex =
+            SEMVER_REGEX.get_or_init(|| Regex::new(r"^v?(\d+)\.(\d+)(?:\.(\d+))?$").unwrap());
+        let x = regex
+            .captures(s.trim())
+            .ok_or("unable to parse semantic version")?;
+        let part = |i| {
+            x.get(i)
+                .map_or(Ok(0), |m: regex::Match| m.as_str().parse::<u32>())
+                .map_err(|_| "version number out of range")
+        };
+        Ok(Self {
+            major: part(1)?,
+            minor: part(2)?,
+            patch: part(3)?,
+            pre: None,
+            explicit_patch: x.get(3).is_some(),
+        })
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct VersionInfo {
-    version: GoVersion,
-    // stable: bool,
-    files: Vec<FileInfo>,
-}
-
-#[derive(Debug, Deserialize)]
-pub struct FileInfo {
-    pub filename: String,
-    pub os: String,
-    pub arch: String,
-    pub version: String,
-    pub sha256: String,
-    pub size: u64,
-    pub kind: String,
+impl Display for GoVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // Go names the pre-releases of a series' first release without a patch number
+        match self.pre {
+            Some(pre) if self.patch == 0 => write!(f, "go{}.{}{}", self.major, self.minor, pre),
+            Some(pre) => write!(f, "go{}.{}.{}{}", self.major, self.minor, self.patch, pre),
+            None => write!(f, "go{}.{}.{}", self.major, self.minor, self.patch),
+        }
+    }
 }
 
-/// A shim that will count the number of bytes read out of the given reader and display it
-/// on a progress bar.
-#[derive(Debug)]
-struct ByteCounter<R: Read> {
-    inner: R,
-    bar: ProgressBar,
+/// A version as a person asks for it, which may leave the choice of release to goup
+///
+/// ```
+/// use goup::version::VersionRequest;
+/// use goup::GoVersion;
+///
+/// let v = |s: &str| s.parse::<GoVersion>().unwrap();
+/// let available = [v("go1.21.5"), v("go1.22.0"), v("go1.22.3")];
+/// let request: VersionRequest = "go1.22".parse().unwrap();
+/// assert_eq!(request.resolve(available), Some(v("go1.22.3")));
+/// let request: VersionRequest = "go1.22.0".parse().unwrap();
+/// assert_eq!(request.resolve(available), Some(v("go1.22.0")));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionRequest {
+    /// The newest release, written `latest`
+    Latest,
+    /// The newest release of a series, written without a patch number, like `go1.22`
+    Series { major: u32, minor: u32 },
+    /// Exactly the given release, like `go1.22.3`, or pre-release, like `go1.23rc1`
+    Exact(GoVersion),
 }
 
-impl<R: Read> ByteCounter<R> {
-    pub fn new(inner: R, total_bytes: u64) -> Self {
-        let bar = ProgressBar::new(total_bytes).with_style(
-            ProgressStyle::with_template(
-                "[{elapsed_precise}] [{bar:40.cyan/blue}] {bytes}/{total_bytes}",
-            )
-            .unwrap()
-            .progress_chars("=> "),
-        );
-
-        Self { inner, bar }
+impl VersionRequest {
+    /// Choose the release to use from `versions`, if any of them match
+    pub fn resolve(&self, versions: impl IntoIterator<Item = GoVersion>) -> Option<GoVersion> {
+        versions.into_iter().filter(|&v| self.matches(v)).max()
     }
-}
 
-impl<R: Read> Read for ByteCounter<R> {
-    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
-        let res = self.inner.read(buf);
-        if let Ok(size) = res {
-            self.bar.inc(size as u64);
+    /// Whether `version` is one of the releases this request could choose. Pre-releases are
+    /// only chosen by name.
+    ///
+    /// ```
+    /// use goup::version::VersionRequest;
+    ///
+    /// let series: VersionRequest = "go1.23".parse().unwrap();
+    /// assert!(!series.matches("go1.23rc1".parse().unwrap()));
+    /// let exact: VersionRequest = "go1.23rc1".parse().unwrap();
+    /// assert!(exact.matches("go1.23rc1".parse().unwrap()));
+    /// ```
+    pub fn matches(&self, version: GoVersion) -> bool {
+        match *self {
+            Self::Latest => !version.is_prerelease(),
+            Self::Series { major, minor } => {
+                (version.major, version.minor) == (major, minor) && !version.is_prerelease()
+            }
+            Self::Exact(exact) => version == exact,
         }
-        res
     }
-}
 
-impl<R: Read> Drop for ByteCounter<R> {
-    fn drop(&mut self) {
-        if self.bar.position() >= self.bar.length().unwrap_or_default() {
-            self.bar.finish();
-        } else {
-            self.bar.abandon();
+    /// Whether `version` is within this request when it is used as a ceiling: no newer than
+    /// an exact release, or than the newest release of a series
+    ///
+    /// ```
+    /// use goup::version::VersionRequest;
+    ///
+    /// let cap: VersionRequest = "go1.22".parse().unwrap();
+    /// assert!(cap.admits("go1.22.9".parse().unwrap()));
+    /// assert!(!cap.admits("go1.23.0".parse().unwrap()));
+    /// ```
+    pub fn admits(&self, version: GoVersion) -> bool {
+        match *self {
+            Self::Latest => true,
+            Self::Series { major, minor } => (version.major, version.minor) <= (major, minor),
+            Self::Exact(cap) => version <= cap,
         }
     }
 }
 
-/// Get the set of available versions of Go from Go's website.
-pub fn available_go_versions() -> Result<BTreeMap<GoVersion, FileInfo>> {
-    let available = ureq::get("https://go.dev/dl/?mode=json")
-        .call()
-        .with_context(|| "Failed to request version info")?
-        .into_json::<Vec<VersionInfo>>()
-        .with_context(|| "Unable to parse version info from remote")?
-        .into_iter()
-        .filter_map(|group| {
-            group
-                .files
-                .into_iter()
-                .find(|file| file.arch == arch() && file.os == env::consts::OS)
-                .map(|f| (group.version, f))
-        })
-        .collect();
-
-    Ok(available)
+/// A shell-style pattern matched against version names, like `go1.2[12].*`. `*` matches any
+/// run of characters, `?` any one character, and `[...]` any one of the characters or ranges
+/// within it, or any other with `[!...]`.
+///
+/// ```
+/// use goup::version::VersionGlob;
+///
+/// let glob: VersionGlob = "go1.2[12].*".parse().unwrap();
+/// assert!(glob.matches("go1.21.13".parse().unwrap()));
+/// assert!(!glob.matches("go1.23.0".parse().unwrap()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VersionGlob(String);
+
+impl VersionGlob {
+    /// Whether the name of `version` matches the whole pattern
+    pub fn matches(&self, version: GoVersion) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let name: Vec<char> = version.to_string().chars().collect();
+        glob_matches(&pattern, &name)
+    }
 }
 
-pub fn download_version(version: GoVersion, file: &FileInfo) -> Result<()> {
-    let mut version_file = VersionFile::load()?;
-    let needs_install = version_file.installed.insert(version);
-
-    if needs_install {
-        let stream_reader = ureq::get(&format!("https://go.dev/dl/{}", file.filename))
-            .call()
-            .with_context(|| "Failed to get version archive from go.dev")?
-            .into_reader();
-        Archive::new(GzDecoder::new(ByteCounter::new(stream_reader, file.size)))
-            .unpack(install_dir(version)?)
-            .with_context(|| "Failed to unpack downloaded archive")?;
-        version_file.store()?;
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob_matches(rest, &text[skip..])),
+        Some(('?', rest)) => !text.is_empty() && glob_matches(rest, &text[1..]),
+        Some(('[', rest)) => {
+            // Checked to be closed when the pattern was parsed
+            let end = rest.iter().skip(1).position(|&c| c == ']').unwrap() + 1;
+            let (negated, set) = match rest[..end].split_first() {
+                Some(('!', set)) => (true, set),
+                _ => (false, &rest[..end]),
+            };
+            let Some(&c) = text.first() else {
+                return false;
+            };
+            let mut found = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    found |= (set[i]..=set[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    found |= set[i] == c;
+                    i += 1;
+                }
+            }
+            found != negated && glob_matches(&rest[end + 1..], &text[1..])
+        }
+        Some((&p, rest)) => text.first() == Some(&p) && glob_matches(rest, &text[1..]),
     }
-
-    Ok(())
 }
 
-#[cfg(unix)]
-pub fn enable_version(version: GoVersion) -> Result<()> {
-    let mut records_file = VersionFile::load()?;
-    if !records_file.installed.contains(&version) {
-        return Err(anyhow!("Version {} is not installed", version));
-    }
+impl FromStr for VersionGlob {
+    type Err = &'static str;
 
-    if let Err(e) = fs::remove_file(goup_dir()?.join("go")) {
-        if !matches!(e.kind(), io::ErrorKind::NotFound) {
-            return Err(anyhow!(e));
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            if c == '[' {
+                // A `]` straight after the opening bracket is one of the characters
+                chars.next();
+                if !chars.any(|c| c == ']') {
+                    return Err("'[' is never closed with ']'");
+                }
+            }
         }
+        Ok(Self(s.to_string()))
     }
+}
 
-    let res = symlink(install_dir(version)?.join("go"), goup_dir()?.join("go"))
-        .with_context(|| "Unable to make symlink");
-    if res.is_ok() {
-        records_file.enabled = Some(version);
-        records_file.store()?;
+impl Display for VersionGlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
     }
-    res
 }
 
-pub fn remove_version(version: GoVersion) -> Result<()> {
-    let mut records_file = VersionFile::load()?;
-    if !records_file.installed.remove(&version) {
-        return Err(anyhow!("Version {} is not installed", version));
-    } else if records_file.pinned.contains(&version) {
-        return Err(anyhow!("Version {} is pinned", version));
-    }
+/// Some versions named for a rule like the blocklist: a release like `go1.22.5`, every release
+/// of a series like `go1.22`, or those matching a [`VersionGlob`] like `go1.22.[5-7]`
+///
+/// ```
+/// use goup::version::VersionPattern;
+///
+/// let v = |s: &str| s.parse().unwrap();
+/// let series: VersionPattern = "go1.22".parse().unwrap();
+/// assert!(series.matches(v("go1.22.5")) && !series.matches(v("go1.23.0")));
+/// let glob: VersionPattern = "go1.22.[5-7]".parse().unwrap();
+/// assert!(glob.matches(v("go1.22.6")) && !glob.matches(v("go1.22.4")));
+/// assert!("latest".parse::<VersionPattern>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum VersionPattern {
+    Request(VersionRequest),
+    Glob(VersionGlob),
+}
 
-    if records_file.enabled.is_some() && records_file.enabled.unwrap() == version {
-        records_file.enabled = None;
-        println!(
-            "Version {} was enabled. Use 'goup enable' to select another.",
-            version
-        );
+impl VersionPattern {
+    /// Whether `version` is one of those named. A series names its pre-releases too.
+    pub fn matches(&self, version: GoVersion) -> bool {
+        match self {
+            Self::Request(VersionRequest::Series { major, minor }) => {
+                (version.major, version.minor) == (*major, *minor)
+            }
+            Self::Request(request) => request.matches(version),
+            Self::Glob(glob) => glob.matches(version),
+        }
     }
+}
+
+impl FromStr for VersionPattern {
+    type Err = &'static str;
 
-    fs::remove_dir_all(install_dir(version)?)?;
-    records_file.store()?;
-    Ok(())
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.contains(['*', '?', '[']) {
+            return s.parse().map(Self::Glob);
+        }
+        match s.parse() {
+            Ok(VersionRequest::Latest) => Err("'latest' is not a version"),
+            Ok(request) => Ok(Self::Request(request)),
+            Err(_) => Err("expected a release like 'go1.22.5', a series like 'go1.22', or a pattern like 'go1.22.*'"),
+        }
+    }
 }
 
-pub fn version_folders() -> Result<BTreeSet<GoVersion>> {
-    let mut versions = BTreeSet::new();
-    for entry in fs::read_dir(goup_dir()?)? {
-        let version = entry?
-            .file_name()
-            .to_str()
-            .and_then(|name| name.parse::<GoVersion>().ok());
-        if let Some(v) = version {
-            versions.insert(v);
+impl Display for VersionPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Request(request) => request.fmt(f),
+            Self::Glob(glob) => glob.fmt(f),
         }
     }
+}
 
-    Ok(versions)
+impl<'de> Deserialize<'de> for VersionPattern {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.parse()
+            .map_err(|e| serde::de::Error::custom(format!("{}, not '{}'", e, text)))
+    }
 }
 
-/// A mapping of the architecture from what Rust calls it to what Go calls it
-fn arch() -> &'static str {
-    match env::consts::ARCH {
-        "x86" => "386",
-        "x86_64" => "amd64",
-        "aarch64" => "arm64",
-        "powerpc64" => "ppc64le",
-        "s390x" => "s390x",
-        _ => "",
+impl Serialize for VersionRequest {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_str(&self.to_string())
     }
 }
 
-/// The directory that goup uses to install Go versions and manage its internal config
-fn goup_dir() -> Result<PathBuf> {
-    match var("GOPATH") {
-        Ok(p) => Ok(Path::new(&p).join("goup")),
-        Err(VarError::NotPresent) => Err(anyhow!("GOPATH variable is not set")),
-        Err(VarError::NotUnicode(_)) => Err(anyhow!("Unable to read GOPATH variable")),
+impl<'de> Deserialize<'de> for VersionRequest {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        text.parse().map_err(|_| {
+            serde::de::Error::custom(format!(
+                "expected 'latest', a series like 'go1.22', or a release like 'go1.22.3', not \
+                 '{}'",
+                text
+            ))
+        })
     }
 }
 
-/// The directory that the provided Go version should be installed into
-pub fn install_dir(version: GoVersion) -> Result<PathBuf> {
-    goup_dir().map(|p| p.join(format!("{}", version)))
+impl FromStr for VersionRequest {
+    type Err = &'static str;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "latest" {
+            return Ok(Self::Latest);
+        }
+        let version: GoVersion = s.parse()?;
+        if version.explicit_patch || version.is_prerelease() {
+            return Ok(Self::Exact(version));
+        }
+        Ok(Self::Series {
+            major: version.major,
+            minor: version.minor,
+        })
+    }
 }
 
-/// The location of the file describing the versions installed and enabled
-fn version_file() -> Result<PathBuf> {
-    goup_dir().map(|p| p.join("versions.json"))
+impl Display for VersionRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Latest => f.write_str("latest"),
+            Self::Series { major, minor } => write!(f, "go{}.{}", major, minor),
+            Self::Exact(version) => version.fmt(f),
+        }
+    }
 }
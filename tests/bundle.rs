@@ -0,0 +1,216 @@
+mod common;
+
+use common::{archive_name, go_arch, go_archive, sha256, tarball, MockServer};
+use goup::{Config, GoVersion, Goup, GoupError};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn version(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+fn goup_in(dir: &TempDir, server: &MockServer) -> Goup {
+    let config = Config {
+        mirror: Some(server.url().to_string()),
+        ..Default::default()
+    };
+    Goup::new(dir.path()).with_config(config).unwrap()
+}
+
+/// A bundle for `go1.22.1` holding a single file that doesn't match the digest in its
+/// manifest, with the given fields of the manifest replaced. It is compressed with gzip, as
+/// the first bundles were, which are still read.
+fn crafted_bundle(dir: &Path, overrides: serde_json::Value, contents: &[u8]) -> std::path::PathBuf {
+    let mut manifest = serde_json::json!({
+        "format": 1,
+        "version": "go1.22.1",
+        "os": std::env::consts::OS,
+        "arch": go_arch(),
+        "files": {
+            "go/VERSION": "6f4e8c3bbd2af9a6be7a5d8d9e6e3c3ac4f2d5aa8f8c2a1d7c9f2a0b5b8e4d11",
+        },
+    });
+    for (key, value) in overrides.as_object().unwrap() {
+        manifest[key] = value.clone();
+    }
+    let json = serde_json::to_vec(&manifest).unwrap();
+    let path = dir.join("crafted.tar.gz");
+    fs::write(
+        &path,
+        tarball(&[("goup-bundle.json", &json), ("go/VERSION", contents)]),
+    )
+    .unwrap();
+    path
+}
+
+#[test]
+fn export_then_import_into_a_fresh_root() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let source_dir = TempDir::new().unwrap();
+    let source = goup_in(&source_dir, &server);
+    source.install(version("go1.22.1")).unwrap();
+
+    let out = TempDir::new().unwrap();
+    let bundle = out.path().join("go1.22.1-bundle.tar.zst");
+    source.export(version("go1.22.1"), &bundle).unwrap();
+    // Compressed with zstd
+    assert_eq!(fs::read(&bundle).unwrap()[..4], [0x28, 0xb5, 0x2f, 0xfd]);
+
+    let target_dir = TempDir::new().unwrap();
+    let target = Goup::new(target_dir.path());
+    assert_eq!(target.import(&bundle).unwrap(), version("go1.22.1"));
+    assert!(target.installed().unwrap().contains(&version("go1.22.1")));
+    for file in ["VERSION", "bin/go", "bin/gofmt", "doc/go_spec.html"] {
+        assert_eq!(
+            fs::read(target.goroot(version("go1.22.1")).join(file)).unwrap(),
+            fs::read(source.goroot(version("go1.22.1")).join(file)).unwrap(),
+        );
+    }
+    assert_eq!(
+        target.provenance(version("go1.22.1")),
+        source.provenance(version("go1.22.1"))
+    );
+    assert!(!target_dir
+        .path()
+        .join("tmp")
+        .read_dir()
+        .unwrap()
+        .any(|_| true));
+
+    let output = target
+        .command(version("go1.22.1"), "go")
+        .unwrap()
+        .arg("version")
+        .output()
+        .unwrap();
+    assert!(String::from_utf8_lossy(&output.stdout).starts_with("go version go1.22.1"));
+
+    assert!(matches!(
+        target.import(&bundle),
+        Err(GoupError::VersionAlreadyInstalled(v)) if v == version("go1.22.1")
+    ));
+}
+
+#[test]
+fn tampered_bundle_is_refused() {
+    let dir = TempDir::new().unwrap();
+    let bundle = crafted_bundle(dir.path(), serde_json::json!({}), b"go1.22.1\n");
+    let goup = Goup::new(dir.path().join("root"));
+
+    let err = goup.import(&bundle).unwrap_err();
+    assert!(matches!(&err, GoupError::InvalidBundle { .. }));
+    assert!(err.to_string().contains("go/VERSION is corrupt"), "{}", err);
+    assert!(!goup.install_dir(version("go1.22.1")).exists());
+    assert!(goup.installed().unwrap().is_empty());
+}
+
+#[test]
+fn gzip_bundles_from_earlier_versions_are_read() {
+    let dir = TempDir::new().unwrap();
+    let contents = b"go1.22.1\n";
+    let overrides = serde_json::json!({
+        "files": { "go/VERSION": sha256(contents) },
+    });
+    let bundle = crafted_bundle(dir.path(), overrides, contents);
+    let goup = Goup::new(dir.path().join("root"));
+
+    assert_eq!(goup.import(&bundle).unwrap(), version("go1.22.1"));
+    assert_eq!(
+        fs::read(goup.goroot(version("go1.22.1")).join("VERSION")).unwrap(),
+        contents
+    );
+}
+
+#[test]
+fn bundle_for_another_platform_is_refused() {
+    let dir = TempDir::new().unwrap();
+    let overrides = serde_json::json!({ "os": "plan9", "arch": "mips" });
+    let bundle = crafted_bundle(dir.path(), overrides, b"go1.22.1\n");
+    let goup = Goup::new(dir.path().join("root"));
+
+    assert!(matches!(
+        goup.import(&bundle),
+        Err(GoupError::BundlePlatformMismatch { bundle, .. }) if bundle == "plan9/mips"
+    ));
+}
+
+#[test]
+fn bundle_in_a_newer_format_is_refused() {
+    let dir = TempDir::new().unwrap();
+    let bundle = crafted_bundle(
+        dir.path(),
+        serde_json::json!({ "format": 2 }),
+        b"go1.22.1\n",
+    );
+    let goup = Goup::new(dir.path().join("root"));
+
+    let err = goup.import(&bundle).unwrap_err();
+    assert!(err.to_string().contains("uses format 2"), "{}", err);
+}
+
+#[test]
+fn bundle_from_an_unofficial_archive_is_refused_once_checksums_are_imported() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let source_dir = TempDir::new().unwrap();
+    let source = goup_in(&source_dir, &server);
+    source.install(version("go1.22.1")).unwrap();
+    let bundle = source_dir.path().join("go1.22.1-bundle.tar.zst");
+    source.export(version("go1.22.1"), &bundle).unwrap();
+
+    let database = |sha256: &str| {
+        let path = source_dir.path().join(format!("{}.json", &sha256[..8]));
+        let json = serde_json::json!({
+            "format": 1,
+            "archives": {
+                archive_name("go1.22.1"): {
+                    "version": "go1.22.1",
+                    "os": std::env::consts::OS,
+                    "arch": go_arch(),
+                    "sha256": sha256,
+                    "source": "https://go.dev/dl/?mode=json",
+                    "exported_at": 0,
+                },
+            },
+        });
+        fs::write(&path, json.to_string()).unwrap();
+        path
+    };
+    let official = database(&sha256(&go_archive("go1.22.1")));
+    let other = database(&"0".repeat(64));
+
+    let target_dir = TempDir::new().unwrap();
+    let target = Goup::new(target_dir.path());
+    target
+        .import_checksums(std::slice::from_ref(&other))
+        .unwrap();
+    let err = target.import(&bundle).unwrap_err();
+    assert!(
+        matches!(&err, GoupError::UnofficialChecksum { file, .. } if *file == archive_name("go1.22.1")),
+        "{:?}",
+        err
+    );
+    assert!(
+        err.to_string().contains(&*other.to_string_lossy()),
+        "{}",
+        err
+    );
+    assert!(target.installed().unwrap().is_empty());
+
+    let target_dir = TempDir::new().unwrap();
+    let target = Goup::new(target_dir.path());
+    assert_eq!(
+        target
+            .import_checksums(std::slice::from_ref(&official))
+            .unwrap(),
+        1
+    );
+    assert!(matches!(
+        target.import_checksums(&[other]),
+        Err(GoupError::ChecksumConflict { .. })
+    ));
+    assert_eq!(target.import_checksums(&[official]).unwrap(), 0);
+    assert_eq!(target.import(&bundle).unwrap(), version("go1.22.1"));
+}
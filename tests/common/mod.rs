@@ -0,0 +1,365 @@
+//! Shared helpers for tests that exercise goup against a local stand-in for go.dev
+
+#![allow(dead_code)]
+
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// A canned HTTP response
+#[derive(Debug, Clone)]
+pub struct Response {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    /// Stop after sending this many bytes of the body, despite advertising the full length
+    pub truncate_at: Option<usize>,
+    /// Keep the connection open for this long after sending the body, without sending more
+    pub stall: Option<Duration>,
+    /// Close the connection without sending anything, as if the server were unreachable
+    pub hang_up: bool,
+    /// Wait this long before sending anything
+    pub delay: Option<Duration>,
+    /// Answer requests with a `Range` header with just that part of the body
+    pub ranges: bool,
+}
+
+impl Response {
+    pub fn ok(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: Vec::new(),
+            body: body.into(),
+            truncate_at: None,
+            stall: None,
+            hang_up: false,
+            delay: None,
+            ranges: false,
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self {
+            status,
+            ..Self::ok("")
+        }
+    }
+
+    pub fn truncated(body: impl Into<Vec<u8>>, truncate_at: usize) -> Self {
+        Self {
+            truncate_at: Some(truncate_at),
+            ..Self::ok(body)
+        }
+    }
+
+    /// Send the first `stall_at` bytes of the body, then go quiet for `duration`
+    pub fn stalled(body: impl Into<Vec<u8>>, stall_at: usize, duration: Duration) -> Self {
+        Self {
+            stall: Some(duration),
+            ..Self::truncated(body, stall_at)
+        }
+    }
+
+    pub fn hang_up() -> Self {
+        Self {
+            hang_up: true,
+            ..Self::status(0)
+        }
+    }
+
+    pub fn delayed(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    pub fn with_ranges(mut self) -> Self {
+        self.ranges = true;
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+/// A request received by the [`MockServer`]
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A minimal HTTP server that answers each path with a canned response, and remembers every
+/// request it received. Unknown paths get a 404.
+pub struct MockServer {
+    url: String,
+    routes: Arc<Mutex<HashMap<String, Vec<Response>>>>,
+    requests: Arc<Mutex<Vec<Request>>>,
+}
+
+impl MockServer {
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let routes = Arc::new(Mutex::new(HashMap::new()));
+        let requests = Arc::new(Mutex::new(Vec::new()));
+
+        let (r, q) = (Arc::clone(&routes), Arc::clone(&requests));
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                let (r, q) = (Arc::clone(&r), Arc::clone(&q));
+                thread::spawn(move || handle(stream, &r, &q));
+            }
+        });
+
+        Self {
+            url,
+            routes,
+            requests,
+        }
+    }
+
+    /// The base URL of the server, without a trailing slash
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Answer requests for `path` with `response`
+    pub fn route(&self, path: &str, response: Response) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), vec![response]);
+    }
+
+    /// Answer successive requests for `path` with each response in turn, repeating the last
+    pub fn route_sequence(&self, path: &str, responses: Vec<Response>) {
+        self.routes
+            .lock()
+            .unwrap()
+            .insert(path.to_string(), responses);
+    }
+
+    /// Serve a go.dev style index and archives for each of the given versions. As on go.dev,
+    /// betas and release candidates are only listed in the index of every release.
+    pub fn serve_versions(&self, versions: &[&str]) {
+        let archives: Vec<_> = versions.iter().map(|v| (*v, go_archive(v))).collect();
+        let stable: Vec<_> = archives
+            .iter()
+            .filter(|(v, _)| !is_prerelease(v))
+            .cloned()
+            .collect();
+        self.route("/?mode=json", Response::ok(index(&stable)));
+        self.route("/?mode=json&include=all", Response::ok(index(&archives)));
+        for (v, archive) in archives {
+            self.route(&format!("/{}", archive_name(v)), Response::ok(archive));
+        }
+    }
+
+    /// Every request received so far
+    pub fn requests(&self) -> Vec<Request> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// The number of requests received for the given path
+    pub fn hits(&self, path: &str) -> usize {
+        self.requests().iter().filter(|r| r.path == path).count()
+    }
+}
+
+fn handle(
+    stream: TcpStream,
+    routes: &Mutex<HashMap<String, Vec<Response>>>,
+    requests: &Mutex<Vec<Request>>,
+) {
+    let mut reader = BufReader::new(&stream);
+    let mut line = String::new();
+    if reader.read_line(&mut line).is_err() {
+        return;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next().unwrap_or_default().to_string();
+    let path = parts.next().unwrap_or_default().to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line).is_err() || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.trim_end().split_once(':') {
+            headers.push((name.trim().to_string(), value.trim().to_string()));
+        }
+    }
+    let range = headers
+        .iter()
+        .find(|(n, _)| n.eq_ignore_ascii_case("Range"))
+        .and_then(|(_, v)| v.strip_prefix("bytes="))
+        .and_then(|v| v.split_once('-'))
+        .and_then(|(a, b)| Some((a.parse::<usize>().ok()?, b.parse::<usize>().ok()?)));
+    requests.lock().unwrap().push(Request {
+        method: method.clone(),
+        path: path.clone(),
+        headers,
+    });
+
+    let mut response = {
+        let mut routes = routes.lock().unwrap();
+        match routes.get_mut(&path) {
+            Some(seq) if seq.len() > 1 => seq.remove(0),
+            Some(seq) => seq[0].clone(),
+            None => Response::status(404),
+        }
+    };
+
+    if let Some(delay) = response.delay {
+        thread::sleep(delay);
+    }
+    if response.hang_up {
+        return;
+    }
+    if let (true, Some((start, end))) = (response.ranges, range) {
+        let total = response.body.len();
+        response.body = response.body[start..=end.min(total - 1)].to_vec();
+        response.status = 206;
+        response.headers.push((
+            "Content-Range".to_string(),
+            format!("bytes {}-{}/{}", start, end.min(total - 1), total),
+        ));
+    }
+
+    let mut out = &stream;
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        response.status,
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    let _ = out.write_all(head.as_bytes());
+    if method != "HEAD" {
+        let end = response.truncate_at.unwrap_or(response.body.len());
+        let _ = out.write_all(&response.body[..end]);
+    }
+    let _ = out.flush();
+    if let Some(duration) = response.stall {
+        thread::sleep(duration);
+    }
+}
+
+/// What go.dev calls the architecture of the machine running the tests
+pub fn go_arch() -> &'static str {
+    match std::env::consts::ARCH {
+        "x86" => "386",
+        "x86_64" => "amd64",
+        "aarch64" => "arm64",
+        other => other,
+    }
+}
+
+/// The filename go.dev would use for the given version's archive on this platform
+pub fn archive_name(version: &str) -> String {
+    format!("{}.{}-{}.tar.gz", version, std::env::consts::OS, go_arch())
+}
+
+/// A tiny stand-in for a Go release archive, containing a `go` directory with a VERSION file,
+/// a `bin/go` script, a `bin/gofmt` script that echoes its arguments, and a file in each of the
+/// directories left out of minimal installs.
+///
+/// The `go` script reports the version, except that `go version -m DIR` describes a `hello`
+/// tool in DIR, and `go install SPEC` appends the version and SPEC to `$GOPATH/installed.txt`
+pub fn go_archive(version: &str) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    let mut add = |path: &str, contents: &[u8], mode: u32| {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(mode);
+        header.set_cksum();
+        builder.append_data(&mut header, path, contents).unwrap();
+    };
+    add("go/VERSION", format!("{}\n", version).as_bytes(), 0o644);
+    let script = format!(
+        r#"#!/bin/sh
+case "$1 $2" in
+"version -m") printf '%s: {version}\n\tpath\texample.com/cmd/hello\n\tmod\texample.com/cmd\tv1.2.3\th1:abc=\n' "$3/hello" ;;
+install*) echo "{version} $2" >> "$GOPATH/installed.txt" ;;
+*) echo "go version {version} {os}/{arch}" ;;
+esac
+"#,
+        version = version,
+        os = std::env::consts::OS,
+        arch = go_arch()
+    );
+    add("go/bin/go", script.as_bytes(), 0o755);
+    add("go/bin/gofmt", b"#!/bin/sh\necho gofmt \"$@\"\n", 0o755);
+    add("go/api/go1.txt", &[b'a'; 100], 0o644);
+    add("go/doc/go_spec.html", &[b'd'; 200], 0o644);
+    add("go/test/fixedbugs/issue1.go", &[b't'; 300], 0o644);
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// A go.dev style JSON index listing each version with its archive for this platform
+pub fn index(archives: &[(&str, Vec<u8>)]) -> String {
+    let entries: Vec<_> = archives
+        .iter()
+        .rev()
+        .map(|(version, archive)| {
+            serde_json::json!({
+                "version": version,
+                "stable": !is_prerelease(version),
+                "files": [{
+                    "filename": archive_name(version),
+                    "os": std::env::consts::OS,
+                    "arch": go_arch(),
+                    "version": version,
+                    "sha256": sha256(archive),
+                    "size": archive.len(),
+                    "kind": "archive",
+                }],
+            })
+        })
+        .collect();
+    serde_json::to_string_pretty(&entries).unwrap()
+}
+
+/// Whether the version name is that of a beta or release candidate
+fn is_prerelease(version: &str) -> bool {
+    version.contains("beta") || version.contains("rc")
+}
+
+/// The hex-encoded SHA256 digest of the given data
+pub fn sha256(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+/// A gzipped tarball containing the given files, each readable and executable
+pub fn tarball(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(GzEncoder::new(Vec::new(), Compression::default()));
+    for (path, contents) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(0o755);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *contents).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
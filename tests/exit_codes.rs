@@ -0,0 +1,124 @@
+mod common;
+
+use common::{MockServer, Response};
+use std::process::Command;
+use tempfile::TempDir;
+
+/// Run goup with the given arguments and a fresh GOPATH, returning the exit code
+fn goup(gopath: &TempDir, args: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_goup"))
+        .args(args)
+        .env("GOPATH", gopath.path())
+        .output()
+        .expect("failed to run goup")
+        .status
+        .code()
+        .expect("goup was killed by a signal")
+}
+
+/// Run goup with the given arguments, downloading from the given server
+fn goup_with(gopath: &TempDir, server: &MockServer, args: &[&str]) -> i32 {
+    Command::new(env!("CARGO_BIN_EXE_goup"))
+        .args(args)
+        .env("GOPATH", gopath.path())
+        .env("GOUP_MIRROR", server.url())
+        .output()
+        .expect("failed to run goup")
+        .status
+        .code()
+        .expect("goup was killed by a signal")
+}
+
+#[test]
+fn usage_errors_exit_with_2() {
+    let gopath = TempDir::new().unwrap();
+    assert_eq!(goup(&gopath, &["frobnicate"]), 2);
+    assert_eq!(goup(&gopath, &["install", "not-a-version"]), 2);
+    assert_eq!(goup(&gopath, &["default", "--copy"]), 2);
+    assert_eq!(goup(&gopath, &["install", "go99999999999.1"]), 2);
+}
+
+#[test]
+fn help_exits_with_0() {
+    let gopath = TempDir::new().unwrap();
+    assert_eq!(goup(&gopath, &["--help"]), 0);
+}
+
+#[test]
+fn missing_versions_exit_with_5() {
+    let gopath = TempDir::new().unwrap();
+    std::fs::create_dir(gopath.path().join("goup")).unwrap();
+    assert_eq!(goup(&gopath, &["enable", "go1.2.3"]), 5);
+    assert_eq!(goup(&gopath, &["remove", "go1.2.3"]), 5);
+    assert_eq!(goup(&gopath, &["pin", "go1.2.3"]), 5);
+}
+
+#[test]
+fn missing_gopath_exits_with_1() {
+    let output = Command::new(env!("CARGO_BIN_EXE_goup"))
+        .args(["pin", "go1.2.3"])
+        .env_remove("GOPATH")
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn corrupt_version_file_exits_with_1() {
+    let gopath = TempDir::new().unwrap();
+    std::fs::create_dir_all(gopath.path().join("goup/state")).unwrap();
+    std::fs::write(gopath.path().join("goup/state/versions.json"), "{ not json").unwrap();
+    assert_eq!(goup(&gopath, &["enable", "go1.2.3"]), 1);
+}
+
+#[test]
+fn pinned_versions_exit_with_1() {
+    let gopath = TempDir::new().unwrap();
+    std::fs::create_dir_all(gopath.path().join("goup/state")).unwrap();
+    std::fs::write(
+        gopath.path().join("goup/state/versions.json"),
+        r#"{ "enabled": null, "installed": ["go1.2.3"], "pinned": ["go1.2.3"] }"#,
+    )
+    .unwrap();
+    assert_eq!(goup(&gopath, &["remove", "go1.2.3"]), 1);
+}
+
+#[test]
+fn network_failures_exit_with_3() {
+    let server = MockServer::start();
+    server.route("/?mode=json", Response::status(500));
+    let gopath = TempDir::new().unwrap();
+    assert_eq!(goup_with(&gopath, &server, &["install", "go1.22.1"]), 3);
+    assert_eq!(goup_with(&gopath, &server, &["update"]), 3);
+}
+
+#[test]
+fn unavailable_versions_exit_with_4() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let gopath = TempDir::new().unwrap();
+    assert_eq!(goup_with(&gopath, &server, &["install", "go1.99.0"]), 4);
+    assert_eq!(goup_with(&gopath, &server, &["install", "go1.22.1"]), 0);
+}
+
+#[test]
+fn missing_versions_exit_with_5_even_when_an_install_could_be_offered() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let gopath = TempDir::new().unwrap();
+    assert_eq!(goup_with(&gopath, &server, &["run", "go1.22.1"]), 5);
+    assert_eq!(
+        goup_with(&gopath, &server, &["--non-interactive", "run", "go1.22.1"]),
+        5
+    );
+}
+
+#[test]
+fn unconfirmed_prompts_exit_with_6() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let gopath = TempDir::new().unwrap();
+    assert_eq!(goup_with(&gopath, &server, &["update"]), 0);
+    assert_eq!(goup_with(&gopath, &server, &["remove", "go1.22.1"]), 6);
+    assert_eq!(goup_with(&gopath, &server, &["remove", "go1.2.3"]), 5);
+}
@@ -0,0 +1,13 @@
+-- Generated by goup for go1.22.1. It is rewritten by `goup modulefiles`, so edits are lost.
+
+help("Go 1.22.1, installed by goup in /gopath/goup/versions/go1.22.1/go")
+
+whatis("Name: go")
+whatis("Version: 1.22.1")
+whatis("Description: The Go programming language, release go1.22.1")
+
+family("go")
+
+setenv("GOROOT", "/gopath/goup/versions/go1.22.1/go")
+setenv("GOTOOLCHAIN", "local")
+prepend_path("PATH", "/gopath/goup/versions/go1.22.1/go/bin")
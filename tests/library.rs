@@ -0,0 +1,949 @@
+#[cfg(unix)]
+use goup::config::EnableMode;
+use goup::layout::{self, Layout};
+use goup::project::{self, Need};
+use goup::trash::Disposal;
+use goup::version::VersionRequest;
+use goup::{managers, provenance, CleanPolicy, Config, GoVersion, Goup, GoupError, VersionFile};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tempfile::TempDir;
+
+fn version(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+/// A goup root in which the given versions are recorded as installed, with a directory
+/// holding just a VERSION file standing in for each installation
+fn root_with(installed: &[&str]) -> (TempDir, Goup) {
+    let dir = TempDir::new().unwrap();
+    let goup = Goup::new(dir.path());
+    let mut state = VersionFile::default();
+    for v in installed {
+        let goroot = goup.goroot(version(v));
+        fs::create_dir_all(&goroot).unwrap();
+        fs::write(goroot.join("VERSION"), format!("{}\ntime 2024-01-01\n", v)).unwrap();
+        state.installed.insert(version(v));
+    }
+    state.store(&goup.version_file()).unwrap();
+    (dir, goup)
+}
+
+#[test]
+fn semver_form_round_trips() {
+    for s in ["go1.20", "go1.21.0", "go1.22.6", "go2.0.15"] {
+        let v = version(s);
+        let semver = v.format_semver();
+        assert!(!semver.starts_with("go"), "{}", semver);
+        assert_eq!(GoVersion::parse_semver(&semver), Ok(v));
+        assert_eq!(format!("go{}", semver).parse::<GoVersion>(), Ok(v));
+    }
+    assert_eq!(GoVersion::parse_semver("v1.22"), Ok(version("go1.22.0")));
+    assert!(GoVersion::parse_semver("go1.22.6").is_err());
+    assert!(GoVersion::parse_semver("1.22.6-rc1").is_err());
+}
+
+#[test]
+fn bootstrap_versions_follow_the_source_install_docs() {
+    for (building, needs) in [
+        ("go1.19.13", "go1.4"),
+        ("go1.20.0", "go1.17.13"),
+        ("go1.21.5", "go1.17.13"),
+        ("go1.22.0", "go1.20.6"),
+        ("go1.23.4", "go1.20.6"),
+        ("go1.24.1", "go1.22.6"),
+        ("go1.26.0", "go1.24.6"),
+    ] {
+        assert_eq!(
+            goup::build::bootstrap_version(version(building)),
+            version(needs),
+            "{}",
+            building
+        );
+    }
+}
+
+#[test]
+fn missing_version_file_is_empty() {
+    let dir = TempDir::new().unwrap();
+    let goup = Goup::new(dir.path());
+    assert!(goup.installed().unwrap().is_empty());
+    assert_eq!(goup.state().unwrap().enabled, None);
+}
+
+#[test]
+fn corrupt_version_file() {
+    let (_dir, goup) = root_with(&[]);
+    fs::write(goup.version_file(), "{ not json").unwrap();
+    assert!(matches!(
+        goup.state(),
+        Err(GoupError::VersionFileCorrupt { path, .. }) if path == goup.version_file()
+    ));
+}
+
+#[test]
+fn unreadable_version_file() {
+    let (_dir, goup) = root_with(&[]);
+    fs::remove_file(goup.version_file()).unwrap();
+    fs::create_dir(goup.version_file()).unwrap();
+    assert!(matches!(
+        goup.state(),
+        Err(GoupError::Io { path, op: "read", .. }) if path == goup.version_file()
+    ));
+}
+
+#[test]
+fn unreachable_server_is_a_network_error() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let goup = goup
+        .with_config(Config {
+            mirror: Some("http://127.0.0.1:1".to_string()),
+            ..Default::default()
+        })
+        .unwrap();
+    assert!(matches!(
+        goup.install(version("go1.22.1")),
+        Err(GoupError::Network { url, .. }) if url.starts_with("http://127.0.0.1:1/")
+    ));
+}
+
+#[test]
+fn version_file_from_before_linked_toolchains_loads() {
+    let (_dir, goup) = root_with(&[]);
+    fs::write(
+        goup.version_file(),
+        r#"{"enabled":"go1.21.0","installed":["go1.21.0"],"pinned":[]}"#,
+    )
+    .unwrap();
+    let state = goup.state().unwrap();
+    assert_eq!(state.enabled, Some(version("go1.21.0")));
+    assert!(state.linked.is_empty());
+    assert_eq!(state.enabled_linked, None);
+    assert!(matches!(
+        goup.unlink("mygo"),
+        Err(GoupError::ToolchainNotLinked(name)) if name == "mygo"
+    ));
+}
+
+/// A root laid out the way goup arranged it before `versions` and `state`, with go1.21.0
+/// installed and enabled, and a directory of someone else's in the root alongside it
+#[cfg(unix)]
+fn flat_root() -> TempDir {
+    let dir = TempDir::new().unwrap();
+    let root = dir.path();
+    fs::create_dir_all(root.join("go1.21.0/go")).unwrap();
+    fs::write(root.join("go1.21.0/go/VERSION"), "go1.21.0\n").unwrap();
+    fs::create_dir_all(root.join("notes")).unwrap();
+    fs::create_dir_all(root.join("staging/go1.22.0")).unwrap();
+    std::os::unix::fs::symlink(root.join("go1.21.0/go"), root.join("go")).unwrap();
+    fs::write(
+        root.join("versions.json"),
+        r#"{"enabled":"go1.21.0","installed":["go1.21.0"],"pinned":[]}"#,
+    )
+    .unwrap();
+    dir
+}
+
+#[cfg(unix)]
+#[test]
+fn migrating_a_flat_root_moves_versions_and_repoints_the_link() {
+    let dir = flat_root();
+    let root = dir.path();
+    let mut goup = Goup::new(root);
+    assert_eq!(goup.layout(), Layout::Flat);
+    assert_eq!(goup.goroot(version("go1.21.0")), root.join("go1.21.0/go"));
+
+    let migration = goup.migrate().unwrap().unwrap();
+    assert_eq!(goup.layout(), Layout::Nested);
+    assert!(migration
+        .moved
+        .contains(&(root.join("go1.21.0"), root.join("versions/go1.21.0"))));
+    assert_eq!(migration.repointed, vec![root.join("go")]);
+    assert_eq!(
+        fs::read_link(root.join("go")).unwrap(),
+        root.join("versions/go1.21.0/go")
+    );
+    assert!(root.join("notes").exists());
+    assert!(!root.join("staging").exists());
+    let state = goup.state().unwrap();
+    assert_eq!(state.enabled, Some(version("go1.21.0")));
+    assert_eq!(state.layout, Some(layout::CURRENT));
+
+    // Older goup binaries read the root's version file, and must refuse it
+    let notice = fs::read_to_string(root.join("versions.json")).unwrap();
+    assert!(serde_json::from_str::<VersionFile>(&notice).is_err());
+    assert_eq!(Goup::new(root).layout(), Layout::Nested);
+    assert!(Goup::new(root).migrate().unwrap().is_none());
+}
+
+#[cfg(unix)]
+#[test]
+fn interrupted_migration_finishes_when_run_again() {
+    let dir = flat_root();
+    let root = dir.path();
+    // Stopped after moving the version, before moving the version file
+    fs::create_dir_all(root.join("versions")).unwrap();
+    fs::rename(root.join("go1.21.0"), root.join("versions/go1.21.0")).unwrap();
+
+    let mut goup = Goup::new(root);
+    assert_eq!(goup.layout(), Layout::Flat);
+    goup.migrate().unwrap().unwrap();
+    assert_eq!(goup.state().unwrap().installed.len(), 1);
+    assert!(root.join("go/VERSION").exists());
+}
+
+#[test]
+fn layouts_from_a_newer_goup_are_refused() {
+    let (dir, goup) = root_with(&["go1.21.0"]);
+    let mut state = goup.state().unwrap();
+    state.layout = Some(layout::CURRENT + 1);
+    state.store(&goup.version_file()).unwrap();
+
+    let mut goup = Goup::new(dir.path());
+    assert_eq!(goup.layout(), Layout::Unknown(layout::CURRENT + 1));
+    assert!(goup.migrate().unwrap().is_none());
+    assert!(matches!(
+        goup.state(),
+        Err(GoupError::LayoutUnsupported { layout, .. }) if layout == layout::CURRENT + 1
+    ));
+}
+
+#[test]
+fn operations_on_missing_versions() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let missing = version("go1.22.0");
+    assert!(matches!(goup.enable(missing), Err(GoupError::VersionNotInstalled(v)) if v == missing));
+    assert!(matches!(goup.remove(missing), Err(GoupError::VersionNotInstalled(v)) if v == missing));
+    assert!(
+        matches!(goup.pin(missing, None), Err(GoupError::VersionNotInstalled(v)) if v == missing)
+    );
+}
+
+#[test]
+fn enable_points_link_at_version() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    goup.enable(version("go1.21.0")).unwrap();
+    goup.enable(version("go1.22.0")).unwrap();
+    assert_eq!(
+        fs::read_link(goup.link_path()).unwrap(),
+        goup.install_dir(version("go1.22.0")).join("go")
+    );
+    assert_eq!(goup.state().unwrap().enabled, Some(version("go1.22.0")));
+}
+
+/// A handle on the same root as `goup` that enables versions by copying them
+#[cfg(unix)]
+fn copying(goup: Goup) -> Goup {
+    goup.with_config(Config {
+        enable_mode: EnableMode::Copy,
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+fn enable_copy_replaces_the_previous_copy() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    goup.enable(version("go1.21.0")).unwrap();
+    let goup = copying(goup);
+
+    goup.enable(version("go1.22.0")).unwrap();
+    let link = goup.link_path();
+    assert!(link.symlink_metadata().unwrap().is_dir());
+    assert!(fs::read_to_string(link.join("VERSION"))
+        .unwrap()
+        .starts_with("go1.22.0"));
+    assert_eq!(goup.copied_version(), Some(version("go1.22.0")));
+    let state = goup.state().unwrap();
+    assert_eq!(state.enabled, Some(version("go1.22.0")));
+    assert!(state.copied);
+
+    goup.enable(version("go1.21.0")).unwrap();
+    assert_eq!(goup.copied_version(), Some(version("go1.21.0")));
+    assert!(fs::read_to_string(link.join("VERSION"))
+        .unwrap()
+        .starts_with("go1.21.0"));
+    // Neither copy is left behind in the temporary directory
+    let staging = goup.root().join("tmp");
+    assert!(fs::read_dir(&staging).map_or(true, |mut d| d.next().is_none()));
+}
+
+#[cfg(unix)]
+#[test]
+fn enable_symlink_replaces_a_copy() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    let copy = copying(goup);
+    copy.enable(version("go1.21.0")).unwrap();
+
+    let goup = copy.with_config(Config::default()).unwrap();
+    goup.enable(version("go1.22.0")).unwrap();
+    assert_eq!(
+        fs::read_link(goup.link_path()).unwrap(),
+        goup.goroot(version("go1.22.0"))
+    );
+    assert_eq!(goup.copied_version(), None);
+    assert!(!goup.state().unwrap().copied);
+}
+
+#[cfg(unix)]
+#[test]
+fn enable_copy_refuses_to_replace_directories_goup_did_not_create() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    fs::create_dir(goup.link_path()).unwrap();
+    fs::write(goup.link_path().join("precious"), "data").unwrap();
+
+    let goup = copying(goup);
+    assert!(matches!(
+        goup.enable(version("go1.21.0")),
+        Err(GoupError::ForeignLink { version: None, .. })
+    ));
+    assert!(goup.link_path().join("precious").exists());
+}
+
+#[test]
+fn enable_reports_an_installation_in_the_way() {
+    let (_dir, goup) = root_with(&["go1.22.0"]);
+    fs::create_dir(goup.link_path()).unwrap();
+    fs::write(goup.link_path().join("VERSION"), "go1.21.5\ntime x\n").unwrap();
+
+    match goup.enable(version("go1.22.0")) {
+        Err(GoupError::ForeignLink { path, version: v }) => {
+            assert_eq!(path, goup.link_path());
+            assert_eq!(v, Some(version("go1.21.5")));
+        }
+        other => panic!("expected ForeignLink, got {:?}", other),
+    }
+    assert!(goup.link_path().join("VERSION").exists());
+
+    goup.transaction(|tx| {
+        assert!(tx.clear_link()?);
+        tx.enable(version("go1.22.0"))
+    })
+    .unwrap();
+    assert_eq!(goup.state().unwrap().enabled, Some(version("go1.22.0")));
+}
+
+#[cfg(unix)]
+#[test]
+fn removing_copied_version_removes_the_copy() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let goup = copying(goup);
+    goup.enable(version("go1.21.0")).unwrap();
+
+    goup.remove(version("go1.21.0")).unwrap();
+    assert!(goup.link_path().symlink_metadata().is_err());
+    let state = goup.state().unwrap();
+    assert_eq!(state.enabled, None);
+    assert!(!state.copied);
+}
+
+#[test]
+fn pinned_versions_cannot_be_removed() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let v = version("go1.21.0");
+    goup.pin(v, None).unwrap();
+    assert!(matches!(goup.remove(v), Err(GoupError::VersionPinned(_))));
+    assert!(goup.install_dir(v).exists());
+
+    goup.unpin(v).unwrap();
+    goup.remove(v).unwrap();
+    assert!(!goup.install_dir(v).exists());
+    assert!(goup.installed().unwrap().is_empty());
+}
+
+#[test]
+fn pin_records_reason_and_updates_it() {
+    let (_dir, goup) = root_with(&["go1.20.14"]);
+    let v = version("go1.20.14");
+    goup.pin(v, Some("legacy payments service".into())).unwrap();
+    let first = goup.state().unwrap().pin_info[&v].clone();
+    assert_eq!(first.reason.as_deref(), Some("legacy payments service"));
+
+    // Pinning again without a reason keeps the old one, and a new reason replaces it
+    goup.pin(v, None).unwrap();
+    assert_eq!(
+        goup.state().unwrap().pin_reason(v),
+        Some("legacy payments service")
+    );
+    goup.pin(v, Some("vendor SDK".into())).unwrap();
+    let state = goup.state().unwrap();
+    assert_eq!(state.pin_reason(v), Some("vendor SDK"));
+    assert_eq!(state.pin_info[&v].pinned_at, first.pinned_at);
+
+    goup.unpin(v).unwrap();
+    let state = goup.state().unwrap();
+    assert!(state.pin_info.is_empty());
+    assert_eq!(state.pin_reason(v), None);
+}
+
+#[test]
+fn pins_from_older_version_files_have_no_reason() {
+    let (_dir, goup) = root_with(&[]);
+    fs::write(
+        goup.version_file(),
+        r#"{"enabled":null,"installed":["go1.20.14"],"pinned":["go1.20.14"]}"#,
+    )
+    .unwrap();
+    let state = goup.state().unwrap();
+    assert!(state.pinned.contains(&version("go1.20.14")));
+    assert_eq!(state.pin_reason(version("go1.20.14")), None);
+}
+
+#[test]
+fn removing_enabled_version_clears_it() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let v = version("go1.21.0");
+    goup.enable(v).unwrap();
+    goup.remove(v).unwrap();
+    assert_eq!(goup.state().unwrap().enabled, None);
+}
+
+#[test]
+fn unpinning_requires_a_pin() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let v = version("go1.21.0");
+    assert!(matches!(goup.unpin(v), Err(GoupError::VersionNotPinned(_))));
+}
+
+#[test]
+fn offline_handle_refuses_network_access() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let goup = goup
+        .with_config(Config {
+            offline: Some(true),
+            ..Default::default()
+        })
+        .unwrap();
+
+    assert!(matches!(
+        goup.client("test"),
+        Err(GoupError::Offline { .. })
+    ));
+    assert!(matches!(goup.available(), Err(GoupError::Offline { .. })));
+    assert!(goup.command(version("go1.21.0"), "go").is_ok());
+}
+
+#[test]
+fn ca_bundle_is_loaded() {
+    let dir = TempDir::new().unwrap();
+    let with_bundle = |path: std::path::PathBuf| {
+        Goup::new(dir.path()).with_config(Config {
+            ca_bundle: Some(path),
+            ..Default::default()
+        })
+    };
+
+    let fixture = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/ca.pem");
+    assert!(with_bundle(fixture).is_ok());
+
+    let missing = dir.path().join("missing.pem");
+    assert!(matches!(with_bundle(missing), Err(GoupError::Io { .. })));
+
+    let invalid = dir.path().join("invalid.pem");
+    fs::write(
+        &invalid,
+        "-----BEGIN CERTIFICATE-----\nAAAA\n-----END CERTIFICATE-----\n",
+    )
+    .unwrap();
+    assert!(matches!(
+        with_bundle(invalid),
+        Err(GoupError::InvalidCertificate { .. })
+    ));
+}
+
+#[test]
+fn trash_is_trimmed_to_its_size_keeping_the_latest() {
+    let (_dir, goup) = root_with(&["go1.20.0", "go1.21.0", "go1.22.0"]);
+    let goup = goup
+        .with_config(Config {
+            use_trash: Some(true),
+            trash_max_size: Some(1),
+            ..Default::default()
+        })
+        .unwrap();
+
+    for v in ["go1.20.0", "go1.21.0"] {
+        let disposal = goup.remove(version(v)).unwrap();
+        assert!(matches!(disposal, Disposal::Trashed(_)), "{:?}", disposal);
+    }
+    let trashed: Vec<_> = goup.trash().unwrap().iter().map(|t| t.version).collect();
+    assert_eq!(trashed, [version("go1.21.0")]);
+
+    goup.restore(version("go1.21.0")).unwrap();
+    assert!(goup.installed().unwrap().contains(&version("go1.21.0")));
+    assert!(matches!(
+        goup.restore(version("go1.22.0")),
+        Err(GoupError::VersionAlreadyInstalled(_))
+    ));
+    assert!(matches!(
+        goup.restore(version("go1.20.0")),
+        Err(GoupError::NotInTrash(_))
+    ));
+}
+
+#[test]
+fn transaction_stores_every_change_at_once() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    goup.transaction(|tx| {
+        tx.pin(version("go1.22.0"), None)?;
+        tx.remove(version("go1.21.0"))?;
+        // Nothing is written until the transaction ends
+        assert!(goup.state()?.pinned.is_empty());
+        Ok::<_, GoupError>(())
+    })
+    .unwrap();
+
+    let state = goup.state().unwrap();
+    assert_eq!(
+        state.installed.into_iter().collect::<Vec<_>>(),
+        [version("go1.22.0")]
+    );
+    assert_eq!(
+        state.pinned.into_iter().collect::<Vec<_>>(),
+        [version("go1.22.0")]
+    );
+}
+
+#[test]
+fn failed_transaction_keeps_completed_changes() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let missing = version("go1.22.0");
+    let res = goup.transaction(|tx| {
+        tx.pin(version("go1.21.0"), None)?;
+        tx.pin(missing, None)
+    });
+    assert!(matches!(res, Err(GoupError::VersionNotInstalled(v)) if v == missing));
+    assert!(goup.state().unwrap().pinned.contains(&version("go1.21.0")));
+}
+
+#[test]
+fn store_leaves_no_staging_file() {
+    let (dir, goup) = root_with(&["go1.21.0"]);
+    goup.pin(version("go1.21.0"), None).unwrap();
+    let names: Vec<_> = fs::read_dir(dir.path())
+        .unwrap()
+        .map(|e| e.unwrap().file_name().into_string().unwrap())
+        .collect();
+    assert!(names.iter().all(|n| !n.ends_with(".tmp")), "{:?}", names);
+}
+
+#[test]
+fn clean_skips_directories_goup_did_not_create() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    let unrelated = goup.install_dir(version("go1.2.3"));
+    fs::create_dir_all(unrelated.join("go")).unwrap();
+    fs::write(unrelated.join("notes.txt"), "keep me").unwrap();
+
+    let policy = CleanPolicy {
+        include_available: true,
+        ..Default::default()
+    };
+    let report = goup.clean(&policy).unwrap();
+    assert_eq!(report.skipped, [version("go1.2.3")]);
+    assert_eq!(report.removed.len(), 1);
+    assert!(unrelated.join("notes.txt").exists());
+}
+
+#[test]
+fn remove_refuses_directories_goup_did_not_create() {
+    let (_dir, goup) = root_with(&["go1.21.0"]);
+    // The wrong release has been unpacked where go1.21.0 should be
+    let version_file = goup.goroot(version("go1.21.0")).join("VERSION");
+    fs::write(&version_file, "go1.19.4\n").unwrap();
+
+    assert!(matches!(
+        goup.remove(version("go1.21.0")),
+        Err(GoupError::NotCreatedByGoup(path)) if path == goup.install_dir(version("go1.21.0"))
+    ));
+    assert!(version_file.exists());
+    assert!(goup.installed().unwrap().contains(&version("go1.21.0")));
+}
+
+#[test]
+fn clean_leaves_files_named_like_versions() {
+    let (_dir, goup) = root_with(&["go1.20.0", "go1.21.0"]);
+    fs::write(goup.install_dir(version("go1.19.0")), "not a directory").unwrap();
+    let mut state = goup.state().unwrap();
+    state.installed.insert(version("go1.19.0"));
+    state.store(&goup.version_file()).unwrap();
+
+    let policy = CleanPolicy {
+        include_available: true,
+        jobs: Some(2),
+        ..Default::default()
+    };
+    let report = goup.clean(&policy).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, [version("go1.20.0"), version("go1.21.0")]);
+    assert!(report.failed.is_empty());
+    assert_eq!(report.skipped, [version("go1.19.0")]);
+
+    let installed: Vec<_> = goup.installed().unwrap().into_iter().collect();
+    assert_eq!(installed, [version("go1.19.0")]);
+    assert!(goup.install_dir(version("go1.19.0")).is_file());
+}
+
+#[test]
+fn provenance_tolerates_older_and_newer_formats() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    assert_eq!(goup.provenance(version("go1.21.0")), None);
+
+    let meta = goup
+        .install_dir(version("go1.22.0"))
+        .join(provenance::FILE_NAME);
+    fs::write(
+        &meta,
+        r#"{"source": "https://go.dev/dl/x.tar.gz", "checksum": "abc"}"#,
+    )
+    .unwrap();
+    let found = goup.provenance(version("go1.22.0")).unwrap();
+    assert_eq!(found.source, "https://go.dev/dl/x.tar.gz");
+    assert!(!found.minimal);
+}
+
+#[test]
+fn clean_older_than_keeps_recent_and_undated_versions() {
+    let (_dir, goup) = root_with(&["go1.19.0", "go1.20.0", "go1.21.0", "go1.22.0"]);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let installed_days_ago = |v: &str, days: u64| {
+        let meta = format!(r#"{{"installed_at": {}}}"#, now - days * 86400);
+        fs::write(
+            goup.install_dir(version(v)).join(provenance::FILE_NAME),
+            meta,
+        )
+        .unwrap();
+    };
+    installed_days_ago("go1.19.0", 400);
+    installed_days_ago("go1.20.0", 200);
+    installed_days_ago("go1.21.0", 10);
+    goup.pin(version("go1.19.0"), None).unwrap();
+
+    let policy = CleanPolicy {
+        include_available: true,
+        older_than: Some(Duration::from_secs(180 * 86400)),
+        ..Default::default()
+    };
+    let report = goup.clean(&policy).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, [version("go1.20.0")]);
+    assert_eq!(report.unknown_age, [version("go1.22.0")]);
+    assert_eq!(report.pinned, [version("go1.19.0")]);
+    assert_eq!(goup.installed().unwrap().len(), 3);
+}
+
+#[test]
+fn clean_unused_for_keeps_recently_used_and_unrecorded_versions() {
+    let (_dir, goup) = root_with(&["go1.20.0", "go1.21.0", "go1.22.0"]);
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs();
+    let mut state = goup.state().unwrap();
+    state
+        .last_used
+        .insert(version("go1.20.0"), now - 200 * 86400);
+    state
+        .last_used
+        .insert(version("go1.21.0"), now - 200 * 86400);
+    state.store(&goup.version_file()).unwrap();
+
+    // A recent use is recorded, but one within the last day is not recorded again
+    goup.record_use(version("go1.21.0")).unwrap();
+    let used = goup.state().unwrap().last_used[&version("go1.21.0")];
+    assert!(used >= now);
+    goup.record_use(version("go1.21.0")).unwrap();
+    assert_eq!(goup.state().unwrap().last_used[&version("go1.21.0")], used);
+
+    let policy = CleanPolicy {
+        include_available: true,
+        unused_for: Some(Duration::from_secs(120 * 86400)),
+        ..Default::default()
+    };
+    let report = goup.clean(&policy).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, [version("go1.20.0")]);
+    assert_eq!(report.never_used, [version("go1.22.0")]);
+    let state = goup.state().unwrap();
+    assert!(!state.last_used.contains_key(&version("go1.20.0")));
+    assert_eq!(state.installed.len(), 2);
+}
+
+#[test]
+fn clean_leaves_ephemeral_versions_to_their_try() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    let mut state = goup.state().unwrap();
+    state.ephemeral.insert(version("go1.21.0"));
+    state.store(&goup.version_file()).unwrap();
+    let policy = CleanPolicy {
+        include_available: true,
+        ..Default::default()
+    };
+
+    let report = goup.clean(&policy).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, [version("go1.22.0")]);
+
+    let settled = goup.transaction(|tx| tx.settle_ephemeral(version("go1.21.0"), false));
+    assert!(settled.unwrap());
+    let state = goup.state().unwrap();
+    assert!(state.installed.is_empty() && state.ephemeral.is_empty());
+    // Only the first to settle it removes it
+    let settled = goup.transaction(|tx| tx.settle_ephemeral(version("go1.21.0"), false));
+    assert!(!settled.unwrap());
+}
+
+#[test]
+fn clean_retains_the_newest_versions() {
+    let (_dir, goup) = root_with(&["go1.19.0", "go1.20.0", "go1.21.0", "go1.22.0", "go1.23.0"]);
+    goup.pin(version("go1.19.0"), None).unwrap();
+    let policy = CleanPolicy {
+        include_available: true,
+        retain: Some(2),
+        keep: [version("go1.20.0")].into(),
+        dry_run: true,
+        ..Default::default()
+    };
+
+    let report = goup.clean(&policy).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, [version("go1.21.0")]);
+    assert_eq!(goup.installed().unwrap().len(), 5);
+
+    let report = goup
+        .clean(&CleanPolicy {
+            dry_run: false,
+            ..policy
+        })
+        .unwrap();
+    assert_eq!(report.removed.len(), 1);
+    let installed: Vec<_> = goup.installed().unwrap().into_iter().collect();
+    assert_eq!(
+        installed,
+        ["go1.19.0", "go1.20.0", "go1.22.0", "go1.23.0"].map(version)
+    );
+}
+
+/// Make a Go tree holding `version` at `dir`, as another version manager would
+fn foreign_tree(dir: &Path, version: &str, built: bool) {
+    fs::create_dir_all(dir.join("bin")).unwrap();
+    fs::write(
+        dir.join("VERSION"),
+        format!("{}\ntime 2024-01-01\n", version),
+    )
+    .unwrap();
+    if built {
+        fs::write(dir.join("bin").join("go"), "#!/bin/sh\n").unwrap();
+    }
+}
+
+#[test]
+fn managers_find_reports_each_invalid_tree() {
+    let other = TempDir::new().unwrap();
+    let gos = other.path();
+    foreign_tree(&gos.join("go1.22.6"), "go1.22.6", true);
+    foreign_tree(&gos.join("1.21.13").join("go"), "go1.21.13", true);
+    foreign_tree(&gos.join("go1.21.0"), "go1.20.0", true);
+    foreign_tree(&gos.join("go1.23.0"), "go1.23.0", false);
+    fs::create_dir(gos.join("pkgsets")).unwrap();
+
+    let found = managers::find(gos).unwrap();
+    let versions: Vec<_> = found
+        .iter()
+        .filter_map(|f| f.tree.as_ref().ok().map(|(_, v)| *v))
+        .collect();
+    assert_eq!(versions, [version("go1.21.13"), version("go1.22.6")]);
+    let problems: Vec<_> = found
+        .iter()
+        .filter_map(|f| f.tree.as_ref().err().map(|e| e.to_string()))
+        .collect();
+    assert_eq!(problems.len(), 3, "{:?}", problems);
+    assert!(problems[0].contains("named go1.21.0, but its VERSION file says go1.20.0"));
+    assert!(problems[1].contains("no bin/go"));
+    assert!(problems[2].contains("no VERSION file"));
+}
+
+#[test]
+fn adopt_copies_or_moves_a_foreign_tree() {
+    let (_dir, goup) = root_with(&[]);
+    let other = TempDir::new().unwrap();
+    let copied = other.path().join("go1.22.6");
+    let moved = other.path().join("go1.21.13");
+    foreign_tree(&copied, "go1.22.6", true);
+    foreign_tree(&moved, "go1.21.13", true);
+
+    goup.adopt(&copied, version("go1.22.6"), false).unwrap();
+    goup.adopt(&moved, version("go1.21.13"), true).unwrap();
+    assert!(copied.join("bin").join("go").is_file());
+    assert!(!moved.exists());
+    for v in ["go1.22.6", "go1.21.13"] {
+        assert!(goup.goroot(version(v)).join("bin").join("go").is_file());
+        assert!(goup.provenance(version(v)).is_some());
+    }
+    assert_eq!(goup.installed().unwrap().len(), 2);
+    assert!(matches!(
+        goup.adopt(&copied, version("go1.22.6"), false),
+        Err(GoupError::VersionAlreadyInstalled(_))
+    ));
+}
+
+#[cfg(unix)]
+#[test]
+fn clean_continues_past_versions_that_cannot_be_deleted() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let (_dir, goup) = root_with(&["go1.19.0", "go1.20.0", "go1.21.0"]);
+    let locked = goup.goroot(version("go1.20.0")).join("pkg");
+    fs::create_dir(&locked).unwrap();
+    fs::write(locked.join("stuck.a"), "").unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o555)).unwrap();
+    // Permissions don't stop root, so there is nothing to test
+    if fs::write(locked.join("probe"), "").is_ok() {
+        fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+        return;
+    }
+
+    let policy = CleanPolicy {
+        include_available: true,
+        ..Default::default()
+    };
+    let report = goup.clean(&policy).unwrap();
+    fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, [version("go1.19.0"), version("go1.21.0")]);
+    assert_eq!(report.failed.len(), 1);
+    let (failed, e) = &report.failed[0];
+    assert_eq!(*failed, version("go1.20.0"));
+    assert!(e.to_string().contains("stuck.a"), "{}", e);
+    assert_eq!(goup.installed().unwrap(), [version("go1.20.0")].into());
+
+    // The VERSION file was kept, so a later clean still recognizes it
+    let report = goup.clean(&policy).unwrap();
+    assert_eq!(report.removed.len(), 1);
+    assert!(goup.installed().unwrap().is_empty());
+}
+
+#[test]
+fn provenance_marks_directories_as_created_by_goup() {
+    let (_dir, goup) = root_with(&[]);
+    let dir = goup.install_dir(version("go1.21.0"));
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(dir.join(provenance::FILE_NAME), "{}").unwrap();
+
+    let policy = CleanPolicy {
+        include_available: true,
+        ..Default::default()
+    };
+    let report = goup.clean(&policy).unwrap();
+    assert_eq!(report.removed.len(), 1);
+    assert!(!dir.exists());
+}
+
+#[test]
+fn transaction_keeps_changes_made_meanwhile_by_others() {
+    let (_dir, goup) = root_with(&["go1.21.0", "go1.22.0"]);
+    let other = Goup::new(goup.root());
+    goup.transaction(|tx| {
+        tx.pin(version("go1.21.0"), None)?;
+        other.pin(version("go1.22.0"), None)
+    })
+    .unwrap();
+
+    let pinned: Vec<_> = goup.state().unwrap().pinned.into_iter().collect();
+    assert_eq!(pinned, [version("go1.21.0"), version("go1.22.0")]);
+    assert!(!goup.root().join("state/locks/versions.lock").exists());
+}
+
+#[test]
+fn project_requirements_come_from_the_nearest_project_file() {
+    let dir = TempDir::new().unwrap();
+    let v = |s: &str| s.parse::<GoVersion>().unwrap();
+    let nested = dir.path().join("cmd").join("tool");
+    fs::create_dir_all(&nested).unwrap();
+    assert_eq!(project::requirement(&nested).unwrap(), None);
+
+    let go_mod = dir.path().join("go.mod");
+    fs::write(
+        &go_mod,
+        "module example.com/x\n\ngo 1.22 // the language version\n",
+    )
+    .unwrap();
+    let found = project::requirement(&nested).unwrap().unwrap();
+    assert_eq!(found.need, Need::AtLeast(v("go1.22.0")));
+    assert_eq!(found.file, go_mod);
+
+    // A toolchain line names the release exactly, and wins over the go line
+    fs::write(
+        &go_mod,
+        "module example.com/x\n\ngo 1.22.1\n\ntoolchain go1.22.6\n",
+    )
+    .unwrap();
+    let found = project::requirement(&nested).unwrap().unwrap();
+    assert_eq!(
+        found.need,
+        Need::Request(VersionRequest::Exact(v("go1.22.6")))
+    );
+    assert_eq!(found.need.to_string(), "go1.22.6");
+
+    // .go-version wins over go.mod in the same directory, with or without the go prefix
+    fs::write(dir.path().join(".go-version"), "1.21\n").unwrap();
+    let found = project::requirement(&nested).unwrap().unwrap();
+    assert_eq!(found.need.to_string(), "a release of go1.21");
+    assert!(found.need.matches(v("go1.21.13")) && !found.need.matches(v("go1.22.6")));
+
+    fs::write(dir.path().join(".go-version"), "not a version\n").unwrap();
+    assert!(matches!(
+        project::requirement(&nested),
+        Err(GoupError::InvalidProjectFile(path)) if path == dir.path().join(".go-version")
+    ));
+}
+
+#[test]
+fn resolutions_are_cached_until_their_inputs_change() {
+    let (dir, goup) = root_with(&["go1.21.5", "go1.22.3"]);
+    let project = dir.path().join("project");
+    let deep = project.join("a/b/c/d/e/f/g/h");
+    fs::create_dir_all(&deep).unwrap();
+    let go_mod = project.join("go.mod");
+    fs::write(&go_mod, "module example.com/project\n\ngo 1.21.0\n").unwrap();
+
+    let first = goup.resolve(&deep).unwrap();
+    assert_eq!(first.version, Some(version("go1.21.5")));
+    let cached = goup.version_file().with_file_name("resolved");
+    assert_eq!(fs::read_dir(&cached).unwrap().count(), 1);
+
+    // The cached path only stats the files the resolution depended on
+    let runs = 200;
+    let start = std::time::Instant::now();
+    for _ in 0..runs {
+        assert_eq!(goup.resolve(&deep).unwrap(), first);
+    }
+    let each = start.elapsed() / runs;
+    assert!(each < Duration::from_millis(5), "{:?} per resolution", each);
+
+    fs::write(&go_mod, "module example.com/project\n\ngo 1.22\n").unwrap();
+    assert_eq!(
+        goup.resolve(&deep).unwrap().version,
+        Some(version("go1.22.3"))
+    );
+    // A project file that didn't exist before counts too
+    fs::write(project.join("a/b/.go-version"), "1.21\n").unwrap();
+    assert_eq!(
+        goup.resolve(&deep).unwrap().version,
+        Some(version("go1.21.5"))
+    );
+
+    // Outside of a project, the enabled version decides
+    let elsewhere = dir.path().join("elsewhere");
+    fs::create_dir_all(&elsewhere).unwrap();
+    assert_eq!(goup.resolve(&elsewhere).unwrap().version, None);
+    goup.enable(version("go1.22.3")).unwrap();
+    assert_eq!(
+        goup.resolve(&elsewhere).unwrap().version,
+        Some(version("go1.22.3"))
+    );
+}
@@ -0,0 +1,238 @@
+use goup::project::{self, resolve, Context, Found, Need, Source};
+use goup::GoVersion;
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+fn v(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+/// A tree of projects, one to a directory:
+///
+/// - `plain`: a go.mod with only a go line
+/// - `plain/nested/deeper`: nothing of its own
+/// - `toolchain`: a go.mod with a toolchain line as well
+/// - `pinned`: a .go-version file naming a series, beside a go.mod
+/// - `bare`: a go.mod with neither line
+/// - `asdf`: a .tool-versions file with a golang line among other tools
+/// - `mixed`: a .tool-versions file beside a go.mod with a toolchain line
+/// - `plain/node`: a .tool-versions file without Go in it
+/// - `nothing`: no project at all
+fn fixtures() -> TempDir {
+    let root = TempDir::new().unwrap();
+    let write = |path: &str, text: &str| {
+        let path = root.path().join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, text).unwrap();
+    };
+    write("plain/go.mod", "module example.com/plain\n\ngo 1.21.2\n");
+    fs::create_dir_all(root.path().join("plain/nested/deeper")).unwrap();
+    write(
+        "toolchain/go.mod",
+        "module example.com/toolchain\n\ngo 1.21.0\ntoolchain go1.22.0\n",
+    );
+    write("pinned/.go-version", "1.21\n");
+    write("pinned/go.mod", "module example.com/pinned\n\ngo 1.22.0\n");
+    write("bare/go.mod", "module example.com/bare\n");
+    write(
+        "asdf/.tool-versions",
+        "nodejs 20.11.0\ngolang 1.22.0 1.21.0 # the first is used\n",
+    );
+    write("mixed/.tool-versions", "golang 1.21.0\n");
+    write(
+        "mixed/go.mod",
+        "module example.com/mixed\n\ngo 1.21.0\ntoolchain go1.22.3\n",
+    );
+    write("plain/node/.tool-versions", "nodejs 20.11.0\n");
+    fs::create_dir_all(root.path().join("nothing")).unwrap();
+    root
+}
+
+/// Which source decides, by how its description ends
+fn decided_by(dir: &Path, context: &Context) -> (Option<GoVersion>, String) {
+    let installed = [v("go1.21.0"), v("go1.21.5"), v("go1.22.0"), v("go1.22.3")];
+    let resolution = resolve(dir, context, &installed).unwrap();
+    let source = match resolution.decision() {
+        Some((Source::File(file), _)) => file.file_name().unwrap().to_string_lossy().into(),
+        Some((source, _)) => source.to_string(),
+        None => "nothing".to_string(),
+    };
+    (resolution.version, source)
+}
+
+#[test]
+fn each_source_decides_in_its_turn() {
+    let root = fixtures();
+    let enabled = Context {
+        enabled: Some(v("go1.22.3")),
+        ..Context::default()
+    };
+    let context = |gotoolchain: Option<&str>, shell: Option<&str>| Context {
+        gotoolchain: gotoolchain.map(str::to_string),
+        shell: shell.map(str::to_string),
+        ..enabled.clone()
+    };
+
+    let cases = [
+        ("plain", context(None, None), Some("go1.21.5"), "go.mod"),
+        (
+            "plain/nested/deeper",
+            context(None, None),
+            Some("go1.21.5"),
+            "go.mod",
+        ),
+        ("toolchain", context(None, None), Some("go1.22.0"), "go.mod"),
+        (
+            "pinned",
+            context(None, None),
+            Some("go1.21.5"),
+            ".go-version",
+        ),
+        (
+            "bare",
+            context(None, None),
+            Some("go1.22.3"),
+            "the default version",
+        ),
+        (
+            "nothing",
+            context(None, None),
+            Some("go1.22.3"),
+            "the default version",
+        ),
+        ("nothing", Context::default(), None, "nothing"),
+        (
+            "asdf",
+            context(None, None),
+            Some("go1.22.0"),
+            ".tool-versions",
+        ),
+        ("mixed", context(None, None), Some("go1.22.3"), "go.mod"),
+        (
+            "plain/node",
+            context(None, None),
+            Some("go1.21.5"),
+            "go.mod",
+        ),
+        (
+            "pinned",
+            context(Some("go1.22.0"), None),
+            Some("go1.22.0"),
+            "GOTOOLCHAIN",
+        ),
+        (
+            "pinned",
+            context(Some("go1.22.0+path"), None),
+            Some("go1.22.0"),
+            "GOTOOLCHAIN",
+        ),
+        (
+            "pinned",
+            context(Some("go1.22.0+auto"), None),
+            Some("go1.21.5"),
+            ".go-version",
+        ),
+        (
+            "pinned",
+            context(Some("local"), None),
+            Some("go1.21.5"),
+            ".go-version",
+        ),
+        (
+            "pinned",
+            context(None, Some("go1.22.0")),
+            Some("go1.22.0"),
+            "GOUP_ACTIVE_SHELL",
+        ),
+        (
+            "plain",
+            context(Some("go1.23.0"), None),
+            None,
+            "GOTOOLCHAIN",
+        ),
+    ];
+    for (dir, context, version, source) in cases {
+        let expected = (version.map(v), source.to_string());
+        let actual = decided_by(&root.path().join(dir), &context);
+        assert_eq!(actual, expected, "in {} with {:?}", dir, context);
+    }
+}
+
+#[test]
+fn every_source_is_listed_whichever_decides() {
+    let root = fixtures();
+    let dir = root.path().join("pinned");
+    let resolution = resolve(&dir, &Context::default(), &[v("go1.21.5")]).unwrap();
+
+    let sources: Vec<_> = resolution
+        .consulted
+        .iter()
+        .map(|(s, _)| s.clone())
+        .collect();
+    assert_eq!(
+        sources,
+        [
+            Source::Gotoolchain,
+            Source::Shell,
+            Source::File(dir.join(".go-version")),
+            Source::File(dir.join("go.mod")),
+            Source::Enabled,
+        ]
+    );
+    assert_eq!(resolution.decided_by, Some(2));
+    assert_eq!(
+        resolution.consulted[3].1,
+        Found::Need(Need::AtLeast(v("go1.22.0")))
+    );
+}
+
+#[test]
+fn tool_versions_come_after_go_version_and_go_mod() {
+    let root = fixtures();
+    let dir = root.path().join("mixed");
+    fs::write(dir.join(".go-version"), "1.21.5\n").unwrap();
+    let resolution = resolve(&dir, &Context::default(), &[v("go1.21.5")]).unwrap();
+    let sources: Vec<_> = resolution
+        .consulted
+        .iter()
+        .map(|(s, _)| s.clone())
+        .collect();
+    assert_eq!(
+        sources,
+        [
+            Source::Gotoolchain,
+            Source::Shell,
+            Source::File(dir.join(".go-version")),
+            Source::File(dir.join("go.mod")),
+            Source::File(dir.join(".tool-versions")),
+            Source::Enabled,
+        ]
+    );
+    assert_eq!(resolution.decided_by, Some(2));
+    assert_eq!(
+        resolution.consulted[4].1,
+        Found::Need(Need::Request("go1.21.0".parse().unwrap()))
+    );
+}
+
+#[test]
+fn local_writes_the_go_line_of_tool_versions() {
+    let root = fixtures();
+    let dir = root.path().join("asdf");
+    let file = project::set_local(&dir, v("go1.23rc1"), true).unwrap();
+    assert_eq!(file, dir.join(".tool-versions"));
+    assert_eq!(
+        fs::read_to_string(&file).unwrap(),
+        "nodejs 20.11.0\ngolang 1.23rc1\n"
+    );
+
+    let dir = root.path().join("plain/node");
+    project::set_local(&dir, v("go1.22.6"), true).unwrap();
+    assert_eq!(
+        fs::read_to_string(dir.join(".tool-versions")).unwrap(),
+        "nodejs 20.11.0\ngolang 1.22.6\n"
+    );
+    let file = project::set_local(&dir, v("go1.22.6"), false).unwrap();
+    assert_eq!(fs::read_to_string(file).unwrap(), "1.22.6\n");
+}
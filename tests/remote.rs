@@ -0,0 +1,1068 @@
+mod common;
+
+use common::{archive_name, go_archive, index, MockServer, Response};
+use goup::mirror::MirrorSelection;
+use goup::response::Kind;
+use goup::retry::RetryClass;
+use goup::transport::ThreadedTransport;
+use goup::{CacheMode, CleanPolicy, Config, GoVersion, Goup, GoupError, Progress, RetryPolicy};
+use std::fs;
+use std::future::Future;
+use std::io::{Cursor, Write};
+use std::pin::pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake, Waker};
+use std::thread;
+use std::time::Duration;
+use tempfile::TempDir;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+fn version(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+/// A goup handle with an empty root, downloading from the given server
+fn goup_for(server: &MockServer) -> (TempDir, Goup) {
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        mirror: Some(server.url().to_string()),
+        stall_timeout: Some(1),
+        ..Default::default()
+    };
+    let goup = Goup::new(dir.path()).with_config(config).unwrap();
+    (dir, goup)
+}
+
+#[test]
+fn lists_available_versions() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.21.0", "go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+
+    let available = goup.available().unwrap();
+    let versions: Vec<_> = available.keys().copied().collect();
+    assert_eq!(versions, vec![version("go1.21.0"), version("go1.22.1")]);
+    assert_eq!(
+        available[&version("go1.22.1")].filename,
+        archive_name("go1.22.1")
+    );
+}
+
+#[test]
+fn install_and_enable() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.21.0", "go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+
+    let v = version("go1.22.1");
+    goup.install(v).unwrap();
+    let installed = goup.install_dir(v).join("go");
+    assert_eq!(
+        fs::read_to_string(installed.join("VERSION")).unwrap(),
+        "go1.22.1\n"
+    );
+    assert!(goup.installed().unwrap().contains(&v));
+
+    goup.enable(v).unwrap();
+    assert_eq!(fs::read_link(goup.link_path()).unwrap(), installed);
+}
+
+/// A zip archive like those go.dev publishes for Windows, holding the given files with their
+/// permissions
+fn zip_archive(files: &[(&str, &[u8], u32)]) -> Vec<u8> {
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    for (path, contents, mode) in files {
+        let options = SimpleFileOptions::default().unix_permissions(*mode);
+        writer.start_file(*path, options).unwrap();
+        writer.write_all(contents).unwrap();
+    }
+    writer.finish().unwrap().into_inner()
+}
+
+#[test]
+fn zip_archives_are_unpacked() {
+    let server = MockServer::start();
+    let archive = zip_archive(&[
+        ("go/VERSION", b"go1.22.1\n", 0o644),
+        ("go/bin/go", b"#!/bin/sh\necho go version go1.22.1\n", 0o755),
+        ("go/doc/go_spec.html", &[b'd'; 200], 0o644),
+        ("../outside", b"escaped", 0o644),
+    ]);
+    let zip_name = archive_name("go1.22.1").replace(".tar.gz", ".zip");
+    let listing =
+        index(&[("go1.22.1", archive.clone())]).replace(&archive_name("go1.22.1"), &zip_name);
+    server.route("/?mode=json", Response::ok(listing));
+    server.route(&format!("/{}", zip_name), Response::ok(archive));
+    let dir = TempDir::new().unwrap();
+    let config = Config {
+        mirror: Some(server.url().to_string()),
+        minimal: Some(true),
+        ..Default::default()
+    };
+    let goup = Goup::new(dir.path()).with_config(config).unwrap();
+
+    let v = version("go1.22.1");
+    goup.install(v).unwrap();
+    let installed = goup.install_dir(v).join("go");
+    assert_eq!(
+        fs::read_to_string(installed.join("VERSION")).unwrap(),
+        "go1.22.1\n"
+    );
+    assert!(!installed.join("doc").exists());
+    assert!(!goup.install_dir(v).join("../outside").exists());
+    assert!(!dir.path().join("outside").exists());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(installed.join("bin/go"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o755);
+    }
+    assert!(goup.installed().unwrap().contains(&v));
+}
+
+/// Without Developer Mode, Windows refuses to make symlinks, so a junction is made instead.
+/// Either way, `go` leads to the enabled version, is replaced when another is enabled, and
+/// can be removed along with the versions.
+#[cfg(windows)]
+#[test]
+fn installing_enabling_and_removing_on_windows() {
+    use std::os::windows::fs::FileTypeExt;
+
+    let server = MockServer::start();
+    server.serve_versions(&["go1.21.0", "go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let link = goup.link_path();
+    for v in [version("go1.21.0"), version("go1.22.1")] {
+        goup.install(v).unwrap();
+        goup.enable(v).unwrap();
+        let file_type = fs::symlink_metadata(&link).unwrap().file_type();
+        assert!(file_type.is_symlink_dir());
+        assert_eq!(
+            fs::read_to_string(link.join("VERSION")).unwrap(),
+            format!("{}\n", v)
+        );
+        assert!(!goup.state().unwrap().copied);
+    }
+
+    for v in [version("go1.21.0"), version("go1.22.1")] {
+        goup.remove(v).unwrap();
+        assert!(!goup.install_dir(v).exists());
+    }
+    assert_eq!(goup.state().unwrap().enabled, None);
+    goup.transaction(|tx| tx.clear_link()).unwrap();
+    assert!(fs::symlink_metadata(&link).is_err());
+    assert!(!goup.state().unwrap().junction);
+}
+
+#[test]
+fn installing_twice_downloads_once() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+
+    goup.install(version("go1.22.1")).unwrap();
+    goup.install(version("go1.22.1")).unwrap();
+    assert_eq!(server.hits(&format!("/{}", archive_name("go1.22.1"))), 1);
+}
+
+#[test]
+fn install_unknown_version() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+
+    let res = goup.install(version("go1.99.0"));
+    assert!(matches!(res, Err(GoupError::VersionNotAvailable(v)) if v == version("go1.99.0")));
+}
+
+#[test]
+fn missing_archive() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    server.route(
+        &format!("/{}", archive_name("go1.22.1")),
+        Response::status(404),
+    );
+    let (_dir, goup) = goup_for(&server);
+
+    let v = version("go1.22.1");
+    assert!(matches!(goup.install(v), Err(GoupError::Network { .. })));
+    assert!(!goup.install_dir(v).exists());
+    assert!(goup.installed().unwrap().is_empty());
+}
+
+#[test]
+fn truncated_archive() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let archive = go_archive("go1.22.1");
+    let half = archive.len() / 2;
+    server.route(
+        &format!("/{}", archive_name("go1.22.1")),
+        Response::truncated(archive, half),
+    );
+    let (_dir, goup) = goup_for(&server);
+
+    let v = version("go1.22.1");
+    assert!(goup.install(v).is_err());
+    assert!(!goup.install_dir(v).exists());
+    assert!(goup.installed().unwrap().is_empty());
+}
+
+#[test]
+fn index_server_error() {
+    let server = MockServer::start();
+    server.route("/?mode=json", Response::status(500));
+    let (_dir, goup) = goup_for(&server);
+
+    assert!(matches!(goup.available(), Err(GoupError::Network { .. })));
+}
+
+#[test]
+fn index_not_json() {
+    let server = MockServer::start();
+    server.route("/?mode=json", Response::ok("<html>hello</html>"));
+    let (_dir, goup) = goup_for(&server);
+
+    assert!(matches!(
+        goup.available(),
+        Err(GoupError::InvalidIndex { .. })
+    ));
+}
+
+const LOGIN_PAGE: &str = "<!DOCTYPE html>\n<html><head><title>Harbor Hotel Wi-Fi</title></head>\
+     <body><form>Please sign in to continue</form></body></html>";
+
+#[test]
+fn index_from_a_captive_portal_is_described_and_not_reused() {
+    let server = MockServer::start();
+    server.route(
+        "/?mode=json",
+        Response::ok(LOGIN_PAGE).header("Content-Type", "text/html; charset=utf-8"),
+    );
+    let (_dir, goup) = goup_for(&server);
+
+    let Err(GoupError::InvalidIndex { received, .. }) = goup.available() else {
+        panic!("a login page was taken for an index");
+    };
+    assert_eq!(received.kind, Kind::LoginPage);
+    assert_eq!(received.status, Some(200));
+    assert_eq!(received.excerpt, "Harbor Hotel Wi-Fi");
+    assert_eq!(
+        received.to_string(),
+        "a login page, like those of captive portals (HTTP 200, text/html; charset=utf-8): \
+         \"Harbor Hotel Wi-Fi\""
+    );
+
+    // Once logged in, the page isn't served from the cache in place of the index
+    server.serve_versions(&["go1.22.1"]);
+    let index = goup.index(Some(Duration::from_secs(3600))).unwrap();
+    assert!(index.versions.contains_key(&version("go1.22.1")));
+}
+
+#[test]
+fn index_error_pages_and_truncated_json_are_described() {
+    let server = MockServer::start();
+    let (_dir, goup) = goup_for(&server);
+
+    // Without a Content-Type, the page is recognized by its markup
+    server.route(
+        "/?mode=json",
+        Response::ok("<html><body><h1>502 Bad Gateway</h1>\n<hr>nginx</body></html>"),
+    );
+    let Err(GoupError::InvalidIndex { received, .. }) = goup.available() else {
+        panic!("an error page was taken for an index");
+    };
+    assert_eq!(received.kind, Kind::ErrorPage);
+    assert_eq!(received.excerpt, "502 Bad Gateway nginx");
+
+    server.route(
+        "/?mode=json",
+        Response::ok(r#"[{"version": "go1.22.1", "stable": true, "files": [{"filen"#),
+    );
+    let Err(e @ GoupError::InvalidIndex { .. }) = goup.available() else {
+        panic!("a truncated index was accepted");
+    };
+    let GoupError::InvalidIndex { received, .. } = &e else {
+        unreachable!()
+    };
+    assert_eq!(received.kind, Kind::Other);
+    assert!(
+        e.to_string().contains(r#"[{"version": "go1.22.1""#),
+        "{}",
+        e
+    );
+}
+
+#[test]
+fn web_pages_in_place_of_archives_are_refused_before_unpacking() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let v = version("go1.22.1");
+    let path = format!("/{}", archive_name("go1.22.1"));
+
+    server.route(
+        &path,
+        Response::ok(LOGIN_PAGE).header("Content-Type", "text/html"),
+    );
+    let Err(GoupError::NotAnArchive { received, .. }) = goup.install(v) else {
+        panic!("a login page was unpacked");
+    };
+    assert_eq!(received.kind, Kind::LoginPage);
+
+    // A tiny body that isn't gzipped is refused too, whatever it claims to be
+    server.route(
+        &path,
+        Response::ok("Access denied\n").header("Content-Type", "application/octet-stream"),
+    );
+    let Err(GoupError::NotAnArchive { received, .. }) = goup.install(v) else {
+        panic!("a tiny body was unpacked");
+    };
+    assert_eq!(received.kind, Kind::Other);
+    assert_eq!(received.excerpt, "Access denied");
+    assert!(!goup.install_dir(v).exists());
+
+    server.route(&path, Response::ok(go_archive("go1.22.1")));
+    goup.install(v).unwrap();
+}
+
+/// The index go.dev served for two releases, in its current shape
+const DL_INDEX: &str = include_str!("fixtures/dl-index.json");
+
+/// Serve the fixture index after `mutate` has changed it, returning what goup made of it
+fn parse_mutated(mutate: impl FnOnce(&mut Vec<serde_json::Value>)) -> goup::Index {
+    let mut releases: Vec<serde_json::Value> = serde_json::from_str(DL_INDEX).unwrap();
+    mutate(&mut releases);
+    let server = MockServer::start();
+    server.route(
+        "/?mode=json",
+        Response::ok(serde_json::to_string(&releases).unwrap()),
+    );
+    let (_dir, goup) = goup_for(&server);
+    goup.index(None).unwrap()
+}
+
+/// The fixture's archive of `version` for this platform
+fn our_file<'a>(releases: &'a mut [serde_json::Value], version: &str) -> &'a mut serde_json::Value {
+    releases
+        .iter_mut()
+        .find(|r| r["version"] == version)
+        .and_then(|r| r["files"].as_array_mut())
+        .and_then(|files| {
+            files.iter_mut().find(|f| {
+                f["os"] == std::env::consts::OS
+                    && f["arch"] == common::go_arch()
+                    && f["kind"] == "archive"
+            })
+        })
+        .expect("the fixture has no archive for this platform")
+}
+
+#[test]
+fn index_in_the_current_shape_is_parsed() {
+    let index = parse_mutated(|_| {});
+    let versions: Vec<_> = index.versions.keys().copied().collect();
+    assert_eq!(versions, [version("go1.21.13"), version("go1.22.6")]);
+    let file = &index.versions[&version("go1.22.6")];
+    assert_eq!(file.kind, "archive");
+    assert!(file.filename.ends_with(".tar.gz"), "{}", file.filename);
+    assert!(file.size > 0);
+    assert!(index.skipped.is_empty(), "{:?}", index.skipped);
+}
+
+#[test]
+fn index_tolerates_extra_fields_unknown_kinds_and_null_sizes() {
+    let index = parse_mutated(|releases| {
+        releases[0]["checksum_url"] = "https://example.com/sums".into();
+        let file = our_file(releases, "go1.22.6");
+        file["signature"] = serde_json::json!({"alg": "ed25519"});
+        file["size"] = serde_json::Value::Null;
+        let mut sbom = file.clone();
+        sbom["kind"] = "sbom".into();
+        sbom["filename"] = "go1.22.6.spdx.json".into();
+        let files = releases[0]["files"].as_array_mut().unwrap();
+        files.insert(0, sbom);
+    });
+    assert!(index.skipped.is_empty(), "{:?}", index.skipped);
+    let file = &index.versions[&version("go1.22.6")];
+    assert_eq!(file.kind, "archive");
+    assert_eq!(file.size, 0);
+}
+
+#[test]
+fn index_skips_entries_it_cannot_use() {
+    let index = parse_mutated(|releases| {
+        our_file(releases, "go1.22.6")
+            .as_object_mut()
+            .unwrap()
+            .remove("sha256");
+        // A file for another platform is never looked at, however it's described
+        releases[1]["files"][0]["size"] = "big".into();
+        let mut odd = releases[1].clone();
+        odd["version"] = "next".into();
+        releases.push(odd);
+    });
+    let versions: Vec<_> = index.versions.keys().copied().collect();
+    assert_eq!(versions, [version("go1.21.13")]);
+    assert_eq!(index.skipped.len(), 2, "{:?}", index.skipped);
+    assert!(index.skipped[0].contains("sha256"), "{:?}", index.skipped);
+    assert!(
+        index.skipped[1].starts_with("release next"),
+        "{:?}",
+        index.skipped
+    );
+}
+
+#[test]
+fn releases_keep_the_files_for_every_platform() {
+    let server = MockServer::start();
+    server.route("/?mode=json", Response::ok(DL_INDEX));
+    let (_dir, goup) = goup_for(&server);
+
+    let releases = goup.releases(None).unwrap();
+    assert_eq!(releases.len(), 2);
+    let files = &releases[0].files;
+    assert_eq!(files.len(), 10);
+    assert_eq!(files.iter().filter(|f| f.is_archive()).count(), 6);
+    assert_eq!(files.iter().filter(|f| f.is_for_this_platform()).count(), 1);
+    // The host's view is built on the same list
+    let available = goup.available().unwrap();
+    let ours = files.iter().find(|f| f.is_for_this_platform()).unwrap();
+    assert_eq!(available[&releases[0].version].filename, ours.filename);
+}
+
+#[test]
+fn index_with_no_usable_release_is_invalid() {
+    let server = MockServer::start();
+    server.route(
+        "/?mode=json",
+        Response::ok(r#"[{"version": 5}, {"files": []}]"#),
+    );
+    let (_dir, goup) = goup_for(&server);
+
+    assert!(matches!(
+        goup.available(),
+        Err(GoupError::InvalidIndex { .. })
+    ));
+}
+
+#[test]
+fn clean_removes_versions_no_longer_available() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.20.0", "go1.21.0", "go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    for v in ["go1.20.0", "go1.21.0", "go1.22.1"] {
+        goup.install(version(v)).unwrap();
+    }
+    goup.pin(version("go1.20.0"), None).unwrap();
+    goup.enable(version("go1.21.0")).unwrap();
+
+    server.serve_versions(&["go1.22.1", "go1.23.0"]);
+    let report = goup.clean(&CleanPolicy::default()).unwrap();
+    assert!(report.removed.is_empty() && report.failed.is_empty());
+
+    goup.unpin(version("go1.20.0")).unwrap();
+    let report = goup.clean(&CleanPolicy::default()).unwrap();
+    let removed: Vec<_> = report.removed.iter().map(|r| r.version).collect();
+    assert_eq!(removed, vec![version("go1.20.0")]);
+    assert!(!goup.install_dir(version("go1.20.0")).exists());
+
+    let installed: Vec<_> = goup.installed().unwrap().into_iter().collect();
+    assert_eq!(installed, vec![version("go1.21.0"), version("go1.22.1")]);
+}
+
+#[test]
+fn stalled_download_is_retried() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let archive = go_archive("go1.22.1");
+    let path = format!("/{}", archive_name("go1.22.1"));
+    let stalled = Response::stalled(archive.clone(), 16, Duration::from_secs(5));
+    server.route_sequence(&path, vec![stalled, Response::ok(archive)]);
+    let (_dir, goup) = goup_for(&server);
+
+    let v = version("go1.22.1");
+    goup.install(v).unwrap();
+    assert_eq!(server.hits(&path), 2);
+    assert!(goup.installed().unwrap().contains(&v));
+}
+
+#[test]
+fn download_that_keeps_stalling_fails() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let path = format!("/{}", archive_name("go1.22.1"));
+    let archive = go_archive("go1.22.1");
+    server.route(
+        &path,
+        Response::stalled(archive, 16, Duration::from_secs(5)),
+    );
+    let (_dir, goup) = goup_for(&server);
+
+    let v = version("go1.22.1");
+    let err = goup.install(v).unwrap_err();
+    assert!(matches!(err, GoupError::DownloadStalled { seconds: 1, .. }));
+    assert_eq!(server.hits(&path), 3);
+    assert!(!goup.install_dir(v).exists());
+    assert!(!goup.installed().unwrap().contains(&v));
+}
+
+/// Serve an index for go1.22.1 with the given ETag, followed by a 304 for every later request
+fn serve_cacheable_index(server: &MockServer) {
+    let body = common::index(&[("go1.22.1", go_archive("go1.22.1"))]);
+    server.route_sequence(
+        "/?mode=json",
+        vec![
+            Response::ok(body).header("ETag", "\"v1\""),
+            Response::status(304),
+        ],
+    );
+}
+
+#[test]
+fn index_is_revalidated_with_etag() {
+    let server = MockServer::start();
+    serve_cacheable_index(&server);
+    let (_dir, goup) = goup_for(&server);
+
+    let first = goup.available().unwrap();
+    let second = goup.available().unwrap();
+    assert_eq!(
+        first.keys().collect::<Vec<_>>(),
+        second.keys().collect::<Vec<_>>()
+    );
+
+    let requests = server.requests();
+    assert_eq!(requests[0].header("If-None-Match"), None);
+    assert_eq!(requests[1].header("If-None-Match"), Some("\"v1\""));
+}
+
+#[test]
+fn refresh_skips_the_cache() {
+    let server = MockServer::start();
+    serve_cacheable_index(&server);
+    let (_dir, goup) = goup_for(&server);
+    goup.available().unwrap();
+
+    server.serve_versions(&["go1.22.1"]);
+    let goup = goup.with_cache_mode(CacheMode::Refresh);
+    assert!(goup.available().unwrap().contains_key(&version("go1.22.1")));
+    assert_eq!(server.requests()[1].header("If-None-Match"), None);
+}
+
+#[test]
+fn corrupt_cache_is_discarded() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    fs::create_dir_all(goup.cache_dir()).unwrap();
+    fs::write(goup.cache_dir().join("index.json"), "{\"url\": tru").unwrap();
+
+    assert!(goup.available().unwrap().contains_key(&version("go1.22.1")));
+    assert_eq!(server.requests()[0].header("If-None-Match"), None);
+}
+
+#[test]
+fn corrupt_cached_index_is_fetched_afresh_instead_of_revalidated() {
+    let server = MockServer::start();
+    let body = common::index(&[("go1.22.1", go_archive("go1.22.1"))]);
+    server.route_sequence(
+        "/?mode=json",
+        vec![Response::status(304), Response::ok(body)],
+    );
+    let (_dir, goup) = goup_for(&server);
+    let entry = serde_json::json!({
+        "url": format!("{}/?mode=json", server.url()),
+        "etag": "\"v1\"",
+        "last_modified": null,
+        "body": "[{\"version\": tru",
+    });
+    fs::create_dir_all(goup.cache_dir()).unwrap();
+    fs::write(goup.cache_dir().join("index.json"), entry.to_string()).unwrap();
+
+    assert!(goup.available().unwrap().contains_key(&version("go1.22.1")));
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].header("If-None-Match"), Some("\"v1\""));
+    assert_eq!(requests[1].header("If-None-Match"), None);
+}
+
+#[test]
+fn recent_index_is_reused_within_ttl() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+
+    let ttl = Some(Duration::from_secs(3600));
+    goup.index(ttl).unwrap();
+    let index = goup.index(ttl).unwrap();
+    assert!(index.versions.contains_key(&version("go1.22.1")));
+    assert!(!index.offline);
+    assert_eq!(server.hits("/?mode=json"), 1);
+
+    goup.index(None).unwrap();
+    assert_eq!(server.hits("/?mode=json"), 2);
+}
+
+#[test]
+fn cached_index_is_used_when_offline() {
+    let server = MockServer::start();
+    let body = common::index(&[("go1.22.1", go_archive("go1.22.1"))]);
+    server.route_sequence("/?mode=json", vec![Response::ok(body), Response::hang_up()]);
+    let (_dir, goup) = goup_for(&server);
+    goup.available().unwrap();
+
+    let index = goup.index(None).unwrap();
+    assert!(index.offline);
+    assert!(index.versions.contains_key(&version("go1.22.1")));
+
+    let goup = goup.with_cache_mode(CacheMode::Off);
+    assert!(matches!(goup.available(), Err(GoupError::Network { .. })));
+}
+
+#[test]
+fn extra_headers_are_sent_to_the_mirror_only() {
+    let mirror = MockServer::start();
+    mirror.serve_versions(&["go1.22.1"]);
+    let docs = MockServer::start();
+    docs.route("/devel/release", Response::ok(""));
+
+    std::env::set_var("GOUP_TEST_MIRROR_TOKEN", "s3cret");
+    let headers = toml::from_str(
+        r#"
+        X-Team = "platform"
+        X-Api-Key = { env = "GOUP_TEST_MIRROR_TOKEN" }
+        X-Missing = { env = "GOUP_TEST_UNSET_VARIABLE" }
+        "#,
+    )
+    .unwrap();
+    let dir = TempDir::new().unwrap();
+    let goup = Goup::new(dir.path())
+        .with_config(Config {
+            mirror: Some(mirror.url().to_string()),
+            docs_url: Some(docs.url().to_string()),
+            headers,
+            ..Default::default()
+        })
+        .unwrap();
+
+    goup.install(version("go1.22.1")).unwrap();
+    goup.release_history().unwrap();
+
+    let requests = mirror.requests();
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        assert_eq!(request.header("X-Team"), Some("platform"));
+        assert_eq!(request.header("X-Api-Key"), Some("s3cret"));
+        assert_eq!(request.header("X-Missing"), None);
+        assert!(request.header("User-Agent").unwrap().starts_with("goup/"));
+    }
+
+    let request = &docs.requests()[0];
+    assert_eq!(request.header("X-Api-Key"), None);
+    assert!(request.header("User-Agent").unwrap().starts_with("goup/"));
+}
+
+/// A goup handle downloading from the server, retrying according to the given policy
+fn goup_retrying(server: &MockServer, retry: RetryPolicy) -> (TempDir, Goup) {
+    let (dir, goup) = goup_for(server);
+    let config = Config {
+        retry,
+        ..goup.config().clone()
+    };
+    (dir, goup.with_config(config).unwrap())
+}
+
+fn quick_retries(attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        attempts,
+        initial_delay: 0.01,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn failed_downloads_are_retried() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let path = format!("/{}", archive_name("go1.22.1"));
+    let responses = vec![
+        Response::status(503),
+        Response::hang_up(),
+        Response::ok(go_archive("go1.22.1")),
+    ];
+    server.route_sequence(&path, responses.clone());
+    let (_dir, goup) = goup_retrying(&server, quick_retries(3));
+    goup.install(version("go1.22.1")).unwrap();
+    assert_eq!(server.hits(&path), 3);
+
+    server.route_sequence(&path, responses);
+    let (_dir, goup) = goup_retrying(&server, quick_retries(2));
+    assert!(goup.install(version("go1.22.1")).is_err());
+    assert_eq!(server.hits(&path), 5);
+}
+
+#[test]
+fn only_configured_failures_are_retried() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let path = format!("/{}", archive_name("go1.22.1"));
+    server.route_sequence(&path, vec![Response::status(500), Response::status(404)]);
+
+    let policy = RetryPolicy {
+        on: vec![RetryClass::Stall],
+        ..quick_retries(3)
+    };
+    let (_dir, goup) = goup_retrying(&server, policy);
+    assert!(goup.install(version("go1.22.1")).is_err());
+    assert_eq!(server.hits(&path), 1);
+
+    let (_dir, goup) = goup_retrying(&server, quick_retries(5));
+    assert!(goup.install(version("go1.22.1")).is_err());
+    assert_eq!(server.hits(&path), 2, "a 404 is not worth retrying");
+}
+
+#[test]
+fn huge_delays_are_capped() {
+    for max_delay in [f64::INFINITY, 1e300, f64::NAN] {
+        let policy = RetryPolicy {
+            initial_delay: 1e200,
+            multiplier: 1e200,
+            max_delay,
+            ..RetryPolicy::default()
+        };
+        assert_eq!(policy.delay(3), Duration::from_secs(60 * 60));
+    }
+}
+
+#[test]
+fn index_is_retried_when_configured() {
+    let server = MockServer::start();
+    let body = common::index(&[("go1.22.1", go_archive("go1.22.1"))]);
+    let responses = vec![Response::status(502), Response::ok(body)];
+
+    server.route_sequence("/?mode=json", responses.clone());
+    let (_dir, goup) = goup_retrying(&server, quick_retries(3));
+    assert!(goup.available().is_err());
+
+    server.route_sequence("/?mode=json", responses);
+    let policy = RetryPolicy {
+        index: true,
+        ..quick_retries(3)
+    };
+    let (_dir, goup) = goup_retrying(&server, policy);
+    assert!(goup.available().unwrap().contains_key(&version("go1.22.1")));
+    assert_eq!(server.hits("/?mode=json"), 3);
+}
+
+/// A goup handle downloading from `primary`, falling back to `secondary`
+fn goup_with_mirrors(
+    primary: &MockServer,
+    secondary: &MockServer,
+    selection: MirrorSelection,
+) -> (TempDir, Goup) {
+    let (dir, goup) = goup_for(primary);
+    let config = Config {
+        mirrors: vec![secondary.url().to_string()],
+        mirror_selection: selection,
+        ..goup.config().clone()
+    };
+    (dir, goup.with_config(config).unwrap())
+}
+
+#[test]
+fn unreachable_mirror_falls_back_to_the_next() {
+    let primary = MockServer::start();
+    primary.serve_versions(&["go1.22.1"]);
+    let archive = format!("/{}", archive_name("go1.22.1"));
+    primary.route(&archive, Response::hang_up());
+    let secondary = MockServer::start();
+    secondary.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_with_mirrors(&primary, &secondary, MirrorSelection::Ordered);
+
+    goup.install(version("go1.22.1")).unwrap();
+    assert_eq!(primary.hits(&archive), 3);
+    assert_eq!(secondary.hits(&archive), 1);
+    assert_eq!(secondary.hits("/?mode=json"), 0);
+}
+
+#[test]
+fn automatic_selection_prefers_the_fastest_mirror() {
+    let slow = MockServer::start();
+    let body = common::index(&[("go1.22.1", go_archive("go1.22.1"))]);
+    slow.route(
+        "/?mode=json",
+        Response::ok(body).delayed(Duration::from_millis(500)),
+    );
+    let fast = MockServer::start();
+    fast.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_with_mirrors(&slow, &fast, MirrorSelection::Auto);
+
+    assert_eq!(goup.mirrors(), [fast.url(), slow.url()]);
+    assert!(goup.mirror_ranking_file().exists());
+
+    // The ranking is reused rather than probing again
+    assert_eq!(goup.mirrors(), [fast.url(), slow.url()]);
+    assert_eq!(fast.hits("/?mode=json"), 1);
+}
+
+#[test]
+fn mirrors_keep_configured_order_when_probes_fail() {
+    let first = MockServer::start();
+    first.route("/?mode=json", Response::hang_up());
+    let second = MockServer::start();
+    second.route("/?mode=json", Response::hang_up());
+    let (_dir, goup) = goup_with_mirrors(&first, &second, MirrorSelection::Auto);
+
+    assert_eq!(goup.mirrors(), [first.url(), second.url()]);
+}
+
+#[test]
+fn archive_missing_its_end_is_rejected() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let archive = go_archive("go1.22.1");
+    let short = archive[..archive.len() - 8].to_vec();
+    let path = format!("/{}", archive_name("go1.22.1"));
+    server.route(&path, Response::ok(short));
+    let (_dir, goup) = goup_retrying(&server, quick_retries(1));
+
+    let v = version("go1.22.1");
+    match goup.install(v) {
+        Err(GoupError::SizeMismatch {
+            expected, received, ..
+        }) => {
+            assert_eq!(expected, archive.len() as u64);
+            assert_eq!(received, archive.len() as u64 - 8);
+        }
+        other => panic!("expected a size mismatch, got {:?}", other),
+    }
+    assert!(!goup.install_dir(v).exists());
+}
+
+#[test]
+fn archive_size_must_match_the_index() {
+    let server = MockServer::start();
+    let archive = go_archive("go1.22.1");
+    let mut padded = archive.clone();
+    padded.extend_from_slice(&[0; 512]);
+    server.route(
+        "/?mode=json",
+        Response::ok(common::index(&[("go1.22.1", archive)])),
+    );
+    let path = format!("/{}", archive_name("go1.22.1"));
+    server.route(&path, Response::ok(padded));
+    let (_dir, goup) = goup_retrying(&server, quick_retries(1));
+
+    let err = goup.install(version("go1.22.1")).unwrap_err();
+    assert!(matches!(err, GoupError::SizeMismatch { .. }), "{:?}", err);
+}
+
+#[test]
+fn archive_not_matching_its_checksum_is_not_installed() {
+    let server = MockServer::start();
+    let archive = go_archive("go1.22.1");
+    let listed = "0".repeat(64);
+    let index =
+        common::index(&[("go1.22.1", archive.clone())]).replace(&common::sha256(&archive), &listed);
+    server.route("/?mode=json", Response::ok(index));
+    server.route(
+        &format!("/{}", archive_name("go1.22.1")),
+        Response::ok(archive.clone()),
+    );
+    let (dir, goup) = goup_for(&server);
+
+    let v = version("go1.22.1");
+    match goup.install(v) {
+        Err(GoupError::ChecksumMismatch {
+            expected, actual, ..
+        }) => {
+            assert_eq!(expected, listed);
+            assert_eq!(actual, common::sha256(&archive));
+        }
+        other => panic!("expected a checksum mismatch, got {:?}", other),
+    }
+    assert!(!goup.install_dir(v).exists());
+    assert!(goup.installed().unwrap().is_empty());
+
+    // Unless checking is turned off
+    let config = Config {
+        verify_checksums: Some(false),
+        ..goup.config().clone()
+    };
+    let goup = Goup::new(dir.path()).with_config(config).unwrap();
+    goup.install(v).unwrap();
+    assert!(goup.install_dir(v).join("go/VERSION").exists());
+    assert!(goup.installed().unwrap().contains(&v));
+}
+
+#[test]
+fn version_being_installed_elsewhere_is_not_downloaded_again() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let lock = goup.root().join("state/locks/go1.22.1.lock");
+    fs::create_dir_all(lock.parent().unwrap()).unwrap();
+    fs::write(&lock, std::process::id().to_string()).unwrap();
+
+    let pid = std::process::id();
+    assert!(matches!(
+        goup.install(version("go1.22.1")),
+        Err(GoupError::InstallInProgress { version: v, pid: p }) if v == version("go1.22.1") && p == pid
+    ));
+    assert!(!goup.install_dir(version("go1.22.1")).exists());
+}
+
+#[test]
+fn stale_install_lock_is_taken_over() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+    let lock = goup.root().join("state/locks/go1.22.1.lock");
+    fs::create_dir_all(lock.parent().unwrap()).unwrap();
+    fs::write(&lock, exited.id().to_string()).unwrap();
+
+    goup.install(version("go1.22.1")).unwrap();
+    assert!(goup.installed().unwrap().contains(&version("go1.22.1")));
+    assert!(!lock.exists());
+}
+
+#[test]
+fn stale_install_lock_is_taken_over_once() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let mut exited = std::process::Command::new("true").spawn().unwrap();
+    exited.wait().unwrap();
+    let lock = goup.root().join("state/locks/go1.22.1.lock");
+    fs::create_dir_all(lock.parent().unwrap()).unwrap();
+    fs::write(&lock, exited.id().to_string()).unwrap();
+
+    let results: Vec<_> = thread::scope(|s| {
+        let installs: Vec<_> = (0..8)
+            .map(|_| s.spawn(|| goup.install(version("go1.22.1"))))
+            .collect();
+        installs.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+    for res in results {
+        assert!(
+            matches!(
+                res,
+                Ok(())
+                    | Err(GoupError::InstallInProgress { .. })
+                    | Err(GoupError::VersionAlreadyInstalled(_))
+            ),
+            "{:?}",
+            res
+        );
+    }
+    assert!(goup.installed().unwrap().contains(&version("go1.22.1")));
+    let leftovers: Vec<_> = fs::read_dir(lock.parent().unwrap())
+        .unwrap()
+        .map(|e| e.unwrap().file_name())
+        .collect();
+    assert!(leftovers.is_empty(), "{:?}", leftovers);
+}
+
+/// Run `future` to completion on this thread, as the simplest possible executor
+fn block_on<F: Future>(future: F) -> F::Output {
+    struct Unpark(thread::Thread);
+
+    impl Wake for Unpark {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    let waker = Waker::from(Arc::new(Unpark(thread::current())));
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+/// Counts what is reported, so tests can tell progress arrived
+#[derive(Clone, Default)]
+struct Counted {
+    bytes: Arc<AtomicU64>,
+    entries: Arc<AtomicU64>,
+}
+
+impl Progress for Counted {
+    fn advance(&self, bytes: u64) {
+        self.bytes.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    fn extracted(&self, entries: u64) {
+        self.entries.fetch_add(entries, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn async_download_installs_and_reports_progress() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.21.0", "go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let counted = Counted::default();
+    let goup = goup.with_progress(counted.clone());
+    let transport = ThreadedTransport::new(&goup);
+
+    let available = block_on(goup.available_async(&transport)).unwrap();
+    let versions: Vec<_> = available.keys().copied().collect();
+    assert_eq!(versions, vec![version("go1.21.0"), version("go1.22.1")]);
+
+    let v = version("go1.22.1");
+    block_on(goup.download_async(&transport, v, &available[&v])).unwrap();
+    assert_eq!(
+        fs::read_to_string(goup.install_dir(v).join("go/VERSION")).unwrap(),
+        "go1.22.1\n"
+    );
+    assert!(goup.installed().unwrap().contains(&v));
+    assert_eq!(
+        counted.bytes.load(Ordering::Relaxed),
+        go_archive("go1.22.1").len() as u64
+    );
+    assert!(counted.entries.load(Ordering::Relaxed) > 0);
+
+    // Installed versions aren't downloaded again
+    block_on(goup.download_async(&transport, v, &available[&v])).unwrap();
+    assert_eq!(server.hits(&format!("/{}", archive_name("go1.22.1"))), 1);
+}
+
+#[test]
+fn async_download_rejects_corrupt_archives() {
+    let server = MockServer::start();
+    server.serve_versions(&["go1.22.1"]);
+    let (_dir, goup) = goup_for(&server);
+    let transport = ThreadedTransport::new(&goup);
+    let v = version("go1.22.1");
+    let file = block_on(goup.available_async(&transport)).unwrap()[&v].clone();
+
+    let path = format!("/{}", archive_name("go1.22.1"));
+    server.route(&path, Response::status(404));
+    let res = block_on(goup.download_async(&transport, v, &file));
+    assert!(matches!(res, Err(GoupError::Network { .. })));
+
+    let mut archive = go_archive("go1.22.1");
+    let last = archive.len() - 1;
+    archive[last] ^= 0xff;
+    server.route(&path, Response::ok(archive));
+    let res = block_on(goup.download_async(&transport, v, &file));
+    assert!(matches!(res, Err(GoupError::ChecksumMismatch { .. })));
+
+    assert!(!goup.install_dir(v).exists());
+    assert!(goup.installed().unwrap().is_empty());
+    assert_eq!(fs::read_dir(goup.archive_cache_dir()).unwrap().count(), 0);
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use common::{sha256, tarball, MockServer, Response};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use tempfile::TempDir;
+
+const NEW_BINARY: &[u8] = b"#!/bin/sh\necho new goup\n";
+
+/// A copy of the goup binary that is safe to replace
+fn copy_of_goup(dir: &TempDir) -> PathBuf {
+    let exe = dir.path().join("goup");
+    fs::copy(env!("CARGO_BIN_EXE_goup"), &exe).unwrap();
+    exe
+}
+
+/// Publish a release with the given tag, whose checksum file says `checksum`
+fn publish(server: &MockServer, tag: &str, checksum: Option<String>) {
+    let name = format!(
+        "goup-{}-{}.tar.gz",
+        std::env::consts::ARCH,
+        std::env::consts::OS
+    );
+    let archive = tarball(&[("goup", NEW_BINARY)]);
+    let checksum = checksum.unwrap_or_else(|| sha256(&archive));
+    let release = serde_json::json!({
+        "tag_name": tag,
+        "assets": [
+            { "name": name, "browser_download_url": format!("{}/{}", server.url(), name) },
+            {
+                "name": format!("{}.sha256", name),
+                "browser_download_url": format!("{}/{}.sha256", server.url(), name),
+            },
+        ],
+    });
+    server.route("/releases/latest", Response::ok(release.to_string()));
+    server.route(&format!("/{}", name), Response::ok(archive));
+    server.route(
+        &format!("/{}.sha256", name),
+        Response::ok(format!("{}  {}\n", checksum, name)),
+    );
+}
+
+fn run(exe: &Path, server: &MockServer, args: &[&str]) -> Output {
+    let gopath = TempDir::new().unwrap();
+    Command::new(exe)
+        .args(args)
+        .env("GOPATH", gopath.path())
+        .env(
+            "GOUP_RELEASES_URL",
+            format!("{}/releases/latest", server.url()),
+        )
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn replaces_binary_with_newer_release() {
+    let server = MockServer::start();
+    publish(&server, "v999.0.0", None);
+    let dir = TempDir::new().unwrap();
+    let exe = copy_of_goup(&dir);
+
+    let output = run(&exe, &server, &["self-update"]);
+    assert!(output.status.success());
+    assert_eq!(fs::read(&exe).unwrap(), NEW_BINARY);
+    assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+}
+
+#[test]
+fn check_only_reports() {
+    let server = MockServer::start();
+    publish(&server, "v999.0.0", None);
+    let dir = TempDir::new().unwrap();
+    let exe = copy_of_goup(&dir);
+
+    let output = run(&exe, &server, &["self-update", "--check"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("goup 999.0.0 is available"));
+    assert_ne!(fs::read(&exe).unwrap(), NEW_BINARY);
+}
+
+#[test]
+fn up_to_date() {
+    let server = MockServer::start();
+    publish(&server, concat!("v", env!("CARGO_PKG_VERSION")), None);
+    let dir = TempDir::new().unwrap();
+    let exe = copy_of_goup(&dir);
+
+    let output = run(&exe, &server, &["self-update"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("is up to date"));
+    assert_eq!(server.hits("/releases/latest"), 1);
+    assert_eq!(server.requests().len(), 1);
+}
+
+#[test]
+fn bad_checksum_leaves_binary_alone() {
+    let server = MockServer::start();
+    publish(&server, "v999.0.0", Some("0".repeat(64)));
+    let dir = TempDir::new().unwrap();
+    let exe = copy_of_goup(&dir);
+
+    let output = run(&exe, &server, &["self-update"]);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stderr).contains("Checksum mismatch"));
+    assert_ne!(fs::read(&exe).unwrap(), NEW_BINARY);
+}
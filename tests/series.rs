@@ -0,0 +1,97 @@
+use goup::series::{group, Series, Status};
+use goup::GoVersion;
+use std::collections::BTreeMap;
+
+fn v(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+const AVAILABLE: Status = Status {
+    installed: false,
+    available: true,
+    enabled: false,
+    pinned: false,
+};
+
+/// Every release from go1.20.0 to go1.20.3 and go1.21.0 to go1.21.5, with go1.20.1 installed
+/// and enabled, and go1.21.2 installed but no longer available
+fn versions() -> BTreeMap<GoVersion, Status> {
+    let mut versions: BTreeMap<_, _> = (0..4)
+        .map(|p| (v(&format!("go1.20.{}", p)), AVAILABLE))
+        .chain((0..6).map(|p| (v(&format!("go1.21.{}", p)), AVAILABLE)))
+        .collect();
+    versions.insert(
+        v("go1.20.1"),
+        Status {
+            installed: true,
+            enabled: true,
+            ..AVAILABLE
+        },
+    );
+    versions.insert(
+        v("go1.21.2"),
+        Status {
+            installed: true,
+            ..Default::default()
+        },
+    );
+    versions
+}
+
+#[test]
+fn collapsed_series_show_newest_and_installed_releases() {
+    assert_eq!(
+        group(&versions(), false),
+        [
+            Series {
+                major: 1,
+                minor: 21,
+                releases: 6,
+                installed: 1,
+                shown: vec![v("go1.21.5"), v("go1.21.2")],
+                hidden: 4,
+            },
+            Series {
+                major: 1,
+                minor: 20,
+                releases: 4,
+                installed: 1,
+                shown: vec![v("go1.20.3"), v("go1.20.1")],
+                hidden: 2,
+            },
+        ]
+    );
+}
+
+#[test]
+fn expanded_series_show_every_release() {
+    let series = group(&versions(), true);
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[0].shown.len(), 6);
+    assert_eq!(series[0].hidden, 0);
+    assert_eq!(series[1].to_string(), "go1.20");
+    assert_eq!(series[1].shown.first(), Some(&v("go1.20.3")));
+    assert_eq!(series[1].shown.last(), Some(&v("go1.20.0")));
+}
+
+#[test]
+fn pinned_releases_are_always_shown() {
+    let mut versions = versions();
+    versions.insert(
+        v("go1.21.0"),
+        Status {
+            pinned: true,
+            ..AVAILABLE
+        },
+    );
+    let series = group(&versions, false);
+    assert_eq!(
+        series[0].shown,
+        [v("go1.21.5"), v("go1.21.2"), v("go1.21.0")]
+    );
+}
+
+#[test]
+fn no_versions_give_no_series() {
+    assert!(group(&BTreeMap::new(), false).is_empty());
+}
@@ -0,0 +1,59 @@
+use goup::size::{dir_size, dir_size_with, format_size};
+use std::fs;
+use std::path::Path;
+use tempfile::TempDir;
+
+/// Lay out a tree shaped roughly like a GOROOT, with files of known sizes spread across many
+/// nested directories
+fn build_tree(root: &Path) -> u64 {
+    let mut total = 0;
+    fs::write(root.join("VERSION"), b"go1.22.1").unwrap();
+    total += 8;
+    for top in 0..12 {
+        for sub in 0..top {
+            let dir = root.join(format!("pkg{}", top)).join(format!("sub{}", sub));
+            fs::create_dir_all(&dir).unwrap();
+            for file in 0..sub + 1 {
+                let len = 100 * top + 10 * sub + file;
+                fs::write(dir.join(format!("f{}.go", file)), vec![b'x'; len]).unwrap();
+                total += len as u64;
+            }
+        }
+    }
+    total
+}
+
+#[test]
+fn parallel_walk_matches_serial_walk() {
+    let dir = TempDir::new().unwrap();
+    let expected = build_tree(dir.path());
+
+    assert_eq!(dir_size_with(dir.path(), 1).unwrap(), expected);
+    for threads in [2, 4, 16] {
+        assert_eq!(dir_size_with(dir.path(), threads).unwrap(), expected);
+    }
+    assert_eq!(dir_size(dir.path()).unwrap(), expected);
+}
+
+#[cfg(unix)]
+#[test]
+fn symlinks_are_not_followed() {
+    let dir = TempDir::new().unwrap();
+    let outside = TempDir::new().unwrap();
+    fs::write(outside.path().join("big"), vec![0; 4096]).unwrap();
+    std::os::unix::fs::symlink(outside.path(), dir.path().join("link")).unwrap();
+    assert!(dir_size(dir.path()).unwrap() < 4096);
+}
+
+#[test]
+fn missing_directory_is_an_error() {
+    let dir = TempDir::new().unwrap();
+    assert!(dir_size(&dir.path().join("missing")).is_err());
+}
+
+#[test]
+fn sizes_are_formatted_in_binary_units() {
+    assert_eq!(format_size(0), "0 B");
+    assert_eq!(format_size(2048), "2.0 KiB");
+    assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+}
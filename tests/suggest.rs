@@ -0,0 +1,46 @@
+use goup::suggest::suggestions;
+use goup::GoVersion;
+
+fn v(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+fn vs(list: &[&str]) -> Vec<GoVersion> {
+    list.iter().map(|s| v(s)).collect()
+}
+
+#[test]
+fn prefers_same_minor_by_patch_distance() {
+    let pool = vs(&["go1.22.0", "go1.22.4", "go1.22.6", "go1.23.0"]);
+    assert_eq!(
+        suggestions(v("go1.22.7"), pool),
+        vs(&["go1.22.6", "go1.22.4", "go1.22.0"])
+    );
+}
+
+#[test]
+fn falls_back_to_adjacent_minors() {
+    let pool = vs(&["go1.20.3", "go1.21.5", "go1.23.1", "go1.23.9"]);
+    assert_eq!(
+        suggestions(v("go1.22.1"), pool),
+        vs(&["go1.23.1", "go1.21.5", "go1.23.9"])
+    );
+}
+
+#[test]
+fn typos_within_two_edits() {
+    let pool = vs(&["go1.2.1", "go1.17.44", "go1.9.9"]);
+    assert_eq!(suggestions(v("go1.12.1"), pool), vs(&["go1.2.1"]));
+}
+
+#[test]
+fn never_suggests_the_target_or_duplicates() {
+    let pool = vs(&["go1.22.1", "go1.22.1", "go1.22.2"]);
+    assert_eq!(suggestions(v("go1.22.1"), pool), vs(&["go1.22.2"]));
+}
+
+#[test]
+fn nothing_close() {
+    let pool = vs(&["go1.11.0", "go1.15.13"]);
+    assert!(suggestions(v("go1.22.1"), pool).is_empty());
+}
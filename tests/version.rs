@@ -0,0 +1,106 @@
+use goup::version::{PreRelease, Stage, VersionPattern, VersionRequest};
+use goup::GoVersion;
+
+fn v(s: &str) -> GoVersion {
+    s.parse().unwrap()
+}
+
+#[test]
+fn pre_releases_are_parsed() {
+    assert_eq!(
+        v("go1.23rc1"),
+        GoVersion {
+            major: 1,
+            minor: 23,
+            patch: 0,
+            pre: Some(PreRelease {
+                stage: Stage::Rc,
+                number: 1,
+            }),
+            explicit_patch: false,
+        }
+    );
+    assert_eq!(
+        v("go1.22beta2").pre,
+        Some(PreRelease {
+            stage: Stage::Beta,
+            number: 2,
+        })
+    );
+    assert_eq!(v("go1.9.2rc2").patch, 2);
+    assert_eq!(v("go1.22.3").pre, None);
+}
+
+#[test]
+fn pre_releases_come_before_their_release() {
+    assert!(v("go1.23rc1") < v("go1.23.0"));
+    assert!(v("go1.23rc1") < v("go1.23rc2"));
+    assert!(v("go1.23beta2") < v("go1.23rc1"));
+    assert!(v("go1.23beta1") < v("go1.23beta2"));
+    assert!(v("go1.22.6") < v("go1.23beta1"));
+    assert!(v("go1.9.2rc2") < v("go1.9.2"));
+    assert!(v("go1.9.1") < v("go1.9.2rc2"));
+}
+
+#[test]
+fn pre_releases_round_trip() {
+    for name in ["go1.23rc1", "go1.22beta2", "go1.9.2rc2", "go1.22.3"] {
+        assert_eq!(v(name).to_string(), name);
+        let json = serde_json::to_string(&v(name)).unwrap();
+        assert_eq!(json, format!("\"{}\"", name));
+        assert_eq!(serde_json::from_str::<GoVersion>(&json).unwrap(), v(name));
+    }
+}
+
+#[test]
+fn pre_releases_are_only_requested_by_name() {
+    let available = [v("go1.22.6"), v("go1.23rc1"), v("go1.23rc2")];
+    let latest: VersionRequest = "latest".parse().unwrap();
+    assert_eq!(latest.resolve(available), Some(v("go1.22.6")));
+    let series: VersionRequest = "go1.23".parse().unwrap();
+    assert_eq!(series.resolve(available), None);
+    let exact: VersionRequest = "go1.23rc1".parse().unwrap();
+    assert_eq!(exact, VersionRequest::Exact(v("go1.23rc1")));
+    assert_eq!(exact.resolve(available), Some(v("go1.23rc1")));
+
+    // A series on the blocklist blocks its pre-releases too
+    let pattern: VersionPattern = "go1.23".parse().unwrap();
+    assert!(pattern.matches(v("go1.23rc2")));
+}
+
+#[test]
+fn only_whole_names_are_versions() {
+    for name in [
+        "xgo1.22.1",
+        "go1.22.1x",
+        "go1.22.1.linux-amd64.tar.gz",
+        "1.22.1",
+        "go1",
+    ] {
+        assert!(name.parse::<GoVersion>().is_err(), "{} parsed", name);
+    }
+    assert_eq!(
+        "go99999999999.1".parse::<GoVersion>(),
+        Err("version number out of range")
+    );
+    assert!("go1.23rc99999999999".parse::<GoVersion>().is_err());
+}
+
+#[test]
+fn versions_remember_whether_their_patch_was_given() {
+    assert!(v("go1.22.0").explicit_patch);
+    assert!(!v("go1.22").explicit_patch);
+    assert!(!v("go1.23rc1").explicit_patch);
+    assert_eq!(v("go1.22"), v("go1.22.0"));
+
+    let request = |s: &str| s.parse::<VersionRequest>().unwrap();
+    assert_eq!(
+        request("go1.22"),
+        VersionRequest::Series {
+            major: 1,
+            minor: 22
+        }
+    );
+    assert_eq!(request("go1.22.0"), VersionRequest::Exact(v("go1.22.0")));
+    assert_eq!(request("go1.23rc1"), VersionRequest::Exact(v("go1.23rc1")));
+}